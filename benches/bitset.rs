@@ -0,0 +1,67 @@
+//! Compares bitwise operations on the packed BitSet with element-wise loops over a
+//! Vec<bool>. BOOLVECTOR items store their elements packed, so the instructions need no
+//! conversion.
+//! Run with `cargo bench --bench bitset`.
+use pushr::push::bitset::BitSet;
+use pushr::push::instructions::InstructionCache;
+use pushr::push::state::PushState;
+use pushr::push::vector::*;
+use std::hint::black_box;
+use std::time::Instant;
+
+const SIZE: usize = 100_000;
+const REPETITIONS: usize = 200;
+
+/// Runs the function and prints the mean time per call.
+fn measure<F: FnMut()>(label: &str, mut run: F) {
+    let start = Instant::now();
+    for _ in 0..REPETITIONS {
+        run();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<28} {:>10.1} us/call",
+        label,
+        elapsed.as_micros() as f64 / REPETITIONS as f64
+    );
+}
+
+fn main() {
+    let icache = InstructionCache::new(vec![]);
+    // Sparse vectors as in sparse distributed representations
+    let a: Vec<bool> = (0..SIZE).map(|i| i % 50 == 0).collect();
+    let b: Vec<bool> = (0..SIZE).map(|i| i % 30 == 0).collect();
+    let (bits_a, bits_b) = (BitSet::from_bools(&a), BitSet::from_bools(&b));
+
+    println!("{} elements, {} calls", SIZE, REPETITIONS);
+    measure("Vec<bool> AND", || {
+        black_box(a.iter().zip(b.iter()).map(|(x, y)| x & y).collect::<Vec<bool>>());
+    });
+    measure("BitSet AND", || {
+        black_box(bits_a.and(&bits_b));
+    });
+    measure("Vec<bool> hamming", || {
+        black_box(a.iter().zip(b.iter()).filter(|(x, y)| x != y).count());
+    });
+    measure("BitSet hamming", || {
+        black_box(bits_a.hamming(&bits_b));
+    });
+    measure("BitSet from Vec<bool>", || {
+        black_box(BitSet::from_bools(&a));
+    });
+    let (vector_a, vector_b) = (BoolVector::new(a.clone()), BoolVector::new(b.clone()));
+    let mut push_state = PushState::new();
+    measure("BOOLVECTOR.AND", || {
+        push_state.bool_vector_stack.push(vector_a.clone());
+        push_state.bool_vector_stack.push(vector_b.clone());
+        push_state.int_stack.push(0);
+        bool_vector_and(&mut push_state, &icache);
+        black_box(push_state.bool_vector_stack.pop());
+    });
+    measure("BOOLVECTOR.HAMMING", || {
+        push_state.bool_vector_stack.push(vector_a.clone());
+        push_state.bool_vector_stack.push(vector_b.clone());
+        bool_vector_hamming(&mut push_state, &icache);
+        black_box(push_state.int_stack.pop());
+    });
+}
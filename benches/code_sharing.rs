@@ -0,0 +1,72 @@
+//! Measures instructions that copy code on a deeply nested program. Lists share their
+//! items between copies, the deep copy of the program is measured for comparison.
+//! Run with `cargo bench --bench code_sharing`.
+use pushr::push::code::{code_do, code_dup, code_pop, code_quote};
+use pushr::push::execution::exec_dup;
+use pushr::push::instructions::InstructionCache;
+use pushr::push::item::Item;
+use pushr::push::state::PushState;
+use std::hint::black_box;
+use std::time::Instant;
+
+const DEPTH: usize = 1_000;
+const REPETITIONS: usize = 10_000;
+
+/// Returns a program of the given nesting depth with three points per level.
+fn nested_program(depth: usize) -> Item {
+    (0..depth).fold(Item::empty_list(), |inner, i| {
+        Item::list(vec![inner, Item::instruction("INTEGER.+".to_string()), Item::int(i as i32)])
+    })
+}
+
+/// Rebuilds the item with new lists, i.e. copies it without sharing.
+fn deep_copy(item: &Item) -> Item {
+    match item {
+        Item::List { items } => Item::list(items.as_slice().iter().map(deep_copy).collect()),
+        _ => item.clone(),
+    }
+}
+
+/// Runs the function on the state and prints the mean time per call.
+fn measure<F: FnMut(&mut PushState)>(label: &str, push_state: &mut PushState, mut run: F) {
+    let start = Instant::now();
+    for _ in 0..REPETITIONS {
+        run(push_state);
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<28} {:>10.3} us/call",
+        label,
+        elapsed.as_nanos() as f64 / 1000.0 / REPETITIONS as f64
+    );
+}
+
+fn main() {
+    let icache = InstructionCache::new(vec![]);
+    let program = nested_program(DEPTH);
+    let mut push_state = PushState::new();
+    push_state.code_stack.push(program.clone());
+
+    println!("{} points, {} calls", Item::size(&program), REPETITIONS);
+    measure("deep copy", &mut push_state, |s| {
+        black_box(deep_copy(s.code_stack.get(0).unwrap()));
+    });
+    measure("CODE.DUP", &mut push_state, |s| {
+        code_dup(s, &icache);
+        code_pop(s, &icache);
+    });
+    measure("CODE.QUOTE", &mut push_state, |s| {
+        s.exec_stack.push(program.clone());
+        code_quote(s, &icache);
+        code_pop(s, &icache);
+    });
+    measure("EXEC.DUP", &mut push_state, |s| {
+        s.exec_stack.push(program.clone());
+        exec_dup(s, &icache);
+        s.exec_stack.flush();
+    });
+    measure("CODE.DO", &mut push_state, |s| {
+        code_do(s, &icache);
+        s.exec_stack.flush();
+    });
+}
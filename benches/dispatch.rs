@@ -0,0 +1,76 @@
+//! Compares the cost of looking up instructions by name with different dispatch
+//! backends. Run with `cargo bench --bench dispatch` (add `--features fxhash` to
+//! measure the instruction set with the Fx hash function).
+use pushr::push::instructions::InstructionSet;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rustc_hash::FxHashMap;
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::Instant;
+
+const DISPATCHES: usize = 2_000_000;
+
+fn increment(counter: &mut u64) {
+    *counter += 1;
+}
+
+/// Runs the dispatch function for each program position and prints the mean time
+/// per dispatch.
+fn measure<F: FnMut(usize, &mut u64)>(label: &str, mut dispatch: F) {
+    let mut counter = 0;
+    let start = Instant::now();
+    for i in 0..DISPATCHES {
+        dispatch(i, &mut counter);
+    }
+    let elapsed = start.elapsed();
+    black_box(counter);
+    println!(
+        "{:<28} {:>8.2} ns/dispatch",
+        label,
+        elapsed.as_nanos() as f64 / DISPATCHES as f64
+    );
+}
+
+fn main() {
+    let mut instruction_set = InstructionSet::new();
+    instruction_set.load();
+    let mut names: Vec<String> = instruction_set.cache().list.to_vec();
+    names.sort();
+    // Random program of instruction names, as generated by CODE.RAND
+    let mut rng = ChaCha8Rng::seed_from_u64(495);
+    let ids: Vec<usize> = (0..4096).map(|_| rng.gen_range(0..names.len())).collect();
+    let program: Vec<&String> = ids.iter().map(|id| &names[*id]).collect();
+    let n = program.len();
+
+    let std_map: HashMap<String, fn(&mut u64)> =
+        names.iter().map(|name| (name.clone(), increment as fn(&mut u64))).collect();
+    let fx_map: FxHashMap<String, fn(&mut u64)> =
+        names.iter().map(|name| (name.clone(), increment as fn(&mut u64))).collect();
+    let table: Vec<fn(&mut u64)> = names.iter().map(|_| increment as fn(&mut u64)).collect();
+
+    println!("{} instructions, {} dispatches", names.len(), DISPATCHES);
+    measure("HashMap<String> (SipHash)", |i, counter| {
+        (std_map[black_box(program[i % n])])(counter)
+    });
+    measure("FxHashMap<String>", |i, counter| {
+        (fx_map[black_box(program[i % n])])(counter)
+    });
+    measure("Vec indexed by ID", |i, counter| {
+        (table[black_box(ids[i % n])])(counter)
+    });
+    measure("InstructionSet", |i, counter| {
+        if instruction_set.get_instruction(black_box(program[i % n])).is_some() {
+            *counter += 1;
+        }
+    });
+    let interned: Vec<usize> = program
+        .iter()
+        .filter_map(|name| instruction_set.instruction_id(name))
+        .collect();
+    measure("InstructionSet by ID", |i, counter| {
+        if instruction_set.get_instruction_by_id(black_box(interned[i % n])).is_some() {
+            *counter += 1;
+        }
+    });
+}
@@ -0,0 +1,67 @@
+//! Measures read-only vector instructions on large vectors. The instructions read the
+//! items through views of the stack, the copy of the item is measured for comparison.
+//! Run with `cargo bench --bench vector_views`.
+use pushr::push::instructions::InstructionCache;
+use pushr::push::state::PushState;
+use pushr::push::vector::*;
+use std::hint::black_box;
+use std::time::Instant;
+
+const SIZE: usize = 100_000;
+const REPETITIONS: usize = 200;
+
+/// Runs the function on the state and prints the mean time per call.
+fn measure<F: FnMut(&mut PushState)>(label: &str, push_state: &mut PushState, mut run: F) {
+    let start = Instant::now();
+    for _ in 0..REPETITIONS {
+        run(push_state);
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<28} {:>10.1} us/call",
+        label,
+        elapsed.as_micros() as f64 / REPETITIONS as f64
+    );
+}
+
+fn main() {
+    let icache = InstructionCache::new(vec![]);
+    let mut push_state = PushState::new();
+    push_state.configuration.max_vector_size = SIZE;
+    let values: Vec<f32> = (0..SIZE).map(|i| i as f32).collect();
+    push_state
+        .float_vector_stack
+        .push(FloatVector::new(values.clone()));
+    push_state
+        .int_vector_stack
+        .push(IntVector::new((0..SIZE as i32).collect()));
+    push_state
+        .bool_vector_stack
+        .push(BoolVector::new(vec![true; SIZE]));
+
+    println!("{} elements, {} calls", SIZE, REPETITIONS);
+    measure("copy FLOATVECTOR", &mut push_state, |s| {
+        black_box(s.float_vector_stack.copy(0));
+    });
+    measure("FLOATVECTOR.SUM", &mut push_state, |s| {
+        float_vector_sum(s, &icache);
+        black_box(s.float_stack.pop());
+    });
+    measure("FLOATVECTOR.MEAN", &mut push_state, |s| {
+        float_vector_mean(s, &icache);
+        black_box(s.float_stack.pop());
+    });
+    measure("INTVECTOR.SUM", &mut push_state, |s| {
+        int_vector_sum(s, &icache);
+        black_box(s.int_stack.pop());
+    });
+    measure("BOOLVECTOR.COUNT", &mut push_state, |s| {
+        bool_vector_count(s, &icache);
+        black_box(s.int_stack.pop());
+    });
+    measure("FLOATVECTOR.+PAD", &mut push_state, |s| {
+        s.float_vector_stack.push(FloatVector::new(values.clone()));
+        s.float_stack.push(0.0);
+        float_vector_add_pad(s, &icache);
+    });
+}
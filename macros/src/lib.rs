@@ -0,0 +1,175 @@
+//! Compile-time parsing of Push programs for pushr.
+//!
+//! ```ignore
+//! use pushr_macros::push_program;
+//!
+//! let program = push_program!("( 2 3 INTEGER.+ )");
+//! push_state.exec_stack.push(program);
+//! ```
+extern crate proc_macro;
+
+use proc_macro::{TokenStream, TokenTree};
+use pushr::push::instructions::InstructionSet;
+use pushr::push::item::{Item, PushType};
+use pushr::push::parser::PushParser;
+use pushr::push::state::PushState;
+
+/// Parses the Push program in the string literal at compile time and expands to an
+/// expression that constructs the parsed Item, i.e. the item that
+/// PushParser::parse_program pushes onto the EXEC stack. A program with several
+/// top-level items is wrapped in a list. Instruction names are resolved against the
+/// default instruction set. Syntax errors (see PushParser::check_program) are reported
+/// as compile errors.
+#[proc_macro]
+pub fn push_program(input: TokenStream) -> TokenStream {
+    let expansion = match string_literal(input) {
+        Some(code) => expand(&code).unwrap_or_else(|e| format!("compile_error!({:?})", e)),
+        None => "compile_error!(\"push_program! expects a string literal\")".to_string(),
+    };
+    expansion.parse().unwrap()
+}
+
+/// Returns the value of the string literal that is the only token of the input.
+fn string_literal(input: TokenStream) -> Option<String> {
+    let mut tokens = input.into_iter();
+    let literal = match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Literal(literal)), None) => literal.to_string(),
+        _ => return None,
+    };
+    if let Some(raw) = literal.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        return raw
+            .get(hashes + 1..raw.len() - hashes - 1)
+            .map(|s| s.to_string());
+    }
+    let quoted = literal.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => value.push('\n'),
+            't' => value.push('\t'),
+            'r' => value.push('\r'),
+            '0' => value.push('\0'),
+            '\n' => {
+                // Line continuation: skip the leading whitespace of the next line
+                let rest: String = chars.clone().collect();
+                let trimmed = rest.trim_start();
+                for _ in 0..rest.chars().count() - trimmed.chars().count() {
+                    chars.next();
+                }
+            }
+            escaped => value.push(escaped),
+        }
+    }
+    Some(value)
+}
+
+/// Parses the program and returns the Rust expression that constructs it or the
+/// rendered syntax errors.
+fn expand(code: &str) -> Result<String, String> {
+    let errors = PushParser::check_program(code);
+    if !errors.is_empty() {
+        return Err(errors
+            .iter()
+            .map(|e| {
+                // compile_error! adds its own "error:" label
+                let diagnostic = e.render_diagnostic(code);
+                diagnostic
+                    .strip_prefix("error: ")
+                    .map_or(diagnostic.clone(), |d| d.to_string())
+            })
+            .collect::<Vec<String>>()
+            .join("\n"));
+    }
+    let mut instruction_set = InstructionSet::new();
+    instruction_set.load();
+    let mut push_state = PushState::new();
+    PushParser::parse_program(&mut push_state, &instruction_set, code);
+    let item = if push_state.exec_stack.size() == 1 {
+        push_state.exec_stack.pop().unwrap()
+    } else {
+        Item::List {
+            items: push_state.exec_stack,
+        }
+    };
+    item_expression(&item)
+}
+
+/// Returns the Rust expression that constructs the item.
+fn item_expression(item: &Item) -> Result<String, String> {
+    let item_path = "::pushr::push::item::Item";
+    let vector_path = "::pushr::push::vector";
+    let floats = |values: &[f32]| {
+        values
+            .iter()
+            .map(|f| format!("f32::from_bits({:#x})", f.to_bits()))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    Ok(match item {
+        Item::List { items } => {
+            let children: Result<Vec<String>, String> =
+                items.as_slice().iter().map(item_expression).collect();
+            format!("{}::list(vec![{}])", item_path, children?.join(", "))
+        }
+        Item::InstructionMeta { name, .. } => {
+            format!("{}::instruction(String::from({:?}))", item_path, name)
+        }
+        Item::Identifier { name } => format!("{}::id(String::from({:?}))", item_path, name),
+        Item::Name { name } => format!("{}::quoted_name(String::from({:?}))", item_path, name),
+        Item::Literal { push_type } => match push_type {
+            PushType::Bool { val } => format!("{}::bool({})", item_path, val),
+            PushType::Int { val } => format!("{}::int({}i32)", item_path, val),
+            PushType::Float { val } => format!("{}::float({})", item_path, floats(&[*val])),
+            PushType::Char { val } => format!("{}::char({:?})", item_path, val),
+            PushType::BoolVector { val } => format!(
+                "{}::boolvec({}::BoolVector::new(vec![{}]))",
+                item_path,
+                vector_path,
+                val.bits
+                    .iter()
+                    .map(|b| b.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            PushType::IntVector { val } => format!(
+                "{}::intvec({}::IntVector::new(vec![{}]))",
+                item_path,
+                vector_path,
+                val.values
+                    .iter()
+                    .map(|i| format!("{}i32", i))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            PushType::FloatVector { val } => format!(
+                "{}::floatvec({}::FloatVector::new(vec![{}]))",
+                item_path,
+                vector_path,
+                floats(&val.values)
+            ),
+            _ => return Err(format!("unsupported literal {} in push_program!", item)),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syntax_errors_are_rendered() {
+        let error = expand("( 1 2 ) )").unwrap_err();
+        assert!(error.starts_with("unmatched closing parenthesis"));
+        assert_eq!(
+            expand("( 1 ) 2").unwrap(),
+            "::pushr::push::item::Item::list(vec![::pushr::push::item::Item::int(2i32), \
+             ::pushr::push::item::Item::list(vec![::pushr::push::item::Item::int(1i32)])])"
+        );
+    }
+}
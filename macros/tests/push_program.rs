@@ -0,0 +1,32 @@
+use pushr::push::instructions::InstructionSet;
+use pushr::push::interpreter::PushInterpreter;
+use pushr::push::item::Item;
+use pushr::push::parser::PushParser;
+use pushr::push::state::PushState;
+use pushr_macros::push_program;
+
+#[test]
+fn embedded_program_matches_parsed_program() {
+    let code = "( 2 3 INTEGER.* 1.1 FLOAT.DUP \\X 'Y foo INT[1,-2] FLOAT[0.5,2.25] BOOL[1,0] TRUE ( ) )";
+    let embedded = push_program!(
+        "( 2 3 INTEGER.* 1.1 FLOAT.DUP \\X 'Y foo INT[1,-2] FLOAT[0.5,2.25] BOOL[1,0] TRUE ( ) )"
+    );
+    let mut push_state = PushState::new();
+    let mut instruction_set = InstructionSet::new();
+    instruction_set.load();
+    PushParser::parse_program(&mut push_state, &instruction_set, code);
+    // PartialEq of items is a shallow comparison
+    assert!(Item::equals(&embedded, push_state.exec_stack.get(0).unwrap()));
+}
+
+#[test]
+fn embedded_program_is_executed() {
+    let mut push_state = PushState::new();
+    let mut instruction_set = InstructionSet::new();
+    instruction_set.load();
+    push_state
+        .exec_stack
+        .push(push_program!(r#"( 1 2 INTEGER.+ 4 INTEGER.* )"#));
+    PushInterpreter::run(&mut push_state, &mut instruction_set);
+    assert_eq!(push_state.int_stack.to_string(), "12");
+}
@@ -1,44 +1,583 @@
 use std::env;
+use std::fs;
+use std::io::{self, BufRead};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use pushr::push::instructions::InstructionSet;
-use pushr::push::interpreter::PushInterpreter;
+use pushr::push::differential::{DifferentialResult, DifferentialRun, Profile};
+use pushr::push::experiment::ExperimentConfig;
+use pushr::push::extract::{self, Selector};
+use pushr::push::fixture::Fixture;
+use pushr::push::interpreter::{Breakpoint, PushInterpreter, PushInterpreterState, RunResult};
 use pushr::push::parser::PushParser;
-use pushr::push::state::PushState;
+use pushr::push::runner::Runner;
+use pushr::push::serialize::SerializeOptions;
+use pushr::push::state::{self, PushState, STACK_NAMES};
 use pushr::push::item::Item;
+use serde_json::{json, Value};
+
+/// Format of the final output.
+#[derive(PartialEq)]
+enum OutputFormat {
+    Pretty,
+    Json,
+    Csv,
+}
+
+fn print_stacks(push_state: &PushState) {
+    println!("> EXEC  : {}", push_state.exec_stack.to_string());
+    println!("> CODE  : {}", push_state.code_stack.to_string());
+    println!("> INT   : {}", push_state.int_stack.to_string());
+    println!("> ------------ ");
+}
+
+const DEBUG_HELP: &str = "\
+> s [N]        : execute 1 (or N) steps, stops at breakpoints (ENTER = s)
+> c            : continue until the end or the next breakpoint
+> b            : step back
+> p <stack>    : print a stack, e.g. p float
+> break [NAME] : set a breakpoint on an instruction or list the breakpoints
+> delete NAME  : remove the breakpoint
+> names [NAME] : print the name bindings or the item bound to NAME
+> q            : quit";
+
+/// Prints the items bound to the names, sorted by name.
+fn print_bindings(push_state: &PushState, name: Option<&str>) {
+    let mut names: Vec<&String> = push_state
+        .name_bindings
+        .keys()
+        .filter(|n| name.map_or(true, |name| *n == name))
+        .collect();
+    names.sort();
+    if names.is_empty() {
+        println!("> No bindings");
+    }
+    for name in names {
+        if let Some(item) = push_state.name_bindings.get(name) {
+            println!("> {} = {}", name, item);
+        }
+    }
+}
+
+/// Interactive debugger that reads commands from stdin (see DEBUG_HELP).
+fn debug(runner: &mut Runner) {
+    let mut breakpoints: Vec<String> = vec![];
+    print_stacks(&runner.push_state);
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line.unwrap_or_default();
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("s");
+        let argument = words.next();
+        let max_steps = match command {
+            "s" => match argument.map(|a| a.parse::<usize>()) {
+                None => 1,
+                Some(Ok(n)) => n,
+                Some(Err(_)) => {
+                    println!("> Invalid number of steps {}", argument.unwrap_or_default());
+                    continue;
+                }
+            },
+            "c" => usize::MAX,
+            "b" => {
+                if !runner.step_back() {
+                    println!("> No history left");
+                }
+                0
+            }
+            "p" => {
+                match argument {
+                    Some(name) => match state::stack_id(name) {
+                        Some(stack_id) => println!(
+                            "> {:<6}: {}",
+                            name.to_uppercase(),
+                            runner.push_state.stack_to_string(stack_id).unwrap_or_default()
+                        ),
+                        None => println!("> Unknown stack {}", name),
+                    },
+                    None => print_stacks(&runner.push_state),
+                }
+                continue;
+            }
+            "break" => {
+                match argument {
+                    Some(name) if runner.instruction_set.is_instruction(name) => {
+                        if !breakpoints.iter().any(|b| b == name) {
+                            breakpoints.push(name.to_string());
+                        }
+                    }
+                    Some(name) => println!("> Unknown instruction {}", name),
+                    None => println!("> Breakpoints: {}", breakpoints.join(" ")),
+                }
+                continue;
+            }
+            "delete" => {
+                breakpoints.retain(|b| Some(b.as_str()) != argument);
+                continue;
+            }
+            "names" => {
+                print_bindings(&runner.push_state, argument);
+                continue;
+            }
+            "q" => break,
+            _ => {
+                println!("{}", DEBUG_HELP);
+                continue;
+            }
+        };
+        if max_steps > 0 {
+            let instruction_breakpoints: Vec<Breakpoint> =
+                breakpoints.iter().map(|b| Breakpoint::Instruction(b.clone())).collect();
+            runner.run_steps(max_steps, &instruction_breakpoints);
+            if let Some(Item::InstructionMeta { name, .. }) = runner.push_state.exec_stack.get(0) {
+                if breakpoints.contains(name) {
+                    println!("> BREAK : {}", name);
+                }
+            }
+        }
+        println!("> STEP  : {}", runner.step_counter());
+        print_stacks(&runner.push_state);
+        if runner.push_state.exec_stack.size() == 0 {
+            println!("> Execution finished");
+        }
+    }
+}
+
+/// Collects all --inject-on-step N "CODE" options as (step, code) pairs.
+fn injections(args: &[String]) -> Vec<(usize, String)> {
+    let mut injections = vec![];
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--inject-on-step" && i + 2 < args.len() {
+            if let Ok(step) = args[i + 1].parse::<usize>() {
+                injections.push((step, args[i + 2].clone()));
+            }
+            i += 2;
+        }
+        i += 1;
+    }
+    injections
+}
+
+/// Returns the value of the option, e.g. "json" for --output json.
+fn option_value<'a>(args: &'a [String], option: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == option)
+        .and_then(|i| args.get(i + 1))
+        .map(|v| v.as_str())
+}
+
+/// Exit code of the process for each termination reason. Exit code 1 is used for
+/// invalid command line options.
+fn exit_code(interpreter_state: &PushInterpreterState) -> i32 {
+    match interpreter_state {
+        PushInterpreterState::NoErrors => 0,
+        PushInterpreterState::StepLimitExceeded => 2,
+        PushInterpreterState::TimeLimitExceeded => 3,
+        PushInterpreterState::GrowthCapExceeded => 4,
+        PushInterpreterState::TerminatedByPredicate | PushInterpreterState::Paused => 5,
+        PushInterpreterState::ProgramTooLarge => 6,
+    }
+}
+
+/// Quotes a CSV field if necessary.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Prints the termination metadata and the selected stacks in the given format.
+fn print_result(
+    push_state: &PushState,
+    interpreter_state: &PushInterpreterState,
+    steps: usize,
+    elapsed: Duration,
+    format: &OutputFormat,
+    selection: &[(&str, i32)],
+) {
+    let termination = format!("{:?}", interpreter_state);
+    let code = exit_code(interpreter_state);
+    let elapsed_ms = elapsed.as_millis();
+    match format {
+        OutputFormat::Pretty => {
+            println!("> TERMINATION: {} (exit code {})", termination, code);
+            println!("> STEPS: {}, ELAPSED: {} ms", steps, elapsed_ms);
+            for (name, stack_id) in selection.iter() {
+                println!("> {:<11}: {}", name.to_uppercase(), push_state.stack_to_string(*stack_id).unwrap_or_default());
+            }
+        }
+        OutputFormat::Json => {
+            let stack_ids = selection.iter().map(|(_, id)| *id).collect();
+            let stacks: Value =
+                serde_json::from_str(&push_state.serialize(&SerializeOptions::stacks(stack_ids)))
+                    .unwrap_or(Value::Null);
+            let output = json!({
+                "termination": termination,
+                "exit_code": code,
+                "steps": steps,
+                "elapsed_ms": elapsed_ms as u64,
+                "stacks": stacks,
+            });
+            println!("{}", output);
+        }
+        OutputFormat::Csv => {
+            println!("key,value");
+            println!("termination,{}", termination);
+            println!("exit_code,{}", code);
+            println!("steps,{}", steps);
+            println!("elapsed_ms,{}", elapsed_ms);
+            for (name, stack_id) in selection.iter() {
+                let items = push_state.stack_to_string(*stack_id).unwrap_or_default();
+                println!("{},{}", name, csv_field(&items));
+            }
+        }
+    }
+}
+
+/// Prints the syntax errors of the program as diagnostics and exits if there are any.
+fn check_syntax(code: &str) {
+    let errors = PushParser::check_program(code);
+    if !errors.is_empty() {
+        for error in errors.iter() {
+            eprint!("{}", error.render_diagnostic(code));
+        }
+        process::exit(1);
+    }
+}
+
+/// Writes the program tree of the file to the output file (-o) or stdout in DOT format.
+fn visualize(args: &[String]) {
+    let path = args.get(2).unwrap_or_else(|| {
+        eprintln!("Usage: pushr viz <program.push> [-o <tree.dot>]");
+        process::exit(1);
+    });
+    let code = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        process::exit(1);
+    });
+    check_syntax(&code);
+    let mut push_state = PushState::new();
+    let mut instruction_set = InstructionSet::new();
+    instruction_set.load();
+    PushParser::parse_program(&mut push_state, &instruction_set, &code);
+    let mut items = push_state.exec_stack.pop_vec(push_state.exec_stack.size()).unwrap_or_default();
+    let program = if items.len() == 1 { items.pop().unwrap() } else { Item::list(items) };
+    match option_value(args, "-o") {
+        Some(output) => {
+            if let Err(e) = fs::write(output, program.to_dot()) {
+                eprintln!("{}: {}", output, e);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", program.to_dot()),
+    }
+}
+
+/// Checks the fixtures of all .pshtest files given as arguments. Exits with 1 if a
+/// fixture fails.
+fn run_fixtures(args: &[String]) {
+    if args.len() < 3 {
+        eprintln!("Usage: pushr test <spec.pshtest>...");
+        process::exit(1);
+    }
+    let mut instruction_set = InstructionSet::new();
+    instruction_set.load();
+    let (mut passed, mut failed) = (0, 0);
+    for path in args[2..].iter() {
+        let fixtures = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Fixture::parse(&text))
+            .unwrap_or_else(|e| {
+                eprintln!("{}: {}", path, e);
+                process::exit(1);
+            });
+        for fixture in fixtures.iter() {
+            check_syntax(&fixture.program);
+            let failures = fixture.check(&mut instruction_set);
+            if failures.is_empty() {
+                println!("test {} ... ok", fixture.name);
+                passed += 1;
+            } else {
+                println!("test {} ... FAILED", fixture.name);
+                for failure in failures {
+                    println!("    {}", failure);
+                }
+                failed += 1;
+            }
+        }
+    }
+    println!("{} passed; {} failed", passed, failed);
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+/// Runs the program under two semantics profiles in lockstep and dumps both states
+/// at the first divergence. Exits with 1 if the executions diverge.
+fn run_differential(args: &[String]) {
+    let code = args.get(2).unwrap_or_else(|| {
+        eprintln!("Usage: pushr diff <program> [--profiles pushr,clojush] [--steps N]");
+        process::exit(1);
+    });
+    check_syntax(code);
+    let names: Vec<&str> = option_value(args, "--profiles")
+        .unwrap_or("pushr,clojush")
+        .split(',')
+        .map(|n| n.trim())
+        .collect();
+    let profiles: Vec<Profile> = names
+        .iter()
+        .map(|name| {
+            Profile::named(name).unwrap_or_else(|| {
+                eprintln!("Unknown profile {} (expected pushr, clojush or strict)", name);
+                process::exit(1);
+            })
+        })
+        .collect();
+    if profiles.len() != 2 {
+        eprintln!("Expected two profiles, found {}", profiles.len());
+        process::exit(1);
+    }
+    let max_steps = option_value(args, "--steps")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(profiles[0].configuration.eval_push_limit as usize);
+    let mut instruction_set = InstructionSet::new();
+    instruction_set.load();
+    let run = DifferentialRun::new(profiles[0].clone(), profiles[1].clone());
+    match run.run(&mut instruction_set, code, max_steps) {
+        DifferentialResult::Agreed(steps) => println!("> NO DIVERGENCE ({} steps)", steps),
+        DifferentialResult::StepLimitExceeded(steps) => {
+            println!("> NO DIVERGENCE WITHIN {} STEPS", steps)
+        }
+        DifferentialResult::Diverged(divergence) => {
+            print!("{}", divergence);
+            process::exit(1);
+        }
+    }
+}
+
+/// Prints the termination of the run and the stacks that differ from the previous
+/// run. Without previous run all non-empty stacks are printed.
+fn print_changes(previous: Option<&RunResult>, result: &RunResult) {
+    println!("> TERMINATION: {:?}, STEPS: {}", result.termination, result.steps);
+    let mut changed = false;
+    for (name, items) in result.stacks.iter() {
+        match previous.and_then(|p| p.stack(name)) {
+            Some(previous_items) if previous_items == items => (),
+            Some(previous_items) => {
+                println!("- {:<11}: {}", name.to_uppercase(), previous_items);
+                println!("+ {:<11}: {}", name.to_uppercase(), items);
+                changed = true;
+            }
+            None if !items.is_empty() => {
+                println!("  {:<11}: {}", name.to_uppercase(), items);
+                changed = true;
+            }
+            None => (),
+        }
+    }
+    if !changed && previous.is_some() {
+        println!("> No changes");
+    }
+}
+
+/// Runs the program file each time it is modified and prints the changes of the final
+/// stacks or the syntax errors. Polls the modification time every --interval ms.
+fn watch(args: &[String]) {
+    let path = args.get(2).unwrap_or_else(|| {
+        eprintln!("Usage: pushr watch <program.push> [--interval MS]");
+        process::exit(1);
+    });
+    let interval = option_value(args, "--interval")
+        .and_then(|i| i.parse::<u64>().ok())
+        .unwrap_or(500);
+    let mut instruction_set = InstructionSet::new();
+    instruction_set.load();
+    let mut modified = None;
+    let mut previous: Option<RunResult> = None;
+    loop {
+        let current_modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        if current_modified.is_some() && current_modified != modified {
+            modified = current_modified;
+            println!("> ------------ {}", path);
+            match fs::read_to_string(path) {
+                Ok(code) => {
+                    let errors = PushParser::check_program(&code);
+                    if errors.is_empty() {
+                        let mut push_state = PushState::new();
+                        let result = PushInterpreter::run_program(&code, &mut push_state, &mut instruction_set);
+                        print_changes(previous.as_ref(), &result);
+                        previous = Some(result);
+                    } else {
+                        for error in errors.iter() {
+                            print!("{}", error.render_diagnostic(&code));
+                        }
+                    }
+                }
+                Err(e) => println!("{}: {}", path, e),
+            }
+        }
+        thread::sleep(Duration::from_millis(interval));
+    }
+}
 
 fn main() {
-    println!("> ------------------");
-    println!(">      PUSHR        ");
-    println!("> ------------------");
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map_or(false, |a| a == "viz") {
+        visualize(&args);
+        return;
+    }
+    if args.get(1).map_or(false, |a| a == "test") {
+        run_fixtures(&args);
+        return;
+    }
+    if args.get(1).map_or(false, |a| a == "watch") {
+        watch(&args);
+        return;
+    }
+    if args.get(1).map_or(false, |a| a == "diff") {
+        run_differential(&args);
+        return;
+    }
+    if args.get(1).map_or(false, |a| a == "schema") {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        println!("{}", instruction_set.to_schema_json());
+        return;
+    }
+    let format = match option_value(&args, "--output").unwrap_or("pretty") {
+        "pretty" => OutputFormat::Pretty,
+        "json" => OutputFormat::Json,
+        "csv" => OutputFormat::Csv,
+        other => {
+            eprintln!("Unknown output format {} (expected json, csv or pretty)", other);
+            process::exit(1);
+        }
+    };
+    let mut selection = vec![];
+    match option_value(&args, "--select") {
+        Some(names) => {
+            for name in names.split(',').map(|n| n.trim()) {
+                match state::stack_id(name) {
+                    Some(stack_id) => selection.push((name, stack_id)),
+                    None => {
+                        eprintln!("Unknown stack {}", name);
+                        process::exit(1);
+                    }
+                }
+            }
+        }
+        None => selection.extend(STACK_NAMES.iter().cloned()),
+    }
+    // Selected values are printed as JSON instead of the result
+    let selectors = option_value(&args, "--extract").map(|text| {
+        Selector::parse_list(text).unwrap_or_else(|e| {
+            eprintln!("Invalid extraction: {}", e);
+            process::exit(1);
+        })
+    });
+    let pretty = format == OutputFormat::Pretty && selectors.is_none();
+
+    if pretty {
+        println!("> ------------------");
+        println!(">      PUSHR        ");
+        println!("> ------------------");
+    }
 
-    let args: Vec<String> = env::args().collect(); 
     if args.len() < 2 {
         println!("No input ... Done");
         return;
     }
-    let input = &args[1]; 
-    println!("Input = {}", input);
-
-    let mut push_state = PushState::new();
-    let mut instruction_set = InstructionSet::new();
+    let (mut push_state, mut instruction_set) = if args[1] == "run" {
+        // Experiment described by a configuration file
+        let path = option_value(&args, "--config").unwrap_or_else(|| {
+            eprintln!("Usage: pushr run --config <experiment.toml>");
+            process::exit(1);
+        });
+        let experiment = ExperimentConfig::load(path).unwrap_or_else(|e| {
+            eprintln!("Invalid experiment configuration: {}", e);
+            process::exit(1);
+        });
+        if pretty {
+            println!("Config = {}", path);
+        }
+        if let Some(program) = &experiment.program {
+            check_syntax(program);
+        }
+        let instruction_set = experiment.instruction_set();
+        (experiment.push_state(&instruction_set), instruction_set)
+    } else {
+        let input = &args[1];
+        if pretty {
+            println!("Input = {}", input);
+        }
+        check_syntax(input);
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, input);
+        (push_state, instruction_set)
+    };
     let instruction_cache = instruction_set.cache();
 
     // Load program
-    instruction_set.load();
-    PushParser::parse_program(&mut push_state, &instruction_set, &input);
     PushParser::copy_to_code_stack(&mut push_state);
 
-    // Inject interpreter binary 
-    push_state.name_bindings.insert("BIN".to_string(), Item::id(args[0].clone())); 
+    // Inject interpreter binary
+    push_state.bind("BIN".to_string(), Item::quoted_name(args[0].clone()));
 
-    loop {
-        println!("> EXEC  : {}", push_state.exec_stack.to_string());
-        println!("> CODE  : {}", push_state.code_stack.to_string());
-        println!("> INT   : {}", push_state.int_stack.to_string());
-        println!("> ------------ ");
+    if args.iter().any(|a| a == "--debug") {
+        let mut runner = Runner::new(push_state, instruction_set);
+        println!("{}", DEBUG_HELP);
+        debug(&mut runner);
+        println!("Done.");
+        return;
+    }
+
+    // Prints the stacks before each step
+    let trace = args.iter().any(|a| a == "--trace");
+    let injections = injections(&args);
+    let mut step_counter = 0;
+    let start = Instant::now();
+    let interpreter_state = loop {
+        for (_, code) in injections.iter().filter(|(step, _)| *step == step_counter) {
+            let mut injected_state = PushState::new();
+            PushParser::parse_program(&mut injected_state, &instruction_set, code);
+            if let Some(item) = injected_state.exec_stack.pop() {
+                if PushInterpreter::inject(&mut push_state, item) && pretty {
+                    println!("> INJECT: {}", code);
+                }
+            }
+        }
+        if step_counter > push_state.configuration.eval_push_limit as usize {
+            break PushInterpreterState::StepLimitExceeded;
+        }
+        if start.elapsed() > Duration::from_millis(push_state.configuration.eval_time_limit) {
+            break PushInterpreterState::TimeLimitExceeded;
+        }
+        if pretty && trace {
+            print_stacks(&push_state);
+        }
+        let size_before_step = push_state.size();
         if PushInterpreter::step(&mut push_state, &mut instruction_set, &instruction_cache) {
-            break;
-       }
+            break PushInterpreterState::NoErrors;
+        }
+        step_counter += 1;
+        if push_state.size() > size_before_step + push_state.configuration.growth_cap {
+            break PushInterpreterState::GrowthCapExceeded;
+        }
+    };
+    match &selectors {
+        Some(selectors) => println!("{}", extract::extract(&push_state, selectors)),
+        None => print_result(&push_state, &interpreter_state, step_counter, start.elapsed(), &format, &selection),
+    }
+    if pretty {
+        println!("Done.");
     }
-    println!("Done.");
+    process::exit(exit_code(&interpreter_state));
 }
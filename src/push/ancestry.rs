@@ -0,0 +1,114 @@
+use crate::push::item::Item;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Describes the variation event that produced a program. Programs are identified by
+/// their structural hash (see Item::hash_structural).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ancestry {
+    // Hashes of the parent programs
+    pub parents: Vec<u64>,
+    // Name of the variation operator, e.g. "crossover"
+    pub operator: String,
+    // Positions in the parents where the operator cut or modified the code
+    pub crossover_points: Vec<usize>,
+}
+
+impl Ancestry {
+    pub fn new(parents: &[&Item], operator: &str, crossover_points: Vec<usize>) -> Self {
+        Self {
+            parents: parents.iter().map(|p| p.hash_structural()).collect(),
+            operator: operator.to_string(),
+            crossover_points,
+        }
+    }
+}
+
+/// Records the ancestry of the programs created by variation operators so that
+/// lineages can be analyzed without external bookkeeping.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Lineage {
+    records: HashMap<u64, Ancestry>,
+}
+
+impl Lineage {
+    pub fn new() -> Self {
+        Self {
+            records: HashMap::new(),
+        }
+    }
+
+    /// Attaches the ancestry to the child program. Returns the hash of the child. A
+    /// child that is a copy of one of its parents is not recorded, so that the ancestry
+    /// of the parent is kept.
+    pub fn record(&mut self, child: &Item, ancestry: Ancestry) -> u64 {
+        let hash = child.hash_structural();
+        if !ancestry.parents.contains(&hash) {
+            self.records.insert(hash, ancestry);
+        }
+        hash
+    }
+
+    /// Returns the ancestry of the program if it was created by a variation operator.
+    pub fn ancestry(&self, program: &Item) -> Option<&Ancestry> {
+        self.records.get(&program.hash_structural())
+    }
+
+    /// Returns the ancestry of the program with the given hash.
+    pub fn ancestry_by_hash(&self, hash: u64) -> Option<&Ancestry> {
+        self.records.get(&hash)
+    }
+
+    /// Returns the hashes of all known ancestors of the program in breadth first order.
+    pub fn ancestors(&self, program: &Item) -> Vec<u64> {
+        let mut ancestors = vec![];
+        let mut visited = HashSet::new();
+        let mut queue = vec![program.hash_structural()];
+        while !queue.is_empty() {
+            let hash = queue.remove(0);
+            if let Some(ancestry) = self.records.get(&hash) {
+                for parent in ancestry.parents.iter() {
+                    if visited.insert(*parent) {
+                        ancestors.push(*parent);
+                        queue.push(*parent);
+                    }
+                }
+            }
+        }
+        ancestors
+    }
+
+    /// Returns the number of recorded variation events.
+    pub fn size(&self) -> usize {
+        self.records.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lineage_tracks_ancestors_over_generations() {
+        let a = Item::list(vec![Item::int(1)]);
+        let b = Item::list(vec![Item::int(2)]);
+        let c = Item::list(vec![Item::int(1), Item::int(2)]);
+        let d = Item::list(vec![Item::int(3), Item::int(2)]);
+        let mut lineage = Lineage::new();
+        lineage.record(&c, Ancestry::new(&[&a, &b], "crossover", vec![0, 1]));
+        lineage.record(&d, Ancestry::new(&[&c], "mutation", vec![1]));
+        let ancestry = lineage.ancestry(&c).unwrap();
+        assert_eq!(ancestry.operator, "crossover");
+        assert_eq!(ancestry.parents, vec![a.hash_structural(), b.hash_structural()]);
+        assert_eq!(ancestry.crossover_points, vec![0, 1]);
+        assert_eq!(
+            lineage.ancestors(&d),
+            vec![c.hash_structural(), a.hash_structural(), b.hash_structural()]
+        );
+        assert!(lineage.ancestry(&a).is_none());
+        // Copies of a parent do not replace its ancestry
+        lineage.record(&c, Ancestry::new(&[&c], "reproduction", vec![]));
+        assert_eq!(lineage.ancestry(&c).unwrap().operator, "crossover");
+        assert_eq!(lineage.size(), 2);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::push::hash::StructuralHasher;
+use crate::push::serialize::SerializeOptions;
+use crate::push::state::PushState;
+use std::hash::Hasher;
+
+/// Returns a hash of the serialized stacks of the state. States with equal stack
+/// contents have equal signatures (see hash::StructuralHasher).
+pub fn stack_signature(push_state: &PushState, options: &SerializeOptions) -> u64 {
+    let mut hasher = StructuralHasher::default();
+    hasher.write(push_state.serialize(options).as_bytes());
+    hasher.finish()
+}
+
+/// Behavior descriptor of a program: the signature of the final state for each
+/// test case in the order of the cases.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Behavior {
+    pub signatures: Vec<u64>,
+}
+
+impl Behavior {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the signature of the final state of the next test case.
+    pub fn record(&mut self, push_state: &PushState, options: &SerializeOptions) {
+        self.signatures.push(stack_signature(push_state, options));
+    }
+
+    /// Returns the number of test cases on which the behaviors differ. Cases that
+    /// are missing in one of the descriptors count as different.
+    pub fn distance(&self, other: &Behavior) -> f64 {
+        let mismatches = self
+            .signatures
+            .iter()
+            .zip(other.signatures.iter())
+            .filter(|(a, b)| a != b)
+            .count();
+        let missing = self.signatures.len().abs_diff(other.signatures.len());
+        (mismatches + missing) as f64
+    }
+}
+
+/// Archive of behaviors for novelty search. The novelty of a behavior is the mean
+/// distance to its k nearest neighbors among the archived behaviors and the
+/// current population.
+#[derive(Clone, Debug)]
+pub struct NoveltyArchive {
+    pub k: usize,
+    // Minimum novelty for a behavior to be archived by add_if_novel
+    pub threshold: f64,
+    behaviors: Vec<Behavior>,
+}
+
+impl NoveltyArchive {
+    pub fn new(k: usize, threshold: f64) -> Self {
+        Self {
+            k,
+            threshold,
+            behaviors: vec![],
+        }
+    }
+
+    /// Returns the archived behaviors in insertion order.
+    pub fn behaviors(&self) -> &[Behavior] {
+        &self.behaviors
+    }
+
+    pub fn len(&self) -> usize {
+        self.behaviors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.behaviors.is_empty()
+    }
+
+    /// Adds the behavior to the archive.
+    pub fn add(&mut self, behavior: Behavior) {
+        self.behaviors.push(behavior);
+    }
+
+    /// Adds the behavior if its novelty reaches the threshold. Returns true if
+    /// the behavior was archived.
+    pub fn add_if_novel(&mut self, behavior: Behavior, novelty: f64) -> bool {
+        if novelty >= self.threshold {
+            self.add(behavior);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the mean distance of the behavior to its k nearest neighbors among
+    /// the archive and the given neighbors. Returns 0 if there are no neighbors.
+    pub fn novelty(&self, behavior: &Behavior, neighbors: &[&Behavior]) -> f64 {
+        let mut distances: Vec<f64> = self
+            .behaviors
+            .iter()
+            .chain(neighbors.iter().copied())
+            .map(|other| behavior.distance(other))
+            .collect();
+        distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        distances.truncate(self.k);
+        if distances.is_empty() {
+            0.0
+        } else {
+            distances.iter().sum::<f64>() / distances.len() as f64
+        }
+    }
+
+    /// Returns the novelty of each member of the population. The other members of
+    /// the population are used as neighbors in addition to the archive.
+    pub fn population_novelty(&self, population: &[Behavior]) -> Vec<f64> {
+        population
+            .iter()
+            .enumerate()
+            .map(|(i, behavior)| {
+                let neighbors: Vec<&Behavior> = population
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, other)| other)
+                    .collect();
+                self.novelty(behavior, &neighbors)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::state::INT_STACK_ID;
+
+    fn behavior(signatures: &[u64]) -> Behavior {
+        Behavior {
+            signatures: signatures.to_vec(),
+        }
+    }
+
+    #[test]
+    fn novelty_is_mean_distance_to_nearest_neighbors() {
+        let mut archive = NoveltyArchive::new(2, 1.5);
+        archive.add(behavior(&[1, 2, 3]));
+        let population = vec![
+            behavior(&[1, 2, 3]),
+            behavior(&[1, 2, 4]),
+            behavior(&[7, 8, 9]),
+        ];
+        let scores = archive.population_novelty(&population);
+        assert_eq!(scores, vec![0.5, 1.0, 3.0]);
+        assert!(!archive.add_if_novel(population[1].clone(), scores[1]));
+        assert!(archive.add_if_novel(population[2].clone(), scores[2]));
+        assert_eq!(archive.len(), 2);
+        assert_eq!(behavior(&[1]).distance(&behavior(&[1, 2])), 1.0);
+    }
+
+    #[test]
+    fn equal_stacks_have_equal_signatures() {
+        let options = SerializeOptions::stacks(vec![INT_STACK_ID]);
+        let mut a = PushState::new();
+        let mut b = PushState::new();
+        a.int_stack.push(3);
+        b.int_stack.push(3);
+        let mut behavior = Behavior::new();
+        behavior.record(&a, &options);
+        behavior.record(&b, &options);
+        b.int_stack.push(4);
+        behavior.record(&b, &options);
+        assert_eq!(behavior.signatures[0], behavior.signatures[1]);
+        assert_ne!(behavior.signatures[1], behavior.signatures[2]);
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// Sources of all modules that register instructions. They are embedded at compile
+/// time so that the instruction table can be audited without access to the source tree.
+const SOURCES: &[(&str, &str)] = &[
+    #[cfg(feature = "bignum")]
+    ("bigint.rs", include_str!("bigint.rs")),
+    ("bitmap.rs", include_str!("bitmap.rs")),
+    ("boolean.rs", include_str!("boolean.rs")),
+    ("char.rs", include_str!("char.rs")),
+    ("code.rs", include_str!("code.rs")),
+    ("constant.rs", include_str!("constant.rs")),
+    ("execution.rs", include_str!("execution.rs")),
+    ("float.rs", include_str!("float.rs")),
+    ("graph.rs", include_str!("graph.rs")),
+    ("index.rs", include_str!("index.rs")),
+    ("instructions.rs", include_str!("instructions.rs")),
+    ("integer.rs", include_str!("integer.rs")),
+    ("io.rs", include_str!("io.rs")),
+    ("list.rs", include_str!("list.rs")),
+    ("matrix.rs", include_str!("matrix.rs")),
+    ("name.rs", include_str!("name.rs")),
+    ("registers.rs", include_str!("registers.rs")),
+    ("string.rs", include_str!("string.rs")),
+    ("vector.rs", include_str!("vector.rs")),
+    ("vector_list.rs", include_str!("vector_list.rs")),
+];
+
+/// A registration of an instruction in one of the load functions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstructionTableEntry {
+    // Name under which the instruction is registered
+    pub name: String,
+    // Identifier of the function that implements the instruction
+    pub function: String,
+    // Instruction name at the beginning of the doc comment of the function
+    pub documented_as: Option<String>,
+    // Doc comment of the function with the lines joined by spaces
+    pub doc: Option<String>,
+    // Module that contains the registration
+    pub source: &'static str,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuditIssue {
+    // The doc comment of the function describes a different instruction
+    NameMismatch {
+        name: String,
+        function: String,
+        documented_as: String,
+    },
+    // The function has no doc comment that names the instruction
+    Undocumented {
+        name: String,
+        function: String,
+    },
+    // Several instructions are implemented by the same function
+    DuplicateTarget {
+        function: String,
+        names: Vec<String>,
+    },
+}
+
+impl fmt::Display for AuditIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuditIssue::NameMismatch {
+                name,
+                function,
+                documented_as,
+            } => write!(
+                f,
+                "{} is implemented by {} which is documented as {}",
+                name, function, documented_as
+            ),
+            AuditIssue::Undocumented { name, function } => {
+                write!(f, "{} is implemented by undocumented {}", name, function)
+            }
+            AuditIssue::DuplicateTarget { function, names } => {
+                write!(f, "{} implements {}", function, names.join(", "))
+            }
+        }
+    }
+}
+
+/// Returns the identifier at the beginning of the string.
+fn leading_identifier(s: &str) -> &str {
+    let end = s
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(s.len());
+    &s[..end]
+}
+
+/// Returns the (name, function) pairs of all instruction registrations of the source.
+/// Commented registrations are ignored.
+fn registrations(source: &str) -> Vec<(String, String)> {
+    let mut registrations = vec![];
+    let mut offset = 0;
+    while let Some(pos) = source[offset..].find(".insert(") {
+        let start = offset + pos;
+        offset = start + ".insert(".len();
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        if source[line_start..start].trim_start().starts_with("//") {
+            continue;
+        }
+        let rest = source[offset..].trim_start();
+        if let Some(rest) = rest.strip_prefix("String::from(\"") {
+            if let Some(name_end) = rest.find('"') {
+                let name = &rest[..name_end];
+                let rest = rest[name_end..].trim_start_matches(['"', ')']);
+                if let Some(rest) = rest
+                    .trim_start_matches(',')
+                    .trim_start()
+                    .strip_prefix("Instruction::new(")
+                {
+                    registrations.push((name.to_string(), leading_identifier(rest).to_string()));
+                }
+            }
+        }
+    }
+    registrations
+}
+
+/// Returns the instruction name at the beginning of a doc comment.
+fn documented_name(doc: &str) -> Option<String> {
+    doc.split_whitespace()
+        .next()
+        .map(|token| token.trim_end_matches(':').to_string())
+}
+
+/// Returns the doc comment of each function of the source.
+fn documented_functions(source: &str) -> HashMap<String, Option<String>> {
+    let mut functions = HashMap::new();
+    let lines: Vec<&str> = source.lines().map(|l| l.trim()).collect();
+    for (i, line) in lines.iter().enumerate() {
+        let signature = line
+            .trim_start_matches("pub ")
+            .trim_start_matches("pub(crate) ");
+        if let Some(rest) = signature.strip_prefix("fn ") {
+            let mut doc_lines = vec![];
+            let mut j = i;
+            while j > 0 && (lines[j - 1].starts_with("///") || lines[j - 1].starts_with("#[")) {
+                j -= 1;
+                let doc_line = lines[j].trim_start_matches('/').trim();
+                if lines[j].starts_with("///") && !doc_line.is_empty() {
+                    doc_lines.insert(0, doc_line);
+                }
+            }
+            let doc = if doc_lines.is_empty() {
+                None
+            } else {
+                Some(doc_lines.join(" "))
+            };
+            functions.insert(leading_identifier(rest).to_string(), doc);
+        }
+    }
+    functions
+}
+
+/// Returns all instruction registrations of the default instruction set together with
+/// the name documented by the implementing function.
+pub fn instruction_table() -> Vec<InstructionTableEntry> {
+    let mut table = vec![];
+    for (file, source) in SOURCES.iter() {
+        let functions = documented_functions(source);
+        for (name, function) in registrations(source) {
+            let doc = functions.get(&function).cloned().flatten();
+            table.push(InstructionTableEntry {
+                name,
+                function,
+                documented_as: doc.as_deref().and_then(documented_name),
+                doc,
+                source: file,
+            });
+        }
+    }
+    table.sort_by(|a, b| a.name.cmp(&b.name));
+    table
+}
+
+/// Cross-checks the instruction names against the identifiers and doc comments of the
+/// implementing functions.
+pub fn audit(table: &[InstructionTableEntry]) -> Vec<AuditIssue> {
+    let mut issues = vec![];
+    let mut targets: HashMap<&str, Vec<String>> = HashMap::new();
+    for entry in table.iter() {
+        targets
+            .entry(&entry.function)
+            .or_default()
+            .push(entry.name.clone());
+        match &entry.documented_as {
+            Some(documented_as) if *documented_as != entry.name => {
+                issues.push(AuditIssue::NameMismatch {
+                    name: entry.name.clone(),
+                    function: entry.function.clone(),
+                    documented_as: documented_as.clone(),
+                })
+            }
+            Some(_) => (),
+            None => issues.push(AuditIssue::Undocumented {
+                name: entry.name.clone(),
+                function: entry.function.clone(),
+            }),
+        }
+    }
+    let mut duplicates: Vec<(&str, Vec<String>)> = targets
+        .into_iter()
+        .filter(|(_, names)| names.len() > 1)
+        .collect();
+    duplicates.sort();
+    for (function, names) in duplicates {
+        issues.push(AuditIssue::DuplicateTarget {
+            function: function.to_string(),
+            names,
+        });
+    }
+    issues
+}
+
+/// Prints the instruction table with one instruction per line followed by the
+/// audit issues.
+pub fn report() -> String {
+    let table = instruction_table();
+    let mut report = String::new();
+    for entry in table.iter() {
+        report.push_str(&format!(
+            "{:<28} {:<36} {}\n",
+            entry.name, entry.function, entry.source
+        ));
+    }
+    for issue in audit(&table) {
+        report.push_str(&format!("ISSUE: {}\n", issue));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::InstructionSet;
+
+    #[test]
+    fn instruction_table_matches_instruction_set() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut loaded = instruction_set.names_with_prefix("");
+        loaded.sort();
+        let table: Vec<String> = instruction_table().into_iter().map(|e| e.name).collect();
+        assert_eq!(table, loaded);
+    }
+
+    #[test]
+    fn instruction_table_passes_audit() {
+        let issues: Vec<String> = audit(&instruction_table())
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        assert!(issues.is_empty(), "{}", issues.join("\n"));
+    }
+
+    #[test]
+    fn audit_detects_mismatches_and_duplicates() {
+        let source = "
+            map.insert(String::from(\"A.X\"), Instruction::new(a_x));
+            map.insert(
+                String::from(\"A.Y\"),
+                Instruction::new(a_x),
+            );
+            // map.insert(String::from(\"A.Z\"), Instruction::new(a_z));
+            /// A.Y: Does Y.
+            fn a_x() {}
+        ";
+        let functions = documented_functions(source);
+        let table: Vec<InstructionTableEntry> = registrations(source)
+            .into_iter()
+            .map(|(name, function)| InstructionTableEntry {
+                documented_as: functions
+                    .get(&function)
+                    .cloned()
+                    .flatten()
+                    .as_deref()
+                    .and_then(documented_name),
+                doc: functions.get(&function).cloned().flatten(),
+                name,
+                function,
+                source: "test.rs",
+            })
+            .collect();
+        assert_eq!(table.len(), 2);
+        assert_eq!(
+            audit(&table),
+            vec![
+                AuditIssue::NameMismatch {
+                    name: "A.X".to_string(),
+                    function: "a_x".to_string(),
+                    documented_as: "A.Y".to_string()
+                },
+                AuditIssue::DuplicateTarget {
+                    function: "a_x".to_string(),
+                    names: vec!["A.X".to_string(), "A.Y".to_string()]
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use crate::push::configuration::PushConfiguration;
+use crate::push::instructions::InstructionSet;
+use crate::push::interpreter::{PushInterpreter, PushInterpreterState};
+use crate::push::parser::PushParser;
+use crate::push::state::PushState;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Parameters for the evaluation of a batch of programs.
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    // Configuration applied to the state of each program
+    pub configuration: PushConfiguration,
+    // The program at index i is seeded with base_seed + i
+    pub base_seed: u64,
+    // Number of consecutive programs a worker claims at once
+    pub chunk_size: usize,
+    // Number of worker threads. A single thread evaluates the batch serially.
+    pub threads: usize,
+}
+
+impl BatchOptions {
+    pub fn new() -> Self {
+        Self {
+            configuration: PushConfiguration::new(),
+            base_seed: 0,
+            chunk_size: 16,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Outcome of a single program of the batch.
+#[derive(Debug)]
+pub struct BatchResult {
+    // Position of the program in the input
+    pub index: usize,
+    // Seed of the random number generator of the program
+    pub seed: u64,
+    pub interpreter_state: PushInterpreterState,
+    pub push_state: PushState,
+}
+
+/// Returns the seed of the program at the given position of the batch.
+pub fn batch_seed(base_seed: u64, index: usize) -> u64 {
+    base_seed.wrapping_add(index as u64)
+}
+
+fn run_program(
+    program: &str,
+    index: usize,
+    options: &BatchOptions,
+    instruction_set: &mut InstructionSet,
+) -> BatchResult {
+    let seed = batch_seed(options.base_seed, index);
+    let mut push_state = PushState::new();
+    push_state.configuration = options.configuration.clone();
+    push_state.seed(seed);
+    PushParser::parse_program(&mut push_state, instruction_set, program);
+    let interpreter_state = PushInterpreter::run(&mut push_state, instruction_set);
+    BatchResult {
+        index,
+        seed,
+        interpreter_state,
+        push_state,
+    }
+}
+
+/// Evaluates the programs on a pool of worker threads. Workers claim chunks of
+/// chunk_size consecutive programs until the batch is exhausted. Since every program
+/// runs on a fresh state seeded by its position, the results are returned in input
+/// order and are identical to a serial evaluation regardless of thread scheduling,
+/// as long as the programs only draw random numbers from the state's generator and
+/// do not hit the time limit of the configuration.
+pub fn run_batch(programs: &[String], options: &BatchOptions) -> Vec<BatchResult> {
+    let chunk_size = usize::max(options.chunk_size, 1);
+    let num_chunks = (programs.len() + chunk_size - 1) / chunk_size;
+    let threads = usize::min(usize::max(options.threads, 1), usize::max(num_chunks, 1));
+    let next_chunk = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(programs.len()));
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                let mut instruction_set = InstructionSet::new();
+                instruction_set.load();
+                loop {
+                    let chunk = next_chunk.fetch_add(1, Ordering::SeqCst);
+                    if chunk >= num_chunks {
+                        break;
+                    }
+                    let start = chunk * chunk_size;
+                    let end = usize::min(start + chunk_size, programs.len());
+                    let chunk_results: Vec<BatchResult> = (start..end)
+                        .map(|i| run_program(&programs[i], i, options, &mut instruction_set))
+                        .collect();
+                    results.lock().unwrap().extend(chunk_results);
+                }
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|r| r.index);
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::serialize::SerializeOptions;
+
+    fn programs() -> Vec<String> {
+        (0..23)
+            .map(|i| {
+                format!(
+                    "( 0.0 1.0 {} FLOATVECTOR.RAND*UNIFORM 2.5 5 INTVECTOR.RAND*POISSON {} INTEGER.* )",
+                    i + 1,
+                    i
+                )
+            })
+            .collect()
+    }
+
+    fn outcomes(results: &[BatchResult]) -> Vec<String> {
+        results
+            .iter()
+            .map(|r| r.push_state.serialize(&SerializeOptions::all()))
+            .collect()
+    }
+
+    #[test]
+    fn run_batch_preserves_input_order() {
+        let mut options = BatchOptions::new();
+        options.threads = 4;
+        options.chunk_size = 3;
+        let results = run_batch(&programs(), &options);
+        assert_eq!(results.len(), 23);
+        for (i, result) in results.iter().enumerate() {
+            assert_eq!(result.index, i);
+            assert_eq!(result.seed, batch_seed(options.base_seed, i));
+            assert_eq!(result.interpreter_state, PushInterpreterState::NoErrors);
+            // The program is copied to the CODE stack once
+            assert_eq!(result.push_state.code_stack.size(), 1);
+            assert!(result.push_state.code_stack.to_string().ends_with(&format!("{} INTEGER.* )", i)));
+        }
+    }
+
+    #[test]
+    fn parallel_and_serial_evaluations_are_identical() {
+        let mut options = BatchOptions::new();
+        options.base_seed = 42;
+        options.threads = 1;
+        let serial = outcomes(&run_batch(&programs(), &options));
+        for (threads, chunk_size) in [(2, 1), (4, 5), (8, 100)] {
+            options.threads = threads;
+            options.chunk_size = chunk_size;
+            assert_eq!(outcomes(&run_batch(&programs(), &options)), serial);
+        }
+        options.base_seed = 43;
+        assert_ne!(outcomes(&run_batch(&programs(), &options)), serial);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::push::instructions::Instruction;
+use crate::push::instructions::InstructionCache;
+use crate::push::stack::PushPrint;
+use crate::push::state::PushState;
+use num_bigint::BigInt;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+impl PushPrint for BigInt {
+    fn to_pstring(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Arbitrary precision integers. INTEGER.+, INTEGER.- and INTEGER.* push their exact
+/// result to the BIGINT stack if it does not fit into an INTEGER.
+pub fn load_bigint_instructions(map: &mut HashMap<String, Instruction>) {
+    map.insert(String::from("BIGINT.%"), Instruction::new(bigint_modulus));
+    map.insert(String::from("BIGINT.*"), Instruction::new(bigint_mult));
+    map.insert(String::from("BIGINT.+"), Instruction::new(bigint_add));
+    map.insert(String::from("BIGINT.-"), Instruction::new(bigint_subtract));
+    map.insert(String::from("BIGINT./"), Instruction::new(bigint_divide));
+    map.insert(String::from("BIGINT.<"), Instruction::new(bigint_smaller));
+    map.insert(String::from("BIGINT.="), Instruction::new(bigint_equal));
+    map.insert(String::from("BIGINT.>"), Instruction::new(bigint_greater));
+    map.insert(String::from("BIGINT.DUP"), Instruction::new(bigint_dup));
+    map.insert(String::from("BIGINT.FLUSH"), Instruction::new(bigint_flush));
+    map.insert(
+        String::from("BIGINT.FROMINTEGER"),
+        Instruction::new(bigint_from_integer),
+    );
+    map.insert(String::from("BIGINT.POP"), Instruction::new(bigint_pop));
+    map.insert(
+        String::from("BIGINT.STACKDEPTH"),
+        Instruction::new(bigint_stack_depth),
+    );
+    map.insert(String::from("BIGINT.SWAP"), Instruction::new(bigint_swap));
+    map.insert(
+        String::from("BIGINT.TOINTEGER"),
+        Instruction::new(bigint_to_integer),
+    );
+}
+
+/// BIGINT.%: Pushes the second item modulo the top item. Acts as NOOP if the top item is zero.
+fn bigint_modulus(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvals) = push_state.bigint_stack.pop_vec(2) {
+        if bvals[1] != BigInt::from(0) {
+            push_state.bigint_stack.push(&bvals[0] % &bvals[1]);
+        } else {
+            push_state.div_zero_count += 1;
+            push_state.bigint_stack.push_vec(bvals);
+        }
+    }
+}
+
+/// BIGINT.*: Pushes the product of the top two items.
+fn bigint_mult(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvals) = push_state.bigint_stack.pop_vec(2) {
+        push_state.bigint_stack.push(&bvals[0] * &bvals[1]);
+    }
+}
+
+/// BIGINT.+: Pushes the sum of the top two items.
+fn bigint_add(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvals) = push_state.bigint_stack.pop_vec(2) {
+        push_state.bigint_stack.push(&bvals[0] + &bvals[1]);
+    }
+}
+
+/// BIGINT.-: Pushes the difference of the top two items; that is, the second item minus the top
+/// item.
+fn bigint_subtract(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvals) = push_state.bigint_stack.pop_vec(2) {
+        push_state.bigint_stack.push(&bvals[0] - &bvals[1]);
+    }
+}
+
+/// BIGINT./: Pushes the quotient of the top two items; that is, the second item divided by the
+/// top item. Acts as NOOP if the top item is zero.
+fn bigint_divide(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvals) = push_state.bigint_stack.pop_vec(2) {
+        if bvals[1] != BigInt::from(0) {
+            push_state.bigint_stack.push(&bvals[0] / &bvals[1]);
+        } else {
+            push_state.div_zero_count += 1;
+            push_state.bigint_stack.push_vec(bvals);
+        }
+    }
+}
+
+/// BIGINT.<: Pushes TRUE onto the BOOLEAN stack if the second item is less than the top item,
+/// or FALSE otherwise.
+fn bigint_smaller(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvals) = push_state.bigint_stack.pop_vec(2) {
+        push_state.bool_stack.push(bvals[0] < bvals[1]);
+    }
+}
+
+/// BIGINT.=: Pushes TRUE onto the BOOLEAN stack if the top two items are equal, or FALSE
+/// otherwise.
+fn bigint_equal(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvals) = push_state.bigint_stack.pop_vec(2) {
+        push_state.bool_stack.push(bvals[0] == bvals[1]);
+    }
+}
+
+/// BIGINT.>: Pushes TRUE onto the BOOLEAN stack if the second item is greater than the top item,
+/// or FALSE otherwise.
+fn bigint_greater(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvals) = push_state.bigint_stack.pop_vec(2) {
+        push_state.bool_stack.push(bvals[0] > bvals[1]);
+    }
+}
+
+/// BIGINT.DUP: Duplicates the top item on the BIGINT stack. Does not pop its argument.
+fn bigint_dup(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bval) = push_state.bigint_stack.copy(0) {
+        push_state.bigint_stack.push(bval);
+    }
+}
+
+/// BIGINT.FLUSH: Empties the BIGINT stack.
+fn bigint_flush(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.bigint_stack.flush();
+}
+
+/// BIGINT.FROMINTEGER: Pushes the top INTEGER as BIGINT.
+fn bigint_from_integer(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(ival) = push_state.int_stack.pop() {
+        push_state.bigint_stack.push(BigInt::from(ival));
+    }
+}
+
+/// BIGINT.POP: Pops the BIGINT stack.
+fn bigint_pop(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.bigint_stack.pop();
+}
+
+/// BIGINT.STACKDEPTH: Pushes the stack depth onto the INTEGER stack.
+fn bigint_stack_depth(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state
+        .int_stack
+        .push(push_state.bigint_stack.size() as i32);
+}
+
+/// BIGINT.SWAP: Swaps the top two BIGINTs.
+fn bigint_swap(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.bigint_stack.shove(1);
+}
+
+/// BIGINT.TOINTEGER: Pushes the top BIGINT to the INTEGER stack. Acts as NOOP if the
+/// value does not fit into an INTEGER.
+fn bigint_to_integer(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bval) = push_state.bigint_stack.get(0) {
+        if let Ok(ival) = i32::try_from(bval) {
+            push_state.bigint_stack.pop();
+            push_state.int_stack.push(ival);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::interpreter::PushInterpreter;
+    use crate::push::parser::PushParser;
+
+    #[test]
+    fn integer_overflow_is_promoted_to_bigint() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut push_state = PushState::new();
+        PushParser::parse_program(
+            &mut push_state,
+            &instruction_set,
+            "( 65536 65536 INTEGER.* 3 BIGINT.FROMINTEGER BIGINT.* 99999999999999999999 BIGINT.+ )",
+        );
+        PushInterpreter::run(&mut push_state, &mut instruction_set);
+        assert_eq!(push_state.int_stack.size(), 0);
+        assert_eq!(push_state.bigint_stack.to_string(), "100000000012884901887");
+    }
+
+    #[test]
+    fn integer_division_overflow_is_promoted_to_bigint() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut push_state = PushState::new();
+        PushParser::parse_program(
+            &mut push_state,
+            &instruction_set,
+            "( -2147483648 -1 INTEGER./ -2147483648 -1 INTEGER.% )",
+        );
+        PushInterpreter::run(&mut push_state, &mut instruction_set);
+        assert_eq!(push_state.int_stack.to_string(), "0");
+        assert_eq!(push_state.bigint_stack.to_string(), "2147483648");
+    }
+
+    #[test]
+    fn bigint_to_integer_requires_fitting_value() {
+        let mut push_state = PushState::new();
+        push_state.bigint_stack.push(BigInt::from(i64::MAX));
+        bigint_to_integer(&mut push_state, &InstructionCache::new(vec![]));
+        assert_eq!(push_state.int_stack.size(), 0);
+        push_state.bigint_stack.push(BigInt::from(-7));
+        bigint_to_integer(&mut push_state, &InstructionCache::new(vec![]));
+        assert_eq!(push_state.int_stack.to_string(), "-7");
+        assert_eq!(push_state.bigint_stack.size(), 1);
+    }
+}
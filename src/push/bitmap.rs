@@ -0,0 +1,318 @@
+use crate::push::instructions::Instruction;
+use crate::push::instructions::InstructionCache;
+use crate::push::stack::PushPrint;
+use crate::push::state::PushState;
+use crate::push::state::*;
+use crate::push::vector::{BoolVector, IntVector};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Two-dimensional grid of booleans stored in row-major order, e.g. the cells
+/// of a cellular automaton. Neighborhoods wrap around the edges (torus).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bitmap {
+    pub rows: usize,
+    pub cols: usize,
+    pub values: Vec<bool>,
+}
+
+impl Bitmap {
+    /// Creates a bitmap where all cells are false.
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            values: vec![false; rows * cols],
+        }
+    }
+
+    /// Creates a bitmap with the given number of columns from the values in
+    /// row-major order. Returns None if the values do not fill the last row.
+    pub fn from_values(cols: usize, values: Vec<bool>) -> Option<Self> {
+        if cols == 0 || values.len() % cols != 0 {
+            return None;
+        }
+        Some(Self {
+            rows: values.len() / cols,
+            cols,
+            values,
+        })
+    }
+
+    /// Returns the cell at the given position or None if it is outside the bitmap.
+    pub fn get(&self, row: usize, col: usize) -> Option<bool> {
+        if row < self.rows && col < self.cols {
+            Some(self.values[row * self.cols + col])
+        } else {
+            None
+        }
+    }
+
+    /// Sets the cell at the given position. Returns false if it is outside the bitmap.
+    pub fn set(&mut self, row: usize, col: usize, value: bool) -> bool {
+        if row < self.rows && col < self.cols {
+            self.values[row * self.cols + col] = value;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the number of true cells in the Moore neighborhood of each cell in
+    /// row-major order.
+    pub fn neighbor_counts(&self) -> Vec<i32> {
+        let mut counts = vec![0; self.values.len()];
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                let mut count = 0;
+                for dr in [self.rows - 1, 0, 1] {
+                    for dc in [self.cols - 1, 0, 1] {
+                        if (dr != 0 || dc != 0)
+                            && self.values[((row + dr) % self.rows) * self.cols
+                                + (col + dc) % self.cols]
+                        {
+                            count += 1;
+                        }
+                    }
+                }
+                counts[row * self.cols + col] = count;
+            }
+        }
+        counts
+    }
+
+    /// Applies one generation of Conway's Game of Life (B3/S23).
+    pub fn step_life(&mut self) {
+        let counts = self.neighbor_counts();
+        for (cell, count) in self.values.iter_mut().zip(counts) {
+            *cell = count == 3 || (*cell && count == 2);
+        }
+    }
+}
+
+impl PushPrint for Bitmap {
+    fn to_pstring(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Bitmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<String> = self
+            .values
+            .chunks(usize::max(self.cols, 1))
+            .map(|row| row.iter().map(|b| if *b { '1' } else { '0' }).collect())
+            .collect();
+        write!(f, "[{}]", rows.join(","))
+    }
+}
+
+/// Two-dimensional boolean grids for cellular automata.
+pub fn load_bitmap_instructions(map: &mut HashMap<String, Instruction>) {
+    map.insert(String::from("BITMAP.DUP"), Instruction::new(bitmap_dup));
+    map.insert(String::from("BITMAP.FLUSH"), Instruction::new(bitmap_flush));
+    map.insert(
+        String::from("BITMAP.FROMBOOLVECTOR"),
+        Instruction::new(bitmap_from_bool_vector),
+    );
+    map.insert(String::from("BITMAP.GET"), Instruction::new(bitmap_get));
+    map.insert(String::from("BITMAP.ID"), Instruction::new(bitmap_id));
+    map.insert(
+        String::from("BITMAP.NEIGHBORS*COUNT"),
+        Instruction::new(bitmap_neighbors_count),
+    );
+    map.insert(String::from("BITMAP.POP"), Instruction::new(bitmap_pop));
+    map.insert(String::from("BITMAP.SET"), Instruction::new(bitmap_set));
+    map.insert(
+        String::from("BITMAP.STACKDEPTH"),
+        Instruction::new(bitmap_stack_depth),
+    );
+    map.insert(
+        String::from("BITMAP.STEP*LIFE"),
+        Instruction::new(bitmap_step_life),
+    );
+    map.insert(String::from("BITMAP.SWAP"), Instruction::new(bitmap_swap));
+    map.insert(
+        String::from("BITMAP.TOBOOLVECTOR"),
+        Instruction::new(bitmap_to_bool_vector),
+    );
+    map.insert(String::from("BITMAP.ZEROS"), Instruction::new(bitmap_zeros));
+}
+
+/// Pops the row (second item) and the column (top item) from the INTEGER stack.
+fn pop_position(push_state: &mut PushState) -> Option<(usize, usize)> {
+    let ivals = push_state.int_stack.pop_vec(2)?;
+    if ivals[0] < 0 || ivals[1] < 0 {
+        return None;
+    }
+    Some((ivals[0] as usize, ivals[1] as usize))
+}
+
+/// BITMAP.DUP: Duplicates the top item on the BITMAP stack. Does not pop its argument.
+fn bitmap_dup(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bitmap) = push_state.bitmap_stack.copy(0) {
+        push_state.bitmap_stack.push(bitmap);
+    }
+}
+
+/// BITMAP.FLUSH: Empties the BITMAP stack.
+fn bitmap_flush(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.bitmap_stack.flush();
+}
+
+/// BITMAP.FROMBOOLVECTOR: Pushes a BITMAP that contains the top BOOLVECTOR item in row-major
+/// order. The number of columns is taken from the INTEGER stack. Acts as NOOP if the size of the
+/// BOOLVECTOR is not a multiple of the number of columns.
+fn bitmap_from_bool_vector(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(cols) = push_state.int_stack.get(0) {
+        if let Some(bvval) = push_state.bool_vector_stack.get(0) {
+            if *cols > 0 {
+                if let Some(bitmap) = Bitmap::from_values(*cols as usize, bvval.to_bools()) {
+                    push_state.int_stack.pop();
+                    push_state.bool_vector_stack.pop();
+                    push_state.bitmap_stack.push(bitmap);
+                }
+            }
+        }
+    }
+}
+
+/// BITMAP.GET: Pushes the cell of the top BITMAP item to the BOOLEAN stack. The row and column
+/// are the second and the top item of the INTEGER stack. Acts as NOOP if the position is outside
+/// the bitmap.
+fn bitmap_get(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.bitmap_stack.size() > 0 {
+        if let Some((row, col)) = pop_position(push_state) {
+            if let Some(value) = push_state.bitmap_stack.get(0).unwrap().get(row, col) {
+                push_state.bool_stack.push(value);
+            }
+        }
+    }
+}
+
+/// BITMAP.ID: Pushes the ID of the BITMAP stack to the INTEGER stack.
+fn bitmap_id(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.int_stack.push(BITMAP_STACK_ID);
+}
+
+/// BITMAP.NEIGHBORS*COUNT: Pushes the number of TRUE cells in the Moore neighborhood of each cell
+/// of the top BITMAP item as INTVECTOR in row-major order. The neighborhood wraps around the
+/// edges of the bitmap.
+fn bitmap_neighbors_count(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bitmap) = push_state.bitmap_stack.get(0) {
+        let counts = bitmap.neighbor_counts();
+        push_state.int_vector_stack.push(IntVector::new(counts));
+    }
+}
+
+/// BITMAP.POP: Pops the BITMAP stack.
+fn bitmap_pop(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.bitmap_stack.pop();
+}
+
+/// BITMAP.SET: Sets the cell of the top BITMAP item to the top item of the BOOLEAN stack. The
+/// row and column are the second and the top item of the INTEGER stack. Acts as NOOP if the
+/// position is outside the bitmap.
+fn bitmap_set(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.bitmap_stack.size() > 0 && push_state.bool_stack.size() > 0 {
+        if let Some((row, col)) = pop_position(push_state) {
+            let value = push_state.bool_stack.get(0).cloned().unwrap();
+            if let Some(bitmap) = push_state.bitmap_stack.get_mut(0) {
+                if bitmap.set(row, col, value) {
+                    push_state.bool_stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// BITMAP.STACKDEPTH: Pushes the stack depth onto the INTEGER stack.
+fn bitmap_stack_depth(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state
+        .int_stack
+        .push(push_state.bitmap_stack.size() as i32);
+}
+
+/// BITMAP.STEP*LIFE: Applies one generation of Conway's Game of Life to the top BITMAP item. A
+/// cell is TRUE afterwards if it has three TRUE neighbors or if it is TRUE and has two TRUE
+/// neighbors.
+fn bitmap_step_life(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bitmap) = push_state.bitmap_stack.get_mut(0) {
+        bitmap.step_life();
+    }
+}
+
+/// BITMAP.SWAP: Swaps the top two BITMAPs.
+fn bitmap_swap(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.bitmap_stack.shove(1);
+}
+
+/// BITMAP.TOBOOLVECTOR: Pops the top BITMAP item and pushes its cells in row-major order to the
+/// BOOLVECTOR stack.
+fn bitmap_to_bool_vector(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bitmap) = push_state.bitmap_stack.pop() {
+        push_state
+            .bool_vector_stack
+            .push(BoolVector::new(bitmap.values));
+    }
+}
+
+/// BITMAP.ZEROS: Pushes a BITMAP where all cells are FALSE. The number of rows and columns are
+/// the second and the top item of the INTEGER stack. Acts as NOOP if the number of cells exceeds
+/// the maximum vector size.
+fn bitmap_zeros(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some((rows, cols)) = pop_position(push_state) {
+        if rows * cols <= push_state.configuration.max_vector_size {
+            push_state.bitmap_stack.push(Bitmap::zeros(rows, cols));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub fn icache() -> InstructionCache {
+        InstructionCache::new(vec![])
+    }
+
+    #[test]
+    fn bitmap_get_and_set_cells() {
+        let mut test_state = PushState::new();
+        test_state.int_stack.push_vec(vec![2, 3]);
+        bitmap_zeros(&mut test_state, &icache());
+        test_state.bool_stack.push(true);
+        test_state.int_stack.push_vec(vec![1, 2]);
+        bitmap_set(&mut test_state, &icache());
+        assert_eq!(test_state.bitmap_stack.to_string(), "[000,001]");
+        test_state.int_stack.push_vec(vec![1, 2]);
+        bitmap_get(&mut test_state, &icache());
+        assert_eq!(test_state.bool_stack.to_string(), "TRUE");
+        // Outside of the bitmap
+        test_state.int_stack.push_vec(vec![2, 0]);
+        bitmap_get(&mut test_state, &icache());
+        assert_eq!(test_state.bool_stack.size(), 1);
+    }
+
+    #[test]
+    fn bitmap_step_life_oscillates_blinker() {
+        let mut test_state = PushState::new();
+        let mut blinker = Bitmap::zeros(5, 5);
+        for col in 1..4 {
+            blinker.set(2, col, true);
+        }
+        test_state.bitmap_stack.push(blinker.clone());
+        bitmap_neighbors_count(&mut test_state, &icache());
+        let counts = test_state.int_vector_stack.pop().unwrap();
+        assert_eq!(counts.values[2 * 5 + 2], 2);
+        assert_eq!(counts.values[5 + 2], 3);
+        bitmap_step_life(&mut test_state, &icache());
+        assert_eq!(
+            test_state.bitmap_stack.get(0).unwrap().to_string(),
+            "[00000,00100,00100,00100,00000]"
+        );
+        bitmap_step_life(&mut test_state, &icache());
+        assert_eq!(*test_state.bitmap_stack.get(0).unwrap(), blinker);
+    }
+}
@@ -0,0 +1,360 @@
+use crate::push::vector::BoolVector;
+use std::convert::TryInto;
+use std::iter::FromIterator;
+use std::ops::Range;
+
+const WORD_BITS: usize = 64;
+// Multiplier that gathers the lowest bits of the 8 bytes of a word in its top byte
+const GATHER: u64 = 0x0102_0408_1020_4080;
+// Added to the masked bytes of a spread byte, sets bit 7 of byte k if bit k is set
+const SPREAD_CARRY: u64 = 0x0040_6070_787C_7E7F;
+
+/// Returns the number of words that hold len bits.
+fn word_count(len: usize) -> usize {
+    len / WORD_BITS + usize::from(len % WORD_BITS != 0)
+}
+
+/// Packs 8 bools into the low byte of a word. The bools are read as the bytes of a word and
+/// gathered by a single multiplication instead of shifting every bit.
+fn gather_byte(values: [bool; 8]) -> u64 {
+    u64::from_le_bytes(values.map(u8::from)).wrapping_mul(GATHER) >> 56
+}
+
+/// Packs up to 64 bools into a word.
+fn pack_word(values: &[bool]) -> u64 {
+    values.chunks(8).enumerate().fold(0, |word, (i, group)| {
+        let mut bytes = [false; 8];
+        bytes[..group.len()].copy_from_slice(group);
+        word | gather_byte(bytes) << (8 * i)
+    })
+}
+
+/// Packs exactly 64 bools into a word.
+fn pack_full_word(values: &[bool; WORD_BITS]) -> u64 {
+    let mut word = 0;
+    for (i, group) in values.chunks_exact(8).enumerate() {
+        word |= gather_byte(group.try_into().unwrap()) << (8 * i);
+    }
+    word
+}
+
+/// Unpacks the bits of a byte into 8 bools. The byte is copied to all bytes of a word, byte
+/// k is masked to bit k and moved to bit 0 by adding a carry.
+fn spread_byte(byte: u8) -> [bool; 8] {
+    let spread = (byte as u64 * 0x0101_0101_0101_0101) & 0x8040_2010_0804_0201;
+    let bits = ((spread + SPREAD_CARRY) >> 7) & 0x0101_0101_0101_0101;
+    bits.to_le_bytes().map(|b| b == 1)
+}
+
+/// Fixed-length set of bits packed into 64 bit words. It is the packed representation of a
+/// BOOLVECTOR for bitwise operations: AND, OR, XOR and the population count process 64
+/// elements at once. Bits beyond the length are always zero.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BitSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitSet {
+    /// Creates a bit set of the given length with all bits cleared.
+    pub fn zeros(len: usize) -> Self {
+        Self {
+            words: vec![0; word_count(len)],
+            len,
+        }
+    }
+
+    /// Creates a bit set of the given length with all bits set.
+    pub fn ones(len: usize) -> Self {
+        let mut bits = Self {
+            words: vec![u64::MAX; word_count(len)],
+            len,
+        };
+        bits.clear_unused();
+        bits
+    }
+
+    pub fn from_bools(values: &[bool]) -> Self {
+        let full_words = values.chunks_exact(WORD_BITS);
+        let rest = full_words.remainder();
+        let mut words: Vec<u64> = full_words
+            .map(|chunk| pack_full_word(chunk.try_into().unwrap()))
+            .collect();
+        if !rest.is_empty() {
+            words.push(pack_word(rest));
+        }
+        Self {
+            words,
+            len: values.len(),
+        }
+    }
+
+    pub fn to_bools(&self) -> Vec<bool> {
+        let mut values = Vec::with_capacity(self.words.len() * WORD_BITS);
+        for word in &self.words {
+            for byte in word.to_le_bytes() {
+                values.extend_from_slice(&spread_byte(byte));
+            }
+        }
+        values.truncate(self.len);
+        values
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the bit at the index or false if the index is out of range.
+    pub fn get(&self, index: usize) -> bool {
+        index < self.len && self.words[index / WORD_BITS] >> (index % WORD_BITS) & 1 == 1
+    }
+
+    /// Sets the bit at the index. Indices out of range are ignored.
+    pub fn set(&mut self, index: usize, value: bool) {
+        if index < self.len {
+            let mask = 1u64 << (index % WORD_BITS);
+            if value {
+                self.words[index / WORD_BITS] |= mask;
+            } else {
+                self.words[index / WORD_BITS] &= !mask;
+            }
+        }
+    }
+
+    /// Appends a bit.
+    pub fn push(&mut self, value: bool) {
+        if self.len % WORD_BITS == 0 {
+            self.words.push(0);
+        }
+        self.len += 1;
+        self.set(self.len - 1, value);
+    }
+
+    /// Shortens the set to the given length. Has no effect if the set is not longer.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            self.len = len;
+            self.words.truncate(word_count(len));
+            self.clear_unused();
+        }
+    }
+
+    /// Returns an iterator over the bits.
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(move |i| self.get(i))
+    }
+
+    /// Creates a bit set of the given length where the bits in the range are set.
+    /// The range is limited to the length.
+    pub fn range(len: usize, range: Range<usize>) -> Self {
+        let end = usize::min(range.end, len);
+        let start = usize::min(range.start, end);
+        let mut bits = BitSet::ones(end);
+        for word in bits.words.iter_mut().take(start / WORD_BITS) {
+            *word = 0;
+        }
+        if start % WORD_BITS != 0 {
+            bits.words[start / WORD_BITS] &= u64::MAX << (start % WORD_BITS);
+        }
+        bits.words.resize(word_count(len), 0);
+        bits.len = len;
+        bits
+    }
+
+    /// Returns a bit set of the given length where bit i is bit i - offset of self.
+    /// Bits without a counterpart in self are cleared. The words are shifted as a
+    /// whole instead of moving every bit.
+    pub fn shifted(&self, offset: i32, len: usize) -> BitSet {
+        let shift = offset.unsigned_abs() as usize;
+        let (word_shift, bit_shift) = (shift / WORD_BITS, shift % WORD_BITS);
+        let word = |i: Option<usize>| i.and_then(|i| self.words.get(i)).copied().unwrap_or(0);
+        let mut bits = BitSet::zeros(len);
+        for (i, w) in bits.words.iter_mut().enumerate() {
+            *w = if offset >= 0 {
+                let high = word(i.checked_sub(word_shift));
+                let low = word(i.checked_sub(word_shift + 1));
+                if bit_shift == 0 {
+                    high
+                } else {
+                    high << bit_shift | low >> (WORD_BITS - bit_shift)
+                }
+            } else {
+                let low = word(i.checked_add(word_shift));
+                let high = word(i.checked_add(word_shift + 1));
+                if bit_shift == 0 {
+                    low
+                } else {
+                    low >> bit_shift | high << (WORD_BITS - bit_shift)
+                }
+            };
+        }
+        bits.clear_unused();
+        bits
+    }
+
+    /// Returns the number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Returns the bitwise AND. The result has the length of self, missing bits of other
+    /// are treated as cleared.
+    pub fn and(&self, other: &BitSet) -> BitSet {
+        self.zip_words(other, |a, b| a & b)
+    }
+
+    /// Returns the bitwise OR. The result has the length of self.
+    pub fn or(&self, other: &BitSet) -> BitSet {
+        self.zip_words(other, |a, b| a | b)
+    }
+
+    /// Returns the bitwise XOR. The result has the length of self.
+    pub fn xor(&self, other: &BitSet) -> BitSet {
+        self.zip_words(other, |a, b| a ^ b)
+    }
+
+    /// Returns the bitwise negation.
+    pub fn not(&self) -> BitSet {
+        let mut bits = Self {
+            words: self.words.iter().map(|w| !w).collect(),
+            len: self.len,
+        };
+        bits.clear_unused();
+        bits
+    }
+
+    /// Returns the number of positions at which the bits differ. The shorter set is padded
+    /// with cleared bits.
+    pub fn hamming(&self, other: &BitSet) -> usize {
+        let (long, short) = if self.len >= other.len {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        long.xor(short).count_ones()
+    }
+
+    /// Returns the number of positions at which both bits are set.
+    pub fn overlap(&self, other: &BitSet) -> usize {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .map(|(a, b)| (a & b).count_ones() as usize)
+            .sum()
+    }
+
+    /// Applies the operation to the words of self and other.
+    fn zip_words<F: Fn(u64, u64) -> u64>(&self, other: &BitSet, op: F) -> BitSet {
+        let mut bits = Self {
+            words: self
+                .words
+                .iter()
+                .enumerate()
+                .map(|(i, a)| op(*a, other.words.get(i).copied().unwrap_or(0)))
+                .collect(),
+            len: self.len,
+        };
+        bits.clear_unused();
+        bits
+    }
+
+    /// Clears the bits of the last word that are beyond the length.
+    fn clear_unused(&mut self) {
+        let used = self.len % WORD_BITS;
+        if used > 0 {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1u64 << used) - 1;
+            }
+        }
+    }
+}
+
+impl FromIterator<bool> for BitSet {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut bits = BitSet::zeros(0);
+        for value in iter {
+            bits.push(value);
+        }
+        bits
+    }
+}
+
+impl From<&BoolVector> for BitSet {
+    fn from(bv: &BoolVector) -> Self {
+        bv.bits.clone()
+    }
+}
+
+impl From<BitSet> for BoolVector {
+    fn from(bits: BitSet) -> Self {
+        BoolVector { bits }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    fn bits(pattern: &str) -> BitSet {
+        BitSet::from_bools(&pattern.chars().map(|c| c == '1').collect::<Vec<bool>>())
+    }
+
+    fn pattern(bits: &BitSet) -> String {
+        bits.to_bools()
+            .iter()
+            .map(|b| if *b { '1' } else { '0' })
+            .collect()
+    }
+
+    #[test]
+    fn bitwise_operations_match_element_wise_results() {
+        let a = bits("1100101");
+        let b = bits("1010");
+        assert_eq!(pattern(&a.and(&b)), "1000000");
+        assert_eq!(pattern(&a.or(&b)), "1110101");
+        assert_eq!(pattern(&a.xor(&b)), "0110101");
+        assert_eq!(pattern(&b.not()), "0101");
+        assert_eq!((a.count_ones(), a.hamming(&b), b.hamming(&a)), (4, 4, 4));
+        assert_eq!(a.overlap(&b), 1);
+        assert_eq!(BitSet::ones(3).not(), BitSet::zeros(3));
+    }
+
+    #[test]
+    fn packing_round_trips_across_word_boundaries() {
+        for len in [0, 7, 63, 64, 65, 200] {
+            let values: Vec<bool> = (0..len).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+            let set = BitSet::from_bools(&values);
+            assert_eq!(set.to_bools(), values);
+            assert_eq!(set.count_ones(), values.iter().filter(|b| **b).count());
+            assert_eq!(set.get(len), false);
+        }
+    }
+
+    #[test]
+    fn shifts_and_ranges_match_element_wise_results() {
+        let values: Vec<bool> = (0..150).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+        let set = BitSet::from_bools(&values);
+        for offset in [-140, -65, -64, -3, 0, 1, 63, 64, 70, 200] {
+            let expected: Vec<bool> = (0..130i32)
+                .map(|i| usize::try_from(i - offset).is_ok_and(|j| j < values.len() && values[j]))
+                .collect();
+            assert_eq!(set.shifted(offset, 130).to_bools(), expected);
+        }
+        for (start, end) in [(0, 0), (3, 70), (64, 128), (70, 70), (100, 200)] {
+            let expected: Vec<bool> = (0..130).map(|i| i >= start && i < end).collect();
+            assert_eq!(BitSet::range(130, start..end).to_bools(), expected);
+        }
+        let mut bits: BitSet = values.iter().copied().collect();
+        assert_eq!(bits, set);
+        bits.set(1, true);
+        bits.push(true);
+        bits.truncate(66);
+        assert_eq!((bits.len(), bits.get(1), bits.get(65)), (66, true, false));
+        assert_eq!(bits.count_ones(), values[..66].iter().filter(|b| **b).count() + 1);
+    }
+}
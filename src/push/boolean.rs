@@ -79,7 +79,7 @@ pub fn boolean_or(push_state: &mut PushState, _instruction_cache: &InstructionCa
 pub fn boolean_def(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(bval) = push_state.bool_stack.pop() {
-            push_state.name_bindings.insert(
+            push_state.bind(
                 name,
                 Item::Literal {
                     push_type: PushType::Bool { val: bval },
@@ -125,15 +125,14 @@ pub fn boolean_not(push_state: &mut PushState, _instruction_cache: &InstructionC
     }
 }
 
-/// BOOLEAN.OR: Pushes the logical OR of the top two BOOLEANs.
+/// BOOLEAN.POP: Pops the BOOLEAN stack.
 pub fn boolean_pop(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     push_state.bool_stack.pop();
 }
 
-/// BOOLEAN.POP: Pops the BOOLEAN stack.
+/// BOOLEAN.RAND: Pushes a random BOOLEAN.
 pub fn boolean_rand(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    let mut rng = rand::thread_rng();
-    let bval = rng.gen_range(0..2) == 1;
+    let bval = push_state.rng.gen_range(0..2) == 1;
     push_state.bool_stack.push(bval);
 }
 
@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum BufferType {
     Queue,
     Stack,
@@ -8,7 +10,8 @@ pub enum BufferType {
 
 /// https://github.com/stjepangolemac/ringvec
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "PushBufferData<T>")]
 pub struct PushBuffer<T> {
     capacity: usize,
     container: Vec<T>,
@@ -18,6 +21,62 @@ pub struct PushBuffer<T> {
     buffer_type: BufferType,
 }
 
+/// Unchecked fields of a deserialized PushBuffer.
+#[derive(Deserialize)]
+struct PushBufferData<T> {
+    capacity: usize,
+    container: Vec<T>,
+    start: usize,
+    end: usize,
+    len: usize,
+    buffer_type: BufferType,
+}
+
+impl<T> TryFrom<PushBufferData<T>> for PushBuffer<T> {
+    type Error = String;
+
+    /// Checks that the positions and the length are consistent with the capacity,
+    /// so that the access functions cannot index outside of the container.
+    fn try_from(data: PushBufferData<T>) -> Result<Self, Self::Error> {
+        if data.container.len() != data.capacity {
+            return Err(format!(
+                "buffer container has {} elements but capacity is {}",
+                data.container.len(),
+                data.capacity
+            ));
+        }
+        if data.len > data.capacity {
+            return Err(format!(
+                "buffer length {} exceeds capacity {}",
+                data.len, data.capacity
+            ));
+        }
+        if data.capacity == 0 {
+            if data.start != 0 || data.end != 0 {
+                return Err("buffer positions of an empty container must be 0".to_string());
+            }
+        } else if data.start >= data.capacity || data.end >= data.capacity {
+            return Err(format!(
+                "buffer positions start {} and end {} must be less than capacity {}",
+                data.start, data.end, data.capacity
+            ));
+        } else if (data.end + data.len) % data.capacity != data.start {
+            return Err(format!(
+                "buffer length {} does not match start {} and end {}",
+                data.len, data.start, data.end
+            ));
+        }
+        Ok(Self {
+            capacity: data.capacity,
+            container: data.container,
+            start: data.start,
+            end: data.end,
+            len: data.len,
+            buffer_type: data.buffer_type,
+        })
+    }
+}
+
 impl<T> PushBuffer<T>
 where
     T: Clone + fmt::Display + Default + PartialEq + fmt::Debug
@@ -39,6 +98,18 @@ where
         }
     }
 
+    /// Returns a copy of the buffer that only contains the first n items
+    /// in the order they are accessed by get.
+    pub fn truncated(&self, n: usize) -> Self {
+        let mut buffer = PushBuffer::new(self.buffer_type.clone(), self.capacity);
+        let n = usize::min(n, self.size());
+        match self.buffer_type {
+            BufferType::Stack => (0..n).rev().for_each(|i| buffer.push(self.copy(i).unwrap())),
+            BufferType::Queue => (0..n).for_each(|i| buffer.push(self.copy(i).unwrap())),
+        }
+        buffer
+    }
+
     pub fn capacity(&self) -> usize {
         self.capacity
     }
@@ -424,4 +495,27 @@ mod test {
         assert_eq!(i.next(), None);
         assert_eq!(i.next(), None);
     }
+
+    #[test]
+    fn buffer_deserialization_validates_positions() {
+        let mut v = PushBuffer::new(BufferType::Queue, 3);
+        v.push_force(1);
+        v.push_force(2);
+        v.push_force(3);
+        v.push_force(4);
+        let json = serde_json::to_string(&v).unwrap();
+        let w: PushBuffer<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(w.to_string(), v.to_string());
+
+        let invalid = [
+            r#"{"capacity":3,"container":[0,0],"start":0,"end":0,"len":0,"buffer_type":"Queue"}"#,
+            r#"{"capacity":3,"container":[0,0,0],"start":0,"end":0,"len":4,"buffer_type":"Queue"}"#,
+            r#"{"capacity":3,"container":[0,0,0],"start":3,"end":0,"len":0,"buffer_type":"Stack"}"#,
+            r#"{"capacity":3,"container":[0,0,0],"start":1,"end":0,"len":2,"buffer_type":"Stack"}"#,
+            r#"{"capacity":0,"container":[],"start":1,"end":0,"len":0,"buffer_type":"Stack"}"#,
+        ];
+        for json in invalid.iter() {
+            assert!(serde_json::from_str::<PushBuffer<i32>>(json).is_err());
+        }
+    }
 }
@@ -0,0 +1,307 @@
+use crate::push::instructions::Instruction;
+use crate::push::instructions::InstructionCache;
+use crate::push::item::Item;
+use crate::push::stack::PushPrint;
+use crate::push::state::PushState;
+use crate::push::state::*;
+use std::collections::HashMap;
+
+/// Named characters of the literal syntax, e.g. \space for ' '.
+const CHAR_NAMES: &[(&str, char)] = &[
+    ("space", ' '),
+    ("newline", '\n'),
+    ("tab", '\t'),
+    ("return", '\r'),
+];
+
+/// Returns the literal of the character as it is written in programs: a backslash
+/// followed by the character (\a), by its name (\space, \newline, \tab, \return) or,
+/// for other whitespace and control characters, by its code point (\u000B).
+pub fn char_literal(c: char) -> String {
+    match CHAR_NAMES.iter().find(|(_, named)| *named == c) {
+        Some((name, _)) => format!("\\{}", name),
+        None if c.is_whitespace() || c.is_control() => format!("\\u{:04X}", c as u32),
+        None => format!("\\{}", c),
+    }
+}
+
+/// Parses a character literal (see char_literal). Returns None if the token is not
+/// a character literal.
+pub fn parse_char_literal(token: &str) -> Option<char> {
+    let rest = token.strip_prefix('\\')?;
+    let mut chars = rest.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        (Some('u'), Some(_)) if rest.len() > 4 && rest[1..].chars().all(|d| d.is_ascii_hexdigit()) => {
+            u32::from_str_radix(&rest[1..], 16)
+                .ok()
+                .and_then(std::char::from_u32)
+        }
+        _ => CHAR_NAMES
+            .iter()
+            .find(|(name, _)| *name == rest)
+            .map(|(_, c)| *c),
+    }
+}
+
+impl PushPrint for char {
+    fn to_pstring(&self) -> String {
+        char_literal(*self)
+    }
+}
+
+/// Characters as used by the Program Synthesis Benchmark suite. CHAR literals are
+/// written as \a, \space, \newline, \tab, \return or \u000B (see char_literal).
+pub fn load_char_instructions(map: &mut HashMap<String, Instruction>) {
+    map.insert(String::from("CHAR.="), Instruction::new(char_equal));
+    map.insert(String::from("CHAR.DEFINE"), Instruction::new(char_define));
+    map.insert(String::from("CHAR.DUP"), Instruction::new(char_dup));
+    map.insert(String::from("CHAR.FLUSH"), Instruction::new(char_flush));
+    map.insert(
+        String::from("CHAR.FROMINTEGER"),
+        Instruction::new(char_from_integer),
+    );
+    map.insert(String::from("CHAR.ID"), Instruction::new(char_id));
+    map.insert(String::from("CHAR.ISDIGIT"), Instruction::new(char_is_digit));
+    map.insert(String::from("CHAR.ISLETTER"), Instruction::new(char_is_letter));
+    map.insert(
+        String::from("CHAR.ISWHITESPACE"),
+        Instruction::new(char_is_whitespace),
+    );
+    map.insert(String::from("CHAR.LOWERCASE"), Instruction::new(char_lowercase));
+    map.insert(String::from("CHAR.POP"), Instruction::new(char_pop));
+    map.insert(String::from("CHAR.ROT"), Instruction::new(char_rot));
+    map.insert(String::from("CHAR.SHOVE"), Instruction::new(char_shove));
+    map.insert(
+        String::from("CHAR.STACKDEPTH"),
+        Instruction::new(char_stack_depth),
+    );
+    map.insert(String::from("CHAR.SWAP"), Instruction::new(char_swap));
+    map.insert(
+        String::from("CHAR.TOINTEGER"),
+        Instruction::new(char_to_integer),
+    );
+    map.insert(String::from("CHAR.UPPERCASE"), Instruction::new(char_uppercase));
+    map.insert(String::from("CHAR.YANK"), Instruction::new(char_yank));
+    map.insert(String::from("CHAR.YANKDUP"), Instruction::new(char_yank_dup));
+}
+
+/// Returns the index taken from the INTEGER stack bound to the valid positions of the
+/// CHAR stack.
+fn pop_char_index(push_state: &mut PushState) -> Option<usize> {
+    let index = push_state.int_stack.pop()?;
+    Some(i32::max(i32::min(push_state.char_stack.size() as i32 - 1, index), 0) as usize)
+}
+
+/// CHAR.=: Pushes TRUE onto the BOOLEAN stack if the top two CHAR items are equal, or FALSE
+/// otherwise.
+fn char_equal(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(cvals) = push_state.char_stack.pop_vec(2) {
+        push_state.bool_stack.push(cvals[0] == cvals[1]);
+    }
+}
+
+/// CHAR.DEFINE: Defines the name on top of the NAME stack as an instruction that will push the
+/// top item of the CHAR stack onto the EXEC stack.
+fn char_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(name) = push_state.name_stack.pop() {
+        if let Some(cval) = push_state.char_stack.pop() {
+            push_state.bind(name, Item::char(cval));
+        }
+    }
+}
+
+/// CHAR.DUP: Duplicates the top item on the CHAR stack. Does not pop its argument.
+fn char_dup(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(cval) = push_state.char_stack.copy(0) {
+        push_state.char_stack.push(cval);
+    }
+}
+
+/// CHAR.FLUSH: Empties the CHAR stack.
+fn char_flush(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.char_stack.flush();
+}
+
+/// CHAR.FROMINTEGER: Pushes the ASCII character of the top INTEGER modulo 128 (Clojush
+/// semantics).
+fn char_from_integer(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(ival) = push_state.int_stack.pop() {
+        push_state
+            .char_stack
+            .push(ival.rem_euclid(128) as u8 as char);
+    }
+}
+
+/// CHAR.ID: Pushes the ID of the CHAR stack to the INTEGER stack.
+fn char_id(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.int_stack.push(CHAR_STACK_ID);
+}
+
+/// CHAR.ISDIGIT: Pops the top CHAR and pushes TRUE to the BOOLEAN stack if it is a decimal
+/// digit.
+fn char_is_digit(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(cval) = push_state.char_stack.pop() {
+        push_state.bool_stack.push(cval.is_ascii_digit());
+    }
+}
+
+/// CHAR.ISLETTER: Pops the top CHAR and pushes TRUE to the BOOLEAN stack if it is a letter.
+fn char_is_letter(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(cval) = push_state.char_stack.pop() {
+        push_state.bool_stack.push(cval.is_alphabetic());
+    }
+}
+
+/// CHAR.ISWHITESPACE: Pops the top CHAR and pushes TRUE to the BOOLEAN stack if it is a space,
+/// newline or tab.
+fn char_is_whitespace(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(cval) = push_state.char_stack.pop() {
+        push_state.bool_stack.push(cval.is_whitespace());
+    }
+}
+
+/// CHAR.LOWERCASE: Converts the top CHAR to lowercase. Characters without a single lowercase
+/// form are not changed.
+fn char_lowercase(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(cval) = push_state.char_stack.get_mut(0) {
+        let mut lower = cval.to_lowercase();
+        if let (Some(c), None) = (lower.next(), lower.next()) {
+            *cval = c;
+        }
+    }
+}
+
+/// CHAR.POP: Pops the CHAR stack.
+fn char_pop(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.char_stack.pop();
+}
+
+/// CHAR.ROT: Rotates the top three items on the CHAR stack, pulling the third item out and
+/// pushing it on top.
+fn char_rot(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.char_stack.yank(2);
+}
+
+/// CHAR.SHOVE: Inserts the top CHAR "deep" in the stack, at the position indexed by the top
+/// INTEGER.
+fn char_shove(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = pop_char_index(push_state) {
+        push_state.char_stack.shove(index);
+    }
+}
+
+/// CHAR.STACKDEPTH: Pushes the stack depth onto the INTEGER stack.
+fn char_stack_depth(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state
+        .int_stack
+        .push(push_state.char_stack.size() as i32);
+}
+
+/// CHAR.SWAP: Swaps the top two CHARs.
+fn char_swap(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.char_stack.shove(1);
+}
+
+/// CHAR.TOINTEGER: Pops the top CHAR and pushes its code point to the INTEGER stack.
+fn char_to_integer(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(cval) = push_state.char_stack.pop() {
+        push_state.int_stack.push(cval as i32);
+    }
+}
+
+/// CHAR.UPPERCASE: Converts the top CHAR to uppercase. Characters without a single uppercase
+/// form are not changed.
+fn char_uppercase(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(cval) = push_state.char_stack.get_mut(0) {
+        let mut upper = cval.to_uppercase();
+        if let (Some(c), None) = (upper.next(), upper.next()) {
+            *cval = c;
+        }
+    }
+}
+
+/// CHAR.YANK: Removes an indexed item from "deep" in the stack and pushes it on top of the
+/// stack. The index is taken from the INTEGER stack.
+fn char_yank(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = pop_char_index(push_state) {
+        push_state.char_stack.yank(index);
+    }
+}
+
+/// CHAR.YANKDUP: Pushes a copy of an indexed item "deep" in the stack onto the top of the
+/// stack, without removing the deep item. The index is taken from the INTEGER stack.
+fn char_yank_dup(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = pop_char_index(push_state) {
+        if let Some(deep_item) = push_state.char_stack.copy(index) {
+            push_state.char_stack.push(deep_item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::parser::PushParser;
+
+    pub fn icache() -> InstructionCache {
+        InstructionCache::new(vec![])
+    }
+
+    #[test]
+    fn char_literals_round_trip() {
+        for c in ['a', 'Z', '\\', ' ', '\n', '\t', '\r', '\u{85}', '\u{3000}'].iter() {
+            assert_eq!(parse_char_literal(&char_literal(*c)), Some(*c));
+        }
+        assert_eq!(char_literal(' '), "\\space");
+        assert_eq!(char_literal('\r'), "\\return");
+        assert_eq!(char_literal('\u{b}'), "\\u000B");
+        assert_eq!(char_literal('u'), "\\u");
+        assert_eq!(parse_char_literal("\\ab"), None);
+        assert_eq!(parse_char_literal("\\uZZZZ"), None);
+        assert_eq!(parse_char_literal("\\uD800"), None);
+        assert_eq!(parse_char_literal("a"), None);
+    }
+
+    #[test]
+    fn char_from_integer_literals_round_trip() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        for i in 0..128 {
+            let mut test_state = PushState::new();
+            test_state.int_stack.push(i);
+            char_from_integer(&mut test_state, &icache());
+            let c = test_state.char_stack.copy(0).unwrap();
+            let literal = char_literal(c);
+            assert!(!literal.chars().any(|l| l.is_whitespace() || l.is_control()), "{}", i);
+            assert_eq!(parse_char_literal(&literal), Some(c));
+            // Programs and fixtures restore the character
+            let program = format!("( {} )", test_state.char_stack.to_string());
+            let mut parsed_state = PushState::new();
+            PushParser::parse_program(&mut parsed_state, &instruction_set, &program);
+            assert_eq!(parsed_state.exec_stack.to_string(), format!("( {} )", literal));
+            let fixture = test_state.to_fixture().unwrap();
+            assert_eq!(PushState::from_fixture(&fixture).unwrap().char_stack.to_string(), literal);
+        }
+    }
+
+    #[test]
+    fn char_classification_and_case_conversion() {
+        let mut test_state = PushState::new();
+        test_state.int_stack.push(97 + 128);
+        char_from_integer(&mut test_state, &icache());
+        char_uppercase(&mut test_state, &icache());
+        assert_eq!(test_state.char_stack.to_string(), "\\A");
+        char_dup(&mut test_state, &icache());
+        char_is_letter(&mut test_state, &icache());
+        test_state.char_stack.push('7');
+        char_is_digit(&mut test_state, &icache());
+        test_state.char_stack.push(' ');
+        char_is_whitespace(&mut test_state, &icache());
+        assert_eq!(test_state.bool_stack.to_string(), "TRUE TRUE TRUE");
+        char_lowercase(&mut test_state, &icache());
+        char_to_integer(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.to_string(), "97");
+    }
+}
@@ -16,6 +16,7 @@ pub fn load_code_instructions(map: &mut HashMap<String, Instruction>) {
     map.insert(String::from("CODE.="), Instruction::new(code_eq));
     map.insert(String::from("CODE.APPEND"), Instruction::new(code_append));
     map.insert(String::from("CODE.ATOM"), Instruction::new(code_item));
+    map.insert(String::from("CODE.BLOAT"), Instruction::new(code_bloat));
     map.insert(String::from("CODE.CAR"), Instruction::new(code_first));
     map.insert(String::from("CODE.CDR"), Instruction::new(code_rest));
     map.insert(String::from("CODE.CONS"), Instruction::new(code_cons));
@@ -41,6 +42,7 @@ pub fn load_code_instructions(map: &mut HashMap<String, Instruction>) {
     map.insert(String::from("CODE.LOOP"), Instruction::new(code_loop));
     map.insert(String::from("CODE.DUP"), Instruction::new(code_dup));
     map.insert(String::from("CODE.EXTRACT"), Instruction::new(code_extract));
+    map.insert(String::from("CODE.FLATTEN"), Instruction::new(code_flatten));
     map.insert(String::from("CODE.FLUSH"), Instruction::new(code_flush));
     map.insert(
         String::from("CODE.FROMBOOLEAN"),
@@ -58,6 +60,19 @@ pub fn load_code_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("CODE.FROMNAME"),
         Instruction::new(code_from_name),
     );
+    map.insert(String::from("CODE.HOLE"), Instruction::new(code_hole));
+    map.insert(
+        String::from("CODE.HOLE*BOOLEAN"),
+        Instruction::new(code_hole_bool),
+    );
+    map.insert(
+        String::from("CODE.HOLE*FLOAT"),
+        Instruction::new(code_hole_float),
+    );
+    map.insert(
+        String::from("CODE.HOLE*INTEGER"),
+        Instruction::new(code_hole_int),
+    );
     map.insert(String::from("CODE.ID"), Instruction::new(code_id));
     map.insert(String::from("CODE.IF"), Instruction::new(code_if));
     map.insert(String::from("CODE.INSERT"), Instruction::new(code_insert));
@@ -73,7 +88,10 @@ pub fn load_code_instructions(map: &mut HashMap<String, Instruction>) {
         Instruction::new(code_position),
     );
     map.insert(String::from("CODE.PRINT"), Instruction::new(code_print));
-    map.insert(String::from("CODE.QUOTE"), Instruction::new(code_quote));
+    map.insert(
+        String::from("CODE.QUOTE"),
+        Instruction::new(code_quote).with_blocks(1),
+    );
     map.insert(String::from("CODE.RAND"), Instruction::new(code_rand));
     map.insert(String::from("CODE.ROT"), Instruction::new(code_rot));
     map.insert(String::from("CODE.SHOVE"), Instruction::new(code_shove));
@@ -84,6 +102,10 @@ pub fn load_code_instructions(map: &mut HashMap<String, Instruction>) {
     );
     map.insert(String::from("CODE.SUBST"), Instruction::new(code_subst));
     map.insert(String::from("CODE.SWAP"), Instruction::new(code_swap));
+    map.insert(
+        String::from("CODE.TEMPLATE"),
+        Instruction::new(code_template),
+    );
     map.insert(String::from("CODE.YANK"), Instruction::new(code_yank));
     map.insert(
         String::from("CODE.YANKDUP"),
@@ -128,6 +150,19 @@ pub fn code_item(push_state: &mut PushState, _instruction_cache: &InstructionCac
     );
 }
 
+/// CODE.BLOAT: Pushes the portion of points of the top piece of code that have not been
+/// executed so far onto the FLOAT stack (see HeatMap::inviable_ratio). Since the program is
+/// copied to the CODE stack before the execution, this measures the inviable code of the running
+/// program. Acts as a NOOP if the heat map of the state is not enabled.
+pub fn code_bloat(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(heat_map) = &push_state.heat_map {
+        if let Some(code) = push_state.code_stack.get(0) {
+            let ratio = heat_map.inviable_ratio(code);
+            push_state.float_stack.push(ratio);
+        }
+    }
+}
+
 /// CODE.CAR: Pushes the first item of the list on top of the CODE stack. For example, if the top
 /// piece of code is "( A B )" then this pushes "A" (after popping the argument). If the code on
 /// top of the stack is not a list then this has no effect. The name derives from the similar Lisp
@@ -218,7 +253,7 @@ pub fn code_contains(push_state: &mut PushState, _instruction_cache: &Instructio
 pub fn code_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(instruction) = push_state.code_stack.pop() {
-            push_state.name_bindings.insert(name, instruction);
+            push_state.bind(name, instruction);
         }
     }
 }
@@ -290,9 +325,8 @@ pub fn code_discrepancy(push_state: &mut PushState, _instruction_cache: &Instruc
 /// else.
 pub fn code_do(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(instruction) = push_state.code_stack.copy(0) {
-        push_state.exec_stack.push(Item::InstructionMeta {
-            name: "CODE.POP".to_string(),
-        });
+        push_state.exec_stack.push(Item::instruction("CODE.POP".to_string()));
+        push_state.enter_binding_scope();
         push_state.exec_stack.push(instruction);
     }
 }
@@ -300,10 +334,9 @@ pub fn code_do(push_state: &mut PushState, _instruction_cache: &InstructionCache
 /// CODE.DO*: Like CODE.DO but pops the stack before, rather than after, the recursive execution.
 pub fn code_pop_and_do(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(instruction) = push_state.code_stack.copy(0) {
+        push_state.enter_binding_scope();
         push_state.exec_stack.push(instruction);
-        push_state.exec_stack.push(Item::InstructionMeta {
-            name: "CODE.POP".to_string(),
-        });
+        push_state.exec_stack.push(Item::instruction("CODE.POP".to_string()));
     }
 }
 
@@ -364,6 +397,14 @@ pub fn code_extract(push_state: &mut PushState, _instruction_cache: &Instruction
     }
 }
 
+/// CODE.FLATTEN: Replaces the top item of the CODE stack by a list of its atoms, i.e. all
+/// nested parentheses are removed. Acts as a NOOP if the top item is not a list.
+pub fn code_flatten(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(item) = push_state.code_stack.get_mut(0) {
+        *item = item.flatten();
+    }
+}
+
 /// CODE.FLUSH: Empties the CODE stack.
 pub fn code_flush(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     push_state.code_stack.flush();
@@ -393,7 +434,7 @@ pub fn code_from_int(push_state: &mut PushState, _instruction_cache: &Instructio
 /// CODE.FROMNAME: Pops the NAME stack and pushes the popped item onto the CODE stack.
 pub fn code_from_name(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(nval) = push_state.name_stack.pop() {
-        push_state.code_stack.push(Item::id(nval.to_string()));
+        push_state.code_stack.push(Item::quoted_name(nval.to_string()));
     }
 }
 
@@ -452,8 +493,8 @@ pub fn code_list(push_state: &mut PushState, _instruction_cache: &InstructionCac
     }
 }
 
-/// CODE.CONTAINS: Pushes TRUE on the BOOLEAN stack if the second CODE stack item contains the
-/// first CODE stack item anywhere (e.g. in a sub-list).
+/// CODE.MEMBER: Pushes TRUE on the BOOLEAN stack if the printed second CODE stack item contains
+/// the printed first CODE stack item.
 pub fn code_member(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(ov) = push_state.code_stack.copy_vec(2) {
         let top_el = ov[1].to_string();
@@ -469,6 +510,22 @@ pub fn code_member(push_state: &mut PushState, _instruction_cache: &InstructionC
 /// CODE.NOOP: Does nothing.
 pub fn code_noop(_push_state: &mut PushState, _instruction_cache: &InstructionCache) {}
 
+/// CODE.HOLE: Marks a hole in a code template that is filled by CODE.TEMPLATE. Acts as NOOP
+/// when executed.
+pub fn code_hole(_push_state: &mut PushState, _instruction_cache: &InstructionCache) {}
+
+/// CODE.HOLE*BOOLEAN: Marks a hole in a code template that is filled with a BOOLEAN literal by
+/// CODE.TEMPLATE. Acts as NOOP when executed.
+pub fn code_hole_bool(_push_state: &mut PushState, _instruction_cache: &InstructionCache) {}
+
+/// CODE.HOLE*FLOAT: Marks a hole in a code template that is filled with a FLOAT literal by
+/// CODE.TEMPLATE. Acts as NOOP when executed.
+pub fn code_hole_float(_push_state: &mut PushState, _instruction_cache: &InstructionCache) {}
+
+/// CODE.HOLE*INTEGER: Marks a hole in a code template that is filled with an INTEGER literal by
+/// CODE.TEMPLATE. Acts as NOOP when executed.
+pub fn code_hole_int(_push_state: &mut PushState, _instruction_cache: &InstructionCache) {}
+
 /// CODE.NTH: Pushes the nth element of the expression on top of the CODE stack (which is coerced
 /// to a list first if necessary). If the expression is an empty list then the result is an empty
 /// list. N is taken from the INTEGER stack and is taken modulo the length of the expression into
@@ -557,7 +614,7 @@ pub fn code_rand(push_state: &mut PushState, instruction_cache: &InstructionCach
             i32::abs(push_state.configuration.max_points_in_random_expressions),
         );
         if let Some(rand_item) =
-            CodeGenerator::random_code(&push_state, &instruction_cache, limit as usize)
+            CodeGenerator::random_code(push_state, &instruction_cache, limit as usize)
         {
             push_state.code_stack.push(rand_item);
         }
@@ -623,6 +680,91 @@ pub fn code_swap(push_state: &mut PushState, _instruction_cache: &InstructionCac
     push_state.code_stack.shove(1);
 }
 
+/// Hole markers recognized by CODE.TEMPLATE. The first marker is filled from the CODE stack,
+/// the others are filled with literals from the BOOLEAN, FLOAT and INTEGER stack.
+const HOLES: [&str; 4] = [
+    "CODE.HOLE",
+    "CODE.HOLE*BOOLEAN",
+    "CODE.HOLE*FLOAT",
+    "CODE.HOLE*INTEGER",
+];
+
+/// CODE.TEMPLATE: Pops the top item of the CODE stack and replaces each CODE.HOLE marker it
+/// contains by an item of the CODE stack. The holes are filled in depth first order (left to
+/// right) with the items popped from the CODE stack, i.e. the first hole is filled with the
+/// second item of the CODE stack. Likewise, the typed markers CODE.HOLE*BOOLEAN,
+/// CODE.HOLE*FLOAT and CODE.HOLE*INTEGER are filled with literals popped from the respective
+/// stack. The instantiated template is pushed to the CODE stack. Acts as NOOP if there are not
+/// enough items to fill all holes.
+pub fn code_template(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(template) = push_state.code_stack.get(0) {
+        let holes = HOLES.map(|name| Item::instruction(name.to_string()));
+        let mut num_holes = [0; 4];
+        count_holes(template, &holes, &mut num_holes);
+        if push_state.code_stack.size() > num_holes[0]
+            && push_state.bool_stack.size() >= num_holes[1]
+            && push_state.float_stack.size() >= num_holes[2]
+            && push_state.int_stack.size() >= num_holes[3]
+        {
+            let mut template = push_state.code_stack.pop().unwrap();
+            let mut fillers = [
+                push_state.code_stack.pop_vec(num_holes[0]).unwrap(),
+                literals(push_state.bool_stack.pop_vec(num_holes[1]), Item::bool),
+                literals(push_state.float_stack.pop_vec(num_holes[2]), Item::float),
+                literals(push_state.int_stack.pop_vec(num_holes[3]), Item::int),
+            ];
+            if let Some(kind) = hole_kind(&template, &holes) {
+                template = fillers[kind].pop().unwrap();
+            } else {
+                fill_holes(&mut template, &holes, &mut fillers);
+            }
+            push_state.code_stack.push(template);
+        }
+    }
+}
+
+/// Converts the values popped from a typed stack to literals.
+fn literals<T>(values: Option<Vec<T>>, literal: fn(T) -> Item) -> Vec<Item> {
+    values.unwrap().into_iter().map(literal).collect()
+}
+
+/// Returns the index of the hole marker that item is equal to.
+fn hole_kind(item: &Item, holes: &[Item]) -> Option<usize> {
+    holes.iter().position(|hole| Item::equals(item, hole))
+}
+
+/// Adds the number of holes in item to num_holes for each kind of hole.
+fn count_holes(item: &Item, holes: &[Item], num_holes: &mut [usize]) {
+    match item {
+        Item::List { items } => {
+            for i in 0..items.size() {
+                count_holes(items.get(i).unwrap(), holes, num_holes);
+            }
+        }
+        _ => {
+            if let Some(kind) = hole_kind(item, holes) {
+                num_holes[kind] += 1;
+            }
+        }
+    }
+}
+
+/// Replaces the holes of item in depth first order by the last
+/// elements of the fillers of their kind.
+fn fill_holes(item: &mut Item, holes: &[Item], fillers: &mut [Vec<Item>]) {
+    if let Item::List { items } = item {
+        for i in 0..items.size() {
+            if let Some(kind) = hole_kind(items.get(i).unwrap(), holes) {
+                if let Some(filler) = fillers[kind].pop() {
+                    let _ = items.replace(i, filler);
+                }
+            } else {
+                fill_holes(items.get_mut(i).unwrap(), holes, fillers);
+            }
+        }
+    }
+}
+
 /// CODE.YANK: Removes an indexed item from "deep" in the stack and pushes it on top of the stack.
 /// The index is taken from the INTEGER stack.
 pub fn code_yank(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
@@ -652,6 +794,7 @@ pub fn code_yank_dup(push_state: &mut PushState, _instruction_cache: &Instructio
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::push::heatmap::HeatMap;
     use crate::push::index::Index;
     use crate::push::parser::PushParser;
     use crate::push::instructions::InstructionSet;
@@ -923,6 +1066,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn code_flatten_removes_nesting() {
+        let mut test_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(
+            &mut test_state,
+            &instruction_set,
+            "( 1 ( 2 ( ) ( 3 ( 4 ) ) ) 5 )",
+        );
+        test_state.code_stack.push(test_state.exec_stack.pop().unwrap());
+        code_flatten(&mut test_state, &icache());
+        assert_eq!(test_state.code_stack.to_string(), "( 1 2 3 4 5 )");
+        test_state.code_stack.push(Item::int(1));
+        code_flatten(&mut test_state, &icache());
+        assert_eq!(test_state.code_stack.to_string(), "1 ( 1 2 3 4 5 )");
+    }
+
     #[test]
     fn code_flush_empties_stack() {
         let mut test_state = PushState::new();
@@ -1140,6 +1301,10 @@ mod tests {
         test_state.int_stack.push(100);
         code_rand(&mut test_state, &icache());
         assert_eq!(test_state.code_stack.size(), 1);
+        // No code is smaller than one point
+        test_state.int_stack.push(1);
+        code_rand(&mut test_state, &icache());
+        assert_eq!(test_state.code_stack.size(), 1);
     }
 
     #[test]
@@ -1178,6 +1343,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn code_bloat_pushes_inviable_ratio() {
+        let mut test_state = PushState::new();
+        code_bloat(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.size(), 0);
+        let program = Item::list(vec![Item::int(2), Item::int(1), Item::int(2)]);
+        let mut heat_map = HeatMap::new();
+        let mut exec_stack = PushStack::new();
+        exec_stack.push(program.clone());
+        // Expand the list and execute its first point
+        heat_map.record(&exec_stack);
+        if let Some(Item::List { mut items }) = exec_stack.pop() {
+            exec_stack.push_vec(items.pop_vec(items.size()).unwrap());
+        }
+        heat_map.record(&exec_stack);
+        test_state.heat_map = Some(heat_map);
+        test_state.code_stack.push(program);
+        code_bloat(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.to_string(), "0.5");
+    }
+
     #[test]
     fn code_size_calculates_top_element() {
         let mut test_state = PushState::new();
@@ -1213,6 +1399,76 @@ mod tests {
         );
     }
 
+    #[test]
+    fn code_template_fills_holes_from_left_to_right() {
+        let mut test_state = PushState::new();
+        let hole = Item::instruction(String::from("CODE.HOLE"));
+        let template = Item::list(vec![
+            Item::list(vec![hole.clone(), Item::int(3)]),
+            hole.clone(),
+            Item::int(1),
+        ]);
+        test_state.code_stack.push(Item::int(5));
+        test_state.code_stack.push(Item::list(vec![Item::int(7), Item::int(6)]));
+        test_state.code_stack.push(Item::int(4));
+        test_state.code_stack.push(template);
+        code_template(&mut test_state, &icache());
+        assert_eq!(
+            test_state.code_stack.to_string(),
+            "( 1 4 ( 3 ( 6 7 ) ) ) 5"
+        );
+    }
+
+    #[test]
+    fn code_template_without_enough_fillers_is_noop() {
+        let mut test_state = PushState::new();
+        let hole = Item::instruction(String::from("CODE.HOLE"));
+        test_state.code_stack.push(Item::int(4));
+        test_state
+            .code_stack
+            .push(Item::list(vec![hole.clone(), hole.clone()]));
+        code_template(&mut test_state, &icache());
+        assert_eq!(
+            test_state.code_stack.to_string(),
+            "( CODE.HOLE CODE.HOLE ) 4"
+        );
+    }
+
+    #[test]
+    fn code_template_fills_typed_holes_from_their_stacks() {
+        let mut test_state = PushState::new();
+        let hole = |name: &str| Item::instruction(name.to_string());
+        let template = Item::list(vec![
+            hole("CODE.HOLE*INTEGER"),
+            Item::list(vec![hole("CODE.HOLE*FLOAT"), hole("CODE.HOLE")]),
+            hole("CODE.HOLE*BOOLEAN"),
+            hole("CODE.HOLE*INTEGER"),
+        ]);
+        test_state.code_stack.push(Item::int(9));
+        test_state.code_stack.push(template.clone());
+        test_state.int_stack.push(1);
+        test_state.int_stack.push(2);
+        test_state.float_stack.push(0.5);
+        test_state.bool_stack.push(true);
+        code_template(&mut test_state, &icache());
+        assert_eq!(
+            test_state.code_stack.to_string(),
+            "( 2 TRUE ( 9 0.500 ) 1 )"
+        );
+        assert_eq!(test_state.int_stack.size(), 0);
+        assert_eq!(test_state.float_stack.size(), 0);
+        assert_eq!(test_state.bool_stack.size(), 0);
+        // Not enough INTEGER items
+        test_state.code_stack.push(Item::int(9));
+        test_state.code_stack.push(template);
+        test_state.int_stack.push(1);
+        test_state.float_stack.push(0.5);
+        test_state.bool_stack.push(true);
+        code_template(&mut test_state, &icache());
+        assert_eq!(test_state.code_stack.size(), 3);
+        assert_eq!(test_state.int_stack.size(), 1);
+    }
+
     #[test]
     fn code_swaps_top_elements() {
         let mut test_state = PushState::new();
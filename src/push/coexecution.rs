@@ -0,0 +1,206 @@
+use crate::push::buffer::{BufferType, PushBuffer};
+use crate::push::graph::Graph;
+use crate::push::instructions::{InstructionCache, InstructionSet};
+use crate::push::interpreter::{PushInterpreter, PushInterpreterState};
+use crate::push::state::PushState;
+
+/// Decides which write to the blackboard is kept if several programs
+/// modify it in the same round.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ConflictPolicy {
+    // The write of the program with the lowest index is kept.
+    FirstWriterWins,
+    // The write of the program with the highest index is kept.
+    LastWriterWins,
+}
+
+/// Executes several programs in an interleaved way. Each program runs on its
+/// own PushState but all programs share a single graph (the blackboard).
+///
+/// In every round each program executes one step. During its step the GRAPH
+/// stack of the program is set aside and replaced by a stack that only holds
+/// the blackboard of the beginning of the round. If the step leaves exactly one
+/// graph in this slot, it counts as a write if it differs from the blackboard.
+/// A step that leaves no or several graphs (e.g. GRAPH.POP, GRAPH.DUP or
+/// GRAPH.NEW) does not write and its graphs are discarded. The GRAPH stack of
+/// the program is restored after the step, so the program cannot swap its own
+/// graphs with the blackboard. At the end of the round one of the writes is
+/// selected by the conflict policy and becomes the new blackboard.
+pub struct CoExecution {
+    pub push_states: Vec<PushState>,
+    pub blackboard: Graph,
+    pub conflict_policy: ConflictPolicy,
+    round_counter: usize,
+}
+
+impl CoExecution {
+    pub fn new(blackboard: Graph, conflict_policy: ConflictPolicy) -> Self {
+        Self {
+            push_states: vec![],
+            blackboard,
+            conflict_policy,
+            round_counter: 0,
+        }
+    }
+
+    /// Adds a program to the co-execution and returns its index.
+    pub fn add_program(&mut self, push_state: PushState) -> usize {
+        self.push_states.push(push_state);
+        self.push_states.len() - 1
+    }
+
+    /// Returns the number of rounds that have been executed so far.
+    pub fn round_counter(&self) -> usize {
+        self.round_counter
+    }
+
+    /// Executes one step of every program that has not halted yet.
+    /// Returns true if the execution stacks of all programs are empty.
+    pub fn round(&mut self, instruction_set: &mut InstructionSet, icache: &InstructionCache) -> bool {
+        let mut all_halted = true;
+        let mut accepted_write: Option<Graph> = None;
+        for push_state in self.push_states.iter_mut() {
+            if push_state.exec_stack.size() == 0 {
+                continue;
+            }
+            all_halted = false;
+            let mut blackboard_slot = PushBuffer::new(BufferType::Stack, push_state.graph_stack.capacity());
+            blackboard_slot.push(self.blackboard.clone());
+            let own_graphs = std::mem::replace(&mut push_state.graph_stack, blackboard_slot);
+            PushInterpreter::step(push_state, instruction_set, icache);
+            let mut blackboard_slot = std::mem::replace(&mut push_state.graph_stack, own_graphs);
+            if blackboard_slot.size() != 1 {
+                continue;
+            }
+            if let Some(graph) = blackboard_slot.pop() {
+                if graph != self.blackboard {
+                    match self.conflict_policy {
+                        ConflictPolicy::FirstWriterWins => {
+                            if accepted_write.is_none() {
+                                accepted_write = Some(graph);
+                            }
+                        }
+                        ConflictPolicy::LastWriterWins => accepted_write = Some(graph),
+                    }
+                }
+            }
+        }
+        if let Some(graph) = accepted_write {
+            self.blackboard = graph;
+        }
+        if !all_halted {
+            self.round_counter += 1;
+        }
+        all_halted
+    }
+
+    /// Runs rounds until all programs have halted. Stops with
+    /// StepLimitExceeded if more than max_rounds rounds are needed.
+    pub fn run(&mut self, instruction_set: &mut InstructionSet, max_rounds: usize) -> PushInterpreterState {
+        for push_state in self.push_states.iter_mut() {
+            PushInterpreter::copy_to_code_stack(push_state);
+        }
+        let icache = instruction_set.cache();
+        loop {
+            if self.round(instruction_set, &icache) {
+                return PushInterpreterState::NoErrors;
+            }
+            if self.round_counter >= max_rounds {
+                return PushInterpreterState::StepLimitExceeded;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::parser::PushParser;
+
+    fn program(instruction_set: &InstructionSet, input: &str) -> PushState {
+        let mut push_state = PushState::new();
+        PushParser::parse_program(&mut push_state, instruction_set, &input);
+        push_state
+    }
+
+    #[test]
+    fn programs_share_blackboard_writes() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut coexec = CoExecution::new(Graph::new(), ConflictPolicy::FirstWriterWins);
+        // The first program adds a node which the second program reads later
+        coexec.add_program(program(&instruction_set, "( 7 GRAPH.NODE*ADD )"));
+        coexec.add_program(program(&instruction_set, "( 1 2 3 1 GRAPH.NODE*GETSTATE )"));
+        assert_eq!(coexec.run(&mut instruction_set, 100), PushInterpreterState::NoErrors);
+        assert_eq!(coexec.blackboard.node_size(), 1);
+        assert_eq!(coexec.push_states[1].int_stack.to_string(), "7 3 2 1");
+    }
+
+    #[test]
+    fn conflict_policy_selects_write() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        for (policy, expected) in [
+            (ConflictPolicy::FirstWriterWins, Some(1)),
+            (ConflictPolicy::LastWriterWins, Some(2)),
+        ]
+        .iter()
+        {
+            let mut coexec = CoExecution::new(Graph::new(), *policy);
+            coexec.add_program(program(&instruction_set, "( 1 GRAPH.NODE*ADD )"));
+            coexec.add_program(program(&instruction_set, "( 2 GRAPH.NODE*ADD )"));
+            assert_eq!(coexec.run(&mut instruction_set, 100), PushInterpreterState::NoErrors);
+            assert_eq!(coexec.blackboard.node_size(), 1);
+            assert_eq!(coexec.blackboard.get_state(&1), *expected);
+        }
+    }
+
+    #[test]
+    fn steps_that_change_graph_depth_do_not_write() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut blackboard = Graph::new();
+        blackboard.add_node(5);
+        for code in &["( GRAPH.POP )", "( GRAPH.DUP )", "( GRAPH.ADD )"] {
+            let mut coexec = CoExecution::new(blackboard.clone(), ConflictPolicy::FirstWriterWins);
+            let mut push_state = program(&instruction_set, code);
+            push_state.graph_stack.push(Graph::new());
+            coexec.add_program(push_state);
+            assert_eq!(coexec.run(&mut instruction_set, 100), PushInterpreterState::NoErrors);
+            assert_eq!(coexec.blackboard, blackboard, "{}", code);
+            assert_eq!(coexec.push_states[0].graph_stack.size(), 1, "{}", code);
+            assert_eq!(coexec.push_states[0].graph_stack.get(0).unwrap().node_size(), 0, "{}", code);
+        }
+        // A program with a full GRAPH stack still writes and keeps its graphs
+        let mut coexec = CoExecution::new(blackboard.clone(), ConflictPolicy::FirstWriterWins);
+        let mut push_state = program(&instruction_set, "( 7 GRAPH.NODE*ADD )");
+        while !push_state.graph_stack.is_full() {
+            push_state.graph_stack.push(Graph::new());
+        }
+        coexec.add_program(push_state);
+        assert_eq!(coexec.run(&mut instruction_set, 100), PushInterpreterState::NoErrors);
+        assert_eq!(coexec.blackboard.node_size(), 2);
+        assert!(coexec.push_states[0].graph_stack.is_full());
+        assert!(coexec.push_states[0].graph_stack.iter().all(|graph| graph.node_size() == 0));
+    }
+
+    #[test]
+    fn programs_cannot_swap_blackboard() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut blackboard = Graph::new();
+        blackboard.add_node(5);
+        let mut own_graph = Graph::new();
+        own_graph.add_node(9);
+        own_graph.add_node(9);
+        let mut coexec = CoExecution::new(blackboard.clone(), ConflictPolicy::FirstWriterWins);
+        let mut push_state = program(&instruction_set, "( GRAPH.SWAP 7 GRAPH.NODE*ADD GRAPH.SWAP )");
+        push_state.graph_stack.push(own_graph.clone());
+        coexec.add_program(push_state);
+        assert_eq!(coexec.run(&mut instruction_set, 100), PushInterpreterState::NoErrors);
+        // Only the node added to the blackboard is published
+        assert_eq!(coexec.blackboard.node_size(), 2);
+        assert_eq!(coexec.push_states[0].graph_stack.size(), 1);
+        assert_eq!(*coexec.push_states[0].graph_stack.get(0).unwrap(), own_graph);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::push::batch::batch_seed;
+use crate::push::configuration::PushConfiguration;
+use crate::push::instructions::InstructionSet;
+use crate::push::interpreter::{PushInterpreter, PushInterpreterState};
+use crate::push::parser::PushParser;
+use crate::push::serialize::SerializeOptions;
+use crate::push::state::PushState;
+use std::collections::HashMap;
+
+/// Final state of a program under one instruction set variant.
+#[derive(Debug, PartialEq)]
+pub struct VariantOutcome {
+    pub interpreter_state: PushInterpreterState,
+    // Compared stacks serialized to JSON
+    pub stacks: String,
+}
+
+/// A program whose outcome differs between the variants.
+#[derive(Debug)]
+pub struct OutcomeDiff {
+    // Position of the program in the corpus
+    pub index: usize,
+    pub program: String,
+    pub a: VariantOutcome,
+    pub b: VariantOutcome,
+}
+
+/// Result of running a program corpus under two instruction set variants.
+#[derive(Debug, Default)]
+pub struct ComparisonReport {
+    pub programs: usize,
+    pub diffs: Vec<OutcomeDiff>,
+    // Number of programs per termination reason, e.g. "StepLimitExceeded"
+    pub terminations_a: HashMap<String, usize>,
+    pub terminations_b: HashMap<String, usize>,
+}
+
+impl ComparisonReport {
+    /// Returns the number of programs with identical outcomes.
+    pub fn identical(&self) -> usize {
+        self.programs - self.diffs.len()
+    }
+
+    /// Returns the fraction of programs whose outcome changed.
+    pub fn change_rate(&self) -> f64 {
+        if self.programs == 0 {
+            0.0
+        } else {
+            self.diffs.len() as f64 / self.programs as f64
+        }
+    }
+}
+
+/// Parameters of a comparison.
+#[derive(Clone, Debug)]
+pub struct ComparisonOptions {
+    // Configuration applied to the state of each run
+    pub configuration: PushConfiguration,
+    // The program at index i is seeded with the same seed in both variants
+    pub base_seed: u64,
+    // Stacks whose final content is compared
+    pub compared: SerializeOptions,
+}
+
+impl ComparisonOptions {
+    pub fn new() -> Self {
+        let mut compared = SerializeOptions::all();
+        compared.include_bindings = false;
+        compared.include_rng = false;
+        Self {
+            configuration: PushConfiguration::new(),
+            base_seed: 0,
+            compared,
+        }
+    }
+}
+
+impl Default for ComparisonOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn run_variant(
+    program: &str,
+    seed: u64,
+    instruction_set: &mut InstructionSet,
+    options: &ComparisonOptions,
+) -> VariantOutcome {
+    let mut push_state = PushState::new();
+    push_state.configuration = options.configuration.clone();
+    push_state.seed(seed);
+    PushParser::parse_program(&mut push_state, instruction_set, program);
+    let interpreter_state = PushInterpreter::run(&mut push_state, instruction_set);
+    VariantOutcome {
+        interpreter_state,
+        stacks: push_state.serialize(&options.compared),
+    }
+}
+
+/// Runs every program of the corpus under both instruction set variants and collects
+/// the programs whose final state or termination reason differ. The variants are
+/// given as functions that build the instruction set, e.g. the default set with and
+/// without a group of new instructions.
+pub fn compare_variants<A, B>(
+    programs: &[String],
+    variant_a: A,
+    variant_b: B,
+    options: &ComparisonOptions,
+) -> ComparisonReport
+where
+    A: Fn() -> InstructionSet,
+    B: Fn() -> InstructionSet,
+{
+    let mut instruction_set_a = variant_a();
+    let mut instruction_set_b = variant_b();
+    let mut report = ComparisonReport {
+        programs: programs.len(),
+        ..Default::default()
+    };
+    for (index, program) in programs.iter().enumerate() {
+        let seed = batch_seed(options.base_seed, index);
+        let a = run_variant(program, seed, &mut instruction_set_a, options);
+        let b = run_variant(program, seed, &mut instruction_set_b, options);
+        *report
+            .terminations_a
+            .entry(format!("{:?}", a.interpreter_state))
+            .or_insert(0) += 1;
+        *report
+            .terminations_b
+            .entry(format!("{:?}", b.interpreter_state))
+            .or_insert(0) += 1;
+        if a != b {
+            report.diffs.push(OutcomeDiff {
+                index,
+                program: program.clone(),
+                a,
+                b,
+            });
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_set() -> InstructionSet {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        instruction_set
+    }
+
+    #[test]
+    fn comparison_reports_changed_programs() {
+        let programs: Vec<String> = vec![
+            "( 1 2 INTEGER.+ )".to_string(),
+            "( 1.5 FLOATVECTOR.ONES 3 FLOATVECTOR.SUM )".to_string(),
+            "( 2 3 INTEGER.* )".to_string(),
+        ];
+        let without_sum = || {
+            let mut instruction_set = default_set();
+            instruction_set.remove("FLOATVECTOR.SUM");
+            instruction_set
+        };
+        let report = compare_variants(
+            &programs,
+            default_set,
+            without_sum,
+            &ComparisonOptions::new(),
+        );
+        assert_eq!(report.programs, 3);
+        assert_eq!(report.identical(), 2);
+        assert_eq!(report.diffs.len(), 1);
+        assert_eq!(report.diffs[0].index, 1);
+        assert!(report.diffs[0].b.stacks.contains("FLOATVECTOR.SUM"));
+        assert_eq!(report.terminations_a.get("NoErrors"), Some(&3));
+        assert!((report.change_rate() - 1.0 / 3.0).abs() < 1e-9);
+    }
+}
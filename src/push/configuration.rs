@@ -1,3 +1,11 @@
+use crate::push::graph::IdPolicy;
+use serde::{Deserialize, Serialize};
+
+/// Names of the semantics profiles that are accepted by PushConfiguration::profile.
+pub const PROFILE_NAMES: &[&str] = &["pushr", "clojush", "strict"];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PushConfiguration {
     // The maximum FLOAT that will be produced as an ephemeral random FLOAT constant or from a call to FLOAT.RAND.
     pub max_random_float: f32,
@@ -27,6 +35,88 @@ pub struct PushConfiguration {
     // The maximum number of points that can occur in any program on the CODE stack. Instructions
     // that would violate this limit act as NOOPs (they do nothing).
     pub max_points_in_program: i32,
+    // The policy that determines how node IDs are assigned by graphs created with GRAPH.ADD.
+    pub graph_id_policy: IdPolicy,
+    // The result of divisions and modulo operations by zero (scalar and vector instructions).
+    pub div_zero_policy: DivZeroPolicy,
+    // The maximum number of elements of a vector item. Instructions that would create larger
+    // vectors truncate them to this size.
+    pub max_vector_size: usize,
+    // Report errors like exceeded limits to the ERROR stack instead of silently
+    // ignoring them.
+    pub strict: bool,
+    // Out-of-range indices of the vector GET and SET instructions are reported to the ERROR
+    // stack and the instruction acts as a NOOP. By default indices are bound to the valid range.
+    pub strict_indexing: bool,
+    // Restore the name bindings after the execution of CODE.DO, CODE.DO* and bound definitions
+    // (dynamic extent). Bindings survive the restore only if they are exported with NAME.EXPORT.
+    pub scoped_bindings: bool,
+    // Element-wise vector instructions (e.g. INTVECTOR.+) take an index offset from the
+    // INTEGER stack. If disabled, the offset is zero and the INTEGER stack is not touched.
+    // Vectors can be shifted explicitly with the ALIGN instructions.
+    pub vector_offsets: bool,
+    // Transfer consecutive literals from the EXEC stack to their stacks in a single
+    // iteration of PushInterpreter::run.
+    pub literal_bursts: LiteralBursts,
+    // The maximum number of name bindings. Definitions of new names beyond this limit are
+    // handled according to the binding eviction policy. Unbounded if None.
+    pub max_name_bindings: Option<usize>,
+    // Handling of new definitions if the maximum number of name bindings is reached.
+    pub binding_eviction: BindingEviction,
+    // The probability that a point of randomly generated code is a tag instruction (TAG,
+    // TAGGED or UNTAG) with a random tag.
+    pub tag_erc_probability: f32,
+    // Tags of randomly generated tag instructions are drawn from 0 (inclusive) to
+    // tag_limit (exclusive).
+    pub tag_limit: i32,
+    // The maximum number of items in the tag space. TAG instructions that would store an
+    // item under a new tag beyond this limit act as NOOPs.
+    pub max_tags: usize,
+}
+
+/// Defines what happens if a new name is defined while the number of name bindings
+/// is at its maximum.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BindingEviction {
+    // The new definition is ignored.
+    RejectNew,
+    // The least recently defined or used binding is removed to make room.
+    EvictLru,
+}
+
+/// Defines how PushInterpreter::run executes consecutive literals on the EXEC stack.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LiteralBursts {
+    // Each literal is executed in a separate step.
+    Off,
+    // Consecutive literals are transferred at once but each literal counts as a step,
+    // so that step limits are hit at the same point as without bursts.
+    Compatible,
+    // Consecutive literals are transferred at once and count as a single step.
+    Fast,
+}
+
+/// Defines the result of a division or modulo operation by zero.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DivZeroPolicy {
+    // The instruction leaves its arguments on the stacks and does nothing.
+    Noop,
+    // The dividend is pushed as result (Clojush semantics).
+    PushDividend,
+    // Zero is pushed as result.
+    PushZero,
+}
+
+impl DivZeroPolicy {
+    /// Returns the result of dividing the dividend by zero or None if the
+    /// instruction should act as a NOOP.
+    pub fn resolve<T: Default>(&self, dividend: T) -> Option<T> {
+        match self {
+            DivZeroPolicy::Noop => None,
+            DivZeroPolicy::PushDividend => Some(dividend),
+            DivZeroPolicy::PushZero => Some(T::default()),
+        }
+    }
 }
 
 impl PushConfiguration {
@@ -42,6 +132,57 @@ impl PushConfiguration {
             new_erc_name_probability: 0.001,
             max_points_in_random_expressions: 25,
             max_points_in_program: 100,
+            graph_id_policy: IdPolicy::Monotonic,
+            div_zero_policy: DivZeroPolicy::Noop,
+            max_vector_size: 10000,
+            strict: false,
+            strict_indexing: false,
+            scoped_bindings: false,
+            vector_offsets: true,
+            literal_bursts: LiteralBursts::Off,
+            max_name_bindings: None,
+            binding_eviction: BindingEviction::RejectNew,
+            tag_erc_probability: 0.0,
+            tag_limit: 10000,
+            max_tags: 1000,
+        }
+    }
+
+    /// Configuration that follows the semantics of Clojush where they differ
+    /// from the defaults.
+    pub fn clojush() -> Self {
+        Self {
+            div_zero_policy: DivZeroPolicy::PushDividend,
+            vector_offsets: false,
+            ..Self::new()
+        }
+    }
+
+    /// Configuration that reports errors and out-of-range indices instead of hiding
+    /// them, e.g. for hand-written programs.
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            strict_indexing: true,
+            ..Self::new()
         }
     }
+
+    /// Returns the configuration of a named semantics profile: "pushr" for the
+    /// defaults, "clojush" for the Clojush semantics or "strict" for strict error
+    /// reporting.
+    pub fn profile(name: &str) -> Option<Self> {
+        match name {
+            "pushr" => Some(Self::new()),
+            "clojush" => Some(Self::clojush()),
+            "strict" => Some(Self::strict()),
+            _ => None,
+        }
+    }
+}
+
+impl Default for PushConfiguration {
+    fn default() -> Self {
+        Self::new()
+    }
 }
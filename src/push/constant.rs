@@ -0,0 +1,49 @@
+use crate::push::instructions::Instruction;
+use crate::push::instructions::InstructionCache;
+use crate::push::state::PushState;
+use std::collections::HashMap;
+
+/// Constants are read-only items that are set for a run by the host, e.g. with
+/// Runner::set_constant. Unlike name bindings they cannot be changed by the program.
+pub fn load_constant_instructions(map: &mut HashMap<String, Instruction>) {
+    map.insert(String::from("CONST.GET"), Instruction::new(const_get));
+}
+
+/// CONST.GET: Pushes the constant whose name is on top of the NAME stack onto the EXEC
+/// stack. Acts as NOOP if there is no constant with this name.
+fn const_get(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(name) = push_state.name_stack.get(0) {
+        if let Some(item) = push_state.constants.get(name) {
+            let item = item.clone();
+            push_state.name_stack.pop();
+            push_state.exec_stack.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::push::instructions::InstructionSet;
+    use crate::push::item::Item;
+    use crate::push::parser::PushParser;
+    use crate::push::runner::Runner;
+    use crate::push::state::PushState;
+
+    #[test]
+    fn const_get_pushes_runner_constants() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(
+            &mut push_state,
+            &instruction_set,
+            "( 'THRESHOLD CONST.GET 'MISSING CONST.GET 2 INTEGER.* )",
+        );
+        let mut runner = Runner::new(push_state, instruction_set);
+        runner.set_constant("THRESHOLD", Item::int(21));
+        while !runner.step() {}
+        assert_eq!(runner.push_state.int_stack.to_string(), "42");
+        assert_eq!(runner.push_state.name_stack.to_string(), "MISSING");
+        assert!(runner.push_state.name_bindings.is_empty());
+    }
+}
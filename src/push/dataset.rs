@@ -0,0 +1,113 @@
+use crate::push::evaluator::CaseSource;
+use crate::push::vector::FloatVector;
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+const VALUE_SIZE: usize = std::mem::size_of::<f32>();
+
+/// Dataset of FLOATVECTOR cases that is memory-mapped instead of loaded. The file
+/// contains the rows one after another as little-endian f32 values without header.
+/// All rows have the same number of columns. Rows are decoded on access so that
+/// the memory usage does not depend on the size of the dataset.
+pub struct MmapDataset {
+    mmap: Mmap,
+    columns: usize,
+}
+
+impl MmapDataset {
+    /// Maps the dataset file with the given number of columns per row. Fails if
+    /// the file size is not a multiple of the row size.
+    pub fn open(path: &str, columns: usize) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // The file must not be modified while it is mapped
+        let mmap = unsafe { Mmap::map(&file)? };
+        if columns == 0 || mmap.len() % (columns * VALUE_SIZE) != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "File size is not a multiple of the row size",
+            ));
+        }
+        Ok(Self { mmap, columns })
+    }
+
+    /// Writes the rows in the format expected by open.
+    pub fn write<'a, I>(path: &str, rows: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = &'a [f32]>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        for row in rows {
+            for value in row {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// Returns the number of columns per row.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the number of rows.
+    pub fn rows(&self) -> usize {
+        self.mmap.len() / (self.columns * VALUE_SIZE)
+    }
+
+    /// Decodes the row with the given index.
+    pub fn row(&self, index: usize) -> Option<FloatVector> {
+        if index >= self.rows() {
+            return None;
+        }
+        let row_size = self.columns * VALUE_SIZE;
+        let bytes = &self.mmap[index * row_size..(index + 1) * row_size];
+        let values = bytes
+            .chunks_exact(VALUE_SIZE)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Some(FloatVector::new(values))
+    }
+}
+
+impl CaseSource for MmapDataset {
+    fn size(&self) -> usize {
+        self.rows()
+    }
+
+    fn case(&self, index: usize) -> Option<FloatVector> {
+        self.row(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::configuration::PushConfiguration;
+    use crate::push::evaluator::Evaluator;
+    use crate::push::instructions::InstructionSet;
+
+    #[test]
+    fn mmap_dataset_streams_rows_to_evaluator() {
+        let path = std::env::temp_dir().join(format!("pushr-dataset-{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        let rows: Vec<Vec<f32>> = (0..1000).map(|i| vec![i as f32, 1.0, -2.5]).collect();
+        MmapDataset::write(path, rows.iter().map(|r| r.as_slice())).unwrap();
+        let dataset = MmapDataset::open(path, 3).unwrap();
+        assert_eq!(dataset.rows(), 1000);
+        assert_eq!(dataset.row(7).unwrap().values, vec![7.0, 1.0, -2.5]);
+        assert!(dataset.row(1000).is_none());
+        assert!(MmapDataset::open(path, 7).is_err());
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let errors = Evaluator::new(PushConfiguration::new()).evaluate(
+            "( FLOATVECTOR.SUM )",
+            &mut instruction_set,
+            &dataset,
+            |index, _, push_state| (push_state.float_stack.get(0).unwrap() - index as f32) as f64,
+        );
+        assert_eq!(errors.len(), 1000);
+        assert!(errors.iter().all(|e| *e == -1.5));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+use crate::push::configuration::PushConfiguration;
+use crate::push::instructions::InstructionSet;
+use crate::push::interpreter::PushInterpreter;
+use crate::push::item::Item;
+use crate::push::parser::PushParser;
+use crate::push::state::{PushState, STACK_NAMES};
+use std::fmt;
+
+/// Semantics under which a program is executed, e.g. the default pushr semantics
+/// or the Clojush compatible semantics.
+#[derive(Clone, Debug)]
+pub struct Profile {
+    pub name: String,
+    pub configuration: PushConfiguration,
+}
+
+impl Profile {
+    pub fn new(name: &str, configuration: PushConfiguration) -> Self {
+        Self {
+            name: name.to_string(),
+            configuration,
+        }
+    }
+
+    /// Returns the built-in profile with the given name (see PushConfiguration::profile).
+    pub fn named(name: &str) -> Option<Self> {
+        PushConfiguration::profile(name).map(|configuration| Profile::new(name, configuration))
+    }
+}
+
+/// First point at which the stacks of the two executions differ.
+#[derive(Clone, Debug)]
+pub struct Divergence {
+    // Number of steps executed by both sides, including the diverging step
+    pub step: usize,
+    // The item executed in the diverging step
+    pub item: Option<Item>,
+    // Short names of the stacks that differ
+    pub stacks: Vec<&'static str>,
+    pub left: PushState,
+    pub right: PushState,
+    pub left_profile: String,
+    pub right_profile: String,
+}
+
+impl fmt::Display for Divergence {
+    /// Dumps all stacks of both states. Stacks that differ are marked with '*'.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let item = self.item.as_ref().map(|i| i.to_string()).unwrap_or_default();
+        writeln!(f, "> DIVERGENCE AT STEP {}: {}", self.step, item)?;
+        let width = self.left_profile.len().max(self.right_profile.len());
+        for (name, stack_id) in STACK_NAMES.iter() {
+            let marker = if self.stacks.contains(name) { '*' } else { ' ' };
+            for (profile, push_state) in [
+                (&self.left_profile, &self.left),
+                (&self.right_profile, &self.right),
+            ]
+            .iter()
+            {
+                writeln!(
+                    f,
+                    "{} {:<11} {:<width$}: {}",
+                    marker,
+                    name.to_uppercase(),
+                    profile,
+                    push_state.stack_to_string(*stack_id).unwrap_or_default(),
+                    width = width
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Result of a differential run.
+#[derive(Clone, Debug)]
+pub enum DifferentialResult {
+    // Both executions halted after the given number of steps with equal stacks.
+    Agreed(usize),
+    // The step limit was reached without a divergence.
+    StepLimitExceeded(usize),
+    Diverged(Box<Divergence>),
+}
+
+/// Executes a program under two profiles in lockstep. After each step the stacks
+/// of both states are compared and the execution halts at the first divergence.
+///
+/// Instructions that draw random numbers are not synchronized, programs that use
+/// them can diverge under equal profiles.
+pub struct DifferentialRun {
+    pub left: Profile,
+    pub right: Profile,
+}
+
+impl DifferentialRun {
+    pub fn new(left: Profile, right: Profile) -> Self {
+        Self { left, right }
+    }
+
+    /// Returns the short names of the stacks that differ.
+    pub fn compare(left: &PushState, right: &PushState) -> Vec<&'static str> {
+        STACK_NAMES
+            .iter()
+            .filter(|(_, id)| left.stack_to_string(*id) != right.stack_to_string(*id))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    fn load(profile: &Profile, instruction_set: &InstructionSet, code: &str) -> PushState {
+        let mut push_state = PushState::new();
+        push_state.configuration = profile.configuration.clone();
+        PushParser::parse_program(&mut push_state, instruction_set, code);
+        PushParser::copy_to_code_stack(&mut push_state);
+        push_state
+    }
+
+    /// Runs the program until both executions halt, a divergence is found or
+    /// max_steps steps are executed.
+    pub fn run(
+        &self,
+        instruction_set: &mut InstructionSet,
+        code: &str,
+        max_steps: usize,
+    ) -> DifferentialResult {
+        let icache = instruction_set.cache();
+        let mut left = DifferentialRun::load(&self.left, instruction_set, code);
+        let mut right = DifferentialRun::load(&self.right, instruction_set, code);
+        let mut step = 0;
+        loop {
+            if left.exec_stack.size() == 0 && right.exec_stack.size() == 0 {
+                return DifferentialResult::Agreed(step);
+            }
+            if step == max_steps {
+                return DifferentialResult::StepLimitExceeded(step);
+            }
+            let item = left.exec_stack.copy(0);
+            PushInterpreter::step(&mut left, instruction_set, &icache);
+            PushInterpreter::step(&mut right, instruction_set, &icache);
+            step += 1;
+            let stacks = DifferentialRun::compare(&left, &right);
+            if !stacks.is_empty() {
+                return DifferentialResult::Diverged(Box::new(Divergence {
+                    step,
+                    item,
+                    stacks,
+                    left,
+                    right,
+                    left_profile: self.left.name.clone(),
+                    right_profile: self.right.name.clone(),
+                }));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction_set() -> InstructionSet {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        instruction_set
+    }
+
+    #[test]
+    fn differential_run_halts_at_first_divergence() {
+        let run = DifferentialRun::new(
+            Profile::named("pushr").unwrap(),
+            Profile::named("clojush").unwrap(),
+        );
+        let mut instruction_set = instruction_set();
+        match run.run(&mut instruction_set, "( 1 2 INTEGER.+ 0 INTEGER./ 5 )", 100) {
+            DifferentialResult::Diverged(divergence) => {
+                assert_eq!(divergence.step, 6);
+                assert_eq!(divergence.item.as_ref().unwrap().to_string(), "INTEGER./");
+                assert_eq!(divergence.stacks, vec!["int"]);
+                assert_eq!(divergence.left.int_stack.to_string(), "0 3");
+                assert_eq!(divergence.right.int_stack.to_string(), "3");
+                let dump = divergence.to_string();
+                assert!(dump.starts_with("> DIVERGENCE AT STEP 6: INTEGER./"));
+                assert!(dump.contains("* INT         pushr  : 0 3"));
+                assert!(dump.contains("* INT         clojush: 3"));
+            }
+            other => panic!("Expected divergence, found {:?}", other),
+        }
+        match run.run(&mut instruction_set, "( 1 2 INTEGER.+ )", 100) {
+            DifferentialResult::Agreed(steps) => assert_eq!(steps, 4),
+            other => panic!("Expected agreement, found {:?}", other),
+        }
+        match run.run(&mut instruction_set, "( 1 2 INTEGER.+ )", 2) {
+            DifferentialResult::StepLimitExceeded(steps) => assert_eq!(steps, 2),
+            other => panic!("Expected step limit, found {:?}", other),
+        }
+    }
+}
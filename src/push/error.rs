@@ -0,0 +1,38 @@
+use crate::push::stack::PushPrint;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Errors that are pushed to the ERROR stack in strict mode. In the default
+/// (forgiving) mode the corresponding instructions only NOOP or truncate.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PushError {
+    // A vector would exceed the configured maximum number of elements.
+    VectorSizeCap { requested: usize, cap: usize },
+    // A new name was defined while the number of name bindings was at its maximum.
+    // The definition was rejected or another binding was evicted.
+    NameBindingCap { name: String, cap: usize },
+    // An index of a vector GET or SET instruction is outside of the vector.
+    IndexOutOfRange { index: i32, len: usize },
+}
+
+impl PushPrint for PushError {
+    fn to_pstring(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for PushError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::VectorSizeCap { requested, cap } => {
+                write!(f, "VECTOR*SIZE*CAP({},{})", requested, cap)
+            }
+            PushError::NameBindingCap { name, cap } => {
+                write!(f, "NAME*BINDING*CAP({},{})", name, cap)
+            }
+            PushError::IndexOutOfRange { index, len } => {
+                write!(f, "INDEX*OUT*OF*RANGE({},{})", index, len)
+            }
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use crate::push::configuration::PushConfiguration;
+use crate::push::hash::StableHasher;
+use crate::push::instructions::InstructionSet;
+use crate::push::interpreter::PushInterpreter;
+use crate::push::parser::PushParser;
+use crate::push::state::PushState;
+use crate::push::vector::FloatVector;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hasher;
+use std::sync::Mutex;
+
+/// Source of test cases that are passed to a program as FLOATVECTOR input.
+/// Cases are requested one at a time so that implementations can load them
+/// lazily.
+pub trait CaseSource {
+    /// Returns the number of cases.
+    fn size(&self) -> usize;
+
+    /// Returns the case with the given index.
+    fn case(&self, index: usize) -> Option<FloatVector>;
+}
+
+impl CaseSource for Vec<FloatVector> {
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    fn case(&self, index: usize) -> Option<FloatVector> {
+        self.get(index).cloned()
+    }
+}
+
+/// Returns a stable 64-bit hash of the program text (see hash::StableHasher). Unlike the hashers
+/// of the standard library the result does not depend on the platform or the
+/// Rust version.
+pub fn program_hash(program: &str) -> u64 {
+    let mut hasher = StableHasher::new();
+    hasher.write(program.as_bytes());
+    hasher.finish()
+}
+
+/// Returns a stable 64-bit hash of the index and the values of a test case (see hash::StableHasher).
+pub fn case_hash(index: usize, case: &FloatVector) -> u64 {
+    let mut hasher = StableHasher::new();
+    hasher.write_usize(index);
+    for value in case.values.iter() {
+        hasher.write_u32(value.to_bits());
+    }
+    hasher.finish()
+}
+
+/// Returns the RNG seed for the evaluation of a program on a test case. The seed
+/// is derived from the base seed of the run, the index of the case and the hash of
+/// the program (see program_hash) by mixing them with the SplitMix64 finalizer:
+///
+/// ```text
+/// seed = mix(mix(mix(base) ^ case_index) ^ program_hash)
+/// ```
+///
+/// Equal inputs give equal seeds on all machines, so that stochastic evaluations
+/// can be reproduced and compared.
+pub fn derive_seed(base: u64, case_index: usize, program_hash: u64) -> u64 {
+    fn mix(value: u64) -> u64 {
+        let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+    mix(mix(mix(base) ^ case_index as u64) ^ program_hash)
+}
+
+/// Hits and misses of an EvaluationCache.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStatistics {
+    pub hits: usize,
+    pub misses: usize,
+    // The number of cached errors
+    pub entries: usize,
+}
+
+impl CacheStatistics {
+    /// Returns the share of lookups that were answered from the cache.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// Errors of programs on test cases keyed by (program_hash, case_hash). If the cache
+/// is full, the least recently used error is evicted.
+pub struct EvaluationCache {
+    capacity: usize,
+    // Error and time of the last use of each key
+    errors: HashMap<(u64, u64), (f64, u64)>,
+    // Keys by time of their last use
+    usage: BTreeMap<u64, (u64, u64)>,
+    time: u64,
+    statistics: CacheStatistics,
+}
+
+impl EvaluationCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            errors: HashMap::new(),
+            usage: BTreeMap::new(),
+            time: 0,
+            statistics: CacheStatistics::default(),
+        }
+    }
+
+    /// Returns the cached error and marks it as most recently used.
+    pub fn get(&mut self, key: (u64, u64)) -> Option<f64> {
+        self.time += 1;
+        match self.errors.get_mut(&key) {
+            Some((error, last_use)) => {
+                self.usage.remove(last_use);
+                self.usage.insert(self.time, key);
+                *last_use = self.time;
+                self.statistics.hits += 1;
+                Some(*error)
+            }
+            None => {
+                self.statistics.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Adds the error and evicts the least recently used errors that exceed the
+    /// capacity.
+    pub fn insert(&mut self, key: (u64, u64), error: f64) {
+        self.time += 1;
+        if let Some((_, last_use)) = self.errors.insert(key, (error, self.time)) {
+            self.usage.remove(&last_use);
+        }
+        self.usage.insert(self.time, key);
+        while self.errors.len() > self.capacity {
+            match self.usage.pop_first() {
+                Some((_, evicted)) => self.errors.remove(&evicted),
+                None => break,
+            };
+        }
+    }
+
+    pub fn statistics(&self) -> CacheStatistics {
+        CacheStatistics {
+            entries: self.errors.len(),
+            ..self.statistics
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = EvaluationCache::new(self.capacity);
+    }
+}
+
+/// Evaluates a program on a set of test cases.
+pub struct Evaluator {
+    pub configuration: PushConfiguration,
+    // Base seed of the run. If set, the state of each case is seeded with
+    // derive_seed, otherwise it is seeded from entropy.
+    pub seed: Option<u64>,
+    // Errors of earlier evaluations (see with_cache)
+    cache: Option<Mutex<EvaluationCache>>,
+}
+
+impl Evaluator {
+    pub fn new(configuration: PushConfiguration) -> Self {
+        Self {
+            configuration,
+            seed: None,
+            cache: None,
+        }
+    }
+
+    /// Makes the evaluations reproducible by seeding each case with derive_seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Memoizes the errors of up to capacity evaluations of a program on a case, so
+    /// that programs that are evaluated again, e.g. parents that are copied to the
+    /// next generation, are not executed. This requires that the error function
+    /// returns the same error for the same program and case, i.e. the evaluations
+    /// must be seeded (see with_seed) if the program uses random instructions.
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Mutex::new(EvaluationCache::new(capacity)));
+        self
+    }
+
+    /// Returns the hits and misses of the cache or None if no cache is used.
+    pub fn cache_statistics(&self) -> Option<CacheStatistics> {
+        self.cache.as_ref().map(|cache| cache.lock().unwrap().statistics())
+    }
+
+    /// Runs the program once per case. Each run starts with a fresh state whose
+    /// FLOATVECTOR stack contains the case. The error function maps the case and
+    /// the final state to the error of the program on this case. Only a single case
+    /// is held in memory at any time. Cached errors are returned without running the
+    /// program.
+    pub fn evaluate<C, F>(
+        &self,
+        program: &str,
+        instruction_set: &mut InstructionSet,
+        cases: &C,
+        mut error: F,
+    ) -> Vec<f64>
+    where
+        C: CaseSource + ?Sized,
+        F: FnMut(usize, &FloatVector, &PushState) -> f64,
+    {
+        // The program is parsed when the first case is not found in the cache
+        let mut initial_state: Option<PushState> = None;
+        let hash = program_hash(program);
+        let mut errors = Vec::with_capacity(cases.size());
+        for index in 0..cases.size() {
+            if let Some(case) = cases.case(index) {
+                let key = (hash, case_hash(index, &case));
+                let cached = self
+                    .cache
+                    .as_ref()
+                    .and_then(|cache| cache.lock().unwrap().get(key));
+                if let Some(cached_error) = cached {
+                    errors.push(cached_error);
+                    continue;
+                }
+                let mut push_state = initial_state
+                    .get_or_insert_with(|| {
+                        let mut push_state = PushState::new();
+                        push_state.configuration = self.configuration.clone();
+                        PushParser::parse_program(&mut push_state, instruction_set, program);
+                        push_state
+                    })
+                    .clone();
+                if let Some(base) = self.seed {
+                    push_state.seed(derive_seed(base, index, hash));
+                }
+                push_state.float_vector_stack.push(case.clone());
+                PushInterpreter::run(&mut push_state, instruction_set);
+                let case_error = error(index, &case, &push_state);
+                if let Some(cache) = self.cache.as_ref() {
+                    cache.lock().unwrap().insert(key, case_error);
+                }
+                errors.push(case_error);
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluator_runs_program_per_case() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let cases = vec![
+            FloatVector::new(vec![1.0, 2.0]),
+            FloatVector::new(vec![3.0, 4.0]),
+        ];
+        let errors = Evaluator::new(PushConfiguration::new()).evaluate(
+            "( FLOATVECTOR.SUM )",
+            &mut instruction_set,
+            &cases,
+            |_, case, push_state| {
+                let target = case.values[0] * case.values[1];
+                (push_state.float_stack.get(0).unwrap() - target).abs() as f64
+            },
+        );
+        assert_eq!(errors, vec![1.0, 5.0]);
+    }
+
+    #[test]
+    fn cached_errors_skip_execution() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let cases = vec![
+            FloatVector::new(vec![1.0, 2.0]),
+            FloatVector::new(vec![3.0, 4.0]),
+        ];
+        let evaluator = Evaluator::new(PushConfiguration::new()).with_cache(3);
+        let mut runs = 0;
+        let mut evaluate = |program: &str| {
+            evaluator.evaluate(program, &mut instruction_set, &cases, |_, _, push_state| {
+                runs += 1;
+                *push_state.float_stack.get(0).unwrap() as f64
+            })
+        };
+        assert_eq!(evaluate("( FLOATVECTOR.SUM )"), vec![3.0, 7.0]);
+        assert_eq!(evaluate("( FLOATVECTOR.SUM )"), vec![3.0, 7.0]);
+        assert_eq!(evaluate("( FLOATVECTOR.MEAN )"), vec![1.5, 3.5]);
+        assert_eq!(runs, 4);
+        // The error of the first case of FLOATVECTOR.SUM was least recently used
+        // and has been evicted
+        let cases = vec![cases[0].clone()];
+        assert_eq!(
+            evaluator.evaluate("( FLOATVECTOR.SUM )", &mut instruction_set, &cases, |_, _, _| 0.0),
+            vec![0.0]
+        );
+        let statistics = evaluator.cache_statistics().unwrap();
+        assert_eq!((statistics.hits, statistics.misses, statistics.entries), (2, 5, 3));
+        assert_eq!(statistics.hit_rate(), 2.0 / 7.0);
+        assert_eq!(Evaluator::new(PushConfiguration::new()).cache_statistics(), None);
+    }
+
+    #[test]
+    fn seeded_evaluations_are_reproducible() {
+        assert_eq!(program_hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(derive_seed(1, 2, 3), derive_seed(1, 2, 3));
+        assert_ne!(derive_seed(1, 2, 3), derive_seed(1, 3, 3));
+        assert_ne!(derive_seed(1, 2, 3), derive_seed(1, 2, 4));
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let cases = vec![FloatVector::new(vec![]); 3];
+        let mut random = |seed: u64| {
+            Evaluator::new(PushConfiguration::new())
+                .with_seed(seed)
+                .evaluate(
+                    "( FLOAT.RAND )",
+                    &mut instruction_set,
+                    &cases,
+                    |_, _, push_state| *push_state.float_stack.get(0).unwrap() as f64,
+                )
+        };
+        let errors = random(7);
+        assert_ne!(errors[0], errors[1]);
+        assert_eq!(errors, random(7));
+        assert_ne!(random(7), random(8));
+    }
+}
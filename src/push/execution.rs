@@ -3,6 +3,8 @@ use crate::push::instructions::InstructionCache;
 use crate::push::item::Item;
 use crate::push::state::PushState;
 use crate::push::state::*;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
 use std::collections::HashMap;
 use std::process::Command;
 use std::{thread, time::Duration};
@@ -13,25 +15,82 @@ use std::{thread, time::Duration};
 /// manipulations to the EXEC stack are "live" in the sense that they are manipulating the actual
 /// execution state of the interpreter, not just code that might later be executed.
 pub fn load_exec_instructions(map: &mut HashMap<String, Instruction>) {
-    map.insert(String::from("EXEC.="), Instruction::new(exec_eq));
+    map.insert(
+        String::from("EXEC.="),
+        Instruction::new(exec_eq).with_blocks(2),
+    );
+    map.insert(
+        String::from("EXEC.CHOOSE"),
+        Instruction::new(exec_choose).with_blocks(2),
+    );
     map.insert(String::from("EXEC.CMD"), Instruction::new(exec_cmd));
-    map.insert(String::from("EXEC.DEFINE"), Instruction::new(exec_define));
-    map.insert(String::from("EXEC.LOOP"), Instruction::new(exec_loop));
-    map.insert(String::from("EXEC.DUP"), Instruction::new(exec_dup));
+    map.insert(
+        String::from("EXEC.DEFINE"),
+        Instruction::new(exec_define).with_blocks(1),
+    );
+    map.insert(
+        String::from("EXEC.LOOP"),
+        Instruction::new(exec_loop).with_blocks(1),
+    );
+    map.insert(
+        String::from("EXEC.DO*BOOLVECTOR"),
+        Instruction::new(exec_do_bool_vector).with_blocks(1),
+    );
+    map.insert(
+        String::from("EXEC.DO*FLOATVECTOR"),
+        Instruction::new(exec_do_float_vector).with_blocks(1),
+    );
+    map.insert(
+        String::from("EXEC.DO*INTVECTOR"),
+        Instruction::new(exec_do_int_vector).with_blocks(1),
+    );
+    map.insert(
+        String::from("EXEC.DUP"),
+        Instruction::new(exec_dup).with_blocks(1),
+    );
+    map.insert(
+        String::from("EXEC.FLATTEN"),
+        Instruction::new(exec_flatten).with_blocks(1),
+    );
     map.insert(String::from("EXEC.FLUSH"), Instruction::new(exec_flush));
     map.insert(String::from("EXEC.ID"), Instruction::new(exec_id));
-    map.insert(String::from("EXEC.IF"), Instruction::new(exec_if));
-    map.insert(String::from("EXEC.K"), Instruction::new(exec_k));
-    map.insert(String::from("EXEC.POP"), Instruction::new(exec_pop));
-    map.insert(String::from("EXEC.ROT"), Instruction::new(exec_rot));
-    map.insert(String::from("EXEC.S"), Instruction::new(exec_s));
-    map.insert(String::from("EXEC.SHOVE"), Instruction::new(exec_shove));
+    map.insert(
+        String::from("EXEC.IF"),
+        Instruction::new(exec_if).with_blocks(2),
+    );
+    map.insert(
+        String::from("EXEC.K"),
+        Instruction::new(exec_k).with_blocks(2),
+    );
+    map.insert(
+        String::from("EXEC.POP"),
+        Instruction::new(exec_pop).with_blocks(1),
+    );
+    map.insert(
+        String::from("EXEC.ROT"),
+        Instruction::new(exec_rot).with_blocks(3),
+    );
+    map.insert(String::from("EXEC.ROULETTE"), Instruction::new(exec_roulette));
+    map.insert(
+        String::from("EXEC.S"),
+        Instruction::new(exec_s).with_blocks(3),
+    );
+    map.insert(
+        String::from("EXEC.SHOVE"),
+        Instruction::new(exec_shove).with_blocks(1),
+    );
     map.insert(
         String::from("EXEC.STACKDEPTH"),
         Instruction::new(exec_stack_depth),
     );
-    map.insert(String::from("EXEC.SWAP"), Instruction::new(exec_swap));
-    map.insert(String::from("EXEC.Y"), Instruction::new(exec_y));
+    map.insert(
+        String::from("EXEC.SWAP"),
+        Instruction::new(exec_swap).with_blocks(2),
+    );
+    map.insert(
+        String::from("EXEC.Y"),
+        Instruction::new(exec_y).with_blocks(1),
+    );
     map.insert(String::from("EXEC.YANK"), Instruction::new(exec_yank));
     map.insert(
         String::from("EXEC.YANKDUP"),
@@ -44,6 +103,24 @@ pub fn exec_id(push_state: &mut PushState, _instruction_set: &InstructionCache)
     push_state.int_stack.push(EXEC_STACK_ID);
 }
 
+/// EXEC.CHOOSE: Pops the top FLOAT item p and the top two items of the EXEC stack. The first
+/// item is pushed back for execution with probability p, the second item otherwise. The random
+/// number generator of the state is used. This acts as a NOOP unless there are at least two
+/// items on the EXEC stack and one item on the FLOAT stack.
+pub fn exec_choose(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.exec_stack.size() > 1 {
+        if let Some(p) = push_state.float_stack.pop() {
+            if let Some(code) = push_state.exec_stack.pop_vec(2) {
+                if push_state.rng.gen::<f32>() < p {
+                    push_state.exec_stack.push(code[1].clone());
+                } else {
+                    push_state.exec_stack.push(code[0].clone());
+                }
+            }
+        }
+    }
+}
+
 /// EXEC.CMD: Executes the top items of the name stack on the command line. The 
 /// number of arguments n is specified by the top INTEGER item. The command is found 
 /// at stack position n where the arguments are added in order of stack postion n-1...1.
@@ -77,7 +154,7 @@ pub fn exec_eq(push_state: &mut PushState, _instruction_cache: &InstructionCache
 pub fn exec_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(instruction) = push_state.exec_stack.pop() {
-            push_state.name_bindings.insert(name, instruction);
+            push_state.bind(name, instruction);
         }
     }
 }
@@ -111,6 +188,64 @@ pub fn exec_loop(push_state: &mut PushState, _instruction_cache: &InstructionCac
     }
 }
 
+/// Pushes a copy of the body for each element onto the EXEC stack. Each copy is preceded by
+/// the element literal so that the first element is pushed and processed first.
+fn push_vector_iterations(push_state: &mut PushState, body: Item, elements: Vec<Item>) {
+    let mut iterations = Vec::with_capacity(2 * elements.len());
+    for element in elements.into_iter().rev() {
+        iterations.push(body.clone());
+        iterations.push(element);
+    }
+    if !iterations.is_empty() {
+        push_state.exec_stack.push(Item::list(iterations));
+    }
+}
+
+/// EXEC.DO*BOOLVECTOR: Executes the top item of the EXEC stack once for each element of the top
+/// BOOLVECTOR item. Before each execution the element is pushed to the BOOLEAN stack. The
+/// elements are pushed as BOOLEAN literals together with the copies of the body onto the EXEC
+/// stack. The vector and the body are popped even if the vector is empty. Acts as NOOP if one
+/// of the stacks is empty.
+pub fn exec_do_bool_vector(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.exec_stack.size() > 0 {
+        if let Some(bv) = push_state.bool_vector_stack.pop() {
+            let body = push_state.exec_stack.pop().unwrap();
+            let elements = (0..bv.len()).map(|i| Item::bool(bv.bits.get(i))).collect();
+            push_vector_iterations(push_state, body, elements);
+        }
+    }
+}
+
+/// EXEC.DO*FLOATVECTOR: Executes the top item of the EXEC stack once for each element of the top
+/// FLOATVECTOR item. Before each execution the element is pushed to the FLOAT stack. The
+/// elements are pushed as FLOAT literals together with the copies of the body onto the EXEC
+/// stack. The vector and the body are popped even if the vector is empty. Acts as NOOP if one
+/// of the stacks is empty.
+pub fn exec_do_float_vector(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.exec_stack.size() > 0 {
+        if let Some(fv) = push_state.float_vector_stack.pop() {
+            let body = push_state.exec_stack.pop().unwrap();
+            let elements = fv.values.into_iter().map(Item::float).collect();
+            push_vector_iterations(push_state, body, elements);
+        }
+    }
+}
+
+/// EXEC.DO*INTVECTOR: Executes the top item of the EXEC stack once for each element of the top
+/// INTVECTOR item. Before each execution the element is pushed to the INTEGER stack. The
+/// elements are pushed as INTEGER literals together with the copies of the body onto the EXEC
+/// stack. The vector and the body are popped even if the vector is empty. Acts as NOOP if one
+/// of the stacks is empty.
+pub fn exec_do_int_vector(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.exec_stack.size() > 0 {
+        if let Some(iv) = push_state.int_vector_stack.pop() {
+            let body = push_state.exec_stack.pop().unwrap();
+            let elements = iv.values.into_iter().map(Item::int).collect();
+            push_vector_iterations(push_state, body, elements);
+        }
+    }
+}
+
 /// EXEC.DUP: Duplicates the top item on the EXEC stack. Does not pop its argument (which, if it
 /// did, would negate the effect of the duplication!). This may be thought of as a "DO TWICE"
 /// instruction.
@@ -120,6 +255,14 @@ pub fn exec_dup(push_state: &mut PushState, _instruction_cache: &InstructionCach
     }
 }
 
+/// EXEC.FLATTEN: Replaces the top item of the EXEC stack by a list of its atoms, i.e. all
+/// nested parentheses are removed. Acts as a NOOP if the top item is not a list.
+pub fn exec_flatten(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(item) = push_state.exec_stack.get_mut(0) {
+        *item = item.flatten();
+    }
+}
+
 /// EXEC.FLUSH: Empties the EXEC stack. This may be thought of as a "HALT" instruction.
 pub fn exec_flush(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     push_state.exec_stack.flush();
@@ -163,6 +306,35 @@ pub fn exec_rot(push_state: &mut PushState, _instruction_cache: &InstructionCach
     push_state.exec_stack.yank(2);
 }
 
+/// EXEC.ROULETTE: Pops the top FLOATVECTOR item with k weights and the top k items of the EXEC
+/// stack. One of the items is selected by roulette wheel sampling and pushed back for execution.
+/// The i-th weight belongs to the i-th item from the top. Negative and non-finite weights are
+/// treated as zero. This acts as a NOOP if the EXEC stack holds less than k items or if all
+/// weights are zero.
+pub fn exec_roulette(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(weights) = push_state.float_vector_stack.get(0) {
+        let weights: Vec<f32> = weights
+            .values
+            .iter()
+            .map(|w| if w.is_finite() { f32::max(*w, 0.0) } else { 0.0 })
+            .collect();
+        // Scaled by the largest weight so that the sum of the weights is finite
+        let largest = weights.iter().cloned().fold(0.0, f32::max);
+        let weights: Vec<f32> = weights.iter().map(|w| w / f32::max(largest, 1.0)).collect();
+        if let Ok(distribution) = WeightedIndex::new(&weights) {
+            if push_state.exec_stack.size() >= weights.len() {
+                push_state.float_vector_stack.pop();
+                if let Some(code) = push_state.exec_stack.pop_vec(weights.len()) {
+                    let selected = distribution.sample(&mut push_state.rng);
+                    push_state
+                        .exec_stack
+                        .push(code[code.len() - 1 - selected].clone());
+                }
+            }
+        }
+    }
+}
+
 /// EXEC.S: The Push implementation of the "S combinator". Pops 3 items from the EXEC stack, which
 /// we will call A, B, and C (with A being the first one popped). Then pushes a list containing B
 /// and C back onto the EXEC stack, followed by another instance of C, followed by another instance
@@ -245,11 +417,36 @@ pub fn exec_yank_dup(push_state: &mut PushState, _instruction_cache: &Instructio
 mod tests {
     use super::*;
     use crate::push::index::Index;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::interpreter::PushInterpreter;
+    use crate::push::vector::FloatVector;
 
     pub fn icache() -> InstructionCache {
         InstructionCache::new(vec![])
     }
 
+    #[test]
+    fn exec_do_vector_iterates_elements() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushInterpreter::run_program(
+            "( INT[3,4,5] 10 EXEC.DO*INTVECTOR INTEGER.+ FLOAT[3.0,2.0] EXEC.DO*FLOATVECTOR ( FLOAT.DUP FLOAT.* ) )",
+            &mut push_state,
+            &mut instruction_set,
+        );
+        assert_eq!(push_state.int_stack.to_string(), "22");
+        assert_eq!(push_state.float_stack.to_string(), "4.0 9.0");
+        // The body is popped with an empty vector
+        PushInterpreter::run_program(
+            "( BOOL[] EXEC.DO*BOOLVECTOR BOOLEAN.NOT BOOL[1,0] EXEC.DO*BOOLVECTOR BOOLEAN.NOT )",
+            &mut push_state,
+            &mut instruction_set,
+        );
+        assert_eq!(push_state.bool_stack.to_string(), "TRUE FALSE");
+        assert_eq!(push_state.bool_vector_stack.size(), 0);
+    }
+
     #[test]
     fn exec_eq_pushes_true_when_elements_equal() {
         let mut test_state = PushState::new();
@@ -350,6 +547,56 @@ mod tests {
         assert_eq!(test_state.bool_stack.to_string(), "");
     }
 
+    #[test]
+    fn exec_choose_selects_item_by_probability() {
+        for (p, expected) in [(1.0, "1"), (0.0, "2")].iter() {
+            let mut test_state = PushState::new();
+            test_state.float_stack.push(*p);
+            test_state.exec_stack.push(Item::int(2));
+            test_state.exec_stack.push(Item::int(1));
+            exec_choose(&mut test_state, &icache());
+            assert_eq!(test_state.exec_stack.to_string(), *expected);
+            assert_eq!(test_state.float_stack.size(), 0);
+        }
+    }
+
+    #[test]
+    fn exec_roulette_selects_weighted_item() {
+        let mut test_state = PushState::new();
+        test_state.seed(42);
+        test_state.exec_stack.push(Item::int(4));
+        test_state.exec_stack.push(Item::int(3));
+        test_state.exec_stack.push(Item::int(2));
+        test_state.exec_stack.push(Item::int(1));
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![0.0, -1.0, 2.5]));
+        exec_roulette(&mut test_state, &icache());
+        assert_eq!(test_state.exec_stack.to_string(), "3 4");
+        assert_eq!(test_state.float_vector_stack.size(), 0);
+        // All weights zero
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![0.0, 0.0]));
+        exec_roulette(&mut test_state, &icache());
+        assert_eq!(test_state.exec_stack.to_string(), "3 4");
+        assert_eq!(test_state.float_vector_stack.size(), 1);
+        // Infinite weights count as zero, large weights do not overflow
+        test_state.float_vector_stack.pop();
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![f32::INFINITY, 1.0]));
+        exec_roulette(&mut test_state, &icache());
+        assert_eq!(test_state.exec_stack.to_string(), "4");
+        test_state.exec_stack.push(Item::int(5));
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![f32::MAX, f32::MAX]));
+        exec_roulette(&mut test_state, &icache());
+        assert_eq!(test_state.exec_stack.size(), 1);
+        assert_eq!(test_state.float_vector_stack.size(), 0);
+    }
+
     #[test]
     fn exec_k_removes_second_item() {
         let mut test_state = PushState::new();
@@ -0,0 +1,200 @@
+use crate::push::configuration::PushConfiguration;
+use crate::push::instructions::InstructionSet;
+use crate::push::item::Item;
+use crate::push::parser::PushParser;
+use crate::push::state::PushState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Selection of the instructions that are available to the programs.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstructionOptions {
+    // Prefixes of the instructions to keep, e.g. "INTEGER.". All instructions are kept if empty.
+    pub include: Vec<String>,
+    // Prefixes of the instructions to remove. Applied after include.
+    pub exclude: Vec<String>,
+    // Selection weights for random code generation
+    pub weights: HashMap<String, f32>,
+}
+
+impl InstructionOptions {
+    /// Returns the default instruction set restricted to the included instructions
+    /// with the configured weights.
+    pub fn instruction_set(&self) -> InstructionSet {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        if !self.include.is_empty() {
+            for name in instruction_set.names_with_prefix("") {
+                if !self.include.iter().any(|p| name.starts_with(p.as_str())) {
+                    instruction_set.remove(&name);
+                }
+            }
+        }
+        for prefix in self.exclude.iter() {
+            for name in instruction_set.names_with_prefix(prefix) {
+                instruction_set.remove(&name);
+            }
+        }
+        for (name, weight) in self.weights.iter() {
+            instruction_set.set_weight(name, *weight);
+        }
+        instruction_set
+    }
+}
+
+/// Reference to the problem and dataset the programs are evaluated on.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProblemReference {
+    pub name: String,
+    pub dataset: Option<PathBuf>,
+}
+
+/// Declarative description of an experiment, e.g.
+///
+/// ```toml
+/// program = "( x x INTEGER.* )"
+/// seed = 42
+///
+/// [configuration]
+/// eval_push_limit = 500
+///
+/// [instructions]
+/// include = ["INTEGER.", "EXEC."]
+/// weights = { "INTEGER.+" = 2.0 }
+///
+/// [bindings]
+/// x = "7"
+///
+/// [problem]
+/// name = "square"
+/// dataset = "data/square.csv"
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExperimentConfig {
+    // Program that is executed by `pushr run`
+    pub program: Option<String>,
+    // Seed of the random number generator of the state
+    pub seed: Option<u64>,
+    // Interpreter limits and other configuration parameters. Omitted parameters keep their
+    // default values.
+    pub configuration: PushConfiguration,
+    pub instructions: InstructionOptions,
+    // Input bindings from names to code
+    pub bindings: HashMap<String, String>,
+    pub problem: Option<ProblemReference>,
+}
+
+impl ExperimentConfig {
+    /// Parses an experiment configuration from TOML.
+    pub fn from_toml(toml: &str) -> Result<Self, String> {
+        toml::from_str(toml).map_err(|e| e.to_string())
+    }
+
+    /// Reads an experiment configuration from a TOML file.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let toml = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        ExperimentConfig::from_toml(&toml)
+    }
+
+    /// Returns the default instruction set restricted to the included instructions
+    /// with the configured weights (see InstructionOptions::instruction_set).
+    pub fn instruction_set(&self) -> InstructionSet {
+        self.instructions.instruction_set()
+    }
+
+    /// Returns a state with the configuration, seed and bindings of the experiment
+    /// and the program on the EXEC stack.
+    pub fn push_state(&self, instruction_set: &InstructionSet) -> PushState {
+        let mut push_state = PushState::new();
+        push_state.configuration = self.configuration.clone();
+        if let Some(seed) = self.seed {
+            push_state.seed(seed);
+        }
+        for (name, code) in self.bindings.iter() {
+            let mut binding_state = PushState::new();
+            PushParser::parse_program(&mut binding_state, instruction_set, code);
+            if let Some(mut items) = binding_state.exec_stack.pop_vec(binding_state.exec_stack.size()) {
+                let item = if items.len() == 1 {
+                    items.pop().unwrap()
+                } else {
+                    Item::list(items)
+                };
+                push_state.name_bindings.insert(name.clone(), item);
+            }
+        }
+        if let Some(program) = &self.program {
+            PushParser::parse_program(&mut push_state, instruction_set, program);
+        }
+        push_state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::graph::IdPolicy;
+    use crate::push::interpreter::{PushInterpreter, PushInterpreterState};
+
+    const EXPERIMENT: &str = r#"
+        program = "( x x INTEGER.* FLOAT.+ )"
+        seed = 42
+
+        [configuration]
+        eval_push_limit = 50
+        graph_id_policy = "ReuseFreed"
+
+        [instructions]
+        include = ["INTEGER.", "EXEC."]
+        exclude = ["EXEC.CMD"]
+        weights = { "INTEGER.+" = 2.0 }
+
+        [bindings]
+        x = "7"
+
+        [problem]
+        name = "square"
+        dataset = "data/square.csv"
+    "#;
+
+    #[test]
+    fn experiment_is_loaded_from_toml() {
+        let experiment = ExperimentConfig::from_toml(EXPERIMENT).unwrap();
+        assert_eq!(experiment.seed, Some(42));
+        assert_eq!(experiment.configuration.eval_push_limit, 50);
+        assert_eq!(experiment.configuration.graph_id_policy, IdPolicy::ReuseFreed);
+        // Omitted parameters keep their defaults
+        assert_eq!(
+            experiment.configuration.growth_cap,
+            PushConfiguration::new().growth_cap
+        );
+        assert_eq!(
+            experiment.problem.as_ref().unwrap().dataset,
+            Some(PathBuf::from("data/square.csv"))
+        );
+        let mut instruction_set = experiment.instruction_set();
+        assert!(instruction_set.is_instruction("INTEGER.*"));
+        assert!(instruction_set.is_instruction("EXEC.DUP"));
+        assert!(!instruction_set.is_instruction("EXEC.CMD"));
+        assert!(!instruction_set.is_instruction("FLOAT.+"));
+        assert_eq!(instruction_set.cache().weights.get("INTEGER.+"), Some(&2.0));
+        let mut push_state = experiment.push_state(&instruction_set);
+        assert_eq!(
+            PushInterpreter::run(&mut push_state, &mut instruction_set),
+            PushInterpreterState::NoErrors
+        );
+        assert_eq!(push_state.int_stack.to_string(), "49");
+        // FLOAT.+ is not part of the instruction set
+        assert_eq!(push_state.name_stack.to_string(), "FLOAT.+");
+    }
+
+    #[test]
+    fn invalid_experiment_is_rejected() {
+        assert!(ExperimentConfig::from_toml("seed = \"abc\"").is_err());
+        assert!(ExperimentConfig::load("does/not/exist.toml").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::push::state::*;
+use serde_json::{json, Map, Value};
+
+/// Part of the final state selected by an extraction expression such as
+/// `int.top`, `floatvector[1].mean` or `graph.nodes`. An expression starts with the
+/// short name of a stack (see STACK_NAMES) followed by an optional position
+/// (0 = top) and properties:
+///
+/// * Stack properties: `size`, `top` (same as `[0]`) and `items`.
+/// * Vector properties: `len`, `sum`, `mean`, `min` and `max`.
+/// * Graph properties: `nodes` and `edges` (number of nodes and edges).
+///
+/// Item properties that follow the stack name directly refer to the top item.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selector {
+    // The expression as written, used as key of the result
+    pub expression: String,
+    pub stack_id: i32,
+    pub index: Option<usize>,
+    pub properties: Vec<String>,
+}
+
+const STACK_PROPERTIES: &[&str] = &["size", "top", "items"];
+const ITEM_PROPERTIES: &[&str] = &["len", "sum", "mean", "min", "max", "nodes", "edges"];
+
+impl Selector {
+    /// Parses a comma separated list of expressions.
+    pub fn parse_list(text: &str) -> Result<Vec<Selector>, String> {
+        text.split(',')
+            .map(|e| e.trim())
+            .filter(|e| !e.is_empty())
+            .map(Selector::parse)
+            .collect()
+    }
+
+    /// Parses a single expression.
+    pub fn parse(expression: &str) -> Result<Selector, String> {
+        let mut parts = expression.split('.');
+        let head = parts.next().unwrap_or_default();
+        let (stack_name, index) = match head.find('[') {
+            Some(pos) if head.ends_with(']') => {
+                let index = head[pos + 1..head.len() - 1]
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid position in {}", expression))?;
+                (&head[..pos], Some(index))
+            }
+            Some(_) => return Err(format!("Unclosed position in {}", expression)),
+            None => (head, None),
+        };
+        let stack_id =
+            stack_id(stack_name).ok_or_else(|| format!("Unknown stack {}", stack_name))?;
+        let properties: Vec<String> = parts.map(|p| p.trim().to_lowercase()).collect();
+        for (i, property) in properties.iter().enumerate() {
+            let is_stack_property = STACK_PROPERTIES.contains(&property.as_str());
+            let valid = if i == 0 && index.is_none() {
+                is_stack_property || ITEM_PROPERTIES.contains(&property.as_str())
+            } else {
+                // Properties of an item or of the result of top
+                ITEM_PROPERTIES.contains(&property.as_str())
+                    && (i == 0 || properties[i - 1] == "top")
+            };
+            if !valid {
+                return Err(format!("Invalid property {} in {}", property, expression));
+            }
+        }
+        Ok(Selector {
+            expression: expression.to_string(),
+            stack_id,
+            index,
+            properties,
+        })
+    }
+
+    /// Evaluates the expression on the state. Returns Null if the selected item
+    /// does not exist or the property is not defined for it.
+    pub fn evaluate(&self, push_state: &PushState) -> Value {
+        let mut properties = self.properties.iter().map(|p| p.as_str()).peekable();
+        let index = match (self.index, properties.peek()) {
+            (Some(index), _) => index,
+            (None, Some(&"size")) => {
+                return json!(push_state.stack_size(self.stack_id).unwrap_or(0));
+            }
+            (None, Some(&"items")) => {
+                let size = push_state.stack_size(self.stack_id).unwrap_or(0);
+                return Value::Array(
+                    (0..size)
+                        .map(|i| item_value(push_state, self.stack_id, i).unwrap_or(Value::Null))
+                        .collect(),
+                );
+            }
+            (None, Some(&"top")) => {
+                properties.next();
+                0
+            }
+            (None, _) => 0,
+        };
+        let mut value = item_value(push_state, self.stack_id, index).unwrap_or(Value::Null);
+        for property in properties {
+            value = item_property(&value, property);
+        }
+        value
+    }
+}
+
+/// Evaluates all selectors and returns the values by expression.
+pub fn extract(push_state: &PushState, selectors: &[Selector]) -> Value {
+    let mut map = Map::new();
+    for selector in selectors.iter() {
+        map.insert(selector.expression.clone(), selector.evaluate(push_state));
+    }
+    Value::Object(map)
+}
+
+/// Returns the item at the given position (0 = top) of the stack as JSON. Scalars,
+/// vectors (as arrays) and graphs are converted to their JSON form, other items
+/// to their printed form.
+fn item_value(push_state: &PushState, stack_id: i32, index: usize) -> Option<Value> {
+    let value = match stack_id {
+        BOOL_STACK_ID => json!(push_state.bool_stack.get(index)?),
+        INT_STACK_ID => json!(push_state.int_stack.get(index)?),
+        FLOAT_STACK_ID => json!(push_state.float_stack.get(index)?),
+        BOOL_VECTOR_STACK_ID => json!(push_state.bool_vector_stack.get(index)?.to_bools()),
+        INT_VECTOR_STACK_ID => json!(push_state.int_vector_stack.get(index)?.values),
+        FLOAT_VECTOR_STACK_ID => json!(push_state.float_vector_stack.get(index)?.values),
+        GRAPH_STACK_ID => json!(push_state.graph_stack.get(index)?),
+        _ => json!(push_state.stack_item_to_string(stack_id, index)?),
+    };
+    Some(value)
+}
+
+/// Computes the property of an item in JSON form.
+fn item_property(value: &Value, property: &str) -> Value {
+    match (value, property) {
+        (Value::Array(elements), _) => {
+            let numbers: Vec<f64> = elements
+                .iter()
+                .filter_map(|e| e.as_f64().or_else(|| e.as_bool().map(|b| b as u8 as f64)))
+                .collect();
+            match property {
+                "len" => json!(elements.len()),
+                "sum" => json!(numbers.iter().sum::<f64>()),
+                "mean" if !numbers.is_empty() => {
+                    json!(numbers.iter().sum::<f64>() / numbers.len() as f64)
+                }
+                "min" => numbers.iter().cloned().reduce(f64::min).map_or(Value::Null, |m| json!(m)),
+                "max" => numbers.iter().cloned().reduce(f64::max).map_or(Value::Null, |m| json!(m)),
+                _ => Value::Null,
+            }
+        }
+        (Value::Object(graph), "nodes") => {
+            json!(graph.get("nodes").and_then(|n| n.as_object()).map_or(0, |n| n.len()))
+        }
+        (Value::Object(graph), "edges") => json!(graph
+            .get("edges")
+            .and_then(|e| e.as_object())
+            .map_or(0, |e| e
+                .values()
+                .map(|incoming| incoming.as_array().map_or(0, |a| a.len()))
+                .sum::<usize>())),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::graph::Graph;
+    use crate::push::vector::FloatVector;
+
+    #[test]
+    fn selectors_extract_values_from_final_state() {
+        let mut push_state = PushState::new();
+        push_state.int_stack.push_vec(vec![1, 2, 3]);
+        push_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![1.0, 2.0, 6.0]));
+        push_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![0.5]));
+        let mut graph = Graph::new();
+        let a = graph.add_node(0);
+        let b = graph.add_node(1);
+        graph.add_edge(a, b, 0.5);
+        push_state.graph_stack.push(graph);
+        let selectors = Selector::parse_list(
+            "int.top, int.size, int[2], floatvector[1].mean, floatvector.max, graph.nodes, \
+             graph.edges, bool.top, int.items",
+        )
+        .unwrap();
+        let result = extract(&push_state, &selectors);
+        assert_eq!(
+            result,
+            json!({
+                "int.top": 3,
+                "int.size": 3,
+                "int[2]": 1,
+                "floatvector[1].mean": 3.0,
+                "floatvector.max": 0.5,
+                "graph.nodes": 2,
+                "graph.edges": 1,
+                "bool.top": null,
+                "int.items": [3, 2, 1],
+            })
+        );
+        assert!(Selector::parse("ints.top").is_err());
+        assert!(Selector::parse("int[x]").is_err());
+        assert!(Selector::parse("int.size.mean").is_err());
+        assert!(Selector::parse("int[0].size").is_err());
+    }
+}
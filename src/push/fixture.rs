@@ -0,0 +1,248 @@
+use crate::push::instructions::InstructionSet;
+use crate::push::interpreter::PushInterpreter;
+use crate::push::parser::PushParser;
+use crate::push::state::{self, PushState};
+use std::fmt;
+
+/// Property of a stack that is compared after the run.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Watch {
+    // All items, printed top first and separated by spaces
+    Items,
+    // The printed top item
+    Top,
+    // The number of items
+    Size,
+}
+
+/// Expected value of a watched stack property. The expected value is compared
+/// to the printed form of the property, e.g. "TRUE" for a BOOLEAN or "2.0" for a FLOAT.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expectation {
+    pub stack: String,
+    pub watch: Watch,
+    pub expected: String,
+}
+
+impl Expectation {
+    /// Returns the actual value of the watched property or None if the stack does
+    /// not exist.
+    fn actual(&self, push_state: &PushState) -> Option<String> {
+        let stack_id = state::stack_id(&self.stack)?;
+        match self.watch {
+            Watch::Items => push_state.stack_to_string(stack_id),
+            Watch::Top => Some(
+                push_state
+                    .stack_item_to_string(stack_id, 0)
+                    .unwrap_or_default(),
+            ),
+            Watch::Size => push_state.stack_size(stack_id).map(|s| s.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.watch {
+            Watch::Items => write!(f, "{} == {}", self.stack, self.expected),
+            Watch::Top => write!(f, "{}.top == {}", self.stack, self.expected),
+            Watch::Size => write!(f, "{}.size == {}", self.stack, self.expected),
+        }
+    }
+}
+
+/// Program together with the expected final state. Fixtures are either built in
+/// code or parsed from the .pshtest format:
+///
+/// ```text
+/// # Comments start with '#'
+/// test addition
+/// program ( 1 2 INTEGER.+ 3 INTEGER.= )
+/// expect int.size == 0
+/// expect bool.top == TRUE
+/// expect state == NoErrors
+/// ```
+///
+/// Each `test` line starts a new fixture. Stacks are referred to by their short
+/// names (see STACK_NAMES). `expect <stack> == <items>` compares all items top first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Fixture {
+    pub name: String,
+    pub program: String,
+    pub expectations: Vec<Expectation>,
+    // Expected termination reason, e.g. "NoErrors"
+    pub interpreter_state: Option<String>,
+}
+
+impl Fixture {
+    pub fn new(program: &str) -> Self {
+        Self {
+            name: program.to_string(),
+            program: program.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn named(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Expects the printed items of the stack (top first), e.g. "3 2 1".
+    pub fn expect_items(self, stack: &str, items: &str) -> Self {
+        self.expect(stack, Watch::Items, items)
+    }
+
+    /// Expects the printed top item of the stack. An empty string expects an empty stack.
+    pub fn expect_top(self, stack: &str, item: &str) -> Self {
+        self.expect(stack, Watch::Top, item)
+    }
+
+    /// Expects the number of items on the stack.
+    pub fn expect_size(self, stack: &str, size: usize) -> Self {
+        self.expect(stack, Watch::Size, &size.to_string())
+    }
+
+    /// Expects the termination reason of the interpreter, e.g. "StepLimitExceeded".
+    pub fn expect_state(mut self, interpreter_state: &str) -> Self {
+        self.interpreter_state = Some(interpreter_state.to_string());
+        self
+    }
+
+    fn expect(mut self, stack: &str, watch: Watch, expected: &str) -> Self {
+        self.add_expectation(stack, watch, expected);
+        self
+    }
+
+    fn add_expectation(&mut self, stack: &str, watch: Watch, expected: &str) {
+        self.expectations.push(Expectation {
+            stack: stack.to_string(),
+            watch,
+            expected: expected.trim().to_string(),
+        });
+    }
+
+    /// Runs the program on a fresh state and returns a description of each failed
+    /// expectation. The fixture passes if the result is empty.
+    pub fn check(&self, instruction_set: &mut InstructionSet) -> Vec<String> {
+        let mut push_state = PushState::new();
+        PushParser::parse_program(&mut push_state, instruction_set, &self.program);
+        let interpreter_state = format!(
+            "{:?}",
+            PushInterpreter::run(&mut push_state, instruction_set)
+        );
+        let mut failures = vec![];
+        if let Some(expected) = &self.interpreter_state {
+            if *expected != interpreter_state {
+                failures.push(format!(
+                    "state == {}: found {}",
+                    expected, interpreter_state
+                ));
+            }
+        }
+        for expectation in self.expectations.iter() {
+            match expectation.actual(&push_state) {
+                Some(actual) if actual == expectation.expected => (),
+                Some(actual) => failures.push(format!("{}: found {}", expectation, actual)),
+                None => failures.push(format!("{}: unknown stack", expectation)),
+            }
+        }
+        failures
+    }
+
+    /// Parses the fixtures of a .pshtest file. Errors contain the line number.
+    pub fn parse(text: &str) -> Result<Vec<Fixture>, String> {
+        let mut fixtures: Vec<Fixture> = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, rest) = match line.find(char::is_whitespace) {
+                Some(pos) => (&line[..pos], line[pos..].trim()),
+                None => (line, ""),
+            };
+            if keyword == "test" {
+                fixtures.push(Fixture::default().named(rest));
+                continue;
+            }
+            let fixture = fixtures
+                .last_mut()
+                .ok_or_else(|| format!("line {}: expected 'test <name>'", i + 1))?;
+            match keyword {
+                "program" => fixture.program = rest.to_string(),
+                "expect" => {
+                    let (target, expected) = match rest.find("==") {
+                        Some(pos) => (rest[..pos].trim(), rest[pos + 2..].trim()),
+                        None => {
+                            return Err(format!("line {}: expected '<stack> == <value>'", i + 1))
+                        }
+                    };
+                    let (stack, watch) = match target.split_once('.') {
+                        Some((stack, "top")) => (stack, Watch::Top),
+                        Some((stack, "size")) => (stack, Watch::Size),
+                        Some(_) => {
+                            return Err(format!("line {}: unknown property {}", i + 1, target))
+                        }
+                        None => (target, Watch::Items),
+                    };
+                    if stack == "state" && watch == Watch::Items {
+                        fixture.interpreter_state = Some(expected.to_string());
+                    } else if state::stack_id(stack).is_none() {
+                        return Err(format!("line {}: unknown stack {}", i + 1, stack));
+                    } else {
+                        fixture.add_expectation(stack, watch, expected);
+                    }
+                }
+                _ => return Err(format!("line {}: unknown keyword {}", i + 1, keyword)),
+            }
+        }
+        Ok(fixtures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction_set() -> InstructionSet {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        instruction_set
+    }
+
+    #[test]
+    fn fixture_builder_reports_failed_expectations() {
+        let fixture = Fixture::new("( 1 2 INTEGER.+ TRUE )")
+            .expect_items("int", "3")
+            .expect_top("bool", "TRUE")
+            .expect_size("float", 1)
+            .expect_state("NoErrors");
+        let failures = fixture.check(&mut instruction_set());
+        assert_eq!(failures, vec!["float.size == 1: found 0".to_string()]);
+    }
+
+    #[test]
+    fn fixtures_are_parsed_from_text() {
+        let text = "# Regression tests\n\
+                    test addition\n\
+                    program ( 1 2 INTEGER.+ 3 INTEGER.= )\n\
+                    expect int.size == 0\n\
+                    expect bool.top == TRUE\n\
+                    \n\
+                    test vector\n\
+                    program ( 2 1 INTVECTOR.ONES INTVECTOR.ONES )\n\
+                    expect intvector == [1,1] [1]\n\
+                    expect state == NoErrors\n";
+        let fixtures = Fixture::parse(text).unwrap();
+        assert_eq!(fixtures.len(), 2);
+        assert_eq!(fixtures[1].name, "vector");
+        let mut instruction_set = instruction_set();
+        for fixture in fixtures.iter() {
+            assert!(fixture.check(&mut instruction_set).is_empty());
+        }
+        assert!(Fixture::parse("program ( 1 )").is_err());
+        assert!(Fixture::parse("test t\nexpect ints == 1").is_err());
+        assert!(Fixture::parse("test t\nexpect int.last == 1").is_err());
+    }
+}
@@ -4,6 +4,7 @@ use crate::push::item::Item;
 use crate::push::random::CodeGenerator;
 use crate::push::state::PushState;
 use crate::push::state::*;
+use crate::push::vector::FloatVector;
 use std::collections::HashMap;
 
 /// Floating-point numbers (that is, numbers with decimal points).
@@ -31,6 +32,7 @@ pub fn load_float_instructions(map: &mut HashMap<String, Instruction>) {
         Instruction::new(float_from_integer),
     );
     map.insert(String::from("FLOAT.MAX"), Instruction::new(float_max));
+    map.insert(String::from("FLOAT.MAX*K"), Instruction::new(float_max_k));
     map.insert(String::from("FLOAT.MIN"), Instruction::new(float_min));
     map.insert(String::from("FLOAT.POP"), Instruction::new(float_pop));
     map.insert(String::from("FLOAT.RAND"), Instruction::new(float_rand));
@@ -55,14 +57,19 @@ pub fn float_id(push_state: &mut PushState, _instruction_set: &InstructionCache)
     push_state.int_stack.push(FLOAT_STACK_ID);
 }
 
-/// INTEGER.ID: Pushes the ID of the INTEGER stack to the INTEGER stack.
-/// FLOAT.%: Pushes the second stack item modulo the top stack item. If the top item is zero this
-/// acts as a NOOP. The modulus is computed as the remainder of the quotient, where the quotient
-/// has first been truncated toward negative infinity.
+/// FLOAT.%: Pushes the second stack item modulo the top stack item. If the top item is zero the
+/// result is determined by the configured divide-by-zero policy. The modulus is computed as the
+/// remainder of the quotient, where the quotient has first been truncated toward negative infinity.
 fn float_modulus(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(fvals) = push_state.float_stack.pop_vec(2) {
         if fvals[1] != 0f32 {
             push_state.float_stack.push(fvals[0] % fvals[1]);
+        } else {
+            push_state.div_zero_count += 1;
+            match push_state.configuration.div_zero_policy.resolve(fvals[0]) {
+                Some(result) => push_state.float_stack.push(result),
+                None => push_state.float_stack.push_vec(fvals),
+            }
         }
     }
 }
@@ -90,11 +97,18 @@ fn float_subtract(push_state: &mut PushState, _instruction_cache: &InstructionCa
 }
 
 /// FLOAT./: Pushes the quotient of the top two items; that is, the second item divided by the top
-/// item. If the top item is zero this acts as a NOOP.
+/// item. If the top item is zero the result is determined by the configured divide-by-zero
+/// policy.
 fn float_divide(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(fvals) = push_state.float_stack.pop_vec(2) {
         if fvals[1] != 0f32 {
             push_state.float_stack.push(fvals[0] / fvals[1]);
+        } else {
+            push_state.div_zero_count += 1;
+            match push_state.configuration.div_zero_policy.resolve(fvals[0]) {
+                Some(result) => push_state.float_stack.push(result),
+                None => push_state.float_stack.push_vec(fvals),
+            }
         }
     }
 }
@@ -141,7 +155,7 @@ fn float_cosine(push_state: &mut PushState, _instruction_cache: &InstructionCach
 pub fn float_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(fval) = push_state.float_stack.pop() {
-            push_state.name_bindings.insert(name, Item::float(fval));
+            push_state.bind(name, Item::float(fval));
         }
     }
 }
@@ -187,6 +201,23 @@ pub fn float_max(push_state: &mut PushState, _instruction_cache: &InstructionCac
     }
 }
 
+/// FLOAT.MAX*K: Pops the top N items of the FLOAT stack and pushes the k largest of them
+/// as a FLOATVECTOR in descending order. The top INTEGER is k and the second INTEGER is N.
+/// Both are bound to the valid range. The items are ordered by f32::total_cmp, which ranks
+/// NaN above infinity.
+pub fn float_max_k(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(params) = push_state.int_stack.pop_vec(2) {
+        let size = push_state.float_stack.size() as i32;
+        let n = i32::max(i32::min(size, params[0]), 0) as usize;
+        let k = i32::max(i32::min(n as i32, params[1]), 0) as usize;
+        if let Some(mut fvals) = push_state.float_stack.pop_vec(n) {
+            fvals.sort_by(|a, b| b.total_cmp(a));
+            fvals.truncate(k);
+            push_state.float_vector_stack.push(FloatVector::new(fvals));
+        }
+    }
+}
+
 /// FLOAT.MIN: Pushes the minimum of the top two items.
 pub fn float_min(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(fvals) = push_state.float_stack.pop_vec(2) {
@@ -284,11 +315,39 @@ pub fn float_yank_dup(push_state: &mut PushState, _instruction_cache: &Instructi
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::push::configuration::PushConfiguration;
 
     pub fn icache() -> InstructionCache {
         InstructionCache::new(vec![])
     }
 
+    #[test]
+    fn float_max_k_pushes_largest_items() {
+        let mut test_state = PushState::new();
+        for f in [9.0, 1.5, -2.0, 7.25, 3.0] {
+            test_state.float_stack.push(f);
+        }
+        test_state.int_stack.push(4);
+        test_state.int_stack.push(2);
+        float_max_k(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.to_string(), "[7.250,3.000]");
+        assert_eq!(test_state.float_stack.to_string(), "9.0");
+        test_state.int_stack.push(5);
+        test_state.int_stack.push(3);
+        float_max_k(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.get(0).unwrap().values, vec![9.0]);
+        assert_eq!(test_state.float_stack.size(), 0);
+        for f in [1.0, f32::NAN, 3.0, 2.0, f32::NAN, 4.0] {
+            test_state.float_stack.push(f);
+        }
+        test_state.int_stack.push(6);
+        test_state.int_stack.push(4);
+        float_max_k(&mut test_state, &icache());
+        let values = &test_state.float_vector_stack.get(0).unwrap().values;
+        assert!(values[0].is_nan() && values[1].is_nan());
+        assert_eq!(values[2..], [4.0, 3.0]);
+    }
+
     #[test]
     fn float_modulus_pushes_result() {
         let mut test_state = PushState::new();
@@ -325,6 +384,17 @@ mod tests {
         assert!(f32::abs(test_state.float_stack.pop().unwrap() + 0.6) < 0.001f32);
     }
 
+    #[test]
+    fn float_divide_by_zero_follows_clojush_profile() {
+        let mut test_state = PushState::new();
+        test_state.configuration = PushConfiguration::clojush();
+        test_state.float_stack.push(4.0);
+        test_state.float_stack.push(0.0);
+        float_divide(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.to_string(), "4.0");
+        assert_eq!(test_state.div_zero_count, 1);
+    }
+
     #[test]
     fn float_divide_pushes_result() {
         let mut test_state = PushState::new();
@@ -0,0 +1,177 @@
+use crate::push::instructions::InstructionCache;
+use crate::push::item::Item;
+use serde::{Deserialize, Serialize};
+
+/// Returns the number of code blocks that the item opens in a Plush translation.
+fn blocks(item: &Item, instructions: &InstructionCache) -> usize {
+    match item {
+        Item::InstructionMeta { name, .. } => instructions.blocks(name),
+        _ => 0,
+    }
+}
+
+/// Gene of a linear Plush genome: an instruction or literal, the number of blocks
+/// that are closed after it and a flag that excludes it from the translation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gene {
+    pub instruction: Item,
+    #[serde(default)]
+    pub close: usize,
+    #[serde(default)]
+    pub silent: bool,
+}
+
+impl Gene {
+    pub fn new(instruction: Item, close: usize) -> Self {
+        Self {
+            instruction,
+            close,
+            silent: false,
+        }
+    }
+}
+
+/// Linear genome in the Plush representation (Helmuth et al., Program synthesis
+/// using uniform mutation by addition and deletion, 2018). Since the nesting of the
+/// program is encoded in the close counts, variation operators can treat the genome
+/// as a flat sequence.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Genome {
+    pub genes: Vec<Gene>,
+}
+
+impl Genome {
+    pub fn new(genes: Vec<Gene>) -> Self {
+        Self { genes }
+    }
+
+    /// Translates the genome to a Push program. The instructions of the genes that are
+    /// not silent are appended in order. An instruction that takes code blocks from the
+    /// EXEC stack (see Instruction::with_blocks) opens the first of its blocks. Each close of a gene
+    /// ends the innermost open block, and if its instruction takes further blocks,
+    /// the next block is opened. Closes without open block are ignored. At the end of
+    /// the genome all pending blocks are closed, i.e. blocks that are never opened
+    /// become empty lists.
+    pub fn translate(&self, instructions: &InstructionCache) -> Item {
+        // Lists that are under construction (program order) and the number of
+        // blocks that are still to be opened after each of them
+        let mut open: Vec<(Vec<Item>, usize)> = vec![(vec![], 0)];
+        for gene in self.genes.iter().filter(|g| !g.silent) {
+            if let Some((current, _)) = open.last_mut() {
+                current.push(gene.instruction.clone());
+            }
+            let b = blocks(&gene.instruction, instructions);
+            if b > 0 {
+                open.push((vec![], b - 1));
+            }
+            for _ in 0..gene.close {
+                close_block(&mut open);
+            }
+        }
+        while open.len() > 1 {
+            close_block(&mut open);
+        }
+        program_list(open.pop().map_or(vec![], |(program, _)| program))
+    }
+}
+
+/// Closes the innermost open block and opens the next block of its instruction if
+/// there is one. The program itself is never closed.
+fn close_block(open: &mut Vec<(Vec<Item>, usize)>) {
+    if open.len() > 1 {
+        if let Some((block, pending)) = open.pop() {
+            if let Some((parent, _)) = open.last_mut() {
+                parent.push(program_list(block));
+            }
+            if pending > 0 {
+                open.push((vec![], pending - 1));
+            }
+        }
+    }
+}
+
+/// Returns a list whose first item in program order is on top.
+fn program_list(mut items: Vec<Item>) -> Item {
+    items.reverse();
+    Item::list(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::InstructionSet;
+
+    fn icache() -> InstructionCache {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        instruction_set.cache()
+    }
+
+    fn gene(instruction: &str, close: usize) -> Gene {
+        let item = match instruction.parse::<i32>() {
+            Ok(ival) => Item::int(ival),
+            Err(_) => Item::instruction(instruction.to_string()),
+        };
+        Gene::new(item, close)
+    }
+
+    #[test]
+    fn translation_nests_blocks_by_close_counts() {
+        let genome = Genome::new(vec![
+            gene("1", 0),
+            gene("EXEC.IF", 0),
+            gene("2", 1),
+            gene("3", 0),
+            gene("INTEGER.+", 2),
+            gene("4", 5),
+        ]);
+        assert_eq!(
+            genome.translate(&icache()).to_string(),
+            "( 1 EXEC.IF ( 2 ) ( 3 INTEGER.+ ) 4 )"
+        );
+    }
+
+    #[test]
+    fn translation_skips_silent_genes_and_adds_missing_blocks() {
+        let mut genome = Genome::new(vec![
+            gene("EXEC.ROT", 0),
+            gene("5", 0),
+            gene("EXEC.DUP", 0),
+            gene("6", 0),
+        ]);
+        genome.genes[1].silent = true;
+        assert_eq!(
+            genome.translate(&icache()).to_string(),
+            "( EXEC.ROT ( EXEC.DUP ( 6 ) ) (  ) (  ) )"
+        );
+        assert_eq!(Genome::default().translate(&icache()).to_string(), "(  )");
+    }
+
+    #[test]
+    fn genome_is_read_from_json() {
+        let genome: Genome = serde_json::from_str(
+            r#"{"genes": [{"instruction": {"InstructionMeta": {"name": "EXEC.DUP"}}, "close": 1}]}"#,
+        )
+        .unwrap();
+        assert_eq!(genome.translate(&icache()).to_string(), "( EXEC.DUP (  ) )");
+    }
+
+    #[test]
+    fn translation_uses_block_arity_of_instruction_set() {
+        let genome = Genome::new(vec![
+            gene("EXEC.=", 0),
+            gene("1", 1),
+            gene("EXEC.FLATTEN", 0),
+            gene("FLOATVECTOR.MAP", 0),
+            gene("2", 0),
+        ]);
+        assert_eq!(
+            genome.translate(&icache()).to_string(),
+            "( EXEC.= ( 1 ) ( EXEC.FLATTEN ( FLOATVECTOR.MAP ( 2 ) ) ) )"
+        );
+        assert_eq!(
+            genome.translate(&InstructionCache::new(vec![])).to_string(),
+            "( EXEC.= 1 EXEC.FLATTEN FLOATVECTOR.MAP 2 )"
+        );
+    }
+}
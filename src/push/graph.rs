@@ -1,25 +1,27 @@
 use crate::push::instructions::Instruction;
 use crate::push::instructions::InstructionCache;
+use crate::push::item::Item;
 use crate::push::state::PushState;
 use crate::push::stack::PushPrint;
-use crate::push::vector::IntVector;
-use std::collections::HashMap;
+use crate::push::vector::{FloatVector, IntVector};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::hash::{Hash, Hasher};
-use std::sync::atomic::{AtomicUsize, Ordering};
 
-static NODE_COUNTER: AtomicUsize = AtomicUsize::new(1);
+/// Node ID of the first node that is added to a graph.
+pub const FIRST_NODE_ID: usize = 1;
 
-#[derive(Clone, Debug, Hash, Eq)]
+#[derive(Clone, Debug, Hash, Eq, Serialize, Deserialize)]
 pub struct Node {
     node_id: usize,
     state: i32,
 }
 
 impl Node {
-    pub fn new(state: i32) -> Self {
+    pub fn new(node_id: usize, state: i32) -> Self {
         Self {
-            node_id: NODE_COUNTER.fetch_add(1, Ordering::Relaxed),
+            node_id,
             state: state,
         }
     }
@@ -85,10 +87,13 @@ impl Node {
     }
 
 
-    #[derive(Copy, Clone, Debug)]
+    #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
     pub struct Edge {
         origin_node_id: usize,
         weight: f32,
+        // True if the edge was added or its weight was set since the last tick
+        #[serde(default)]
+        touched: bool,
     }
 
     impl Edge {
@@ -96,6 +101,7 @@ impl Node {
             Self {
                 origin_node_id: node_id,
                 weight: weight,
+                touched: true,
             }
         }
         /// Returns the difference between this edge and the
@@ -130,6 +136,13 @@ impl Node {
 
         pub fn set_weight(&mut self, weight : f32) {
             self.weight = weight;
+            self.touched = true;
+        }
+
+        /// Returns true if the edge was added or its weight was set
+        /// since the last tick of the graph.
+        pub fn is_touched(&self) -> bool {
+            self.touched
         }
 
     }
@@ -164,12 +177,69 @@ impl Node {
         }
     }
 
-    #[derive(Clone, Debug, Default)]
+    /// Numbers of changes between two graphs (see Graph::diff_counts).
+    #[derive(Copy, Clone, Debug, Default, PartialEq)]
+    pub struct GraphDiffCounts {
+        pub nodes_added: usize,
+        pub nodes_removed: usize,
+        // Nodes whose state changed
+        pub nodes_changed: usize,
+        pub edges_added: usize,
+        pub edges_removed: usize,
+        // Edges whose weight changed
+        pub edges_changed: usize,
+        // Sum of the absolute weight changes
+        pub weight_delta: f32,
+    }
+
+    impl GraphDiffCounts {
+        /// Returns the counts in the order nodes added, removed, changed and edges
+        /// added, removed, changed.
+        pub fn to_vec(&self) -> Vec<i32> {
+            vec![
+                self.nodes_added as i32,
+                self.nodes_removed as i32,
+                self.nodes_changed as i32,
+                self.edges_added as i32,
+                self.edges_removed as i32,
+                self.edges_changed as i32,
+            ]
+        }
+    }
+
+    /// Defines how IDs are assigned to new nodes of a graph.
+    #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+    pub enum IdPolicy {
+        // Every node gets a new ID. IDs of removed nodes are never assigned again.
+        Monotonic,
+        // The smallest ID of a removed node is assigned before a new ID is allocated.
+        ReuseFreed,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
     pub struct Graph {
         // Incoming edge list
         pub edges: HashMap<usize, Vec<Edge>>,
         // Nodes by Id
         pub nodes: HashMap<usize, Node>,
+        // ID that is assigned to the next new node
+        next_id: usize,
+        // IDs of removed nodes that can be reassigned in ascending order. Only the
+        // ReuseFreed policy stores them.
+        freed_ids: Vec<usize>,
+        id_policy: IdPolicy,
+        // Reach sets by origin ID. They are computed on demand and cleared when
+        // edges or nodes are removed or edges are added.
+        #[serde(skip)]
+        reach_cache: HashMap<usize, Vec<usize>>,
+    }
+
+    /// Returns the entries of the map in ascending order of the node IDs, so that the
+    /// printed graphs and differences do not depend on the iteration order of HashMap.
+    fn sorted_by_id<V>(map: &HashMap<usize, V>) -> Vec<(&usize, &V)> {
+        let mut entries: Vec<(&usize, &V)> = map.iter().collect();
+        entries.sort_by_key(|(id, _)| **id);
+        entries
     }
 
     impl PushPrint for Graph {
@@ -180,14 +250,14 @@ impl Node {
     impl fmt::Display for Graph {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             let mut node_string: String = "".to_owned();
-            for (_, node) in self.nodes.iter() {
+            for (_, node) in sorted_by_id(&self.nodes) {
                 node_string.push_str("\n");
                 node_string.push_str(&node.to_string());
                 node_string.push_str(", ");
             }
             node_string = node_string.trim_end_matches(", ").to_string();
             let mut edge_string: String = "".to_owned();
-            for (node, edges) in self.edges.iter() {
+            for (node, edges) in sorted_by_id(&self.edges) {
                 for e in edges.iter() {
                     edge_string.push_str("\n");
                     edge_string.push_str("E[");
@@ -210,14 +280,61 @@ impl Node {
         }
     }
 
+    impl Default for Graph {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
     impl Graph {
         pub fn new() -> Self {
+            Graph::with_id_policy(IdPolicy::Monotonic)
+        }
+
+        /// Creates an empty graph that assigns node IDs according to
+        /// the given policy. IDs are counted per graph instance starting
+        /// at FIRST_NODE_ID, i.e. the same sequence of operations always
+        /// produces the same IDs.
+        pub fn with_id_policy(id_policy: IdPolicy) -> Self {
             Self {
                 edges: HashMap::new(),
                 nodes: HashMap::new(),
+                next_id: FIRST_NODE_ID,
+                freed_ids: vec![],
+                id_policy,
+                reach_cache: HashMap::new(),
             }
         }
 
+        /// Returns the ID allocation policy of this graph.
+        pub fn get_id_policy(&self) -> IdPolicy {
+            self.id_policy
+        }
+
+        /// Returns true if a node with the given ID was removed
+        /// and its ID has not been reassigned yet. With the monotonic
+        /// policy IDs are never reassigned, so every assigned ID without
+        /// a node is a tombstone.
+        pub fn is_tombstone(&self, id: &usize) -> bool {
+            match self.id_policy {
+                IdPolicy::Monotonic => {
+                    (FIRST_NODE_ID..self.next_id).contains(id) && !self.nodes.contains_key(id)
+                }
+                IdPolicy::ReuseFreed => self.freed_ids.binary_search(id).is_ok(),
+            }
+        }
+
+        /// Returns the ID for a new node depending on the
+        /// ID policy.
+        fn allocate_id(&mut self) -> usize {
+            if self.id_policy == IdPolicy::ReuseFreed && !self.freed_ids.is_empty() {
+                return self.freed_ids.remove(0);
+            }
+            let id = self.next_id;
+            self.next_id += 1;
+            id
+        }
+
         /// Returns the difference between two graphs as String
         /// or None if they are identical.
         pub fn diff(&self, other: &Graph) -> Option<String>{
@@ -226,7 +343,7 @@ impl Node {
             // 1. Nodes
             // Left side
             let mut node_diff : String = "".to_owned();
-            for (lk,lv) in self.nodes.iter() {
+            for (lk, lv) in sorted_by_id(&self.nodes) {
                 if !other.nodes.contains_key(lk) {
                     // Node removed
                     node_diff.push_str("\n");
@@ -237,7 +354,7 @@ impl Node {
                 }
             }
             // Right side 
-            for (rk, rv) in other.nodes.iter() {
+            for (rk, rv) in sorted_by_id(&other.nodes) {
             if !self.nodes.contains_key(rk) {
                 // Node added
                 node_diff.push_str("\n");
@@ -263,7 +380,7 @@ impl Node {
            let mut edge_diff : String = "".to_owned();
            // 2. Edges
            // Left side
-           for (lk,_lv) in self.edges.iter() {
+           for (lk, _lv) in sorted_by_id(&self.edges) {
                 if !other.edges.contains_key(lk) {
                     // All edges of this node are removed
                     if let Some(ies) = self.edges.get(lk) {
@@ -294,7 +411,7 @@ impl Node {
                     }
                 }
            }
-        for (rk,_rv) in other.edges.iter() {
+        for (rk, _rv) in sorted_by_id(&other.edges) {
              if !self.edges.contains_key(rk) {
                  // All edges of this node are added
                  if let Some(ies) = other.edges.get(rk) {
@@ -354,20 +471,69 @@ impl Node {
            }
         }
 
+        /// Counts the changes from this graph to the argument graph. Nodes are matched by
+        /// ID and edges by destination and origin ID. The weight delta is the sum of the
+        /// absolute weight changes of all edges where added and removed edges count with
+        /// their full weight.
+        pub fn diff_counts(&self, other: &Graph) -> GraphDiffCounts {
+            let mut counts = GraphDiffCounts::default();
+            for (id, node) in self.nodes.iter() {
+                match other.nodes.get(id) {
+                    None => counts.nodes_removed += 1,
+                    Some(other_node) if other_node.get_state() != node.get_state() => {
+                        counts.nodes_changed += 1
+                    }
+                    Some(_) => (),
+                }
+            }
+            counts.nodes_added = other.nodes.keys().filter(|id| !self.nodes.contains_key(id)).count();
+            let no_edges = vec![];
+            for (destination, incoming) in self.edges.iter() {
+                let other_incoming = other.edges.get(destination).unwrap_or(&no_edges);
+                for edge in incoming.iter() {
+                    match other_incoming.iter().find(|e| *e == edge) {
+                        None => {
+                            counts.edges_removed += 1;
+                            counts.weight_delta += edge.get_weight().abs();
+                        }
+                        Some(other_edge) if other_edge.get_weight() != edge.get_weight() => {
+                            counts.edges_changed += 1;
+                            counts.weight_delta += (other_edge.get_weight() - edge.get_weight()).abs();
+                        }
+                        Some(_) => (),
+                    }
+                }
+            }
+            for (destination, other_incoming) in other.edges.iter() {
+                let incoming = self.edges.get(destination).unwrap_or(&no_edges);
+                for edge in other_incoming.iter().filter(|e| !incoming.contains(e)) {
+                    counts.edges_added += 1;
+                    counts.weight_delta += edge.get_weight().abs();
+                }
+            }
+            counts
+        }
+
         /// Adds an new node with the given state and activity
         /// and returns its assigned IDs.
         pub fn add_node(&mut self, state: i32) -> usize {
-            let node = Node::new(state);
-            let node_id = node.node_id;
-            self.nodes.insert(node_id, node);
-            node_id.clone()
+            let node_id = self.allocate_id();
+            self.nodes.insert(node_id, Node::new(node_id, state));
+            self.reach_cache.clear();
+            node_id
         }
 
         /// Removes the node with the given id and
-        /// all its outgoing connections from the graph.
+        /// all its outgoing connections from the graph. The ID
+        /// is kept as tombstone.
         pub fn remove_node(&mut self, id: usize) {
-            self.nodes.remove(&id);
+            if self.nodes.remove(&id).is_some() && self.id_policy == IdPolicy::ReuseFreed {
+                if let Err(pos) = self.freed_ids.binary_search(&id) {
+                    self.freed_ids.insert(pos, id);
+                }
+            }
             self.edges.remove(&id);
+            self.reach_cache.clear();
             for (_, edges) in self.edges.iter_mut() {
                 if let Some(edge_idx) = edges.iter().position(|x| x == &Edge::new(id, 0.0)) {
                     edges.remove(edge_idx);
@@ -380,6 +546,7 @@ impl Node {
         /// weight parameter to it.
         pub fn add_edge(&mut self, origin_id: usize, destination_id: usize, weight: f32) {
             if self.nodes.contains_key(&origin_id) && self.nodes.contains_key(&destination_id) {
+                self.reach_cache.clear();
                 if let Some(incoming_edges) = self.edges.get_mut(&destination_id) {
                     // Use origin_id to create an incoming edge
                     let edge = Edge::new(origin_id, weight);
@@ -397,6 +564,7 @@ impl Node {
         /// Removes the connection from origin_id to destination_id
         /// from the graph
         pub fn remove_edge(&mut self, origin_id: usize, destination_id: usize) {
+            self.reach_cache.clear();
             if let Some(incoming_edges) = self.edges.get_mut(&destination_id) {
                 incoming_edges.retain(|x| x != &Edge::new(origin_id, 0.0));
             }
@@ -431,6 +599,11 @@ impl Node {
             None
         }
 
+        /// Returns true if the weights of all edges are finite.
+        pub fn has_finite_weights(&self) -> bool {
+            self.edges.values().flatten().all(|edge| edge.weight.is_finite())
+        }
+
         /// Set the weight of the edge between the nodes with
         /// origin_id and destination_id.
         pub fn set_weight(&mut self, origin_id: &usize, destination_id: &usize, weight: f32) {
@@ -441,6 +614,91 @@ impl Node {
             }
         }
 
+        /// Marks all edges as untouched. Edges that are added or whose
+        /// weight is set afterwards count as touched until the next tick.
+        pub fn tick(&mut self) {
+            for edge in self.edges.values_mut().flatten() {
+                edge.touched = false;
+            }
+        }
+
+        /// Multiplies the weights of all edges by the factor. If
+        /// untouched_only is set only the edges that have not been touched
+        /// since the last tick decay. Decaying does not touch an edge.
+        pub fn decay(&mut self, factor: f32, untouched_only: bool) {
+            for edge in self.edges.values_mut().flatten() {
+                if !(untouched_only && edge.touched) {
+                    edge.weight *= factor;
+                }
+            }
+        }
+
+        /// Scales the incoming edge weights of each node so that their sum (or their
+        /// Euclidean norm if l2 is true) is one. Nodes whose incoming weights have a
+        /// norm of zero are left unchanged. The scaled edges count as touched.
+        pub fn normalize_in_weights(&mut self, l2: bool) {
+            for incoming_edges in self.edges.values_mut() {
+                let norm = if l2 {
+                    incoming_edges.iter().map(|e| e.weight * e.weight).sum::<f32>().sqrt()
+                } else {
+                    incoming_edges.iter().map(|e| e.weight).sum::<f32>()
+                };
+                if norm != 0.0 && norm.is_finite() {
+                    for edge in incoming_edges.iter_mut() {
+                        let weight = edge.weight / norm;
+                        edge.set_weight(weight);
+                    }
+                }
+            }
+        }
+
+        /// Returns the IDs of all nodes that can be reached from the origin by following
+        /// the directed edges in ascending order. The origin is part of its reach set.
+        /// Returns an empty set if the origin does not exist. Reach sets are cached until
+        /// the structure of the graph changes. Code that modifies the edges or nodes
+        /// fields directly has to call clear_reach_cache afterwards.
+        pub fn reach_set(&mut self, origin_id: usize) -> Vec<usize> {
+            if let Some(reach_set) = self.reach_cache.get(&origin_id) {
+                return reach_set.clone();
+            }
+            let mut reach_set = vec![];
+            if self.nodes.contains_key(&origin_id) {
+                let mut successors: HashMap<usize, Vec<usize>> = HashMap::new();
+                for (destination_id, incoming_edges) in self.edges.iter() {
+                    for edge in incoming_edges.iter() {
+                        successors
+                            .entry(edge.origin_node_id)
+                            .or_default()
+                            .push(*destination_id);
+                    }
+                }
+                let mut visited = HashSet::new();
+                visited.insert(origin_id);
+                let mut queue = VecDeque::from(vec![origin_id]);
+                while let Some(node_id) = queue.pop_front() {
+                    for successor in successors.get(&node_id).into_iter().flatten() {
+                        if visited.insert(*successor) {
+                            queue.push_back(*successor);
+                        }
+                    }
+                }
+                reach_set = visited.into_iter().collect();
+                reach_set.sort_unstable();
+            }
+            self.reach_cache.insert(origin_id, reach_set.clone());
+            reach_set
+        }
+
+        /// Returns true if there is a directed path from the origin to the destination.
+        pub fn is_reachable(&mut self, origin_id: usize, destination_id: usize) -> bool {
+            self.reach_set(origin_id).binary_search(&destination_id).is_ok()
+        }
+
+        /// Clears the cached reach sets.
+        pub fn clear_reach_cache(&mut self) {
+            self.reach_cache.clear();
+        }
+
         /// Returns the number of nodes
         pub fn node_size(&self) -> usize {
             self.nodes.len()
@@ -463,6 +721,52 @@ impl Node {
             filtered_nodes
         }
         
+        /// Detects communities by label propagation. Each node starts with its ID as label.
+        /// In each iteration the nodes are visited in ascending ID order and adopt the label
+        /// with the highest sum of edge weights among their neighbors (ignoring edge directions).
+        /// Ties are resolved in favor of the smallest label. Returns the labels ordered by node ID.
+        pub fn communities(&self, iterations: usize) -> Vec<(usize, usize)> {
+            let mut ids: Vec<usize> = self.nodes.keys().cloned().collect();
+            ids.sort_unstable();
+            let mut neighbors: HashMap<usize, Vec<(usize, f32)>> = HashMap::new();
+            for (destination_id, incoming_edges) in self.edges.iter() {
+                for edge in incoming_edges {
+                    let origin_id = edge.get_origin_id();
+                    let weight = edge.get_weight();
+                    neighbors.entry(*destination_id).or_default().push((origin_id, weight));
+                    neighbors.entry(origin_id).or_default().push((*destination_id, weight));
+                }
+            }
+            let mut labels: HashMap<usize, usize> = ids.iter().map(|id| (*id, *id)).collect();
+            for _ in 0..iterations {
+                let mut changed = false;
+                for id in ids.iter() {
+                    if let Some(node_neighbors) = neighbors.get(id) {
+                        let mut sums: HashMap<usize, f32> = HashMap::new();
+                        for (n, weight) in node_neighbors {
+                            *sums.entry(labels[n]).or_insert(0.0) += weight;
+                        }
+                        let mut best = labels[id];
+                        let mut best_sum = f32::NEG_INFINITY;
+                        for (label, sum) in sums {
+                            if sum > best_sum || (sum == best_sum && label < best) {
+                                best = label;
+                                best_sum = sum;
+                            }
+                        }
+                        if best != labels[id] {
+                            labels.insert(*id, best);
+                            changed = true;
+                        }
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+            ids.iter().map(|id| (*id, labels[id])).collect()
+        }
+
         /// Returns the number of edges
         pub fn edge_size(&self) -> usize {
             let mut num_edges = 0;
@@ -480,13 +784,90 @@ impl Node {
         }
     }
 
+    #[cfg(feature = "petgraph")]
+    impl Graph {
+        /// Converts the graph to a directed petgraph graph. The nodes are added
+        /// in ascending ID order and keep their ID and state as node weight.
+        pub fn to_petgraph(&self) -> petgraph::graph::DiGraph<Node, f32> {
+            let mut pg = petgraph::graph::DiGraph::new();
+            let mut ids: Vec<&usize> = self.nodes.keys().collect();
+            ids.sort();
+            let mut indices = HashMap::new();
+            for id in ids {
+                indices.insert(*id, pg.add_node(self.nodes[id].clone()));
+            }
+            let mut destinations: Vec<&usize> = self.edges.keys().collect();
+            destinations.sort();
+            for destination_id in destinations {
+                for edge in self.edges[destination_id].iter() {
+                    if let (Some(origin), Some(destination)) =
+                        (indices.get(&edge.get_origin_id()), indices.get(destination_id))
+                    {
+                        pg.add_edge(*origin, *destination, edge.get_weight());
+                    }
+                }
+            }
+            pg
+        }
+
+        /// Creates a graph from a directed petgraph graph. Node IDs are taken
+        /// from the node weights, new nodes are assigned IDs above the largest one.
+        pub fn from_petgraph(pg: &petgraph::graph::DiGraph<Node, f32>) -> Self {
+            let mut graph = Graph::new();
+            for node in pg.node_weights() {
+                graph.nodes.insert(node.get_id(), node.clone());
+                graph.next_id = usize::max(graph.next_id, node.get_id() + 1);
+            }
+            for edge in pg.raw_edges() {
+                graph.add_edge(
+                    pg[edge.source()].get_id(),
+                    pg[edge.target()].get_id(),
+                    edge.weight,
+                );
+            }
+            graph
+        }
+    }
+
     pub fn load_graph_instructions(map: &mut HashMap<String, Instruction>) {
         map.insert(String::from("GRAPH.ADD"), Instruction::new(graph_add));
+        map.insert(
+            String::from("GRAPH.COMMUNITIES"),
+            Instruction::new(graph_communities),
+        );
+        map.insert(
+            String::from("GRAPH.DECAY"),
+            Instruction::new(graph_decay),
+        );
+        map.insert(
+            String::from("GRAPH.DECAY*UNTOUCHED"),
+            Instruction::new(graph_decay_untouched),
+        );
+        map.insert(
+            String::from("GRAPH.DO*IDS"),
+            Instruction::new(graph_do_ids).with_blocks(1),
+        );
+        map.insert(
+            String::from("GRAPH.DO*NODES"),
+            Instruction::new(graph_do_nodes).with_blocks(1),
+        );
         map.insert(String::from("GRAPH.DUP"), Instruction::new(graph_dup));
         map.insert(
             String::from("GRAPH.NODE*ADD"),
             Instruction::new(graph_node_add),
         );
+        map.insert(
+            String::from("GRAPH.NODE*ADD*BATCH"),
+            Instruction::new(graph_node_add_batch),
+        );
+        map.insert(
+            String::from("GRAPH.REACHABLE"),
+            Instruction::new(graph_reachable),
+        );
+        map.insert(
+            String::from("GRAPH.REACHSET"),
+            Instruction::new(graph_reach_set),
+        );
         map.insert(
             String::from("GRAPH.NODE*GETSTATE"),
             Instruction::new(graph_node_get_state),
@@ -495,6 +876,10 @@ impl Node {
             String::from("GRAPH.NODE*HISTORY"),
             Instruction::new(graph_node_history),
         );
+        map.insert(
+            String::from("GRAPH.NODE*INWEIGHTS"),
+            Instruction::new(graph_node_in_weights),
+        );
         map.insert(
             String::from("GRAPH.NODE*SETSTATE"),
             Instruction::new(graph_node_set_state),
@@ -523,6 +908,11 @@ impl Node {
             String::from("GRAPH.NODES*HISTORY"),
             Instruction::new(graph_nodes_history),
         );
+        map.insert(
+            String::from("GRAPH.NORMALIZE*INWEIGHTS"),
+            Instruction::new(graph_normalize_in_weights),
+        );
+        map.insert(String::from("GRAPH.POP"), Instruction::new(graph_pop));
         map.insert(
             String::from("GRAPH.STACKDEPTH"),
             Instruction::new(graph_stack_depth),
@@ -531,6 +921,10 @@ impl Node {
             String::from("GRAPH.PRINT"),
             Instruction::new(graph_print),
             );
+        map.insert(
+            String::from("GRAPH.DIFF*COUNTS"),
+            Instruction::new(graph_diff_counts),
+            );
         map.insert(
             String::from("GRAPH.PRINT*DIFF"),
             Instruction::new(graph_print_diff),
@@ -539,6 +933,10 @@ impl Node {
             String::from("GRAPH.EDGE*ADD"),
             Instruction::new(graph_edge_add),
         );
+        map.insert(
+            String::from("GRAPH.EDGE*EXISTS"),
+            Instruction::new(graph_edge_exists),
+        );
         map.insert(
             String::from("GRAPH.EDGE*HISTORY"),
             Instruction::new(graph_edge_history),
@@ -551,11 +949,19 @@ impl Node {
             String::from("GRAPH.EDGE*SETWEIGHT"),
             Instruction::new(graph_edge_set_weight),
         );
+        map.insert(
+            String::from("GRAPH.SELECT*APPLY"),
+            Instruction::new(graph_select_apply),
+        );
+        map.insert(String::from("GRAPH.SWAP"), Instruction::new(graph_swap));
+        map.insert(String::from("GRAPH.TICK"), Instruction::new(graph_tick));
     }
 
-    /// GRAPH.ADD: Pushes a new instance of an empty graph to the graph stack.
+    /// GRAPH.ADD: Pushes a new instance of an empty graph to the graph stack. Node IDs
+    /// are assigned according to the configured ID policy.
     fn graph_add(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-        push_state.graph_stack.push(Graph::new());
+        let id_policy = push_state.configuration.graph_id_policy;
+        push_state.graph_stack.push(Graph::with_id_policy(id_policy));
     }
 
     /// GRAPH.DUP: Duplicates the top item on the GRAPH stack.
@@ -565,6 +971,21 @@ impl Node {
         }
     }
 
+    /// GRAPH.POP: Pops the GRAPH stack.
+    fn graph_pop(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        push_state.graph_stack.pop();
+    }
+
+    /// GRAPH.SWAP: Swaps the top two GRAPHs.
+    fn graph_swap(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if push_state.graph_stack.size() > 1 {
+            let top = push_state.graph_stack.pop().unwrap();
+            let second = push_state.graph_stack.pop().unwrap();
+            push_state.graph_stack.push(top);
+            push_state.graph_stack.push(second);
+        }
+    }
+
     /// GRAPH.NODE*ADD: Adds a new node to the graph on top of the GRAPH stack. The ID
     /// of the node is pushed to the INTEGER stack.
     fn graph_node_add(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
@@ -577,6 +998,22 @@ impl Node {
         }
     }
 
+    /// GRAPH.NODE*ADD*BATCH: Adds a new node for each element of the top INTVECTOR item to the
+    /// graph on top of the GRAPH stack. The element is the initial state of the node. The IDs
+    /// of the nodes are pushed as INTVECTOR in the same order.
+    fn graph_node_add_batch(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if let Some(graph) = push_state.graph_stack.get_mut(0) {
+            if let Some(states) = push_state.int_vector_stack.pop() {
+                let ids = states
+                    .values
+                    .iter()
+                    .map(|state| graph.add_node(*state) as i32)
+                    .collect();
+                push_state.int_vector_stack.push(IntVector::new(ids));
+            }
+        }
+    }
+
     
     /// GRAPH.NODE*STATESWITCH: Sets the state defined by the top two INTEGER items to the nodes 
     /// with the IDs specified by top item of the INTVECTOR stack. If the element at position i 
@@ -590,9 +1027,9 @@ impl Node {
                     if let Some(states) = push_state.int_stack.pop_vec(2) {
                         let on_state = states[0];
                         let off_state = states[1];
-                        let switch_len = i32::max(i32::min(node_ids.values.len() as i32 , state_switch.values.len() as i32), 0) as usize;
+                        let switch_len = i32::max(i32::min(node_ids.values.len() as i32 , state_switch.len() as i32), 0) as usize;
                         for i in 0..switch_len {
-                            if state_switch.values[i] {
+                            if state_switch.bits.get(i) {
                                 graph.set_state(&(node_ids.values[i] as usize), on_state);
                             } else {
                                 graph.set_state(&(node_ids.values[i] as usize), off_state);
@@ -604,6 +1041,72 @@ impl Node {
         }
     }
 
+    /// GRAPH.COMMUNITIES: Detects communities in the top graph by label propagation and
+    /// pushes the community label of each node to the INTVECTOR stack ordered by node ID.
+    /// The maximum number of iterations is taken from the INTEGER stack.
+    fn graph_communities(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if let Some(graph) = push_state.graph_stack.get(0) {
+            if let Some(iterations) = push_state.int_stack.pop() {
+                if iterations >= 0 {
+                    let labels = graph.communities(iterations as usize);
+                    push_state.int_vector_stack.push(IntVector::new(
+                        labels.iter().map(|l| l.1 as i32).collect(),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Executes the body once per node ID. The first ID is pushed to the INTEGER stack
+    /// and the body is pushed to the EXEC stack on top of a continuation that repeats
+    /// this for the remaining IDs (see GRAPH.DO*IDS).
+    fn do_node_ids(push_state: &mut PushState, body: Item, mut ids: Vec<i32>) {
+        if ids.is_empty() {
+            return;
+        }
+        let id = ids.remove(0);
+        if !ids.is_empty() {
+            push_state.exec_stack.push(Item::list(vec![
+                body.clone(),
+                Item::instruction("GRAPH.DO*IDS".to_string()),
+                Item::intvec(IntVector::new(ids)),
+            ]));
+        }
+        push_state.exec_stack.push(body);
+        push_state.int_stack.push(id);
+    }
+
+    /// GRAPH.DO*IDS: Executes the top item of the EXEC stack once for each node ID of the
+    /// top INTVECTOR item. Before each execution the current ID is pushed to the INTEGER
+    /// stack. This acts as a NOOP if the INTVECTOR or the EXEC stack is empty.
+    fn graph_do_ids(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if push_state.exec_stack.size() > 0 {
+            if let Some(ids) = push_state.int_vector_stack.pop() {
+                if let Some(body) = push_state.exec_stack.pop() {
+                    do_node_ids(push_state, body, ids.values);
+                }
+            }
+        }
+    }
+
+    /// GRAPH.DO*NODES: Executes the top item of the EXEC stack once for each node of the
+    /// top graph in ascending ID order. Before each execution the node ID is pushed to the
+    /// INTEGER stack. Only nodes that are in one of the states of the top INTVECTOR item
+    /// are visited. If the INTVECTOR is empty all nodes are visited.
+    fn graph_do_nodes(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if let Some(graph) = push_state.graph_stack.get(0) {
+            if push_state.exec_stack.size() > 0 {
+                if let Some(states) = push_state.int_vector_stack.pop() {
+                    let mut ids = graph.filter(&states.values);
+                    ids.sort_unstable();
+                    if let Some(body) = push_state.exec_stack.pop() {
+                        do_node_ids(push_state, body, ids);
+                    }
+                }
+            }
+        }
+    }
+
     /// GRAPH.NODES: Pushes the IDs of the nodes that are in one of the predefined states 
     /// to the INTVECTOR stack. The states are taken from the top item 
     /// of the INTVECTOR stack. If the array is empty all node IDs of the graph are pushed. 
@@ -674,6 +1177,20 @@ impl Node {
         }
     }
 
+    /// GRAPH.DIFF*COUNTS: Compares the top to the second item on the GRAPH stack. Pushes the
+    /// numbers of added, removed and changed nodes followed by the numbers of added, removed
+    /// and changed edges as INTVECTOR and the sum of the absolute weight changes to the FLOAT
+    /// stack.
+    fn graph_diff_counts(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if let Some(new_graph) = push_state.graph_stack.get(0) {
+            if let Some(old_graph) = push_state.graph_stack.get(1) {
+                let counts = old_graph.diff_counts(new_graph);
+                push_state.int_vector_stack.push(IntVector::new(counts.to_vec()));
+                push_state.float_stack.push(counts.weight_delta);
+            }
+        }
+    }
+
     /// GRAPH.PRINT*DIFF: Pushes a string representation of the diff of the top to the second 
     /// item on the GRAPH stack to the name stack.
     fn graph_print_diff(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
@@ -693,6 +1210,31 @@ impl Node {
             .push(push_state.graph_stack.size() as i32);
     }
 
+    /// GRAPH.NODE*INWEIGHTS: Pushes the weights of all incoming edges of the node with the
+    /// id on top of the INTEGER stack to the FLOATVECTOR stack. The IDs of the corresponding
+    /// origin nodes are pushed to the INTVECTOR stack. Both are ordered by origin ID.
+    fn graph_node_in_weights(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if let Some(graph) = push_state.graph_stack.get(0) {
+            if let Some(node_id) = push_state.int_stack.pop() {
+                if node_id > 0 {
+                    let mut incoming = vec![];
+                    if let Some(incoming_edges) = graph.edges.get(&(node_id as usize)) {
+                        for edge in incoming_edges {
+                            incoming.push((edge.get_origin_id(), edge.get_weight()));
+                        }
+                    }
+                    incoming.sort_by_key(|e| e.0);
+                    push_state.int_vector_stack.push(IntVector::new(
+                        incoming.iter().map(|e| e.0 as i32).collect(),
+                    ));
+                    push_state.float_vector_stack.push(FloatVector::new(
+                        incoming.iter().map(|e| e.1).collect(),
+                    ));
+                }
+            }
+        }
+    }
+
     /// GRAPH.NODE*SETSTATE: Sets the state for the node with the specified id where the
     /// new state and the id are the first and second element of the stack.
     /// If the id does not exist this acts as NOOP.
@@ -830,6 +1372,23 @@ impl Node {
         }
      }
 
+    /// GRAPH.EDGE*EXISTS: Pushes TRUE to the BOOLEAN stack if the top graph contains an edge
+    /// from the origin to the destination node. The origin and destination id are the second
+    /// and the top item of the INTEGER stack respectively.
+    fn graph_edge_exists(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if let Some(graph) = push_state.graph_stack.get(0) {
+            if let Some(ids) = push_state.int_stack.pop_vec(2) {
+                if ids[0] >= 0 && ids[1] >= 0 {
+                    let origin_id = ids[0] as usize;
+                    let destination_id = ids[1] as usize;
+                    push_state
+                        .bool_stack
+                        .push(graph.get_weight(&origin_id, &destination_id).is_some());
+                }
+            }
+        }
+    }
+
     /// GRAPH.EDGE*HISTORY: Gets the weight for the edge with the specified stack postition, 
     /// origin and destination id. The stack position is top item of the INTEGER stack
     /// destination and origin ids are second and third items respectively.
@@ -840,7 +1399,6 @@ impl Node {
                      if let Some(ids) = push_state.int_stack.pop_vec(2) {
                         let origin_id = ids[0] as usize;
                         let destination_id = ids[1] as usize;
-                        println!("Origin = {}, Destination = {}", origin_id,destination_id);
                         if let Some(weight) = graph.get_weight(&origin_id, &destination_id) {
                            push_state.float_stack.push(weight);
                         }
@@ -864,6 +1422,113 @@ impl Node {
         }
     }
 
+    /// GRAPH.DECAY: Multiplies the weights of all edges of the top graph by the top
+    /// item of the FLOAT stack.
+    fn graph_decay(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if let Some(graph) = push_state.graph_stack.get_mut(0) {
+            if let Some(factor) = push_state.float_stack.pop() {
+                graph.decay(factor, false);
+            }
+        }
+    }
+
+    /// GRAPH.DECAY*UNTOUCHED: Multiplies the weights of all edges of the top graph that
+    /// have not been added or set since the last GRAPH.TICK by the top item of the FLOAT
+    /// stack.
+    fn graph_decay_untouched(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if let Some(graph) = push_state.graph_stack.get_mut(0) {
+            if let Some(factor) = push_state.float_stack.pop() {
+                graph.decay(factor, true);
+            }
+        }
+    }
+
+    /// GRAPH.NORMALIZE*INWEIGHTS: Scales the incoming edge weights of each node of the top
+    /// graph so that they sum to one (mode 0) or have unit Euclidean norm (mode 1). The mode
+    /// is taken from the INTEGER stack. Nodes whose incoming weights sum (or have a norm)
+    /// of zero are left unchanged. Acts as NOOP for other modes.
+    fn graph_normalize_in_weights(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if let Some(graph) = push_state.graph_stack.get_mut(0) {
+            if let Some(mode) = push_state.int_stack.get(0) {
+                if *mode == 0 || *mode == 1 {
+                    graph.normalize_in_weights(*mode == 1);
+                    push_state.int_stack.pop();
+                }
+            }
+        }
+    }
+
+    /// GRAPH.TICK: Marks all edges of the top graph as untouched. Edges that are added or
+    /// whose weight is set afterwards are excluded from GRAPH.DECAY*UNTOUCHED until the
+    /// next tick.
+    fn graph_tick(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if let Some(graph) = push_state.graph_stack.get_mut(0) {
+            graph.tick();
+        }
+    }
+
+    /// GRAPH.REACHABLE: Pushes TRUE to the BOOLEAN stack if the node with the ID given by the
+    /// top INTEGER item can be reached from the node with the ID given by the second INTEGER
+    /// item by following the directed edges of the graph on top of the GRAPH stack, or FALSE
+    /// otherwise. Every existing node is reachable from itself.
+    fn graph_reachable(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if let Some(graph) = push_state.graph_stack.get_mut(0) {
+            if let Some(ids) = push_state.int_stack.pop_vec(2) {
+                if ids[0] >= 0 && ids[1] >= 0 {
+                    let reachable = graph.is_reachable(ids[0] as usize, ids[1] as usize);
+                    push_state.bool_stack.push(reachable);
+                }
+            }
+        }
+    }
+
+    /// GRAPH.REACHSET: Pushes the IDs of all nodes that can be reached from the node with the
+    /// ID given by the top INTEGER item as INTVECTOR in ascending order. The origin node is part
+    /// of the result. Acts as NOOP if the origin node does not exist.
+    fn graph_reach_set(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if let Some(graph) = push_state.graph_stack.get_mut(0) {
+            if let Some(origin_id) = push_state.int_stack.pop() {
+                if origin_id >= 0 {
+                    let reach_set = graph.reach_set(origin_id as usize);
+                    if !reach_set.is_empty() {
+                        push_state.int_vector_stack.push(IntVector::new(
+                            reach_set.into_iter().map(|id| id as i32).collect(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    /// GRAPH.SELECT*APPLY: Sets the state of the nodes of the top graph whose IDs are given by
+    /// the top INTVECTOR item to the top INTEGER item and pushes their previous states as INTVECTOR
+    /// in the order of the IDs. All states are read before the first one is written, i.e. the
+    /// previous state of an ID that occurs twice is its state before the instruction. IDs of
+    /// nodes that do not exist are skipped. Acts as NOOP if one of the stacks is empty.
+    fn graph_select_apply(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+        if push_state.int_vector_stack.size() == 0 || push_state.int_stack.size() == 0 {
+            return;
+        }
+        if let Some(graph) = push_state.graph_stack.get_mut(0) {
+            if let (Some(node_ids), Some(state)) =
+                (push_state.int_vector_stack.pop(), push_state.int_stack.pop())
+            {
+                let selected: Vec<(usize, i32)> = node_ids
+                    .values
+                    .iter()
+                    .filter(|id| **id > 0)
+                    .filter_map(|id| graph.get_state(&(*id as usize)).map(|s| (*id as usize, s)))
+                    .collect();
+                for (id, _) in selected.iter() {
+                    graph.set_state(id, state);
+                }
+                push_state.int_vector_stack.push(IntVector::new(
+                    selected.into_iter().map(|(_, previous)| previous).collect(),
+                ));
+            }
+        }
+    }
+
 #[cfg(test)]
 mod tests {
     use crate::push::vector::BoolVector;
@@ -885,6 +1550,162 @@ mod tests {
         graph_edge_add(test_state, &icache());
     }
 
+    #[test]
+    fn graph_reachability_follows_directed_edges() {
+        let mut test_state = PushState::new();
+        graph_add(&mut test_state, &icache());
+        let ids: Vec<i32> = (0..4).map(|_| test_node(&mut test_state, 0)).collect();
+        test_edge(&mut test_state, ids[0], ids[1], 1.0);
+        test_edge(&mut test_state, ids[1], ids[2], 1.0);
+        test_state.int_stack.push(ids[0]);
+        graph_reach_set(&mut test_state, &icache());
+        assert_eq!(
+            test_state.int_vector_stack.pop().unwrap().values,
+            vec![ids[0], ids[1], ids[2]]
+        );
+        test_state.int_stack.push_vec(vec![ids[2], ids[0]]);
+        graph_reachable(&mut test_state, &icache());
+        test_state.int_stack.push_vec(vec![ids[0], ids[2]]);
+        graph_reachable(&mut test_state, &icache());
+        assert_eq!(test_state.bool_stack.to_string(), "TRUE FALSE");
+        // Cached reach sets are cleared by structural changes
+        test_state
+            .graph_stack
+            .get_mut(0)
+            .unwrap()
+            .remove_edge(ids[1] as usize, ids[2] as usize);
+        test_state.int_stack.push_vec(vec![ids[0], ids[2]]);
+        graph_reachable(&mut test_state, &icache());
+        assert_eq!(test_state.bool_stack.pop(), Some(false));
+        test_state.int_stack.push(99);
+        graph_reach_set(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.size(), 0);
+    }
+
+    #[test]
+    fn reach_sets_include_added_nodes() {
+        let mut graph = Graph::new();
+        // The empty reach set of the missing node is cached
+        assert!(graph.reach_set(FIRST_NODE_ID).is_empty());
+        let id = graph.add_node(0);
+        assert_eq!(id, FIRST_NODE_ID);
+        assert_eq!(graph.reach_set(id), vec![id]);
+        assert!(graph.is_reachable(id, id));
+    }
+
+    #[test]
+    fn graph_node_add_batch_pushes_node_ids() {
+        let mut test_state = PushState::new();
+        graph_add(&mut test_state, &icache());
+        test_node(&mut test_state, 7);
+        test_state.int_vector_stack.push(IntVector::new(vec![1, 0, 1]));
+        graph_node_add_batch(&mut test_state, &icache());
+        let ids = test_state.int_vector_stack.pop().unwrap();
+        assert_eq!(ids.values.len(), 3);
+        let graph = test_state.graph_stack.get(0).unwrap();
+        assert_eq!(graph.node_size(), 4);
+        let states: Vec<Option<i32>> = ids
+            .values
+            .iter()
+            .map(|id| graph.get_state(&(*id as usize)))
+            .collect();
+        assert_eq!(states, vec![Some(1), Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn graph_communities_pushes_labels_by_node_id() {
+        let mut test_state = PushState::new();
+        graph_add(&mut test_state, &icache());
+        // Two fully connected clusters linked by a weak edge
+        for _ in 0..8 {
+            test_node(&mut test_state, 0);
+        }
+        for cluster in [1, 5].iter() {
+            for i in 0..4 {
+                for j in i + 1..4 {
+                    test_edge(&mut test_state, cluster + i, cluster + j, 1.0);
+                }
+            }
+        }
+        test_edge(&mut test_state, 4, 5, 0.1);
+        test_state.int_stack.push(10);
+        graph_communities(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[2,2,2,2,6,6,6,6]");
+        test_state.int_stack.push(0);
+        graph_communities(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.copy(0).unwrap().to_string(), "[1,2,3,4,5,6,7,8]");
+    }
+
+    #[test]
+    fn graph_do_nodes_executes_body_per_node() {
+        use crate::push::instructions::InstructionSet;
+        use crate::push::interpreter::PushInterpreter;
+        use crate::push::parser::PushParser;
+        let mut test_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut test_graph = Graph::new();
+        for state in [3, 4, 3, 3].iter() {
+            test_graph.add_node(*state);
+        }
+        test_state.graph_stack.push(test_graph);
+        test_state.int_vector_stack.push(IntVector::new(vec![3]));
+        PushParser::parse_program(&mut test_state, &instruction_set, "( GRAPH.DO*NODES ( 10 INTEGER.* ) )");
+        PushInterpreter::run(&mut test_state, &mut instruction_set);
+        assert_eq!(test_state.int_stack.to_string(), "40 30 10");
+        assert_eq!(test_state.int_vector_stack.size(), 0);
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn graph_petgraph_conversion_roundtrip() {
+        let mut test_graph = Graph::new();
+        let n1 = test_graph.add_node(5);
+        let n2 = test_graph.add_node(6);
+        let n3 = test_graph.add_node(7);
+        test_graph.remove_node(n2);
+        test_graph.add_edge(n1, n3, 0.5);
+        test_graph.add_edge(n3, n1, 0.25);
+        let pg = test_graph.to_petgraph();
+        assert_eq!(pg.node_count(), 2);
+        assert_eq!(pg.edge_count(), 2);
+        assert!(petgraph::algo::is_cyclic_directed(&pg));
+        let mut converted = Graph::from_petgraph(&pg);
+        assert!(converted == test_graph);
+        assert_eq!(converted.add_node(8), n3 + 1);
+    }
+
+    #[test]
+    fn graph_edge_exists_pushes_bool() {
+        let mut test_state = PushState::new();
+        graph_add(&mut test_state, &icache());
+        let origin_id = test_node(&mut test_state, 1);
+        let destination_id = test_node(&mut test_state, 2);
+        test_edge(&mut test_state, origin_id, destination_id, 0.5);
+        test_state.int_stack.push(origin_id);
+        test_state.int_stack.push(destination_id);
+        graph_edge_exists(&mut test_state, &icache());
+        test_state.int_stack.push(destination_id);
+        test_state.int_stack.push(origin_id);
+        graph_edge_exists(&mut test_state, &icache());
+        assert_eq!(test_state.bool_stack.to_string(), "FALSE TRUE");
+    }
+
+    #[test]
+    fn graph_node_in_weights_are_ordered_by_origin_id() {
+        let mut test_state = PushState::new();
+        graph_add(&mut test_state, &icache());
+        let origin_id1 = test_node(&mut test_state, 1);
+        let origin_id2 = test_node(&mut test_state, 1);
+        let destination_id = test_node(&mut test_state, 2);
+        test_edge(&mut test_state, origin_id2, destination_id, 0.2);
+        test_edge(&mut test_state, origin_id1, destination_id, 0.1);
+        test_state.int_stack.push(destination_id);
+        graph_node_in_weights(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[1,2]");
+        assert_eq!(test_state.float_vector_stack.to_string(), "[0.100,0.200]");
+    }
+
     #[test]
     fn graph_node_selected_predecessors_states_are_pushed() {
         let mut test_state = PushState::new();
@@ -1149,6 +1970,28 @@ mod tests {
         assert_eq!(modified_graph.get_state(&(ids_to_switch[3] as usize)).unwrap(), initial_state); 
     }
 
+    #[test]
+    fn graph_select_apply_pushes_previous_states() {
+        let mut test_state = PushState::new();
+        let mut test_graph = Graph::new();
+        let first_id = test_graph.add_node(3) as i32;
+        let second_id = test_graph.add_node(4) as i32;
+        let third_id = test_graph.add_node(5) as i32;
+        test_state.graph_stack.push(test_graph);
+        test_state.int_stack.push(9);
+        test_state.int_vector_stack.push(IntVector::new(vec![second_id, 99, first_id, second_id]));
+        graph_select_apply(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[4,3,4]");
+        assert_eq!(test_state.int_stack.size(), 0);
+        let graph = test_state.graph_stack.get(0).unwrap();
+        assert_eq!(graph.get_state(&(first_id as usize)), Some(9));
+        assert_eq!(graph.get_state(&(second_id as usize)), Some(9));
+        assert_eq!(graph.get_state(&(third_id as usize)), Some(5));
+        // Missing INTEGER
+        graph_select_apply(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[4,3,4]");
+    }
+
     #[test]
     fn graph_edge_add_updates_graph() {
         let mut test_state = PushState::new();
@@ -1171,6 +2014,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn graph_node_ids_are_assigned_per_instance() {
+        let mut g1 = Graph::new();
+        let mut g2 = Graph::new();
+        assert_eq!(g1.add_node(1), FIRST_NODE_ID);
+        assert_eq!(g1.add_node(1), FIRST_NODE_ID + 1);
+        assert_eq!(g2.add_node(1), FIRST_NODE_ID);
+    }
+
+    #[test]
+    fn graph_monotonic_policy_does_not_reuse_ids() {
+        let mut test_graph = Graph::with_id_policy(IdPolicy::Monotonic);
+        let id1 = test_graph.add_node(1);
+        let id2 = test_graph.add_node(1);
+        test_graph.remove_node(id1);
+        assert!(test_graph.is_tombstone(&id1));
+        let id3 = test_graph.add_node(1);
+        assert_eq!(id3, id2 + 1);
+        assert!(test_graph.is_tombstone(&id1));
+        assert!(!test_graph.is_tombstone(&id2));
+        assert!(!test_graph.is_tombstone(&(id3 + 1)));
+        for _ in 0..100 {
+            let id = test_graph.add_node(1);
+            test_graph.remove_node(id);
+        }
+        assert!(test_graph.freed_ids.is_empty());
+    }
+
+    #[test]
+    fn graph_reuse_policy_assigns_smallest_freed_id() {
+        let mut test_graph = Graph::with_id_policy(IdPolicy::ReuseFreed);
+        let id1 = test_graph.add_node(1);
+        let id2 = test_graph.add_node(1);
+        let id3 = test_graph.add_node(1);
+        test_graph.remove_node(id3);
+        test_graph.remove_node(id1);
+        assert_eq!(test_graph.add_node(2), id1);
+        assert_eq!(test_graph.add_node(2), id3);
+        assert_eq!(test_graph.add_node(2), id3 + 1);
+        assert!(!test_graph.is_tombstone(&id1));
+        assert_eq!(test_graph.get_state(&id2), Some(1));
+    }
+
     #[test]
     fn graph_print_differences() {
         let mut test_graph = Graph::new();
@@ -1200,7 +2086,30 @@ mod tests {
         assert!(diff.contains("EDGES(2)"));
         assert!(diff.contains(&format!("+E[{} <= [ONID: {}, WEIGHT: 1.2]]", test_ids[0], test_ids[4])));
         assert!(diff.contains(&format!("~E[{} <= [ONID: {}, 1.3 <= WEIGHT => 0.2]]",test_ids[0], test_ids[1])));
+        changed_test_graph.remove_node(test_ids[3]);
+        let counts = test_graph.diff_counts(&changed_test_graph);
+        assert_eq!(counts.to_vec(), vec![1, 1, 1, 1, 1, 1]);
+        assert!((counts.weight_delta - (1.2 + 1.1 + 1.5)).abs() < 1e-5);
+        assert_eq!(test_graph.diff_counts(&test_graph), GraphDiffCounts::default());
+
+    }
 
+    #[test]
+    fn graph_diff_counts_pushes_change_magnitude() {
+        let mut test_state = PushState::new();
+        let mut test_graph = Graph::new();
+        let a = test_graph.add_node(1);
+        let b = test_graph.add_node(2);
+        test_graph.add_edge(a, b, 0.5);
+        test_state.graph_stack.push(test_graph.clone());
+        graph_diff_counts(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.size(), 0);
+        test_graph.add_edge(b, a, -1.5);
+        test_graph.set_state(&a, 3);
+        test_state.graph_stack.push(test_graph);
+        graph_diff_counts(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[0,0,1,1,0,0]");
+        assert_eq!(test_state.float_stack.to_string(), "1.5");
     }
 
     #[test]
@@ -1309,5 +2218,78 @@ mod tests {
         assert_eq!(test_state.int_stack.pop().unwrap(),2);
     }
 
+    #[test]
+    fn graph_normalize_in_weights_scales_incoming_edges() {
+        let mut test_state = PushState::new();
+        graph_add(&mut test_state, &icache());
+        let ids: Vec<i32> = (0..4).map(|_| test_node(&mut test_state, 0)).collect();
+        test_edge(&mut test_state, ids[0], ids[3], 3.0);
+        test_edge(&mut test_state, ids[1], ids[3], 4.0);
+        test_edge(&mut test_state, ids[2], ids[1], 2.0);
+        let mut l2_state = test_state.clone();
+        test_state.int_stack.push(0);
+        graph_normalize_in_weights(&mut test_state, &icache());
+        let graph = test_state.graph_stack.get(0).unwrap();
+        let id = |i: usize| ids[i] as usize;
+        assert_eq!(graph.get_weight(&id(0), &id(3)), Some(3.0 / 7.0));
+        assert_eq!(graph.get_weight(&id(1), &id(3)), Some(4.0 / 7.0));
+        assert_eq!(graph.get_weight(&id(2), &id(1)), Some(1.0));
+        l2_state.int_stack.push(1);
+        graph_normalize_in_weights(&mut l2_state, &icache());
+        let graph = l2_state.graph_stack.get(0).unwrap();
+        assert_eq!(graph.get_weight(&id(0), &id(3)), Some(0.6));
+        assert_eq!(graph.get_weight(&id(1), &id(3)), Some(0.8));
+        l2_state.int_stack.push(2);
+        graph_normalize_in_weights(&mut l2_state, &icache());
+        assert_eq!(l2_state.int_stack.to_string(), "2");
+        // Scaled edges are excluded from GRAPH.DECAY*UNTOUCHED until the next tick
+        graph_tick(&mut test_state, &icache());
+        test_state.int_stack.push(0);
+        graph_normalize_in_weights(&mut test_state, &icache());
+        test_state.float_stack.push(0.5);
+        graph_decay_untouched(&mut test_state, &icache());
+        let graph = test_state.graph_stack.get(0).unwrap();
+        assert_eq!(graph.get_weight(&id(0), &id(3)), Some(3.0 / 7.0));
+    }
+
+    #[test]
+    fn graph_decay_skips_touched_edges() {
+        let mut test_state = PushState::new();
+        test_state.graph_stack.push(Graph::new());
+        let n1 = test_node(&mut test_state, 1);
+        let n2 = test_node(&mut test_state, 2);
+        let n3 = test_node(&mut test_state, 3);
+        test_edge(&mut test_state, n1, n2, 1.0);
+        test_edge(&mut test_state, n2, n3, 1.0);
+        test_state.float_stack.push(0.5);
+        graph_decay(&mut test_state, &icache());
+        graph_tick(&mut test_state, &icache());
+        test_state.int_stack.push(n1);
+        test_state.int_stack.push(n2);
+        test_state.float_stack.push(2.0);
+        graph_edge_set_weight(&mut test_state, &icache());
+        test_state.float_stack.push(0.5);
+        graph_decay_untouched(&mut test_state, &icache());
+        let graph = test_state.graph_stack.get(0).unwrap();
+        assert_eq!(graph.get_weight(&(n1 as usize), &(n2 as usize)), Some(2.0));
+        assert_eq!(graph.get_weight(&(n2 as usize), &(n3 as usize)), Some(0.25));
+    }
+
+    #[test]
+    fn graph_swap_and_pop_top_items() {
+        let mut test_state = PushState::new();
+        let mut test_graph = Graph::new();
+        test_graph.add_node(1);
+        test_state.graph_stack.push(test_graph);
+        test_state.graph_stack.push(Graph::new());
+        graph_swap(&mut test_state, &icache());
+        assert_eq!(test_state.graph_stack.get(0).unwrap().node_size(), 1);
+        assert_eq!(test_state.graph_stack.get(1).unwrap().node_size(), 0);
+        graph_pop(&mut test_state, &icache());
+        assert_eq!(test_state.graph_stack.size(), 1);
+        assert_eq!(test_state.graph_stack.get(0).unwrap().node_size(), 0);
+        graph_swap(&mut test_state, &icache());
+        assert_eq!(test_state.graph_stack.size(), 1);
+    }
 
 }
@@ -0,0 +1,288 @@
+use crate::push::graph::Graph;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+
+/// Maximum number of random node pairs that add_random_edge draws before it enumerates
+/// the unconnected pairs.
+const EDGE_SAMPLING_TRIES: usize = 32;
+
+/// Returns the node IDs in ascending order so that the choices of the operators only
+/// depend on the RNG and not on the iteration order of the node map.
+fn node_ids(graph: &Graph) -> Vec<usize> {
+    let mut ids: Vec<usize> = graph.nodes.keys().cloned().collect();
+    ids.sort_unstable();
+    ids
+}
+
+/// Returns all edges as (origin, destination, weight) ordered by destination and origin.
+fn edge_list(graph: &Graph) -> Vec<(usize, usize, f32)> {
+    let mut edges: Vec<(usize, usize, f32)> = graph
+        .edges
+        .iter()
+        .flat_map(|(destination, incoming)| {
+            incoming
+                .iter()
+                .map(move |e| (e.get_origin_id(), *destination, e.get_weight()))
+        })
+        .collect();
+    edges.sort_unstable_by_key(|(origin, destination, _)| (*destination, *origin));
+    edges
+}
+
+/// Samples a value from N(0, sigma). Returns 0 if sigma is not a valid deviation.
+fn gaussian<R: Rng>(rng: &mut R, sigma: f32) -> f32 {
+    Normal::new(0.0, sigma).map_or(0.0, |n| n.sample(rng))
+}
+
+/// Adds an unconnected node with the given state. Returns the ID of the new node.
+pub fn add_random_node(graph: &mut Graph, state: i32) -> usize {
+    graph.add_node(state)
+}
+
+/// Removes a random node together with its edges. Returns the ID of the removed
+/// node or None if the graph is empty.
+pub fn remove_random_node<R: Rng>(graph: &mut Graph, rng: &mut R) -> Option<usize> {
+    let ids = node_ids(graph);
+    if ids.is_empty() {
+        return None;
+    }
+    let id = ids[rng.gen_range(0..ids.len())];
+    graph.remove_node(id);
+    Some(id)
+}
+
+/// Adds an edge between two random nodes that are not connected yet. The weight is
+/// drawn from N(0, sigma). Returns the (origin, destination) of the new edge or None
+/// if the graph is fully connected.
+///
+/// The pair is found by rejection sampling with at most EDGE_SAMPLING_TRIES draws.
+/// Only if less than an eighth of the pairs is unconnected or all draws fail, the
+/// unconnected pairs are enumerated, which takes quadratic time in the number of nodes.
+pub fn add_random_edge<R: Rng>(
+    graph: &mut Graph,
+    rng: &mut R,
+    sigma: f32,
+) -> Option<(usize, usize)> {
+    let ids = node_ids(graph);
+    let pairs = ids.len() * ids.len();
+    let unconnected = pairs.saturating_sub(graph.edge_size());
+    if unconnected == 0 {
+        return None;
+    }
+    let mut pair = None;
+    if unconnected * 8 >= pairs {
+        for _ in 0..EDGE_SAMPLING_TRIES {
+            let origin = ids[rng.gen_range(0..ids.len())];
+            let destination = ids[rng.gen_range(0..ids.len())];
+            if graph.get_weight(&origin, &destination).is_none() {
+                pair = Some((origin, destination));
+                break;
+            }
+        }
+    }
+    let (origin, destination) = match pair {
+        Some(pair) => pair,
+        None => {
+            let candidates: Vec<(usize, usize)> = ids
+                .iter()
+                .flat_map(|origin| ids.iter().map(move |destination| (*origin, *destination)))
+                .filter(|(origin, destination)| graph.get_weight(origin, destination).is_none())
+                .collect();
+            if candidates.is_empty() {
+                return None;
+            }
+            candidates[rng.gen_range(0..candidates.len())]
+        }
+    };
+    let weight = gaussian(rng, sigma);
+    graph.add_edge(origin, destination, weight);
+    Some((origin, destination))
+}
+
+/// Removes a random edge. Returns its (origin, destination) or None if the graph
+/// has no edges.
+pub fn remove_random_edge<R: Rng>(graph: &mut Graph, rng: &mut R) -> Option<(usize, usize)> {
+    let edges = edge_list(graph);
+    if edges.is_empty() {
+        return None;
+    }
+    let (origin, destination, _) = edges[rng.gen_range(0..edges.len())];
+    graph.remove_edge(origin, destination);
+    Some((origin, destination))
+}
+
+/// Adds noise drawn from N(0, sigma) to the weight of each edge with probability
+/// rate. Returns the number of perturbed edges.
+pub fn perturb_weights<R: Rng>(graph: &mut Graph, rng: &mut R, rate: f32, sigma: f32) -> usize {
+    let mut perturbed = 0;
+    for (origin, destination, weight) in edge_list(graph) {
+        if rng.gen::<f32>() < rate {
+            graph.set_weight(&origin, &destination, weight + gaussian(rng, sigma));
+            perturbed += 1;
+        }
+    }
+    perturbed
+}
+
+/// Splits a random edge (NEAT add node mutation): the edge from origin to destination
+/// is replaced by a new node with the given state, an edge from the origin to the new
+/// node with weight 1 and an edge from the new node to the destination with the old
+/// weight. Returns the ID of the new node or None if the graph has no edges.
+pub fn split_random_edge<R: Rng>(graph: &mut Graph, rng: &mut R, state: i32) -> Option<usize> {
+    let edges = edge_list(graph);
+    if edges.is_empty() {
+        return None;
+    }
+    let (origin, destination, weight) = edges[rng.gen_range(0..edges.len())];
+    graph.remove_edge(origin, destination);
+    let id = graph.add_node(state);
+    graph.add_edge(origin, id, 1.0);
+    graph.add_edge(id, destination, weight);
+    Some(id)
+}
+
+/// Rates of the structural mutation operators. Each structural operator is applied
+/// at most once per call of mutate with its rate as probability. Weights are
+/// perturbed per edge.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphMutation {
+    pub add_node_rate: f32,
+    pub remove_node_rate: f32,
+    pub add_edge_rate: f32,
+    pub remove_edge_rate: f32,
+    pub split_edge_rate: f32,
+    // Probability that the weight of an edge is perturbed
+    pub perturb_rate: f32,
+    // Standard deviation of weight perturbations and new edge weights
+    pub sigma: f32,
+    // State of new nodes
+    pub node_state: i32,
+}
+
+impl Default for GraphMutation {
+    fn default() -> Self {
+        Self {
+            add_node_rate: 0.03,
+            remove_node_rate: 0.01,
+            add_edge_rate: 0.05,
+            remove_edge_rate: 0.02,
+            split_edge_rate: 0.03,
+            perturb_rate: 0.8,
+            sigma: 0.1,
+            node_state: 0,
+        }
+    }
+}
+
+impl GraphMutation {
+    /// Applies the operators to the graph in a fixed order using the RNG, e.g. the
+    /// RNG of the state. Returns the number of structural changes.
+    pub fn mutate<R: Rng>(&self, graph: &mut Graph, rng: &mut R) -> usize {
+        let mut changes = 0;
+        if rng.gen::<f32>() < self.add_node_rate {
+            add_random_node(graph, self.node_state);
+            changes += 1;
+        }
+        if rng.gen::<f32>() < self.remove_node_rate && remove_random_node(graph, rng).is_some() {
+            changes += 1;
+        }
+        if rng.gen::<f32>() < self.add_edge_rate
+            && add_random_edge(graph, rng, self.sigma).is_some()
+        {
+            changes += 1;
+        }
+        if rng.gen::<f32>() < self.remove_edge_rate && remove_random_edge(graph, rng).is_some() {
+            changes += 1;
+        }
+        if rng.gen::<f32>() < self.split_edge_rate
+            && split_random_edge(graph, rng, self.node_state).is_some()
+        {
+            changes += 1;
+        }
+        perturb_weights(graph, rng, self.perturb_rate, self.sigma);
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn chain() -> Graph {
+        let mut graph = Graph::new();
+        let a = graph.add_node(1);
+        let b = graph.add_node(2);
+        graph.add_edge(a, b, 0.5);
+        graph
+    }
+
+    #[test]
+    fn split_edge_inserts_node_between_origin_and_destination() {
+        let mut rng = ChaCha8Rng::seed_from_u64(500);
+        let mut graph = chain();
+        let id = split_random_edge(&mut graph, &mut rng, 7).unwrap();
+        assert_eq!(graph.get_state(&id), Some(7));
+        assert_eq!(graph.get_weight(&1, &2), None);
+        assert_eq!(graph.get_weight(&1, &id), Some(1.0));
+        assert_eq!(graph.get_weight(&id, &2), Some(0.5));
+        assert_eq!(split_random_edge(&mut Graph::new(), &mut rng, 0), None);
+    }
+
+    #[test]
+    fn structural_operators_change_graph() {
+        let mut rng = ChaCha8Rng::seed_from_u64(500);
+        let mut graph = chain();
+        // Only the edge from 1 to 2 exists, three more are possible (incl. self loops)
+        for _ in 0..3 {
+            assert!(add_random_edge(&mut graph, &mut rng, 0.1).is_some());
+        }
+        assert_eq!(graph.edge_size(), 4);
+        assert_eq!(add_random_edge(&mut graph, &mut rng, 0.1), None);
+        assert!(remove_random_edge(&mut graph, &mut rng).is_some());
+        assert_eq!(graph.edge_size(), 3);
+        assert_eq!(perturb_weights(&mut graph, &mut rng, 1.0, 0.1), 3);
+        assert_eq!(perturb_weights(&mut graph, &mut rng, 0.0, 0.1), 0);
+        let removed = remove_random_node(&mut graph, &mut rng).unwrap();
+        assert_eq!(graph.node_size(), 1);
+        assert!(graph.is_tombstone(&removed));
+        // Only a self loop of the remaining node can be left
+        assert!(graph.edge_size() <= 1);
+    }
+
+    #[test]
+    fn random_edges_fill_large_graphs() {
+        let mut rng = ChaCha8Rng::seed_from_u64(500);
+        let mut graph = Graph::new();
+        for _ in 0..20 {
+            graph.add_node(0);
+        }
+        for _ in 0..400 {
+            let (origin, destination) = add_random_edge(&mut graph, &mut rng, 0.1).unwrap();
+            assert!(graph.get_weight(&origin, &destination).is_some());
+        }
+        assert_eq!(graph.edge_size(), 400);
+        assert_eq!(add_random_edge(&mut graph, &mut rng, 0.1), None);
+    }
+
+    #[test]
+    fn mutation_is_reproducible_for_seeded_rng() {
+        let mutation = GraphMutation {
+            add_node_rate: 1.0,
+            add_edge_rate: 1.0,
+            split_edge_rate: 1.0,
+            ..Default::default()
+        };
+        let mutate = |seed: u64| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let mut graph = chain();
+            for _ in 0..5 {
+                mutation.mutate(&mut graph, &mut rng);
+            }
+            graph
+        };
+        assert_eq!(mutate(1), mutate(1));
+        assert_eq!(mutate(1).node_size(), 12);
+    }
+}
@@ -0,0 +1,129 @@
+use std::hash::Hasher;
+
+/// Version of the output of StableHasher. It is incremented whenever the hash values
+/// change, so that stored hashes (e.g. archive signatures or ancestry records) can be
+/// checked for compatibility.
+pub const STABLE_HASH_VERSION: u32 = 1;
+
+/// Hash function with fixed output (64-bit FNV-1a). Unlike the DefaultHasher of the
+/// standard library, whose algorithm may change between Rust versions, and unlike
+/// the default Hasher methods, which write integers in native byte order and usize
+/// with the width of the platform, the hashes are equal on all machines: integers are
+/// written in little-endian byte order and usize as u64.
+#[derive(Clone, Debug)]
+pub struct StableHasher {
+    state: u64,
+}
+
+impl StableHasher {
+    pub fn new() -> Self {
+        Self {
+            state: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.state
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state = (self.state ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// Hasher of structural hashes (Item::hash_structural and archive::stack_signature). With the feature "stable-hash" this is StableHasher and the
+/// hashes are reproducible across platforms and Rust versions, otherwise the
+/// DefaultHasher of the standard library is used.
+#[cfg(feature = "stable-hash")]
+pub type StructuralHasher = StableHasher;
+#[cfg(not(feature = "stable-hash"))]
+pub type StructuralHasher = std::collections::hash_map::DefaultHasher;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::hash::Hash;
+
+    fn stable_hash<T: Hash>(value: T) -> u64 {
+        let mut hasher = StableHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn stable_hashes_do_not_change() {
+        assert_eq!(STABLE_HASH_VERSION, 1);
+        assert_eq!(StableHasher::new().finish(), 0xcbf2_9ce4_8422_2325);
+        // FNV-1a test vector
+        let mut hasher = StableHasher::new();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(stable_hash(1usize), stable_hash(1u64));
+        assert_eq!(stable_hash(-1i32), stable_hash(u32::MAX));
+        assert_eq!(stable_hash("INTEGER.+"), 14708589734615224227);
+        // Length as u64 followed by the elements
+        assert_eq!(stable_hash(vec![1, 2, 3]), 2880993650339734278);
+    }
+
+    #[cfg(feature = "stable-hash")]
+    #[test]
+    fn structural_hashes_are_stable() {
+        use crate::push::item::Item;
+        let program = Item::list(vec![
+            Item::float(0.5),
+            Item::instruction("INTEGER.+".to_string()),
+            Item::int(2),
+        ]);
+        assert_eq!(program.hash_structural(), 6758797459334988016);
+    }
+}
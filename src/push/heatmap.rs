@@ -0,0 +1,168 @@
+use crate::push::instructions::InstructionSet;
+use crate::push::interpreter::PushInterpreter;
+use crate::push::item::Item;
+use crate::push::parser::PushParser;
+use crate::push::stack::PushStack;
+use crate::push::state::PushState;
+
+/// Execution counts of the points of the program on the EXEC stack. The interpreter
+/// records them if the heat map of the state is set. Points are identified by their
+/// index in depth first order (see Item::traverse), so equal points at different
+/// positions have separate counts. A list counts as executed when it is expanded on
+/// the EXEC stack.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeatMap {
+    // Executions by point index
+    counts: Vec<usize>,
+    // Point index and structural hash of the EXEC items (bottom first). Items that
+    // were not part of the program are None.
+    positions: Vec<Option<(usize, u64)>>,
+    started: bool,
+}
+
+impl HeatMap {
+    pub fn new() -> Self {
+        Self {
+            counts: vec![],
+            positions: vec![],
+            started: false,
+        }
+    }
+
+    /// Records one execution of the top item of the EXEC stack. It has to be called
+    /// before the item is popped. The first call assigns the point indices to the
+    /// items on the stack, starting with the bottom item.
+    pub fn record(&mut self, exec_stack: &PushStack<Item>) {
+        if !self.started {
+            self.started = true;
+            let mut offset = 0;
+            for item in exec_stack.as_slice() {
+                self.positions.push(Some((offset, item.hash_structural())));
+                offset += Item::size(item);
+            }
+        }
+        let item = match exec_stack.get(0) {
+            Some(item) => item,
+            None => return,
+        };
+        // Instructions can remove, move and copy EXEC items. The topmost entry with
+        // the same hash is taken as origin of the item.
+        let hash = item.hash_structural();
+        let origin = self
+            .positions
+            .iter()
+            .rposition(|entry| matches!(entry, Some((_, h)) if *h == hash));
+        let point = origin.and_then(|i| self.positions[i].map(|(point, _)| point));
+        if let Some(i) = origin {
+            self.positions.truncate(i);
+        }
+        self.positions.resize(exec_stack.size() - 1, None);
+        if let Some(point) = point {
+            if self.counts.len() <= point {
+                self.counts.resize(point + 1, 0);
+            }
+            self.counts[point] += 1;
+            if let Item::List { items } = item {
+                // The first element of the list ends up on top of the stack
+                let mut children = vec![];
+                let mut offset = point + 1;
+                for i in 0..items.size() {
+                    let child = items.get(i).unwrap();
+                    children.push(Some((offset, child.hash_structural())));
+                    offset += Item::size(child);
+                }
+                self.positions.extend(children.into_iter().rev());
+            }
+        }
+    }
+
+    /// Returns the number of executions of the point with the given index.
+    pub fn heat(&self, point: usize) -> usize {
+        self.counts.get(point).copied().unwrap_or(0)
+    }
+
+    /// Adds the counts of another heat map, e.g. of the run on another test case.
+    pub fn merge(&mut self, other: &HeatMap) {
+        if self.counts.len() < other.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other_count;
+        }
+    }
+
+    /// Returns the number of executions of each point of the program in depth first
+    /// order (see Item::traverse).
+    pub fn point_heat(&self, program: &Item) -> Vec<usize> {
+        (0..Item::size(program)).map(|point| self.heat(point)).collect()
+    }
+
+    /// Returns the portion of points of the program that were never executed
+    /// (inviable code).
+    pub fn inviable_ratio(&self, program: &Item) -> f32 {
+        let heat = self.point_heat(program);
+        heat.iter().filter(|h| **h == 0).count() as f32 / heat.len() as f32
+    }
+}
+
+/// Runs the program on each of the initial states and returns the percentage of its
+/// points that were not executed in any of the runs, i.e. the inviable code across
+/// the test suite.
+pub fn inviable_code_percentage(
+    code: &str,
+    instruction_set: &mut InstructionSet,
+    initial_states: &[PushState],
+) -> f32 {
+    let mut program_state = PushState::new();
+    PushParser::parse_program(&mut program_state, instruction_set, code);
+    let mut suite_heat_map = HeatMap::new();
+    for initial_state in initial_states.iter() {
+        let mut push_state = initial_state.clone();
+        push_state.heat_map = Some(HeatMap::new());
+        PushParser::parse_program(&mut push_state, instruction_set, code);
+        PushInterpreter::run(&mut push_state, instruction_set);
+        if let Some(heat_map) = push_state.heat_map {
+            suite_heat_map.merge(&heat_map);
+        }
+    }
+    let points: usize = program_state.exec_stack.as_slice().iter().map(Item::size).sum();
+    let heat: Vec<usize> = (0..points).map(|point| suite_heat_map.heat(point)).collect();
+    if heat.is_empty() {
+        return 0.0;
+    }
+    100.0 * heat.iter().filter(|h| **h == 0).count() as f32 / heat.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn branches_that_are_never_taken_are_inviable() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let code = "( INTEGER.DUP 0 INTEGER.< EXEC.IF ( INTEGER.ABS ) ( 2 INTEGER.* ) )";
+        let mut positive = PushState::new();
+        positive.int_stack.push(3);
+        let mut negative = PushState::new();
+        negative.int_stack.push(-3);
+        // 10 points, the list and the instruction of the first branch are not executed
+        let percentage = inviable_code_percentage(code, &mut instruction_set, &[positive.clone()]);
+        assert!((percentage - 20.0).abs() < 1e-4);
+        let percentage = inviable_code_percentage(code, &mut instruction_set, &[positive, negative]);
+        assert_eq!(percentage, 0.0);
+    }
+
+    #[test]
+    fn equal_points_are_counted_by_position() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut push_state = PushState::new();
+        push_state.heat_map = Some(HeatMap::new());
+        PushParser::parse_program(&mut push_state, &instruction_set, "( TRUE EXEC.IF ( 1 ) ( 1 ) )");
+        let program = push_state.exec_stack.get(0).unwrap().clone();
+        PushInterpreter::run(&mut push_state, &mut instruction_set);
+        let heat_map = push_state.heat_map.unwrap();
+        assert_eq!(heat_map.point_heat(&program), vec![1, 1, 1, 1, 1, 0, 0]);
+    }
+}
@@ -3,9 +3,10 @@ use crate::push::instructions::InstructionCache;
 use crate::push::state::PushState;
 use crate::push::stack::PushPrint;
 use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Index {
     pub current: usize,
     pub destination: usize,
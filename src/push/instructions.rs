@@ -1,8 +1,21 @@
+use crate::push::item::Item;
 use crate::push::state::PushState;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "fxhash")]
+type DispatchHasher = std::hash::BuildHasherDefault<rustc_hash::FxHasher>;
+#[cfg(not(feature = "fxhash"))]
+type DispatchHasher = std::collections::hash_map::RandomState;
+
+#[cfg(feature = "bignum")]
+use crate::push::bigint::*;
+use crate::push::bitmap::*;
 use crate::push::boolean::*;
+use crate::push::char::*;
 use crate::push::code::*;
+use crate::push::constant::*;
 use crate::push::execution::*;
 use crate::push::float::*;
 use crate::push::graph::*;
@@ -10,84 +23,458 @@ use crate::push::index::*;
 use crate::push::integer::*;
 use crate::push::io::*;
 use crate::push::list::*;
+use crate::push::matrix::*;
 use crate::push::name::*;
+use crate::push::registers::*;
+use crate::push::string::*;
 use crate::push::vector::*;
+use crate::push::vector_list::*;
 
+/// Instructions by name. Each name is assigned a dense ID when it is added. Parsed
+/// programs carry the IDs of their instructions (see intern), so that the interpreter
+/// dispatches them through a vector. Other instructions are looked up by name, which
+/// is hashed with SipHash by default or with the faster Fx hash function if the
+/// feature "fxhash" is enabled (see benches/dispatch.rs).
 pub struct InstructionSet {
-    map: HashMap<String, Instruction>,
+    // Instructions by ID. A removed instruction leaves a gap so that the IDs of the
+    // other instructions stay valid. It gets its ID back when it is added again.
+    instructions: Vec<Option<Instruction>>,
+    // Names by ID
+    names: Vec<String>,
+    // IDs by name
+    ids: HashMap<String, usize, DispatchHasher>,
+    // Selection weights for random code generation (default 1.0)
+    weights: HashMap<String, f32>,
+    // Snapshot of the names and weights that is refreshed on each change
+    cache: InstructionCache,
+    // Call counts and execution times by instruction name if profiling is enabled
+    profile: Option<HashMap<String, (usize, Duration)>>,
+}
+
+/// Call count and cumulative execution time of an instruction (see
+/// InstructionSet::enable_profiling).
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileEntry {
+    pub name: String,
+    pub calls: usize,
+    pub total_time: Duration,
+}
+
+impl ProfileEntry {
+    /// Returns the average execution time per call.
+    pub fn mean_time(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::ZERO
+        } else {
+            self.total_time / self.calls as u32
+        }
+    }
 }
 
 impl InstructionSet {
     pub fn new() -> Self {
         Self {
-            map: HashMap::new(),
+            instructions: vec![],
+            names: vec![],
+            ids: HashMap::default(),
+            weights: HashMap::new(),
+            cache: InstructionCache::new(vec![]),
+            profile: None,
         }
     }
 
     /// Load the default instrcution set for the stack types
     /// bool, int, float, code, exec, name and vector types
     pub fn load(&mut self) {
-        self.map
-            .insert(String::from("NOOP"), Instruction::new(noop));
-        load_boolean_instructions(&mut self.map);
-        load_code_instructions(&mut self.map);
-        load_exec_instructions(&mut self.map);
-        load_float_instructions(&mut self.map);
-        load_index_instructions(&mut self.map);
-        load_int_instructions(&mut self.map);
-        load_list_instructions(&mut self.map);
-        load_name_instructions(&mut self.map);
-        load_vector_instructions(&mut self.map);
-        load_io_instructions(&mut self.map);
-        load_graph_instructions(&mut self.map);
-    }
-
-    /// Create a snapshot of the current instruction names
+        let mut map = HashMap::new();
+        map.insert(String::from("NOOP"), Instruction::new(noop));
+        map.insert(
+            String::from("INSTRUCTION.WEIGHT"),
+            Instruction::new(instruction_weight),
+        );
+        load_boolean_instructions(&mut map);
+        load_code_instructions(&mut map);
+        load_constant_instructions(&mut map);
+        load_exec_instructions(&mut map);
+        load_float_instructions(&mut map);
+        load_index_instructions(&mut map);
+        load_int_instructions(&mut map);
+        load_list_instructions(&mut map);
+        load_name_instructions(&mut map);
+        load_vector_instructions(&mut map);
+        load_io_instructions(&mut map);
+        load_output_instructions(&mut map);
+        load_graph_instructions(&mut map);
+        load_bitmap_instructions(&mut map);
+        load_char_instructions(&mut map);
+        load_matrix_instructions(&mut map);
+        load_vector_list_instructions(&mut map);
+        load_string_instructions(&mut map);
+        #[cfg(feature = "bignum")]
+        load_bigint_instructions(&mut map);
+        // IDs are assigned in the order of the names
+        let mut instructions: Vec<(String, Instruction)> = map.into_iter().collect();
+        instructions.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, instruction) in instructions {
+            self.insert(name, instruction);
+        }
+        self.refresh_cache();
+    }
+
+    /// Returns a snapshot of the current instruction names and weights. The
+    /// snapshot shares its data with the instruction set so that it can be
+    /// passed to many interpreters (or threads) without copying.
     pub fn cache(&self) -> InstructionCache {
-        InstructionCache::new(self.map.keys().cloned().collect())
+        self.cache.clone()
+    }
+
+    fn refresh_cache(&mut self) {
+        let blocks = self
+            .names
+            .iter()
+            .zip(self.instructions.iter())
+            .filter_map(|(name, instruction)| match instruction {
+                Some(instruction) if instruction.blocks > 0 => {
+                    Some((name.clone(), instruction.blocks))
+                }
+                _ => None,
+            })
+            .collect();
+        self.cache = InstructionCache {
+            list: Arc::new(self.names_with_prefix("")),
+            weights: Arc::new(self.weights.clone()),
+            blocks: Arc::new(blocks),
+        };
+    }
+
+    /// Sets the weight with which the instruction is selected by random code
+    /// generation relative to the other instructions. Negative weights are ignored,
+    /// larger weights than MAX_INSTRUCTION_WEIGHT are reduced to it.
+    pub fn set_weight(&mut self, name: &str, weight: f32) {
+        if weight >= 0.0 {
+            self.weights.insert(name.to_string(), f32::min(weight, MAX_INSTRUCTION_WEIGHT));
+            self.refresh_cache();
+        }
+    }
+
+    /// Stores the instruction under the ID of the name and returns the replaced
+    /// instruction.
+    fn insert(&mut self, name: String, instruction: Instruction) -> Option<Instruction> {
+        match self.ids.get(&name) {
+            Some(id) => self.instructions[*id].replace(instruction),
+            None => {
+                self.ids.insert(name.clone(), self.names.len());
+                self.names.push(name);
+                self.instructions.push(Some(instruction));
+                None
+            }
+        }
     }
 
     /// Add a new instruction
     pub fn add(&mut self, name: String, instruction: Instruction) -> Option<Instruction> {
-        self.map.insert(name, instruction)
+        let replaced = self.insert(name, instruction);
+        self.refresh_cache();
+        replaced
+    }
+
+    /// Remove an instruction
+    pub fn remove(&mut self, name: &str) -> Option<Instruction> {
+        let removed = match self.ids.get(name) {
+            Some(id) => self.instructions[*id].take(),
+            None => None,
+        };
+        if removed.is_some() {
+            self.refresh_cache();
+        }
+        removed
+    }
+
+    /// Returns the names of all instructions that start
+    /// with the given prefix
+    pub fn names_with_prefix(&self, prefix: &str) -> Vec<String> {
+        self.names
+            .iter()
+            .zip(self.instructions.iter())
+            .filter(|(name, instruction)| instruction.is_some() && name.starts_with(prefix))
+            .map(|(name, _)| name.clone())
+            .collect()
     }
 
     /// Returns true if there exists an instruction
     /// under the given name.
     pub fn is_instruction(&self, name: &str) -> bool {
-        match self.map.get(name) {
-            Some(_i) => true,
-            None => false,
+        self.instruction_id(name).is_some()
+    }
+
+    /// Returns the ID of the instruction with the given name.
+    pub fn instruction_id(&self, name: &str) -> Option<usize> {
+        self.ids
+            .get(name)
+            .copied()
+            .filter(|id| self.instructions[*id].is_some())
+    }
+
+    /// Returns the name of the instruction with the given ID.
+    pub fn name(&self, id: usize) -> Option<&str> {
+        self.names.get(id).map(|n| n.as_str())
+    }
+
+    /// Sets the IDs of the instructions of the item and its sublists.
+    pub fn intern(&self, item: &mut Item) {
+        match item {
+            Item::InstructionMeta { name, id } => *id = self.instruction_id(name),
+            Item::List { items } => {
+                for child in items.as_mut_slice().iter_mut() {
+                    self.intern(child);
+                }
+            }
+            _ => (),
         }
     }
 
     /// Get a mutable reference of an instruction by name
     pub fn get_instruction(&mut self, name: &str) -> Option<&mut Instruction> {
-        self.map.get_mut(name)
+        let id = *self.ids.get(name)?;
+        self.instructions[id].as_mut()
+    }
+
+    /// Get a mutable reference of an instruction by ID
+    pub fn get_instruction_by_id(&mut self, id: usize) -> Option<&mut Instruction> {
+        self.instructions.get_mut(id).and_then(|i| i.as_mut())
+    }
+
+    /// Executes the instruction with the given name and records its execution time
+    /// if profiling is enabled. The ID of an interned item is only used if it belongs
+    /// to the name in this instruction set, since the item may have been interned by
+    /// another instruction set. Returns false if there is no such instruction.
+    pub fn execute(
+        &mut self,
+        name: &str,
+        id: Option<usize>,
+        push_state: &mut PushState,
+        icache: &InstructionCache,
+    ) -> bool {
+        let id = match id {
+            Some(id) if self.names.get(id).map_or(false, |n| n == name) => id,
+            _ => match self.ids.get(name) {
+                Some(id) => *id,
+                None => return false,
+            },
+        };
+        let instruction = match self.instructions[id].as_mut() {
+            Some(instruction) => instruction,
+            None => return false,
+        };
+        match self.profile.as_mut() {
+            Some(profile) => {
+                let start = Instant::now();
+                (instruction.execute)(push_state, icache);
+                let elapsed = start.elapsed();
+                let entry = profile.entry(name.to_string()).or_insert((0, Duration::ZERO));
+                entry.0 += 1;
+                entry.1 += elapsed;
+            }
+            None => (instruction.execute)(push_state, icache),
+        }
+        true
+    }
+
+    /// Starts recording the call count and the execution time of each instruction
+    /// that is executed by the interpreter. Records of an earlier profiling are
+    /// discarded.
+    pub fn enable_profiling(&mut self) {
+        self.profile = Some(HashMap::new());
+    }
+
+    /// Stops recording and discards the records.
+    pub fn disable_profiling(&mut self) {
+        self.profile = None;
+    }
+
+    /// Returns the recorded instructions sorted by cumulative execution time, the
+    /// most expensive first. Empty if profiling is not enabled.
+    pub fn profile_report(&self) -> Vec<ProfileEntry> {
+        let mut report: Vec<ProfileEntry> = self
+            .profile
+            .iter()
+            .flatten()
+            .map(|(name, (calls, total_time))| ProfileEntry {
+                name: name.clone(),
+                calls: *calls,
+                total_time: *total_time,
+            })
+            .collect();
+        report.sort_by(|a, b| {
+            b.total_time
+                .cmp(&a.total_time)
+                .then(b.calls.cmp(&a.calls))
+                .then(a.name.cmp(&b.name))
+        });
+        report
     }
 }
 
+/// Largest weight of an instruction for random code generation. It keeps the sum of the
+/// weights of all instructions finite.
+pub const MAX_INSTRUCTION_WEIGHT: f32 = 1.0e6;
+
+/// Instruction names and weights used by the interpreter and random code generation.
+/// Clones are cheap since the data is shared.
+#[derive(Clone, Debug)]
 pub struct InstructionCache {
-    pub list: Vec<String>,
+    pub list: Arc<Vec<String>>,
+    // Selection weights for random code generation (default 1.0)
+    pub weights: Arc<HashMap<String, f32>>,
+    // Number of code blocks of the instructions that take blocks from the EXEC stack
+    pub blocks: Arc<HashMap<String, usize>>,
 }
 
 impl InstructionCache {
     pub fn new(arg_list: Vec<String>) -> Self {
-        Self { list: arg_list }
+        Self {
+            list: Arc::new(arg_list),
+            weights: Arc::new(HashMap::new()),
+            blocks: Arc::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the number of code blocks that the instruction takes from the EXEC stack
+    /// (see Instruction::with_blocks).
+    pub fn blocks(&self, name: &str) -> usize {
+        self.blocks.get(name).cloned().unwrap_or(0)
+    }
+
+    /// Returns the weight with which the instruction is selected by random code
+    /// generation. Weights set by INSTRUCTION.WEIGHT take precedence over the
+    /// weights of the instruction set.
+    pub fn weight(&self, push_state: &PushState, name: &str) -> f32 {
+        push_state
+            .instruction_weights
+            .get(name)
+            .or_else(|| self.weights.get(name))
+            .cloned()
+            .unwrap_or(1.0)
     }
 }
 
 pub struct Instruction {
     pub execute: Box<dyn FnMut(&mut PushState, &InstructionCache) + Send>,
+    // Number of code blocks that the instruction takes from the EXEC stack, e.g. 2 for
+    // EXEC.IF. Plush genomes open this many blocks after the instruction.
+    pub blocks: usize,
 }
 
 impl Instruction {
     pub fn new(execute: impl FnMut(&mut PushState, &InstructionCache) + 'static + std::marker::Send) -> Self {
         Self {
             execute: Box::new(execute),
+            blocks: 0,
         }
     }
+
+    /// Sets the number of code blocks that the instruction takes from the EXEC stack.
+    pub fn with_blocks(mut self, blocks: usize) -> Self {
+        self.blocks = blocks;
+        self
+    }
 }
 
 /// NOOP: No operation.
 fn noop(_push_state: &mut PushState, _instruction_cache: &InstructionCache) {}
+
+/// INSTRUCTION.WEIGHT: Sets the weight with which the instruction named by the top NAME is
+/// selected by CODE.RAND and other random code generators to the top FLOAT. Weights larger
+/// than MAX_INSTRUCTION_WEIGHT are reduced to it. Acts as NOOP if the name is not an
+/// instruction or the weight is negative or NaN.
+fn instruction_weight(push_state: &mut PushState, instruction_cache: &InstructionCache) {
+    if let Some(name) = push_state.name_stack.get(0) {
+        if let Some(weight) = push_state.float_stack.get(0) {
+            if *weight >= 0.0 && instruction_cache.list.contains(name) {
+                let name = push_state.name_stack.pop().unwrap();
+                let weight = push_state.float_stack.pop().unwrap();
+                push_state
+                    .instruction_weights
+                    .insert(name, f32::min(weight, MAX_INSTRUCTION_WEIGHT));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::interpreter::PushInterpreter;
+    use crate::push::parser::PushParser;
+
+    #[test]
+    fn parsed_instructions_are_interned() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut push_state = PushState::new();
+        PushParser::parse_program(&mut push_state, &instruction_set, "( 2 INTEGER.DUP ( INTEGER.* ) )");
+        let mut program = push_state.exec_stack.pop().unwrap();
+        let dup_id = instruction_set.instruction_id("INTEGER.DUP");
+        assert!(dup_id.is_some());
+        assert!(matches!(
+            program.clone().flatten(),
+            Item::List { items } if matches!(items.get(1), Some(Item::InstructionMeta { id, .. }) if *id == dup_id)
+        ));
+        // Removing an instruction does not change the IDs of the others
+        instruction_set.remove("INTEGER.+");
+        assert!(!instruction_set.is_instruction("INTEGER.+"));
+        assert_eq!(instruction_set.instruction_id("INTEGER.DUP"), dup_id);
+        // IDs that belong to other names are ignored
+        let mut other_set = InstructionSet::new();
+        other_set.add("INTEGER.*".to_string(), Instruction::new(noop));
+        other_set.add("INTEGER.DUP".to_string(), Instruction::new(noop));
+        other_set.intern(&mut program);
+        push_state.exec_stack.push(program);
+        PushInterpreter::run(&mut push_state, &mut instruction_set);
+        assert_eq!(push_state.int_stack.to_string(), "4");
+    }
+
+    #[test]
+    fn profile_report_counts_executed_instructions() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushInterpreter::run_program("( 1 INTEGER.DUP INTEGER.+ )", &mut push_state, &mut instruction_set);
+        assert!(instruction_set.profile_report().is_empty());
+        instruction_set.enable_profiling();
+        let mut push_state = PushState::new();
+        PushInterpreter::run_program(
+            "( 1 INTEGER.DUP INTEGER.DUP INTEGER.+ TAG.INTEGER.3 )",
+            &mut push_state,
+            &mut instruction_set,
+        );
+        let mut calls: Vec<(String, usize)> = instruction_set
+            .profile_report()
+            .into_iter()
+            .map(|entry| (entry.name, entry.calls))
+            .collect();
+        calls.sort();
+        assert_eq!(
+            calls,
+            vec![("INTEGER.+".to_string(), 1), ("INTEGER.DUP".to_string(), 2)]
+        );
+        instruction_set.disable_profiling();
+        assert!(instruction_set.profile_report().is_empty());
+    }
+
+    #[test]
+    fn instruction_weights_are_bounded() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushInterpreter::run_program(
+            "( 'INTEGER.+ 3e38 INSTRUCTION.WEIGHT 'FLOAT.+ 3e38 INSTRUCTION.WEIGHT 20 CODE.RAND )",
+            &mut push_state,
+            &mut instruction_set,
+        );
+        assert_eq!(push_state.instruction_weights.get("INTEGER.+"), Some(&MAX_INSTRUCTION_WEIGHT));
+        assert_eq!(push_state.instruction_weights.get("FLOAT.+"), Some(&MAX_INSTRUCTION_WEIGHT));
+        // The program itself and the random code
+        assert_eq!(push_state.code_stack.size(), 2);
+    }
+}
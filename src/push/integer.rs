@@ -4,7 +4,11 @@ use crate::push::item::Item;
 use crate::push::random::CodeGenerator;
 use crate::push::state::PushState;
 use crate::push::state::*;
+use crate::push::vector::IntVector;
 use std::collections::HashMap;
+use std::convert::TryFrom;
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
 
 /// Integer numbers (that is, numbers without decimal points).
 pub fn load_int_instructions(map: &mut HashMap<String, Instruction>) {
@@ -41,6 +45,7 @@ pub fn load_int_instructions(map: &mut HashMap<String, Instruction>) {
     map.insert(String::from("INTEGER.ID"), Instruction::new(integer_id));
     map.insert(String::from("INTEGER.MAX"), Instruction::new(integer_max));
     map.insert(String::from("INTEGER.MIN"), Instruction::new(integer_min));
+    map.insert(String::from("INTEGER.MIN*K"), Instruction::new(integer_min_k));
     map.insert(String::from("INTEGER.POP"), Instruction::new(integer_pop));
     map.insert(String::from("INTEGER.RAND"), Instruction::new(integer_rand));
     map.insert(String::from("INTEGER.ROT"), Instruction::new(integer_rot));
@@ -65,28 +70,48 @@ pub fn integer_id(push_state: &mut PushState, _instruction_set: &InstructionCach
     push_state.int_stack.push(INT_STACK_ID);
 }
 
-/// INTEGER.%: Pushes the second stack item modulo the top stack item. If the top item is zero this
-/// acts as a NOOP. The modulus is computed as the remainder of the quotient, where the quotient
-/// has first been truncated toward negative infinity.
+/// INTEGER.%: Pushes the second stack item modulo the top stack item. If the top item is zero the
+/// result is determined by the configured divide-by-zero policy. The modulus is computed as the
+/// remainder of the quotient, where the quotient has first been truncated toward negative infinity.
 pub fn integer_modulus(push_state: &mut PushState, _instruction_set: &InstructionCache) {
     if let Some(ivals) = push_state.int_stack.pop_vec(2) {
         if ivals[1] != 0i32 {
-            push_state.int_stack.push(ivals[0] % ivals[1]);
+            // Only i32::MIN % -1 overflows, its remainder is 0
+            push_state.int_stack.push(ivals[0].checked_rem(ivals[1]).unwrap_or(0));
+        } else {
+            push_state.div_zero_count += 1;
+            match push_state.configuration.div_zero_policy.resolve(ivals[0]) {
+                Some(result) => push_state.int_stack.push(result),
+                None => push_state.int_stack.push_vec(ivals),
+            }
         }
     }
 }
 
+/// Pushes the exact result of an arithmetic instruction. Results that do not fit
+/// into an INTEGER are pushed to the BIGINT stack if the bignum feature is enabled
+/// and wrap around otherwise.
+fn push_exact(push_state: &mut PushState, result: i64) {
+    match i32::try_from(result) {
+        Ok(ival) => push_state.int_stack.push(ival),
+        #[cfg(feature = "bignum")]
+        Err(_) => push_state.bigint_stack.push(BigInt::from(result)),
+        #[cfg(not(feature = "bignum"))]
+        Err(_) => push_state.int_stack.push(result as i32),
+    }
+}
+
 /// INTEGER.*: Pushes the product of the top two items.
 fn integer_mult(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(ivals) = push_state.int_stack.pop_vec(2) {
-        push_state.int_stack.push(ivals[0] * ivals[1]);
+        push_exact(push_state, ivals[0] as i64 * ivals[1] as i64);
     }
 }
 
 /// INTEGER.+: Pushes the sum of the top two items.
 fn integer_add(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(ivals) = push_state.int_stack.pop_vec(2) {
-        push_state.int_stack.push(ivals[0] + ivals[1]);
+        push_exact(push_state, ivals[0] as i64 + ivals[1] as i64);
     }
 }
 
@@ -94,16 +119,24 @@ fn integer_add(push_state: &mut PushState, _instruction_cache: &InstructionCache
 /// item.
 fn integer_subtract(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(ivals) = push_state.int_stack.pop_vec(2) {
-        push_state.int_stack.push(ivals[0] - ivals[1]);
+        push_exact(push_state, ivals[0] as i64 - ivals[1] as i64);
     }
 }
 
 /// INTEGER./: Pushes the quotient of the top two items; that is, the second item divided by the
-/// top item. If the top item is zero this acts as a NOOP.
+/// top item. If the top item is zero the result is determined by the configured divide-by-zero
+/// policy.
 fn integer_divide(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(ivals) = push_state.int_stack.pop_vec(2) {
         if ivals[1] != 0i32 {
-            push_state.int_stack.push(ivals[0] / ivals[1]);
+            // The quotient of i32::MIN / -1 does not fit into an INTEGER
+            push_exact(push_state, ivals[0] as i64 / ivals[1] as i64);
+        } else {
+            push_state.div_zero_count += 1;
+            match push_state.configuration.div_zero_policy.resolve(ivals[0]) {
+                Some(result) => push_state.int_stack.push(result),
+                None => push_state.int_stack.push_vec(ivals),
+            }
         }
     }
 }
@@ -144,7 +177,7 @@ fn integer_abs(push_state: &mut PushState, _instruction_cache: &InstructionCache
 pub fn integer_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(ival) = push_state.int_stack.pop() {
-            push_state.name_bindings.insert(name, Item::int(ival));
+            push_state.bind(name, Item::int(ival));
         }
     }
 }
@@ -209,6 +242,22 @@ pub fn integer_min(push_state: &mut PushState, _instruction_cache: &InstructionC
     }
 }
 
+/// INTEGER.MIN*K: Pops the top N items of the INTEGER stack and pushes the k smallest of
+/// them as an INTVECTOR in ascending order. The top INTEGER is k and the second INTEGER is N.
+/// Both are bound to the valid range.
+pub fn integer_min_k(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(params) = push_state.int_stack.pop_vec(2) {
+        let size = push_state.int_stack.size() as i32;
+        let n = i32::max(i32::min(size, params[0]), 0) as usize;
+        let k = i32::max(i32::min(n as i32, params[1]), 0) as usize;
+        if let Some(mut ivals) = push_state.int_stack.pop_vec(n) {
+            ivals.sort_unstable();
+            ivals.truncate(k);
+            push_state.int_vector_stack.push(IntVector::new(ivals));
+        }
+    }
+}
+
 /// INTEGER.POP: Pops the INTEGER stack.
 pub fn integer_pop(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     push_state.int_stack.pop();
@@ -279,11 +328,25 @@ pub fn integer_yank_dup(push_state: &mut PushState, _instruction_cache: &Instruc
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::push::configuration::DivZeroPolicy;
 
     pub fn icache() -> InstructionCache {
         InstructionCache::new(vec![])
     }
 
+    #[test]
+    fn integer_min_k_pushes_smallest_items() {
+        let mut test_state = PushState::new();
+        for i in [42, 8, -3, 15, 0] {
+            test_state.int_stack.push(i);
+        }
+        test_state.int_stack.push(4);
+        test_state.int_stack.push(3);
+        integer_min_k(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[-3,0,8]");
+        assert_eq!(test_state.int_stack.to_string(), "42");
+    }
+
     #[test]
     fn integer_modulus_pushes_result() {
         let mut test_state = PushState::new();
@@ -291,6 +354,10 @@ mod tests {
         test_state.int_stack.push(10);
         integer_modulus(&mut test_state, &icache());
         assert_eq!(test_state.int_stack.pop().unwrap(), -3);
+        test_state.int_stack.push(i32::MIN);
+        test_state.int_stack.push(-1);
+        integer_modulus(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.pop().unwrap(), 0);
     }
 
     #[test]
@@ -327,6 +394,38 @@ mod tests {
         test_state.int_stack.push(2);
         integer_divide(&mut test_state, &icache());
         assert_eq!(test_state.int_stack.pop().unwrap(), 2);
+        // Without the BIGINT stack the overflowing quotient wraps around
+        #[cfg(not(feature = "bignum"))]
+        {
+            test_state.int_stack.push(i32::MIN);
+            test_state.int_stack.push(-1);
+            integer_divide(&mut test_state, &icache());
+            assert_eq!(test_state.int_stack.pop().unwrap(), i32::MIN);
+        }
+    }
+
+    #[test]
+    fn integer_divide_by_zero_follows_policy() {
+        for (policy, expected) in [
+            (DivZeroPolicy::Noop, "0 4"),
+            (DivZeroPolicy::PushDividend, "4"),
+            (DivZeroPolicy::PushZero, "0"),
+        ]
+        .iter()
+        {
+            let mut test_state = PushState::new();
+            test_state.configuration.div_zero_policy = *policy;
+            test_state.int_stack.push(4);
+            test_state.int_stack.push(0);
+            integer_divide(&mut test_state, &icache());
+            assert_eq!(test_state.int_stack.to_string(), *expected);
+            test_state.int_stack.flush();
+            test_state.int_stack.push(4);
+            test_state.int_stack.push(0);
+            integer_modulus(&mut test_state, &icache());
+            assert_eq!(test_state.int_stack.to_string(), *expected);
+            assert_eq!(test_state.div_zero_count, 2);
+        }
     }
 
     #[test]
@@ -1,6 +1,12 @@
+use crate::push::configuration::{LiteralBursts, PushConfiguration};
 use crate::push::instructions::{InstructionCache, InstructionSet};
 use crate::push::item::{Item, PushType};
-use crate::push::state::PushState;
+use crate::push::metrics::MetricsSink;
+use crate::push::parser::PushParser;
+use crate::push::state::{PushState, STACK_NAMES};
+use crate::push::statistics::{missing_stacks, ExecutionTally, NoopExplanation};
+use crate::push::tag::TagInstruction;
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
 #[derive(Debug, PartialEq)]
@@ -9,6 +15,116 @@ pub enum PushInterpreterState {
     StepLimitExceeded,
     TimeLimitExceeded,
     GrowthCapExceeded,
+    TerminatedByPredicate,
+    Paused,
+    // The program exceeds max_points_in_program of the PushInterpreterConfig and was
+    // not executed.
+    ProgramTooLarge,
+}
+
+/// Limits that PushInterpreter::run_with_config enforces. The termination reason
+/// tells which limit was exhausted.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PushInterpreterConfig {
+    // The maximum number of steps.
+    pub eval_push_limit: i32,
+    // The maximum number of points of the program on the EXEC stack at the start of
+    // the execution. Unlimited if None.
+    pub max_points_in_program: Option<usize>,
+    // The maximum execution time in milliseconds.
+    pub max_runtime_ms: u64,
+}
+
+impl PushInterpreterConfig {
+    /// Returns the limits that run applies: the step and time limits of the
+    /// configuration without a limit of the program size.
+    pub fn from_configuration(configuration: &PushConfiguration) -> Self {
+        Self {
+            eval_push_limit: configuration.eval_push_limit,
+            max_points_in_program: None,
+            max_runtime_ms: configuration.eval_time_limit,
+        }
+    }
+}
+
+/// Outcome of PushInterpreter::run_program.
+#[derive(Debug, PartialEq)]
+pub struct RunResult {
+    // The reason why the execution stopped
+    pub termination: PushInterpreterState,
+    // The number of executed steps
+    pub steps: usize,
+    // The final stacks as (short name, printed items) in the order of STACK_NAMES
+    pub stacks: Vec<(&'static str, String)>,
+}
+
+impl RunResult {
+    /// Returns the printed items of the stack with the given short name, e.g. "int".
+    pub fn stack(&self, name: &str) -> Option<&str> {
+        self.stacks
+            .iter()
+            .find(|(stack_name, _)| *stack_name == name)
+            .map(|(_, items)| items.as_str())
+    }
+}
+
+/// Condition that pauses the execution before the next step.
+pub enum Breakpoint {
+    // Pauses before the named instruction is executed.
+    Instruction(String),
+    // Pauses when the stack with the given ID holds at least the given number of items.
+    StackDepth { stack_id: i32, depth: usize },
+    // Pauses when the predicate returns true for the current state.
+    Predicate(Box<dyn Fn(&PushState) -> bool>),
+}
+
+impl Breakpoint {
+    /// Returns true if the breakpoint is hit by the state before the next step.
+    pub fn is_hit(&self, push_state: &PushState) -> bool {
+        match self {
+            Breakpoint::Instruction(instruction) => matches!(
+                push_state.exec_stack.get(0),
+                Some(Item::InstructionMeta { name, .. }) if name == instruction
+            ),
+            Breakpoint::StackDepth { stack_id, depth } => push_state
+                .stack_size(*stack_id)
+                .map_or(false, |size| size >= *depth),
+            Breakpoint::Predicate(predicate) => predicate(push_state),
+        }
+    }
+}
+
+/// Callbacks for tracing an execution, e.g. for logging, visualization or coverage
+/// analysis. See PushInterpreter::run_with_observer.
+pub trait InterpreterObserver {
+    /// Called before each step with the number of the step, the state before the step
+    /// and the item on top of the EXEC stack that the step executes. If literal bursts
+    /// are enabled, a step can transfer several literals and item is the first of them.
+    fn on_step(&mut self, _step: usize, _push_state: &PushState, _item: &Item) {}
+
+    /// Called once when the execution stops with the termination reason.
+    fn on_halt(&mut self, _reason: &PushInterpreterState) {}
+}
+
+/// Options of PushInterpreter::run_internal. Each run function sets the options it
+/// supports and leaves the others at their defaults.
+#[derive(Default)]
+struct RunOptions<'a> {
+    // Stops the execution after a step if it returns true
+    termination_predicate: Option<&'a dyn Fn(&PushState) -> bool>,
+    // Records the executed instructions
+    tally: Option<&'a mut ExecutionTally>,
+    // Pause the execution before a step
+    breakpoints: &'a [Breakpoint],
+    // Resumes a paused execution: the CODE stack is kept and the breakpoints are not
+    // checked before the first step
+    resume: bool,
+    // Limits that replace the ones of the state configuration
+    config: Option<&'a PushInterpreterConfig>,
+    // Records the evaluation and execution times
+    metrics: Option<&'a MetricsSink>,
+    // Is notified of each step and the termination
+    observer: Option<&'a mut dyn InterpreterObserver>,
 }
 
 pub struct PushInterpreter {}
@@ -22,6 +138,54 @@ impl PushInterpreter {
         }
     }
 
+    /// Pushes an item onto the execution stack so that it is executed
+    /// by the next step. Items with more points than max_points_in_program
+    /// are rejected. Returns true if the item was injected.
+    pub fn inject(push_state: &mut PushState, item: Item) -> bool {
+        if Item::size(&item) > push_state.configuration.max_points_in_program as usize {
+            return false;
+        }
+        push_state.exec_stack.push(item);
+        true
+    }
+
+    /// Pushes the literal to the stack of its type.
+    fn push_literal(push_state: &mut PushState, push_type: PushType) {
+        match push_type {
+            PushType::Bool { val } => push_state.bool_stack.push(val),
+            PushType::Int { val } => push_state.int_stack.push(val),
+            PushType::Index { val } => push_state.index_stack.push(val),
+            PushType::Float { val } => push_state.float_stack.push(val),
+            PushType::BoolVector { val } => push_state.bool_vector_stack.push(val),
+            PushType::FloatVector { val } => push_state.float_vector_stack.push(val),
+            PushType::IntVector { val } => push_state.int_vector_stack.push(val),
+            PushType::Graph { val } => push_state.graph_stack.push(val),
+            PushType::Char { val } => push_state.char_stack.push(val),
+            #[cfg(feature = "bignum")]
+            PushType::BigInt { val } => push_state.bigint_stack.push(val),
+        }
+    }
+
+    /// Transfers up to max consecutive literals from the top of the execution stack
+    /// to their stacks. Returns the number of transferred literals.
+    pub fn transfer_literals(push_state: &mut PushState, max: usize) -> usize {
+        let mut transferred = 0;
+        while transferred < max {
+            match push_state.exec_stack.get(0) {
+                Some(Item::Literal { .. }) => (),
+                _ => break,
+            }
+            if let Some(heat_map) = push_state.heat_map.as_mut() {
+                heat_map.record(&push_state.exec_stack);
+            }
+            if let Some(Item::Literal { push_type }) = push_state.exec_stack.pop() {
+                PushInterpreter::push_literal(push_state, push_type);
+            }
+            transferred += 1;
+        }
+        transferred
+    }
+
     /// Executes one instruction from the top of the execution stack.
     /// Returns true if the execution stack is empty.
     pub fn step(
@@ -29,19 +193,16 @@ impl PushInterpreter {
         instruction_set: &mut InstructionSet,
         icache: &InstructionCache,
     ) -> bool {
-        match push_state.exec_stack.pop() {
+        // Literal bursts can empty a binding scope without a step
+        push_state.leave_binding_scopes();
+        if let Some(heat_map) = push_state.heat_map.as_mut() {
+            heat_map.record(&push_state.exec_stack);
+        }
+        let item = push_state.exec_stack.pop();
+        let halted = match item {
             None => true,
             Some(Item::Literal { push_type }) => {
-                match push_type {
-                    PushType::Bool { val } => push_state.bool_stack.push(val),
-                    PushType::Int { val } => push_state.int_stack.push(val),
-                    PushType::Index { val } => push_state.index_stack.push(val),
-                    PushType::Float { val } => push_state.float_stack.push(val),
-                    PushType::BoolVector { val } => push_state.bool_vector_stack.push(val),
-                    PushType::FloatVector { val } => push_state.float_vector_stack.push(val),
-                    PushType::IntVector { val } => push_state.int_vector_stack.push(val),
-                    PushType::Graph { val } => push_state.graph_stack.push(val),
-                }
+                PushInterpreter::push_literal(push_state, push_type);
                 false
             }
             Some(Item::Identifier { name }) => {
@@ -50,18 +211,29 @@ impl PushInterpreter {
                     push_state.name_stack.push(name);
                     push_state.quote_name = false;
                 } else {
-                    if let Some(item) = push_state.name_bindings.get(&*name) {
+                    if let Some(item) = push_state.name_bindings.get(&*name).cloned() {
+                        push_state.touch_binding(&name);
+                        // Literals cannot modify the bindings
+                        if !matches!(item, Item::Literal { .. }) {
+                            push_state.enter_binding_scope();
+                        }
                         // Evaluate item for this name in next iteration
-                        push_state.exec_stack.push(item.clone());
+                        push_state.exec_stack.push(item);
                     } else {
                         push_state.name_stack.push(name);
                     }
                 }
                 false
             }
-            Some(Item::InstructionMeta { name }) => {
-                if let Some(instruction) = instruction_set.get_instruction(&name) {
-                    (instruction.execute)(push_state, &icache);
+            Some(Item::Name { name }) => {
+                push_state.name_stack.push(name);
+                false
+            }
+            Some(Item::InstructionMeta { name, id }) => {
+                if !instruction_set.execute(&name, id, push_state, icache) {
+                    if let Some(tag_instruction) = TagInstruction::parse(&name) {
+                        tag_instruction.execute(push_state);
+                    }
                 }
                 false
             }
@@ -71,7 +243,9 @@ impl PushInterpreter {
                 }
                 false
             }
-        }
+        };
+        push_state.leave_binding_scopes();
+        halted
     }
     /// Copies execution stack to code stac and recursively runs execution stack.
     /// Stops execution if Step Limit, Time Limit or Growth Cap are exceeded and
@@ -80,33 +254,264 @@ impl PushInterpreter {
         push_state: &mut PushState,
         instruction_set: &mut InstructionSet,
     ) -> PushInterpreterState {
-        PushInterpreter::copy_to_code_stack(push_state);
+        PushInterpreter::run_until(push_state, instruction_set, |_| false)
+    }
+
+    /// Parses the program onto the EXEC stack of the state, copies it to the CODE stack
+    /// and executes it with the limits of the state configuration. This is the entry
+    /// point for embedding pushr; the state can be prepared with inputs and bindings
+    /// before the call.
+    pub fn run_program(
+        code: &str,
+        push_state: &mut PushState,
+        instruction_set: &mut InstructionSet,
+    ) -> RunResult {
+        PushParser::parse_program(push_state, instruction_set, code);
+        let (termination, steps) =
+            PushInterpreter::run_internal(push_state, instruction_set, RunOptions::default());
+        RunResult {
+            termination,
+            steps,
+            stacks: STACK_NAMES
+                .iter()
+                .map(|(name, id)| (*name, push_state.stack_to_string(*id).unwrap_or_default()))
+                .collect(),
+        }
+    }
+
+    /// Like run but enforces the given limits instead of the step and time limits of
+    /// the state configuration.
+    pub fn run_with_config(
+        push_state: &mut PushState,
+        instruction_set: &mut InstructionSet,
+        config: &PushInterpreterConfig,
+    ) -> PushInterpreterState {
+        let options = RunOptions {
+            config: Some(config),
+            ..RunOptions::default()
+        };
+        PushInterpreter::run_internal(push_state, instruction_set, options).0
+    }
+
+    /// Like run but evaluates the termination predicate after each step. Execution
+    /// stops with TerminatedByPredicate as soon as the predicate returns true, e.g.
+    /// when the expected output has been produced.
+    pub fn run_until<F>(
+        push_state: &mut PushState,
+        instruction_set: &mut InstructionSet,
+        termination_predicate: F,
+    ) -> PushInterpreterState
+    where
+        F: Fn(&PushState) -> bool,
+    {
+        let options = RunOptions {
+            termination_predicate: Some(&termination_predicate),
+            ..RunOptions::default()
+        };
+        PushInterpreter::run_internal(push_state, instruction_set, options).0
+    }
+
+    /// Like run but pauses before a step if one of the breakpoints is hit. In this
+    /// case Paused is returned and the execution can be resumed with continue_run.
+    pub fn run_with_breakpoints(
+        push_state: &mut PushState,
+        instruction_set: &mut InstructionSet,
+        breakpoints: &[Breakpoint],
+    ) -> PushInterpreterState {
+        let options = RunOptions {
+            breakpoints,
+            ..RunOptions::default()
+        };
+        PushInterpreter::run_internal(push_state, instruction_set, options).0
+    }
+
+    /// Resumes a paused execution. The next step is executed without checking the
+    /// breakpoints so that the execution does not pause at the same position again.
+    /// Step and time limits apply to each call separately.
+    pub fn continue_run(
+        push_state: &mut PushState,
+        instruction_set: &mut InstructionSet,
+        breakpoints: &[Breakpoint],
+    ) -> PushInterpreterState {
+        let options = RunOptions {
+            breakpoints,
+            resume: true,
+            ..RunOptions::default()
+        };
+        PushInterpreter::run_internal(push_state, instruction_set, options).0
+    }
+
+    /// Like run but records each executed instruction in the tally. An instruction
+    /// that leaves the state unchanged is counted as NOOP.
+    pub fn run_with_tally(
+        push_state: &mut PushState,
+        instruction_set: &mut InstructionSet,
+        tally: &mut ExecutionTally,
+    ) -> PushInterpreterState {
+        let options = RunOptions {
+            tally: Some(tally),
+            ..RunOptions::default()
+        };
+        PushInterpreter::run_internal(push_state, instruction_set, options).0
+    }
+
+    /// Like run but records the evaluation and the execution time of each instruction
+    /// in the metrics sink.
+    pub fn run_with_metrics(
+        push_state: &mut PushState,
+        instruction_set: &mut InstructionSet,
+        metrics: &MetricsSink,
+    ) -> PushInterpreterState {
+        let options = RunOptions {
+            metrics: Some(metrics),
+            ..RunOptions::default()
+        };
+        let (termination, steps) = PushInterpreter::run_internal(push_state, instruction_set, options);
+        metrics.record_evaluation(&termination, steps);
+        termination
+    }
+
+    /// Like run but reports each step and the termination to the observer.
+    pub fn run_with_observer(
+        push_state: &mut PushState,
+        instruction_set: &mut InstructionSet,
+        observer: &mut dyn InterpreterObserver,
+    ) -> PushInterpreterState {
+        let options = RunOptions {
+            observer: Some(observer),
+            ..RunOptions::default()
+        };
+        PushInterpreter::run_internal(push_state, instruction_set, options).0
+    }
+
+    fn run_internal(
+        push_state: &mut PushState,
+        instruction_set: &mut InstructionSet,
+        options: RunOptions,
+    ) -> (PushInterpreterState, usize) {
+        let RunOptions {
+            termination_predicate,
+            mut tally,
+            breakpoints,
+            resume,
+            config,
+            metrics,
+            mut observer,
+        } = options;
+        let limits = config
+            .copied()
+            .unwrap_or_else(|| PushInterpreterConfig::from_configuration(&push_state.configuration));
+        if let Some(max_points) = limits.max_points_in_program {
+            let points: usize = push_state.exec_stack.as_slice().iter().map(Item::size).sum();
+            if points > max_points {
+                if let Some(observer) = observer {
+                    observer.on_halt(&PushInterpreterState::ProgramTooLarge);
+                }
+                return (PushInterpreterState::ProgramTooLarge, 0);
+            }
+        }
+        if !resume {
+            PushInterpreter::copy_to_code_stack(push_state);
+        }
         let icache = instruction_set.cache();
+        // Instruction ID => (executions, total execution time) of this run, merged into
+        // the metrics sink at the end
+        let mut instruction_times: HashMap<usize, (u64, Duration)> = HashMap::new();
         let mut step_counter = 0;
         let start = Instant::now();
-        loop {
-            if step_counter > push_state.configuration.eval_push_limit {
-                return PushInterpreterState::StepLimitExceeded;
+        let result = loop {
+            if step_counter > limits.eval_push_limit {
+                break (PushInterpreterState::StepLimitExceeded, step_counter as usize);
+            }
+            if start.elapsed() > Duration::from_millis(limits.max_runtime_ms) {
+                break (PushInterpreterState::TimeLimitExceeded, step_counter as usize);
             }
-            if start.elapsed() > Duration::from_millis(push_state.configuration.eval_time_limit) {
-                return PushInterpreterState::TimeLimitExceeded;
+            if !(resume && step_counter == 0)
+                && push_state.exec_stack.size() > 0
+                && breakpoints.iter().any(|b| b.is_hit(push_state))
+            {
+                break (PushInterpreterState::Paused, step_counter as usize);
+            }
+            if let (Some(observer), Some(item)) = (observer.as_mut(), push_state.exec_stack.get(0)) {
+                observer.on_step(step_counter as usize, push_state, item);
             }
             let size_before_step = push_state.size();
-            if PushInterpreter::step(push_state, instruction_set, &icache) {
-                break;
+            let burst = match push_state.configuration.literal_bursts {
+                LiteralBursts::Off => 0,
+                LiteralBursts::Compatible => {
+                    let remaining = limits.eval_push_limit - step_counter + 1;
+                    PushInterpreter::transfer_literals(push_state, remaining as usize)
+                }
+                LiteralBursts::Fast => PushInterpreter::transfer_literals(push_state, usize::MAX),
+            };
+            let mut tallied_instruction = None;
+            if tally.is_some() && burst == 0 {
+                if let Some(Item::InstructionMeta { name, .. }) = push_state.exec_stack.get(0) {
+                    let mut state_before_step = push_state.clone();
+                    state_before_step.exec_stack.pop();
+                    tallied_instruction = Some((name.clone(), state_before_step));
+                }
+            }
+            let mut timed_instruction = None;
+            if metrics.is_some() && burst == 0 {
+                if let Some(Item::InstructionMeta { name, .. }) = push_state.exec_stack.get(0) {
+                    timed_instruction = instruction_set
+                        .instruction_id(name)
+                        .map(|id| (id, Instant::now()));
+                }
+            }
+            if burst == 0 && PushInterpreter::step(push_state, instruction_set, &icache) {
+                break (PushInterpreterState::NoErrors, step_counter as usize);
+            }
+            if let Some((id, step_start)) = timed_instruction {
+                let entry = instruction_times
+                    .entry(id)
+                    .or_insert((0, Duration::default()));
+                entry.0 += 1;
+                entry.1 += step_start.elapsed();
+            }
+            if let (Some(tally), Some((name, state_before_step))) = (tally.as_mut(), tallied_instruction) {
+                let noop = !ExecutionTally::changed(&state_before_step, push_state);
+                tally.record(&name, noop);
+                if noop && tally.explains_noops() {
+                    tally.explain(NoopExplanation {
+                        step: step_counter as usize,
+                        missing_stacks: missing_stacks(instruction_set, &icache, &name, &state_before_step),
+                        instruction: name,
+                    });
+                }
             }
             if push_state.size() > size_before_step + push_state.configuration.growth_cap as usize {
-                return PushInterpreterState::GrowthCapExceeded;
+                break (PushInterpreterState::GrowthCapExceeded, step_counter as usize + 1);
+            }
+            if termination_predicate.map_or(false, |predicate| predicate(push_state)) {
+                break (PushInterpreterState::TerminatedByPredicate, step_counter as usize + 1);
+            }
+            if push_state.configuration.literal_bursts == LiteralBursts::Compatible && burst > 1 {
+                step_counter += burst as i32 - 1;
             }
             step_counter += 1;
+        };
+        if let Some(metrics) = metrics {
+            metrics.record_instructions(instruction_times.iter().filter_map(
+                |(id, (executions, duration))| {
+                    instruction_set
+                        .name(*id)
+                        .map(|name| (name, *executions, *duration))
+                },
+            ));
+        }
+        if let Some(observer) = observer {
+            observer.on_halt(&result.0);
         }
-        PushInterpreterState::NoErrors
+        result
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::push::state::FLOAT_STACK_ID;
     use crate::push::parser::PushParser;
 
     pub fn icache() -> InstructionCache {
@@ -158,6 +563,74 @@ mod tests {
         assert_eq!(push_state.bool_stack.to_string(), "TRUE");
     }
 
+    #[test]
+    fn name_literals_are_not_resolved() {
+        let input = "( X 'X )";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        push_state.name_bindings.insert("X".to_string(), Item::int(1));
+        PushInterpreter::run(&mut push_state, &mut instruction_set);
+        assert_eq!(push_state.int_stack.to_string(), "1");
+        assert_eq!(push_state.name_stack.to_string(), "X");
+    }
+
+    #[test]
+    fn inject_executes_item_in_next_step() {
+        let input = "( 2 3 INTEGER.* )";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        // Expand list and push literals
+        for _ in 0..3 {
+            PushInterpreter::step(&mut push_state, &mut instruction_set, &icache());
+        }
+        assert!(PushInterpreter::inject(&mut push_state, Item::instruction("INTEGER.DUP".to_string())));
+        loop {
+            if PushInterpreter::step(&mut push_state, &mut instruction_set, &icache()) {
+                break;
+            }
+        }
+        assert_eq!(push_state.int_stack.to_string(), "9 2");
+    }
+
+    #[test]
+    fn inject_rejects_items_exceeding_max_points() {
+        let mut push_state = PushState::new();
+        push_state.configuration.max_points_in_program = 2;
+        let item = Item::list(vec![Item::int(1), Item::int(2), Item::int(3)]);
+        assert!(!PushInterpreter::inject(&mut push_state, item));
+        assert_eq!(push_state.exec_stack.size(), 0);
+    }
+
+    #[test]
+    fn run_with_config_reports_exhausted_limit() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let config = PushInterpreterConfig {
+            eval_push_limit: 100,
+            max_points_in_program: Some(4),
+            max_runtime_ms: 1000,
+        };
+        let run = |input: &str, instruction_set: &mut InstructionSet| {
+            let mut push_state = PushState::new();
+            PushParser::parse_program(&mut push_state, instruction_set, input);
+            PushInterpreter::run_with_config(&mut push_state, instruction_set, &config)
+        };
+        assert_eq!(run("( 1 2 INTEGER.+ )", &mut instruction_set), PushInterpreterState::NoErrors);
+        assert_eq!(
+            run("( 1 2 3 INTEGER.+ )", &mut instruction_set),
+            PushInterpreterState::ProgramTooLarge
+        );
+        // Infinite loop
+        assert_eq!(
+            run("( EXEC.Y ( ) )", &mut instruction_set),
+            PushInterpreterState::StepLimitExceeded
+        );
+    }
+
     #[test]
     pub fn run_potentiation_program() {
         let input = "( ARG FLOAT.DEFINE EXEC.Y ( ARG FLOAT.* 1 INTEGER.- INTEGER.DUP 0 INTEGER.> EXEC.IF ( ) EXEC.POP ) ) ";
@@ -174,6 +647,81 @@ mod tests {
         assert_eq!(push_state.float_stack.to_string(), "16.0");
     }
 
+    #[test]
+    fn run_until_stops_when_predicate_holds() {
+        let input = "( 1 2 3 4 5 )";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        assert_eq!(
+            PushInterpreter::run_until(&mut push_state, &mut instruction_set, |state| {
+                state.int_stack.size() == 3
+            }),
+            PushInterpreterState::TerminatedByPredicate
+        );
+        assert_eq!(push_state.int_stack.to_string(), "3 2 1");
+        assert_eq!(push_state.exec_stack.to_string(), "4 5");
+    }
+
+    #[derive(Default)]
+    struct TraceObserver {
+        steps: Vec<String>,
+        halt: Option<String>,
+    }
+
+    impl InterpreterObserver for TraceObserver {
+        fn on_step(&mut self, step: usize, push_state: &PushState, item: &Item) {
+            self.steps.push(format!("{} {} [{}]", step, item, push_state.int_stack.to_string()));
+        }
+
+        fn on_halt(&mut self, reason: &PushInterpreterState) {
+            self.halt = Some(format!("{:?}", reason));
+        }
+    }
+
+    #[test]
+    fn run_with_observer_reports_steps_and_halt() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, "( 1 2 INTEGER.+ )");
+        let mut observer = TraceObserver::default();
+        assert_eq!(
+            PushInterpreter::run_with_observer(&mut push_state, &mut instruction_set, &mut observer),
+            PushInterpreterState::NoErrors
+        );
+        assert_eq!(
+            observer.steps,
+            vec!["0 ( 1 2 INTEGER.+ ) []", "1 1 []", "2 2 [1]", "3 INTEGER.+ [2 1]"]
+        );
+        assert_eq!(observer.halt, Some("NoErrors".to_string()));
+    }
+
+    #[test]
+    fn run_pauses_at_breakpoints_and_continues() {
+        let input = "( 1 2 INTEGER.+ 4 INTEGER.+ 1.5 2.5 FLOAT.+ INTEGER.DUP )";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        let breakpoints = vec![
+            Breakpoint::Instruction("INTEGER.+".to_string()),
+            Breakpoint::StackDepth { stack_id: FLOAT_STACK_ID, depth: 2 },
+            Breakpoint::Predicate(Box::new(|state| state.float_stack.get(0) == Some(&4.0))),
+        ];
+        let mut pauses = vec![];
+        let mut result = PushInterpreter::run_with_breakpoints(&mut push_state, &mut instruction_set, &breakpoints);
+        while result == PushInterpreterState::Paused {
+            pauses.push(format!("{}|{}", push_state.int_stack.to_string(), push_state.float_stack.to_string()));
+            result = PushInterpreter::continue_run(&mut push_state, &mut instruction_set, &breakpoints);
+        }
+        assert_eq!(result, PushInterpreterState::NoErrors);
+        assert_eq!(pauses, vec!["2 1|", "4 3|", "7|2.5 1.5", "7|4.0"]);
+        assert_eq!(push_state.int_stack.to_string(), "7 7");
+        assert_eq!(push_state.code_stack.size(), 1);
+    }
+
     #[test]
     pub fn run_factorial_program() {
         let input = "( CODE.QUOTE ( INTEGER.POP 1 )
@@ -261,4 +809,72 @@ mod tests {
         assert_eq!(push_state.index_stack.to_string(), "");
         assert_eq!(push_state.exec_stack.to_string(), "");
     }
+
+    #[test]
+    fn instruction_cache_is_shared_across_threads() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let shared_cache = instruction_set.cache();
+        assert!(std::sync::Arc::ptr_eq(&shared_cache.list, &instruction_set.cache().list));
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let icache = shared_cache.clone();
+                std::thread::spawn(move || {
+                    let mut instruction_set = InstructionSet::new();
+                    instruction_set.load();
+                    let mut push_state = PushState::new();
+                    PushParser::parse_program(
+                        &mut push_state,
+                        &instruction_set,
+                        &format!("( {} 2 INTEGER.* )", i),
+                    );
+                    while !PushInterpreter::step(&mut push_state, &mut instruction_set, &icache) {}
+                    push_state.int_stack.pop()
+                })
+            })
+            .collect();
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(results, vec![Some(0), Some(2), Some(4), Some(6)]);
+        instruction_set.remove("NOOP");
+        assert!(!instruction_set.cache().list.contains(&"NOOP".to_string()));
+        assert!(shared_cache.list.contains(&"NOOP".to_string()));
+    }
+
+    #[test]
+    fn literal_bursts_count_steps_by_mode() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut results = vec![];
+        for mode in [LiteralBursts::Off, LiteralBursts::Compatible, LiteralBursts::Fast].iter() {
+            let mut push_state = PushState::new();
+            push_state.configuration.eval_push_limit = 4;
+            push_state.configuration.literal_bursts = *mode;
+            PushParser::parse_program(&mut push_state, &instruction_set, "( 1 2 3 4 5 INTEGER.+ )");
+            let interpreter_state = PushInterpreter::run(&mut push_state, &mut instruction_set);
+            results.push((interpreter_state, push_state.int_stack.to_string()));
+        }
+        assert_eq!(results[0], (PushInterpreterState::StepLimitExceeded, "4 3 2 1".to_string()));
+        assert_eq!(results[1], results[0]);
+        assert_eq!(results[2], (PushInterpreterState::NoErrors, "9 3 2 1".to_string()));
+    }
+
+    #[test]
+    fn run_program_returns_final_stacks_and_steps() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut push_state = PushState::new();
+        push_state.int_stack.push(4);
+        let result =
+            PushInterpreter::run_program("( 2 INTEGER.* 1.5 )", &mut push_state, &mut instruction_set);
+        assert_eq!(result.termination, PushInterpreterState::NoErrors);
+        assert_eq!(result.steps, 4);
+        assert_eq!(result.stack("int"), Some("8"));
+        assert_eq!(result.stack("float"), Some("1.5"));
+        assert_eq!(result.stack("code"), Some("( 2 INTEGER.* 1.500 )"));
+        assert_eq!(result.stack("ints"), None);
+        push_state.configuration.eval_push_limit = 2;
+        let result = PushInterpreter::run_program("( 1 2 3 4 )", &mut push_state, &mut instruction_set);
+        assert_eq!(result.termination, PushInterpreterState::StepLimitExceeded);
+        assert_eq!(result.steps, 3);
+    }
 }
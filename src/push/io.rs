@@ -4,9 +4,10 @@ use crate::push::state::PushState;
 use crate::push::stack::PushPrint;
 use crate::push::vector::{BoolVector,IntVector};
 use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct PushMessage {
     pub header: IntVector,
     pub body: BoolVector,
@@ -33,7 +34,7 @@ impl fmt::Display for PushMessage {
 
 impl PartialEq for PushMessage {
     fn eq(&self, other: &Self) -> bool {
-        self.header.values == other.header.values && self.body.values == other.body.values
+        self.header == other.header && self.body == other.body
     }
 }
 
@@ -83,8 +84,8 @@ pub fn input_get(push_state: &mut PushState, _instruction_cache: &InstructionCac
         if input_size > 0 {
             if let Some(input) = push_state.input_stack.peek_oldest() {
                 let list_index =
-                    i32::max(i32::min(input.body.values.len() as i32 - 1, index), 0) as usize;
-                push_state.bool_stack.push(input.body.values[list_index]);
+                    i32::max(i32::min(input.body.len() as i32 - 1, index), 0) as usize;
+                push_state.bool_stack.push(input.body.bits.get(list_index));
             }
         }
     }
@@ -1,21 +1,35 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
+use crate::push::char::char_literal;
 use crate::push::graph::Graph;
+use crate::push::hash::StructuralHasher;
 use crate::push::index::Index;
 use crate::push::stack::{PushStack, PushPrint};
 use crate::push::vector::{BoolVector, FloatVector, IntVector};
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
 
 // Items
 #[allow(dead_code)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Item {
     List { items: PushStack<Item> },
-    InstructionMeta { name: String },
+    // The ID is assigned when the program is parsed (see InstructionSet::intern) and
+    // lets the interpreter dispatch the instruction without hashing the name.
+    InstructionMeta {
+        name: String,
+        #[serde(skip)]
+        id: Option<usize>,
+    },
     Literal { push_type: PushType },
     Identifier { name: String },
+    Name { name: String },
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum PushType {
     Bool { val: bool },
     Int { val: i32 },
@@ -25,6 +39,9 @@ pub enum PushType {
     IntVector { val: IntVector },
     FloatVector { val: FloatVector },
     Graph { val: Graph },
+    Char { val: char },
+    #[cfg(feature = "bignum")]
+    BigInt { val: BigInt },
 }
 
 #[allow(dead_code)]
@@ -68,6 +85,19 @@ impl Item {
         }
     }
 
+    #[cfg(feature = "bignum")]
+    pub fn bigint(arg: BigInt) -> Item {
+        Item::Literal {
+            push_type: PushType::BigInt { val: arg },
+        }
+    }
+
+    pub fn char(arg: char) -> Item {
+        Item::Literal {
+            push_type: PushType::Char { val: arg },
+        }
+    }
+
     pub fn graph() -> Item {
         Item::Literal {
             push_type: PushType::Graph { val: Graph::new() },
@@ -75,16 +105,27 @@ impl Item {
     }
 
     pub fn instruction(arg: String) -> Item {
-        Item::InstructionMeta { name: arg }
+        Item::InstructionMeta { name: arg, id: None }
     }
 
+    /// Creates an identifier. Kept for compatibility, NAME literals are created
+    /// with Item::quoted_name.
+    #[deprecated(note = "use Item::id for identifiers or Item::quoted_name for NAME literals")]
     pub fn name(arg: String) -> Item {
-        Item::Identifier { name: arg }
+        Item::id(arg)
+    }
+
+    /// Creates a NAME literal that is always pushed to the NAME stack when executed.
+    /// It is printed with a leading quote, e.g. 'X. Use Item::id for identifiers
+    /// that are resolved via the name bindings.
+    pub fn quoted_name(arg: String) -> Item {
+        Item::Name { name: arg }
     }
 
     pub fn noop() -> Item {
         Item::InstructionMeta {
             name: "NOOP".to_string(),
+            id: None,
         }
     }
     pub fn empty_list() -> Item {
@@ -97,6 +138,14 @@ impl Item {
             items: PushStack::from_vec(arg),
         }
     }
+    /// Returns the name of NAME literals and identifiers or None for all other items.
+    pub fn get_name(&self) -> Option<&str> {
+        match self {
+            Item::Identifier { name } | Item::Name { name } => Some(name),
+            _ => None,
+        }
+    }
+
     pub fn id(arg: String) -> Item {
         Item::Identifier { name: arg }
     }
@@ -117,6 +166,20 @@ impl Item {
         return size;
     }
 
+    /// Returns the maximum nesting depth of parenthesized expressions. Items
+    /// that are not lists have depth 0.
+    pub fn depth(item: &Item) -> usize {
+        match item {
+            Item::List { items } => {
+                1 + (0..items.size())
+                    .map(|i| Item::depth(items.get(i).unwrap()))
+                    .max()
+                    .unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
     /// Returns the number of elements the items cotains up to a depth of 1.
     pub fn shallow_size(item: &Item) -> usize {
         let mut size = 0;
@@ -289,8 +352,8 @@ impl Item {
                 }
                 _ => false,
             },
-            Item::InstructionMeta { name } => match pattern {
-                Item::InstructionMeta { name: pname } => name == pname,
+            Item::InstructionMeta { name, .. } => match pattern {
+                Item::InstructionMeta { name: pname, .. } => name == pname,
                 _ => false,
             },
             Item::Literal { push_type } => match pattern {
@@ -301,6 +364,10 @@ impl Item {
                 Item::Identifier { name: pname } => name == pname,
                 _ => false,
             },
+            Item::Name { name } => match pattern {
+                Item::Name { name: pname } => name == pname,
+                _ => false,
+            },
         }
     }
 }
@@ -309,13 +376,13 @@ impl Item {
 /// ignoring differences in the value.
 impl PartialEq for Item {
     fn eq(&self, other: &Self) -> bool {
-        match &*self {
+        match self {
             Item::List { items: _ } => match &*other {
                 Item::List { items: _ } => return true,
                 _ => return false,
             },
-            Item::InstructionMeta { name: _ } => match &*other {
-                Item::InstructionMeta { name: _ } => return true,
+            Item::InstructionMeta { .. } => match &*other {
+                Item::InstructionMeta { .. } => return true,
                 _ => return false,
             },
             Item::Literal {
@@ -357,6 +424,13 @@ impl PartialEq for Item {
                             PushType::Graph { val: _ } => return true,
                             _ => return false,
                         },
+                        PushType::Char { val: _ } => {
+                            return matches!(other_type, PushType::Char { val: _ })
+                        }
+                        #[cfg(feature = "bignum")]
+                        PushType::BigInt { val: _ } => {
+                            return matches!(other_type, PushType::BigInt { val: _ })
+                        }
                     };
                 }
                 _ => return false,
@@ -365,10 +439,176 @@ impl PartialEq for Item {
                 Item::Identifier { name: _ } => return true,
                 _ => return false,
             },
+            Item::Name { name: _ } => matches!(other, Item::Name { name: _ }),
         }
     }
 }
 
+/// Escapes a label for the DOT language.
+fn dot_label(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Item {
+    /// Renders the program tree in the DOT language of Graphviz. Lists are drawn as
+    /// points whose children are ordered as in the program. Instructions are drawn
+    /// as boxes and all other items as ellipses labeled with their value.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph program {\n");
+        let mut counter = 0;
+        self.append_dot(&mut dot, &mut counter);
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Appends the node of this item and its subtree. Returns the node ID.
+    fn append_dot(&self, dot: &mut String, counter: &mut usize) -> usize {
+        let id = *counter;
+        *counter += 1;
+        match self {
+            Item::List { items } => {
+                dot.push_str(&format!("  n{} [label=\"\", shape=point];\n", id));
+                for child in items.as_slice().iter().rev() {
+                    let child_id = child.append_dot(dot, counter);
+                    dot.push_str(&format!("  n{} -> n{};\n", id, child_id));
+                }
+            }
+            Item::InstructionMeta { name, .. } => {
+                dot.push_str(&format!("  n{} [label=\"{}\", shape=box];\n", id, dot_label(name)));
+            }
+            _ => {
+                dot.push_str(&format!(
+                    "  n{} [label=\"{}\", shape=ellipse];\n",
+                    id,
+                    dot_label(&self.to_string())
+                ));
+            }
+        }
+        id
+    }
+
+    /// Returns a hash of the program tree that is equal for syntactically equal
+    /// programs. In contrast to PartialEq, literal values are taken into account.
+    /// Floats are compared bitwise. The hashes are only reproducible across platforms
+    /// and Rust versions with the feature "stable-hash" (see hash::StructuralHasher).
+    pub fn hash_structural(&self) -> u64 {
+        let mut hasher = StructuralHasher::default();
+        self.hash_into(&mut hasher);
+        hasher.finish()
+    }
+
+    fn hash_into(&self, hasher: &mut StructuralHasher) {
+        match self {
+            Item::List { items } => {
+                hasher.write_u8(0);
+                hasher.write_usize(items.size());
+                for child in items.as_slice().iter() {
+                    child.hash_into(hasher);
+                }
+            }
+            Item::InstructionMeta { name, .. } => {
+                hasher.write_u8(1);
+                name.hash(hasher);
+            }
+            Item::Literal { push_type } => {
+                hasher.write_u8(2);
+                push_type.hash_into(hasher);
+            }
+            Item::Identifier { name } => {
+                hasher.write_u8(3);
+                name.hash(hasher);
+            }
+            Item::Name { name } => {
+                hasher.write_u8(4);
+                name.hash(hasher);
+            }
+        }
+    }
+
+    /// Returns a normalized copy of the program where NOOP instructions and
+    /// empty lists are removed and lists with a single element are replaced
+    /// by this element. Programs with the same normalized form behave the same.
+    pub fn normalized(&self) -> Item {
+        match self {
+            Item::List { items } => {
+                let children: Vec<Item> = items
+                    .as_slice()
+                    .iter()
+                    .map(|child| child.normalized())
+                    .filter(|child| !child.is_removable())
+                    .collect();
+                if children.len() == 1 {
+                    children.into_iter().next().unwrap()
+                } else {
+                    Item::list(children)
+                }
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// Returns a list of all atoms (non-list items) of the item in the same order,
+    /// i.e. the nesting of sublists is removed. Empty sublists disappear. Items that
+    /// are not lists are returned unchanged.
+    pub fn flatten(&self) -> Item {
+        match self {
+            Item::List { .. } => {
+                let mut atoms = vec![];
+                self.collect_atoms(&mut atoms);
+                Item::list(atoms)
+            }
+            _ => self.clone(),
+        }
+    }
+
+    fn collect_atoms(&self, atoms: &mut Vec<Item>) {
+        match self {
+            Item::List { items } => {
+                for child in items.as_slice().iter() {
+                    child.collect_atoms(atoms);
+                }
+            }
+            _ => atoms.push(self.clone()),
+        }
+    }
+
+    fn is_removable(&self) -> bool {
+        match self {
+            Item::List { items } => items.size() == 0,
+            Item::InstructionMeta { name, .. } => name == "NOOP",
+            _ => false,
+        }
+    }
+}
+
+/// Returns the indices of the programs that are kept if syntactic duplicates are
+/// removed, i.e. the first occurrence of each program.
+pub fn dedup_programs(programs: &[Item]) -> Vec<usize> {
+    dedup_by_hash(programs, Item::hash_structural)
+}
+
+/// Like dedup_programs but compares the normalized forms of the programs so that
+/// near-duplicates are removed as well.
+pub fn dedup_programs_normalized(programs: &[Item]) -> Vec<usize> {
+    dedup_by_hash(programs, |program| program.normalized().hash_structural())
+}
+
+fn dedup_by_hash<F>(programs: &[Item], hash: F) -> Vec<usize>
+where
+    F: Fn(&Item) -> u64,
+{
+    let mut seen = HashSet::new();
+    programs
+        .iter()
+        .enumerate()
+        .filter(|(_, program)| seen.insert(hash(program)))
+        .map(|(i, _)| i)
+        .collect()
+}
+
 impl PushPrint for Item {
    fn to_pstring(&self) -> String {
        format!("{}", self.to_string())
@@ -379,7 +619,7 @@ impl fmt::Display for Item {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &*self {
             Item::List { items } => write!(f, "( {} )", items.to_string()),
-            Item::InstructionMeta { name } => {
+            Item::InstructionMeta { name, .. } => {
                 write!(f, "{}", name)
             }
             Item::Literal { push_type } => {
@@ -393,17 +633,73 @@ impl fmt::Display for Item {
                     PushType::FloatVector { val } => info = val.to_string(),
                     PushType::IntVector { val } => info = val.to_string(),
                     PushType::Graph { val } => info = val.to_string(),
+                    PushType::Char { val } => info = char_literal(*val),
+                    #[cfg(feature = "bignum")]
+                    PushType::BigInt { val } => info = val.to_string(),
                 }
                 write!(f, "{}", info)
             }
             Item::Identifier { name } => {
                 write!(f, "{}", name)
             }
+            Item::Name { name } => {
+                write!(f, "'{}", name)
+            }
         }
     }
 }
 
 impl PushType {
+    fn hash_into(&self, hasher: &mut StructuralHasher) {
+        match self {
+            PushType::Bool { val } => {
+                hasher.write_u8(0);
+                val.hash(hasher);
+            }
+            PushType::Int { val } => {
+                hasher.write_u8(1);
+                val.hash(hasher);
+            }
+            PushType::Index { val } => {
+                hasher.write_u8(2);
+                val.current.hash(hasher);
+                val.destination.hash(hasher);
+            }
+            PushType::Float { val } => {
+                hasher.write_u8(3);
+                val.to_bits().hash(hasher);
+            }
+            PushType::BoolVector { val } => {
+                hasher.write_u8(4);
+                val.to_bools().hash(hasher);
+            }
+            PushType::IntVector { val } => {
+                hasher.write_u8(5);
+                val.values.hash(hasher);
+            }
+            PushType::FloatVector { val } => {
+                hasher.write_u8(6);
+                hasher.write_usize(val.values.len());
+                for fval in val.values.iter() {
+                    fval.to_bits().hash(hasher);
+                }
+            }
+            PushType::Graph { val } => {
+                hasher.write_u8(7);
+                val.to_string().hash(hasher);
+            }
+            PushType::Char { val } => {
+                hasher.write_u8(9);
+                val.hash(hasher);
+            }
+            #[cfg(feature = "bignum")]
+            PushType::BigInt { val } => {
+                hasher.write_u8(8);
+                val.hash(hasher);
+            }
+        }
+    }
+
     /// Returns true if type and value are equal
     pub fn equals(&self, other: &PushType) -> bool {
         match &*self {
@@ -439,6 +735,13 @@ impl PushType {
                 PushType::Graph { val: other_val } => return val == other_val,
                 _ => false,
             },
+            PushType::Char { val } => {
+                matches!(other, PushType::Char { val: other_val } if val == other_val)
+            }
+            #[cfg(feature = "bignum")]
+            PushType::BigInt { val } => {
+                matches!(other, PushType::BigInt { val: other_val } if val == other_val)
+            }
         }
     }
 }
@@ -447,6 +750,46 @@ impl PushType {
 mod tests {
     use super::*;
 
+    #[test]
+    fn dedup_programs_removes_duplicates() {
+        let programs = vec![
+            Item::list(vec![Item::int(1), Item::instruction("INTEGER.+".to_string())]),
+            Item::list(vec![Item::int(2), Item::instruction("INTEGER.+".to_string())]),
+            Item::list(vec![Item::int(1), Item::instruction("INTEGER.+".to_string())]),
+            Item::list(vec![
+                Item::list(vec![Item::int(1), Item::noop()]),
+                Item::empty_list(),
+                Item::instruction("INTEGER.+".to_string()),
+            ]),
+            Item::list(vec![Item::float(1.0), Item::instruction("INTEGER.+".to_string())]),
+        ];
+        assert_eq!(
+            programs[0].hash_structural(),
+            programs[2].hash_structural()
+        );
+        assert_ne!(
+            programs[0].hash_structural(),
+            programs[1].hash_structural()
+        );
+        assert_eq!(dedup_programs(&programs), vec![0, 1, 3, 4]);
+        assert_eq!(dedup_programs_normalized(&programs), vec![0, 1, 4]);
+        assert_eq!(programs[3].normalized().to_string(), "( INTEGER.+ 1 )");
+    }
+
+    #[test]
+    fn to_dot_renders_program_tree() {
+        let program = Item::list(vec![
+            Item::instruction("INTEGER.+".to_string()),
+            Item::list(vec![Item::quoted_name("X".to_string()), Item::bool(true)]),
+            Item::int(2),
+        ]);
+        assert_eq!(
+            program.to_dot(),
+            "digraph program {\n  n0 [label=\"\", shape=point];\n  n1 [label=\"2\", shape=ellipse];\n  n0 -> n1;\n  n2 [label=\"\", shape=point];\n  n3 [label=\"TRUE\", shape=ellipse];\n  n2 -> n3;\n  n4 [label=\"'X\", shape=ellipse];\n  n2 -> n4;\n  n0 -> n2;\n  n5 [label=\"INTEGER.+\", shape=box];\n  n0 -> n5;\n}\n"
+        );
+        assert_eq!(dot_label("a\"b\nc"), "a\\\"b\\nc");
+    }
+
     #[test]
     fn shallow_equality_returns_true_comparing_items_with_different_content() {
         let literal_a = Item::int(0);
@@ -456,6 +799,7 @@ mod tests {
         let inst_a = Item::noop();
         let inst_b = Item::InstructionMeta {
             name: "BOOLEAN.AND".to_string(),
+            id: Some(3),
         };
         assert_eq!(list_a, list_b);
         assert_eq!(inst_a, inst_b);
@@ -484,6 +828,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn depth_returns_max_nesting_level() {
+        let test_item = Item::list(vec![
+            Item::int(4),
+            Item::list(vec![Item::list(vec![]), Item::int(3)]),
+            Item::int(1),
+        ]);
+        assert_eq!(Item::depth(&test_item), 3);
+        assert_eq!(Item::depth(&Item::int(1)), 0);
+    }
+
     #[test]
     fn find_returns_integer_pattern() {
         let test_item = Item::list(vec![
@@ -3,7 +3,7 @@ use crate::push::item::Item;
 use crate::push::item::PushType;
 use crate::push::state::*;
 use crate::push::topology::Topology;
-use crate::push::vector::{BoolVector, FloatVector, IntVector};
+use crate::push::vector::{cap_vector_size, BoolVector, FloatVector, IntVector};
 use std::collections::HashMap;
 
 /// Integer numbers (that is, numbers without decimal points).
@@ -135,7 +135,7 @@ pub fn load_items(push_state: &mut PushState) -> Option<Vec<Item>> {
                 }
                 NAME_STACK_ID => {
                     if let Some(ni) = push_state.name_stack.pop() {
-                        items.push(Item::name(ni));
+                        items.push(Item::id(ni));
                     }
                 }
                 _ => (),
@@ -253,17 +253,17 @@ pub fn list_set(push_state: &mut PushState, _instruction_cache: &InstructionCach
     }
 }
 
-/// LIST.NEIGHBORS*ID: Calculates the neighborhood for a given index element and length. It
+/// LIST.NEIGHBOR*IDS: Calculates the neighborhood for a given index element and length. It
 /// pushes the indices that are contained in this neighborhood to the INTVECTOR stack.
 /// The size, the number of dimensions and index (vector topology) are taken from the INTEGER
 /// stack in that order. The radius is taken from the float stack. Distances are calculated using the
 /// Eucledian metric. All values are corrected by max-min. If the size of the top element is not a power
 /// of the dimensions the smallest hypercube that includes the indices is used to represent the
 /// topology, e.g. two dimensions and size = 38 is represented by[7,7]. Neighbor indices that
-/// do no exist (e.g. 40) are ignored.
+/// do no exist (e.g. 40) are ignored. The size is limited to the maximum vector size.
 pub fn list_neighbor_ids(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(topology) = push_state.int_stack.pop_vec(3) {
-        let size = i32::max(topology[2], 0);
+        let size = i32::max(cap_vector_size(push_state, topology[2]), 0);
         let index = i32::max(i32::min(size - 1, topology[1]), 0) as usize;
         let dimensions = i32::max(i32::min(size, topology[0]), 0) as usize;
         if let Some(fval) = push_state.float_stack.pop() {
@@ -286,7 +286,7 @@ pub fn list_neighbor_ids(push_state: &mut PushState, _instruction_cache: &Instru
 pub fn list_neighbor_bvals(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(topology) = push_state.int_stack.pop_vec(4) {
         let position = topology[3] as usize;
-        let size = i32::max(topology[2], 0);
+        let size = i32::max(cap_vector_size(push_state, topology[2]), 0);
         let index = i32::max(i32::min(size - 1, topology[1]), 0) as usize;
         let dimensions = i32::max(i32::min(size, topology[0]), 0) as usize;
         if let Some(fval) = push_state.float_stack.pop() {
@@ -311,7 +311,7 @@ pub fn list_neighbor_bvals(push_state: &mut PushState, _instruction_cache: &Inst
 pub fn list_neighbor_ivals(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(topology) = push_state.int_stack.pop_vec(4) {
         let position = topology[3] as usize;
-        let size = i32::max(topology[2], 0);
+        let size = i32::max(cap_vector_size(push_state, topology[2]), 0);
         let index = i32::max(i32::min(size - 1, topology[1]), 0) as usize;
         let dimensions = i32::max(i32::min(size, topology[0]), 0) as usize;
         if let Some(fval) = push_state.float_stack.pop() {
@@ -336,7 +336,7 @@ pub fn list_neighbor_ivals(push_state: &mut PushState, _instruction_cache: &Inst
 pub fn list_neighbor_fvals(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(topology) = push_state.int_stack.pop_vec(4) {
         let position = topology[3] as usize;
-        let size = i32::max(topology[2], 0);
+        let size = i32::max(cap_vector_size(push_state, topology[2]), 0);
         let index = i32::max(i32::min(size - 1, topology[1]), 0) as usize;
         let dimensions = i32::max(i32::min(size, topology[0]), 0) as usize;
         if let Some(rval) = push_state.float_stack.pop() {
@@ -592,6 +592,15 @@ mod tests {
             test_state.int_vector_stack.to_string(),
             String::from("[0,1,10,11]")
         );
+        // The size is limited to the maximum vector size
+        test_state.int_vector_stack.flush();
+        test_state.configuration.max_vector_size = 4;
+        test_state.float_stack.push(1e9); // Radius
+        test_state.int_stack.push(1); // Dimensions
+        test_state.int_stack.push(0); // Index
+        test_state.int_stack.push(i32::MAX); // Size
+        list_neighbor_ids(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[0,1,2,3]");
     }
 
     #[test]
@@ -0,0 +1,612 @@
+use crate::push::instructions::Instruction;
+use crate::push::instructions::InstructionCache;
+use crate::push::random::CodeGenerator;
+use crate::push::stack::PushPrint;
+use crate::push::state::PushState;
+use crate::push::state::*;
+use crate::push::vector::FloatVector;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Two-dimensional matrix of floats stored in row-major order, e.g. an image or a
+/// multi-channel signal.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "FloatMatrixData")]
+pub struct FloatMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub values: Vec<f32>,
+}
+
+/// Unchecked fields of a deserialized FloatMatrix.
+#[derive(Deserialize)]
+struct FloatMatrixData {
+    rows: usize,
+    cols: usize,
+    values: Vec<f32>,
+}
+
+impl TryFrom<FloatMatrixData> for FloatMatrix {
+    type Error = String;
+
+    /// Checks that the values fill the matrix, so that the access functions cannot
+    /// index outside of the values.
+    fn try_from(data: FloatMatrixData) -> Result<Self, Self::Error> {
+        let (rows, cols, len) = (data.rows, data.cols, data.values.len());
+        FloatMatrix::new(data.rows, data.cols, data.values).ok_or_else(|| {
+            format!(
+                "matrix has {} values but {} rows and {} columns",
+                len, rows, cols
+            )
+        })
+    }
+}
+
+impl FloatMatrix {
+    /// Creates a matrix from the values in row-major order. Returns None if the number
+    /// of values differs from the number of elements.
+    pub fn new(rows: usize, cols: usize, values: Vec<f32>) -> Option<Self> {
+        if rows.checked_mul(cols)? != values.len() {
+            return None;
+        }
+        Some(Self { rows, cols, values })
+    }
+
+    /// Creates a matrix where all elements have the given value.
+    pub fn filled(rows: usize, cols: usize, value: f32) -> Self {
+        Self {
+            rows,
+            cols,
+            values: vec![value; rows * cols],
+        }
+    }
+
+    /// Creates a matrix with the given number of columns from the values in row-major
+    /// order. Returns None if the values do not fill the last row.
+    pub fn from_values(cols: usize, values: Vec<f32>) -> Option<Self> {
+        if cols == 0 || values.len() % cols != 0 {
+            return None;
+        }
+        Some(Self {
+            rows: values.len() / cols,
+            cols,
+            values,
+        })
+    }
+
+    /// Returns the element at the given position or None if it is outside the matrix.
+    pub fn get(&self, row: usize, col: usize) -> Option<f32> {
+        if row < self.rows && col < self.cols {
+            Some(self.values[row * self.cols + col])
+        } else {
+            None
+        }
+    }
+
+    /// Sets the element at the given position. Returns false if it is outside the matrix.
+    pub fn set(&mut self, row: usize, col: usize, value: f32) -> bool {
+        if row < self.rows && col < self.cols {
+            self.values[row * self.cols + col] = value;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the elements of the given row or None if it is outside the matrix.
+    pub fn row(&self, row: usize) -> Option<Vec<f32>> {
+        if row < self.rows {
+            Some(self.values[row * self.cols..(row + 1) * self.cols].to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the elements of the given column or None if it is outside the matrix.
+    pub fn col(&self, col: usize) -> Option<Vec<f32>> {
+        if col < self.cols {
+            Some(
+                (0..self.rows)
+                    .map(|row| self.values[row * self.cols + col])
+                    .collect(),
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Returns the transposed matrix.
+    pub fn transpose(&self) -> Self {
+        let mut values = Vec::with_capacity(self.values.len());
+        for col in 0..self.cols {
+            for row in 0..self.rows {
+                values.push(self.values[row * self.cols + col]);
+            }
+        }
+        Self {
+            rows: self.cols,
+            cols: self.rows,
+            values,
+        }
+    }
+
+    /// Applies the operation to the elements at the same positions of both matrices.
+    /// Returns None if the dimensions differ.
+    pub fn zip_with<F: Fn(f32, f32) -> f32>(&self, other: &FloatMatrix, op: F) -> Option<Self> {
+        if self.rows != other.rows || self.cols != other.cols {
+            return None;
+        }
+        Some(Self {
+            rows: self.rows,
+            cols: self.cols,
+            values: self
+                .values
+                .iter()
+                .zip(other.values.iter())
+                .map(|(a, b)| op(*a, *b))
+                .collect(),
+        })
+    }
+
+    /// Returns the matrix product self x other. Returns None if the number of columns
+    /// of self differs from the number of rows of other.
+    pub fn matmul(&self, other: &FloatMatrix) -> Option<Self> {
+        if self.cols != other.rows {
+            return None;
+        }
+        let mut values = vec![0.0; self.rows * other.cols];
+        for row in 0..self.rows {
+            for k in 0..self.cols {
+                let a = self.values[row * self.cols + k];
+                for col in 0..other.cols {
+                    values[row * other.cols + col] += a * other.values[k * other.cols + col];
+                }
+            }
+        }
+        Some(Self {
+            rows: self.rows,
+            cols: other.cols,
+            values,
+        })
+    }
+}
+
+impl PushPrint for FloatMatrix {
+    fn to_pstring(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for FloatMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<String> = self
+            .values
+            .chunks(usize::max(self.cols, 1))
+            .map(|row| {
+                let elements: Vec<String> = row.iter().map(|v| format!("{:.3}", v)).collect();
+                format!("[{}]", elements.join(","))
+            })
+            .collect();
+        write!(f, "[{}]", rows.join(","))
+    }
+}
+
+/// Two-dimensional float matrices for image and signal processing.
+pub fn load_matrix_instructions(map: &mut HashMap<String, Instruction>) {
+    map.insert(String::from("MATRIX.+"), Instruction::new(matrix_add));
+    map.insert(String::from("MATRIX.*"), Instruction::new(matrix_mult));
+    map.insert(String::from("MATRIX.="), Instruction::new(matrix_equal));
+    map.insert(String::from("MATRIX.COL"), Instruction::new(matrix_col));
+    map.insert(String::from("MATRIX.DUP"), Instruction::new(matrix_dup));
+    map.insert(String::from("MATRIX.FLUSH"), Instruction::new(matrix_flush));
+    map.insert(String::from("MATRIX.GET"), Instruction::new(matrix_get));
+    map.insert(String::from("MATRIX.ID"), Instruction::new(matrix_id));
+    map.insert(
+        String::from("MATRIX.MATMUL"),
+        Instruction::new(matrix_matmul),
+    );
+    map.insert(String::from("MATRIX.ONES"), Instruction::new(matrix_ones));
+    map.insert(String::from("MATRIX.POP"), Instruction::new(matrix_pop));
+    map.insert(String::from("MATRIX.RAND"), Instruction::new(matrix_rand));
+    map.insert(String::from("MATRIX.ROT"), Instruction::new(matrix_rot));
+    map.insert(String::from("MATRIX.ROW"), Instruction::new(matrix_row));
+    map.insert(String::from("MATRIX.SET"), Instruction::new(matrix_set));
+    map.insert(String::from("MATRIX.SHOVE"), Instruction::new(matrix_shove));
+    map.insert(
+        String::from("MATRIX.STACKDEPTH"),
+        Instruction::new(matrix_stack_depth),
+    );
+    map.insert(String::from("MATRIX.SWAP"), Instruction::new(matrix_swap));
+    map.insert(
+        String::from("MATRIX.TRANSPOSE"),
+        Instruction::new(matrix_transpose),
+    );
+    map.insert(String::from("MATRIX.YANK"), Instruction::new(matrix_yank));
+    map.insert(
+        String::from("MATRIX.YANKDUP"),
+        Instruction::new(matrix_yank_dup),
+    );
+    map.insert(String::from("MATRIX.ZEROS"), Instruction::new(matrix_zeros));
+}
+
+/// Returns the row (second item) and the column (top item) of the INTEGER stack without
+/// popping them. The caller pops both items once the instruction is known to succeed.
+fn peek_position(push_state: &PushState) -> Option<(usize, usize)> {
+    let row = usize::try_from(*push_state.int_stack.get(1)?).ok()?;
+    let col = usize::try_from(*push_state.int_stack.get(0)?).ok()?;
+    Some((row, col))
+}
+
+/// Returns the number of rows and columns like peek_position. Returns None if the number
+/// of elements exceeds the maximum vector size.
+fn peek_dimensions(push_state: &PushState) -> Option<(usize, usize)> {
+    let (rows, cols) = peek_position(push_state)?;
+    if rows.checked_mul(cols)? > push_state.configuration.max_vector_size {
+        return None;
+    }
+    Some((rows, cols))
+}
+
+/// Returns the index taken from the INTEGER stack bound to the valid positions of the
+/// MATRIX stack.
+fn pop_matrix_index(push_state: &mut PushState) -> Option<usize> {
+    let index = push_state.int_stack.pop()?;
+    Some(i32::max(
+        i32::min(push_state.matrix_stack.size() as i32 - 1, index),
+        0,
+    ) as usize)
+}
+
+/// Replaces the top two MATRIX items by the result of the operation applied to the second
+/// and the top item. Acts as NOOP if the operation is not defined for the items.
+fn binary_op<F>(push_state: &mut PushState, op: F)
+where
+    F: Fn(&FloatMatrix, &FloatMatrix) -> Option<FloatMatrix>,
+{
+    if let (Some(top), Some(second)) = (
+        push_state.matrix_stack.get(0),
+        push_state.matrix_stack.get(1),
+    ) {
+        if let Some(result) = op(second, top) {
+            push_state.matrix_stack.pop_vec(2);
+            push_state.matrix_stack.push(result);
+        }
+    }
+}
+
+/// MATRIX.+: Pushes the element-wise sum of the top two MATRIX items. Acts as NOOP if their
+/// dimensions differ.
+fn matrix_add(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    binary_op(push_state, |a, b| a.zip_with(b, |x, y| x + y));
+}
+
+/// MATRIX.*: Pushes the element-wise product of the top two MATRIX items. Acts as NOOP if
+/// their dimensions differ. See MATRIX.MATMUL for the matrix product.
+fn matrix_mult(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    binary_op(push_state, |a, b| a.zip_with(b, |x, y| x * y));
+}
+
+/// MATRIX.=: Pushes TRUE onto the BOOLEAN stack if the top two MATRIX items are equal, or
+/// FALSE otherwise.
+fn matrix_equal(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(mvals) = push_state.matrix_stack.pop_vec(2) {
+        push_state.bool_stack.push(mvals[0] == mvals[1]);
+    }
+}
+
+/// MATRIX.COL: Pushes the column of the top MATRIX item to the FLOATVECTOR stack. The column
+/// index is taken from the INTEGER stack. Acts as NOOP if the column is outside the matrix.
+fn matrix_col(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(matrix) = push_state.matrix_stack.get(0) {
+        if let Some(col) = push_state.int_stack.get(0) {
+            if let Some(values) = usize::try_from(*col).ok().and_then(|c| matrix.col(c)) {
+                push_state.int_stack.pop();
+                push_state.float_vector_stack.push(FloatVector::new(values));
+            }
+        }
+    }
+}
+
+/// MATRIX.DUP: Duplicates the top item on the MATRIX stack. Does not pop its argument.
+fn matrix_dup(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(matrix) = push_state.matrix_stack.copy(0) {
+        push_state.matrix_stack.push(matrix);
+    }
+}
+
+/// MATRIX.FLUSH: Empties the MATRIX stack.
+fn matrix_flush(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.matrix_stack.flush();
+}
+
+/// MATRIX.GET: Pushes the element of the top MATRIX item to the FLOAT stack. The row and column
+/// are the second and the top item of the INTEGER stack. Acts as NOOP if the position is outside
+/// the matrix.
+fn matrix_get(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let (Some(matrix), Some((row, col))) =
+        (push_state.matrix_stack.get(0), peek_position(push_state))
+    {
+        if let Some(value) = matrix.get(row, col) {
+            push_state.int_stack.pop_vec(2);
+            push_state.float_stack.push(value);
+        }
+    }
+}
+
+/// MATRIX.ID: Pushes the ID of the MATRIX stack to the INTEGER stack.
+fn matrix_id(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.int_stack.push(MATRIX_STACK_ID);
+}
+
+/// MATRIX.MATMUL: Pushes the matrix product of the second and the top MATRIX item. Acts as NOOP
+/// if the number of columns of the second item differs from the number of rows of the top item
+/// or if the result would exceed the maximum vector size.
+fn matrix_matmul(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    let cap = push_state.configuration.max_vector_size;
+    binary_op(push_state, |a, b| {
+        // Check the size before the result is allocated
+        if a.rows.saturating_mul(b.cols) > cap {
+            return None;
+        }
+        a.matmul(b)
+    });
+}
+
+/// MATRIX.ONES: Pushes a MATRIX where all elements are 1.0. The number of rows and columns are
+/// the second and the top item of the INTEGER stack. Acts as NOOP if the number of elements
+/// exceeds the maximum vector size.
+fn matrix_ones(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some((rows, cols)) = peek_dimensions(push_state) {
+        push_state.int_stack.pop_vec(2);
+        push_state
+            .matrix_stack
+            .push(FloatMatrix::filled(rows, cols, 1.0));
+    }
+}
+
+/// MATRIX.POP: Pops the MATRIX stack.
+fn matrix_pop(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.matrix_stack.pop();
+}
+
+/// MATRIX.RAND: Pushes a MATRIX with elements drawn from the normal distribution N(mu,sig). The
+/// number of rows and columns are the second and the top item of the INTEGER stack, mu and sig
+/// are the top and the second item of the FLOAT stack. Acts as NOOP if sig < 0, sig is not
+/// finite or the number of elements exceeds the maximum vector size.
+fn matrix_rand(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let (Some(mean), Some(stddev), Some((rows, cols))) = (
+        push_state.float_stack.get(0).cloned(),
+        push_state.float_stack.get(1).cloned(),
+        peek_dimensions(push_state),
+    ) {
+        if let Some(fvval) = CodeGenerator::random_float_vector(
+            &mut push_state.rng,
+            (rows * cols) as i32,
+            mean,
+            stddev,
+        ) {
+            push_state.int_stack.pop_vec(2);
+            push_state.float_stack.pop_vec(2);
+            if let Some(matrix) = FloatMatrix::new(rows, cols, fvval.values) {
+                push_state.matrix_stack.push(matrix);
+            }
+        }
+    }
+}
+
+/// MATRIX.ROT: Rotates the top three items on the MATRIX stack, pulling the third item out and
+/// pushing it on top.
+fn matrix_rot(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.matrix_stack.yank(2);
+}
+
+/// MATRIX.ROW: Pushes the row of the top MATRIX item to the FLOATVECTOR stack. The row index is
+/// taken from the INTEGER stack. Acts as NOOP if the row is outside the matrix.
+fn matrix_row(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(matrix) = push_state.matrix_stack.get(0) {
+        if let Some(row) = push_state.int_stack.get(0) {
+            if let Some(values) = usize::try_from(*row).ok().and_then(|r| matrix.row(r)) {
+                push_state.int_stack.pop();
+                push_state.float_vector_stack.push(FloatVector::new(values));
+            }
+        }
+    }
+}
+
+/// MATRIX.SET: Sets the element of the top MATRIX item to the top item of the FLOAT stack. The
+/// row and column are the second and the top item of the INTEGER stack. Acts as NOOP if the
+/// position is outside the matrix.
+fn matrix_set(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let (Some(value), Some((row, col))) = (
+        push_state.float_stack.get(0).cloned(),
+        peek_position(push_state),
+    ) {
+        if let Some(matrix) = push_state.matrix_stack.get_mut(0) {
+            if matrix.set(row, col, value) {
+                push_state.int_stack.pop_vec(2);
+                push_state.float_stack.pop();
+            }
+        }
+    }
+}
+
+/// MATRIX.SHOVE: Inserts the top MATRIX "deep" in the stack, at the position indexed by the top
+/// INTEGER.
+fn matrix_shove(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = pop_matrix_index(push_state) {
+        push_state.matrix_stack.shove(index);
+    }
+}
+
+/// MATRIX.STACKDEPTH: Pushes the stack depth onto the INTEGER stack.
+fn matrix_stack_depth(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state
+        .int_stack
+        .push(push_state.matrix_stack.size() as i32);
+}
+
+/// MATRIX.SWAP: Swaps the top two MATRIX items.
+fn matrix_swap(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.matrix_stack.shove(1);
+}
+
+/// MATRIX.TRANSPOSE: Transposes the top MATRIX item.
+fn matrix_transpose(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(matrix) = push_state.matrix_stack.get_mut(0) {
+        *matrix = matrix.transpose();
+    }
+}
+
+/// MATRIX.YANK: Removes an indexed item from "deep" in the stack and pushes it on top of the
+/// stack. The index is taken from the INTEGER stack.
+fn matrix_yank(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = pop_matrix_index(push_state) {
+        push_state.matrix_stack.yank(index);
+    }
+}
+
+/// MATRIX.YANKDUP: Pushes a copy of an indexed item "deep" in the stack onto the top of the
+/// stack, without removing the deep item. The index is taken from the INTEGER stack.
+fn matrix_yank_dup(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = pop_matrix_index(push_state) {
+        if let Some(deep_item) = push_state.matrix_stack.copy(index) {
+            push_state.matrix_stack.push(deep_item);
+        }
+    }
+}
+
+/// MATRIX.ZEROS: Pushes a MATRIX where all elements are 0.0. The number of rows and columns are
+/// the second and the top item of the INTEGER stack. Acts as NOOP if the number of elements
+/// exceeds the maximum vector size.
+fn matrix_zeros(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some((rows, cols)) = peek_dimensions(push_state) {
+        push_state.int_stack.pop_vec(2);
+        push_state
+            .matrix_stack
+            .push(FloatMatrix::filled(rows, cols, 0.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub fn icache() -> InstructionCache {
+        InstructionCache::new(vec![])
+    }
+
+    fn matrix(cols: usize, values: Vec<f32>) -> FloatMatrix {
+        FloatMatrix::from_values(cols, values).unwrap()
+    }
+
+    #[test]
+    fn matrix_get_set_and_extract_rows() {
+        let mut test_state = PushState::new();
+        test_state.int_stack.push_vec(vec![2, 3]);
+        matrix_zeros(&mut test_state, &icache());
+        test_state.float_stack.push(1.5);
+        test_state.int_stack.push_vec(vec![1, 2]);
+        matrix_set(&mut test_state, &icache());
+        assert_eq!(
+            test_state.matrix_stack.to_string(),
+            "[[0.000,0.000,0.000],[0.000,0.000,1.500]]"
+        );
+        test_state.int_stack.push_vec(vec![1, 2]);
+        matrix_get(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.to_string(), "1.5");
+        // Outside of the matrix, the position is not popped
+        test_state.int_stack.push_vec(vec![2, 0]);
+        matrix_get(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.size(), 1);
+        test_state.float_stack.push(2.5);
+        matrix_set(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.size(), 2);
+        assert_eq!(test_state.int_stack.to_string(), "0 2");
+        test_state.float_stack.pop();
+        test_state.int_stack.push(1);
+        matrix_row(&mut test_state, &icache());
+        test_state.int_stack.push(2);
+        matrix_col(&mut test_state, &icache());
+        test_state.int_stack.push(3);
+        matrix_col(&mut test_state, &icache());
+        assert_eq!(
+            test_state.float_vector_stack.to_string(),
+            "[0.000,1.500] [0.000,0.000,1.500]"
+        );
+        assert_eq!(test_state.int_stack.to_string(), "3 0 2");
+    }
+
+    #[test]
+    fn matrix_arithmetic_and_products() {
+        let mut test_state = PushState::new();
+        test_state
+            .matrix_stack
+            .push(matrix(2, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]));
+        matrix_dup(&mut test_state, &icache());
+        matrix_transpose(&mut test_state, &icache());
+        assert_eq!(
+            test_state.matrix_stack.get(0).unwrap().to_string(),
+            "[[1.000,3.000,5.000],[2.000,4.000,6.000]]"
+        );
+        // Dimensions differ
+        matrix_add(&mut test_state, &icache());
+        assert_eq!(test_state.matrix_stack.size(), 2);
+        // (3x2) x (2x3)
+        matrix_matmul(&mut test_state, &icache());
+        assert_eq!(
+            test_state.matrix_stack.to_string(),
+            "[[5.000,11.000,17.000],[11.000,25.000,39.000],[17.000,39.000,61.000]]"
+        );
+        test_state.int_stack.push_vec(vec![3, 3]);
+        matrix_ones(&mut test_state, &icache());
+        matrix_add(&mut test_state, &icache());
+        test_state.int_stack.push_vec(vec![3, 3]);
+        matrix_zeros(&mut test_state, &icache());
+        matrix_mult(&mut test_state, &icache());
+        assert_eq!(
+            *test_state.matrix_stack.get(0).unwrap(),
+            FloatMatrix::filled(3, 3, 0.0)
+        );
+    }
+
+    #[test]
+    fn matrix_rand_respects_dimensions_and_size_cap() {
+        let mut test_state = PushState::new();
+        test_state.seed(506);
+        test_state.float_stack.push_vec(vec![0.5, 2.0]);
+        test_state.int_stack.push_vec(vec![4, 5]);
+        matrix_rand(&mut test_state, &icache());
+        let rand_matrix = test_state.matrix_stack.pop().unwrap();
+        assert_eq!((rand_matrix.rows, rand_matrix.cols), (4, 5));
+        assert_eq!(rand_matrix.values.len(), 20);
+        for sig in [f32::NAN, f32::INFINITY].iter() {
+            test_state.float_stack.push_vec(vec![*sig, 0.0]);
+            test_state.int_stack.push_vec(vec![2, 2]);
+            matrix_rand(&mut test_state, &icache());
+            assert_eq!(test_state.matrix_stack.size(), 0);
+            assert_eq!(test_state.float_stack.pop_vec(2).map(|f| f.len()), Some(2));
+            assert_eq!(test_state.int_stack.pop_vec(2), Some(vec![2, 2]));
+        }
+        test_state.configuration.max_vector_size = 10;
+        test_state.int_stack.push_vec(vec![4, 5]);
+        matrix_zeros(&mut test_state, &icache());
+        assert_eq!(test_state.matrix_stack.size(), 0);
+        assert_eq!(test_state.int_stack.to_string(), "5 4");
+    }
+
+    #[test]
+    fn matrix_values_must_fill_dimensions() {
+        assert!(FloatMatrix::new(2, 3, vec![0.0; 6]).is_some());
+        assert!(FloatMatrix::new(2, 3, vec![0.0; 5]).is_none());
+        assert!(FloatMatrix::new(usize::MAX, 2, vec![]).is_none());
+        let matrix: FloatMatrix =
+            serde_json::from_str(r#"{"rows": 1, "cols": 2, "values": [1.0, 2.0]}"#).unwrap();
+        assert_eq!(matrix.get(0, 1), Some(2.0));
+        assert!(serde_json::from_str::<FloatMatrix>(
+            r#"{"rows": 2, "cols": 2, "values": [1.0, 2.0]}"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::push::interpreter::PushInterpreterState;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Counters of a metrics sink.
+#[derive(Clone, Debug, Default)]
+struct Metrics {
+    evaluations: u64,
+    steps: u64,
+    // Termination reason => number of evaluations
+    terminations: BTreeMap<String, u64>,
+    // Instruction name => (executions, total execution time)
+    instructions: BTreeMap<String, (u64, Duration)>,
+}
+
+/// Thread-safe sink for operational metrics of the interpreter, e.g. in evolution
+/// services that evaluate programs over a long time. It counts evaluations, executed
+/// steps, terminations by reason and the execution times per instruction. The sink
+/// is filled by PushInterpreter::run_with_metrics and exported in the Prometheus
+/// text format with to_prometheus, so that it can be served by the metrics endpoint
+/// of the embedding application. All metrics carry the name of the experiment as
+/// label. Clones share the same metrics.
+#[derive(Clone, Debug)]
+pub struct MetricsSink {
+    experiment: String,
+    metrics: Arc<Mutex<Metrics>>,
+}
+
+impl MetricsSink {
+    pub fn new(experiment: &str) -> Self {
+        Self {
+            experiment: experiment.to_string(),
+            metrics: Arc::new(Mutex::new(Metrics::default())),
+        }
+    }
+
+    /// Returns the name of the experiment.
+    pub fn experiment(&self) -> &str {
+        &self.experiment
+    }
+
+    /// Records a program evaluation with its termination reason and number of steps.
+    pub fn record_evaluation(&self, termination: &PushInterpreterState, steps: usize) {
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.evaluations += 1;
+        metrics.steps += steps as u64;
+        *metrics
+            .terminations
+            .entry(termination_reason(termination))
+            .or_insert(0) += 1;
+    }
+
+    /// Records the number of executions and the total execution time of instructions,
+    /// e.g. the ones of a program evaluation. The sink is locked once for all of them.
+    pub fn record_instructions<'a>(
+        &self,
+        instructions: impl IntoIterator<Item = (&'a str, u64, Duration)>,
+    ) {
+        let mut metrics = self.metrics.lock().unwrap();
+        for (name, executions, duration) in instructions {
+            match metrics.instructions.get_mut(name) {
+                Some(entry) => {
+                    entry.0 += executions;
+                    entry.1 += duration;
+                }
+                None => {
+                    metrics
+                        .instructions
+                        .insert(name.to_string(), (executions, duration));
+                }
+            }
+        }
+    }
+
+    /// Returns the number of recorded evaluations.
+    pub fn evaluations(&self) -> u64 {
+        self.metrics.lock().unwrap().evaluations
+    }
+
+    /// Returns the number of recorded executions of the named instruction.
+    pub fn instruction_executions(&self, name: &str) -> u64 {
+        self.metrics
+            .lock()
+            .unwrap()
+            .instructions
+            .get(name)
+            .map_or(0, |(executions, _)| *executions)
+    }
+
+    /// Removes all collected metrics.
+    pub fn clear(&self) {
+        *self.metrics.lock().unwrap() = Metrics::default();
+    }
+
+    /// Exports the metrics in the Prometheus text exposition format:
+    ///
+    /// ```text
+    /// pushr_evaluations_total{experiment="x"} 2
+    /// pushr_steps_total{experiment="x"} 14
+    /// pushr_terminations_total{experiment="x",reason="step_limit_exceeded"} 1
+    /// pushr_instruction_seconds_sum{experiment="x",instruction="INTEGER.+"} 0.000002
+    /// pushr_instruction_seconds_count{experiment="x",instruction="INTEGER.+"} 3
+    /// ```
+    pub fn to_prometheus(&self) -> String {
+        let metrics = self.metrics.lock().unwrap();
+        let experiment = format!("experiment=\"{}\"", escape_label(&self.experiment));
+        let mut text = String::new();
+        let header = |name: &str, kind: &str, help: &str| {
+            format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind)
+        };
+        text += &header(
+            "pushr_evaluations_total",
+            "counter",
+            "Number of evaluated programs.",
+        );
+        text += &format!(
+            "pushr_evaluations_total{{{}}} {}\n",
+            experiment, metrics.evaluations
+        );
+        text += &header(
+            "pushr_steps_total",
+            "counter",
+            "Number of executed interpreter steps.",
+        );
+        text += &format!("pushr_steps_total{{{}}} {}\n", experiment, metrics.steps);
+        text += &header(
+            "pushr_terminations_total",
+            "counter",
+            "Number of evaluated programs by termination reason.",
+        );
+        for (reason, count) in metrics.terminations.iter() {
+            text += &format!(
+                "pushr_terminations_total{{{},reason=\"{}\"}} {}\n",
+                experiment, reason, count
+            );
+        }
+        text += &header(
+            "pushr_instruction_seconds",
+            "summary",
+            "Execution time of instructions in seconds.",
+        );
+        for (name, (executions, duration)) in metrics.instructions.iter() {
+            let labels = format!("{},instruction=\"{}\"", experiment, escape_label(name));
+            text += &format!(
+                "pushr_instruction_seconds_sum{{{}}} {:.9}\n",
+                labels,
+                duration.as_secs_f64()
+            );
+            text += &format!(
+                "pushr_instruction_seconds_count{{{}}} {}\n",
+                labels, executions
+            );
+        }
+        text
+    }
+}
+
+/// Returns the termination reason in snake case, e.g. step_limit_exceeded.
+fn termination_reason(termination: &PushInterpreterState) -> String {
+    let mut reason = String::new();
+    for (i, c) in format!("{:?}", termination).chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            reason.push('_');
+        }
+        reason.push(c.to_ascii_lowercase());
+    }
+    reason
+}
+
+/// Escapes backslashes, double quotes and line feeds of a label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::interpreter::PushInterpreter;
+    use crate::push::parser::PushParser;
+    use crate::push::state::PushState;
+
+    #[test]
+    fn evaluations_are_exported_as_prometheus_text() {
+        let metrics = MetricsSink::new("sym\"reg");
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        for program in ["( 1 2 INTEGER.+ 3 INTEGER.+ )", "( EXEC.Y INTEGER.+ )"] {
+            let mut push_state = PushState::new();
+            push_state.configuration.eval_push_limit = 20;
+            PushParser::parse_program(&mut push_state, &instruction_set, program);
+            PushInterpreter::run_with_metrics(&mut push_state, &mut instruction_set, &metrics);
+        }
+        assert_eq!(metrics.evaluations(), 2);
+        assert!(metrics.instruction_executions("INTEGER.+") > 2);
+        let text = metrics.to_prometheus();
+        assert!(text.contains("# TYPE pushr_evaluations_total counter\n"));
+        assert!(text.contains("pushr_evaluations_total{experiment=\"sym\\\"reg\"} 2\n"));
+        assert!(text.contains("reason=\"no_errors\"} 1\n"));
+        assert!(text.contains("reason=\"step_limit_exceeded\"} 1\n"));
+        assert!(text.contains("pushr_instruction_seconds_count{experiment=\"sym\\\"reg\",instruction=\"EXEC.Y\"}"));
+        metrics.clear();
+        assert_eq!(metrics.evaluations(), 0);
+    }
+}
@@ -1,10 +1,34 @@
+pub mod ancestry;
+pub mod archive;
+pub mod audit;
+pub mod batch;
+#[cfg(feature = "bignum")]
+pub mod bigint;
+pub mod bitmap;
+pub mod bitset;
 pub mod boolean;
 pub mod buffer;
+pub mod char;
+pub mod coexecution;
 pub mod code;
+pub mod comparison;
 pub mod configuration;
+pub mod constant;
+#[cfg(feature = "mmap")]
+pub mod dataset;
+pub mod differential;
+pub mod error;
+pub mod evaluator;
 pub mod execution;
+pub mod experiment;
+pub mod extract;
+pub mod fixture;
 pub mod float;
+pub mod genome;
 pub mod graph;
+pub mod graph_variation;
+pub mod hash;
+pub mod heatmap;
 pub mod index;
 pub mod instructions;
 pub mod integer;
@@ -12,10 +36,27 @@ pub mod interpreter;
 pub mod io;
 pub mod item;
 pub mod list;
+pub mod matrix;
+pub mod metrics;
 pub mod name;
 pub mod parser;
+pub mod pushgp;
 pub mod random;
+pub mod registers;
+pub mod runner;
+pub mod sandbox;
+pub mod schema;
+pub mod selection;
+pub mod serialize;
+pub mod shrink;
 pub mod stack;
 pub mod state;
+pub mod statistics;
+pub mod stream;
+pub mod string;
+pub mod tag;
+#[cfg(feature = "testkit")]
+pub mod testkit;
 pub mod topology;
 pub mod vector;
+pub mod vector_list;
@@ -11,14 +11,17 @@ use std::collections::HashMap;
 /// of any other type is considered a NAME and will be pushed onto the NAME stack when
 /// encountered, unless it has a definition (in which case its associated value will be pushed
 /// on the EXEC stack when it is encountered. The NAME.QUOTE instruction can be used to get a
-/// name that already has a definition onto the NAME stack.
+/// name that already has a definition onto the NAME stack. Alternatively, a NAME literal can be
+/// written with a leading quote (e.g. 'X) which is always pushed onto the NAME stack.
 pub fn load_name_instructions(map: &mut HashMap<String, Instruction>) {
     map.insert(String::from("NAME.="), Instruction::new(name_equal));
     map.insert(String::from("NAME.CAT"), Instruction::new(name_cat));
     map.insert(String::from("NAME.DUP"), Instruction::new(name_dup));
+    map.insert(String::from("NAME.EXPORT"), Instruction::new(name_export));
     map.insert(String::from("NAME.FLUSH"), Instruction::new(name_flush));
     map.insert(String::from("NAME.ID"), Instruction::new(name_id));
     map.insert(String::from("NAME.POP"), Instruction::new(name_pop));
+    map.insert(String::from("NAME.PREFIX*ALL"), Instruction::new(name_prefix_all));
     map.insert(String::from("NAME.QUOTE"), Instruction::new(name_quote));
     map.insert(String::from("NAME.RAND"), Instruction::new(name_rand));
     map.insert(
@@ -71,6 +74,19 @@ pub fn name_dup(push_state: &mut PushState, _instruction_cache: &InstructionCach
     }
 }
 
+/// NAME.EXPORT: Keeps the current binding of the top NAME when the enclosing binding scopes are
+/// left, i.e. the binding becomes global. Acts as NOOP if the name is not bound.
+pub fn name_export(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(name) = push_state.name_stack.get(0) {
+        if let Some(item) = push_state.name_bindings.get(name) {
+            for (_, saved_bindings) in push_state.binding_scopes.iter_mut() {
+                saved_bindings.insert(name.clone(), item.clone());
+            }
+            push_state.name_stack.pop();
+        }
+    }
+}
+
 /// NAME.FLUSH: Empties the NAME stack.
 pub fn name_flush(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     push_state.name_stack.flush();
@@ -81,6 +97,14 @@ pub fn name_pop(push_state: &mut PushState, _instruction_cache: &InstructionCach
     push_state.name_stack.pop();
 }
 
+/// NAME.PREFIX*ALL: Prepends the top item of the NAME stack to the names of all bindings.
+/// References to the bindings inside the bound items are renamed accordingly.
+pub fn name_prefix_all(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(prefix) = push_state.name_stack.pop() {
+        push_state.namespace_bindings(&prefix);
+    }
+}
+
 /// NAME.QUOTE: Sets a flag indicating that the next name encountered will be pushed onto the NAME
 /// stack (and not have its associated value pushed onto the EXEC stack), regardless of whether or
 /// not it has a definition. Upon encountering such a name and pushing it onto the NAME stack the
@@ -91,14 +115,14 @@ pub fn name_quote(push_state: &mut PushState, _instruction_cache: &InstructionCa
 
 /// NAME.RAND: Pushes a newly generated random NAME.
 pub fn name_rand(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    push_state.name_stack.push(CodeGenerator::new_random_name());
+    let rand_name = CodeGenerator::new_random_name(&mut push_state.rng);
+    push_state.name_stack.push(rand_name);
 }
 
 /// NAME.RANDBOUNDNAME: Pushes a randomly selected NAME that already has a definition.
 pub fn name_rand_bound(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    push_state
-        .name_stack
-        .push(CodeGenerator::existing_random_name(push_state));
+    let rand_name = CodeGenerator::existing_random_name(push_state);
+    push_state.name_stack.push(rand_name);
 }
 
 /// NAME.ROT: Rotates the top three items on the NAME stack, pulling the third item out and pushing
@@ -165,12 +189,75 @@ pub fn name_yank_dup(push_state: &mut PushState, _instruction_cache: &Instructio
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::interpreter::PushInterpreter;
     use crate::push::item::Item;
+    use crate::push::parser::PushParser;
 
     pub fn icache() -> InstructionCache {
         InstructionCache::new(vec![])
     }
 
+    #[test]
+    fn scoped_bindings_are_restored_unless_exported() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program = "( F X Y )";
+        let definition = "( 'X 5 INTEGER.DEFINE 'Y 6 INTEGER.DEFINE 'Y NAME.EXPORT )";
+        for scoped_bindings in [false, true] {
+            let mut push_state = PushState::new();
+            push_state.configuration.scoped_bindings = scoped_bindings;
+            PushParser::parse_program(&mut push_state, &instruction_set, definition);
+            let body = push_state.exec_stack.pop().unwrap();
+            push_state.name_bindings.insert("F".to_string(), body);
+            PushParser::parse_program(&mut push_state, &instruction_set, program);
+            PushInterpreter::run(&mut push_state, &mut instruction_set);
+            assert!(push_state.name_bindings.contains_key("Y"));
+            assert_eq!(push_state.name_bindings.contains_key("X"), !scoped_bindings);
+            assert!(push_state.binding_scopes.is_empty());
+            if scoped_bindings {
+                assert_eq!(push_state.int_stack.to_string(), "6");
+                assert_eq!(push_state.name_stack.to_string(), "X");
+            } else {
+                assert_eq!(push_state.int_stack.to_string(), "6 5");
+            }
+        }
+    }
+
+    #[test]
+    fn binding_scope_ends_when_its_items_leave_exec() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        for definition in &[
+            "( 'X 5 INTEGER.DEFINE EXEC.POP )",
+            "( 'X 5 INTEGER.DEFINE EXEC.FLUSH )",
+        ] {
+            let mut push_state = PushState::new();
+            push_state.configuration.scoped_bindings = true;
+            PushParser::parse_program(&mut push_state, &instruction_set, definition);
+            let body = push_state.exec_stack.pop().unwrap();
+            push_state.name_bindings.insert("F".to_string(), body);
+            PushParser::parse_program(&mut push_state, &instruction_set, "( F 7 X )");
+            PushInterpreter::run(&mut push_state, &mut instruction_set);
+            assert!(!push_state.name_bindings.contains_key("X"), "{}", definition);
+            assert!(push_state.binding_scopes.is_empty());
+        }
+        assert!(!instruction_set.is_instruction("NAME.ENDSCOPE"));
+    }
+
+    #[test]
+    fn name_prefix_all_renames_bindings() {
+        let mut test_state = PushState::new();
+        test_state.name_bindings.insert("A".to_string(), Item::int(1));
+        test_state.name_bindings.insert("B".to_string(), Item::int(2));
+        test_state.name_stack.push("MOD*".to_string());
+        name_prefix_all(&mut test_state, &icache());
+        let mut names: Vec<&String> = test_state.name_bindings.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["MOD*A", "MOD*B"]);
+        assert_eq!(test_state.name_stack.size(), 0);
+    }
+
     #[test]
     fn name_cat_appends_second_item() {
         let mut test_state = PushState::new();
@@ -216,7 +303,7 @@ mod tests {
         let mut test_state = PushState::new();
         test_state
             .name_bindings
-            .insert(CodeGenerator::new_random_name(), Item::int(1));
+            .insert(CodeGenerator::new_random_name(&mut rand::thread_rng()), Item::int(1));
         name_rand_bound(&mut test_state, &icache());
         assert_eq!(test_state.name_stack.size(), 1);
     }
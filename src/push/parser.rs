@@ -1,11 +1,86 @@
+use crate::push::char::parse_char_literal;
 use crate::push::instructions::InstructionSet;
 use crate::push::item::Item;
 use crate::push::stack::PushStack;
 use crate::push::state::PushState;
+use crate::push::tag::TagInstruction;
 use crate::push::vector::{BoolVector, FloatVector, IntVector};
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
+use std::fmt;
 
 pub struct PushParser {}
 
+/// Kind of a syntax error in a program.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    // Closing parenthesis without matching opening parenthesis
+    UnmatchedClose,
+    // Opening parenthesis that is never closed
+    UnclosedList,
+    // Vector literal with missing bracket or inconsistent elements
+    InvalidVector,
+}
+
+/// Syntax error located in the program text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    // Byte offset of the offending token in the program text
+    pub offset: usize,
+    // Length of the offending token in bytes
+    pub len: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self.kind {
+            ParseErrorKind::UnmatchedClose => "unmatched closing parenthesis",
+            ParseErrorKind::UnclosedList => "unclosed list",
+            ParseErrorKind::InvalidVector => "invalid vector literal",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl ParseError {
+    /// Renders the error together with the line of the source that contains it. The
+    /// offending token is underlined with carets, e.g.
+    ///
+    /// ```text
+    /// error: unmatched closing parenthesis
+    ///  --> 1:9
+    ///   |
+    /// 1 | ( 1 2 ) )
+    ///   |         ^
+    /// ```
+    pub fn render_diagnostic(&self, source: &str) -> String {
+        let offset = usize::min(self.offset, source.len());
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |i| offset + i);
+        let line_number = source[..offset].matches('\n').count() + 1;
+        let column = source[line_start..offset].chars().count();
+        let token_end = usize::min(offset + self.len, line_end);
+        let width = usize::max(source[offset..token_end].chars().count(), 1);
+        let gutter = " ".repeat(line_number.to_string().len());
+        format!(
+            "error: {}\n{}--> {}:{}\n{} |\n{} | {}\n{} | {}{}\n",
+            self,
+            gutter,
+            line_number,
+            column + 1,
+            gutter,
+            line_number,
+            &source[line_start..line_end],
+            gutter,
+            " ".repeat(column),
+            "^".repeat(width)
+        )
+    }
+}
+
 #[derive(Debug)]
 pub enum VectorType {
     Bool,
@@ -57,13 +132,19 @@ impl PushParser {
         vector_type: &VectorType,
         vector_token: &str,
     ) {
+        // An empty vector is written without elements, e.g. INT[]
+        let elements: Vec<&str> = if vector_token.is_empty() {
+            vec![]
+        } else {
+            vector_token.split(',').collect()
+        };
         match vector_type {
             VectorType::Bool => {
                 let mut bv = vec![];
-                for el in vector_token.split(",") {
-                    if "1" == el || "true" == el {
+                for el in elements.iter() {
+                    if "1" == *el || "true" == *el {
                         bv.push(true);
-                    } else if "0" == el || "false" == el {
+                    } else if "0" == *el || "false" == *el {
                         bv.push(false);
                     } else {
                         return;
@@ -77,7 +158,7 @@ impl PushParser {
             }
             VectorType::Int => {
                 let mut iv = vec![];
-                for el in vector_token.split(",") {
+                for el in elements.iter() {
                     match el.to_string().parse::<i32>() {
                         Ok(ival) => iv.push(ival),
                         Err(_) => return,
@@ -91,7 +172,7 @@ impl PushParser {
             }
             VectorType::Float => {
                 let mut fv = vec![];
-                for el in vector_token.split(",") {
+                for el in elements.iter() {
                     match el.to_string().parse::<f32>() {
                         Ok(fval) => fv.push(fval),
                         Err(_) => return,
@@ -106,6 +187,62 @@ impl PushParser {
         }
     }
 
+    /// Returns true if the elements of the vector literal between the brackets
+    /// are consistent with the vector type. Empty vectors are valid.
+    fn is_valid_vector(vector_type: &VectorType, vector_token: &str) -> bool {
+        vector_token.is_empty() || vector_token.split(',').all(|el| match vector_type {
+            VectorType::Bool => ["1", "0", "true", "false"].contains(&el),
+            VectorType::Int => el.parse::<i32>().is_ok(),
+            VectorType::Float => el.parse::<f32>().is_ok(),
+        })
+    }
+
+    /// Checks the syntax of the program without parsing it. Returns all errors in
+    /// the order of their occurrence.
+    pub fn check_program(code: &str) -> Vec<ParseError> {
+        let mut errors = vec![];
+        let mut open_lists = vec![];
+        for token in code.split_whitespace() {
+            let offset = token.as_ptr() as usize - code.as_ptr() as usize;
+            let error = |kind| ParseError {
+                kind,
+                offset,
+                len: token.len(),
+            };
+            let vector = [
+                ("INT[", VectorType::Int),
+                ("FLOAT[", VectorType::Float),
+                ("BOOL[", VectorType::Bool),
+            ]
+            .iter()
+            .find(|(prefix, _)| token.starts_with(prefix));
+            if let Some((prefix, vector_type)) = vector {
+                if token.len() <= prefix.len()
+                    || !token.ends_with(']')
+                    || !PushParser::is_valid_vector(
+                        vector_type,
+                        &token[prefix.len()..token.len() - 1],
+                    )
+                {
+                    errors.push(error(ParseErrorKind::InvalidVector));
+                }
+            } else if "(" == token {
+                open_lists.push(offset);
+            } else if ")" == token && open_lists.pop().is_none() {
+                errors.push(error(ParseErrorKind::UnmatchedClose));
+            }
+        }
+        for offset in open_lists {
+            errors.push(ParseError {
+                kind: ParseErrorKind::UnclosedList,
+                offset,
+                len: 1,
+            });
+        }
+        errors.sort_by_key(|e| e.offset);
+        errors
+    }
+
     /// Splits a string into tokens and front pushes it to the stack s.t. the
     /// end of the string ends up at the top of the stack.
     pub fn parse_program(push_state: &mut PushState, instruction_set: &InstructionSet, code: &str) {
@@ -151,16 +288,19 @@ impl PushParser {
                 continue;
             }
             if ")" == token {
-                // End of (sub) list
-                depth -= 1;
+                // End of (sub) list. Unmatched parentheses are ignored (see check_program)
+                depth = depth.saturating_sub(1);
                 continue;
             }
 
             // Check for instruction
-            if instruction_set.is_instruction(token) {
+            if instruction_set.is_instruction(token) || TagInstruction::parse(token).is_some() {
                 PushParser::rec_push(
                     &mut push_state.exec_stack,
-                    Item::instruction(token.to_string()),
+                    Item::InstructionMeta {
+                        name: token.to_string(),
+                        id: instruction_set.instruction_id(token),
+                    },
                     depth,
                 );
                 continue;
@@ -173,6 +313,12 @@ impl PushParser {
                 }
                 Err(_) => (),
             }
+            // Integer literals that do not fit into an INTEGER
+            #[cfg(feature = "bignum")]
+            if let Ok(bval) = token.parse::<BigInt>() {
+                PushParser::rec_push(&mut push_state.exec_stack, Item::bigint(bval), depth);
+                continue;
+            }
             match token.to_string().parse::<f32>() {
                 Ok(fval) => {
                     PushParser::rec_push(&mut push_state.exec_stack, Item::float(fval), depth);
@@ -181,6 +327,11 @@ impl PushParser {
                 Err(_) => (),
             }
 
+            if let Some(cval) = parse_char_literal(token) {
+                PushParser::rec_push(&mut push_state.exec_stack, Item::char(cval), depth);
+                continue;
+            }
+
             match token {
                 "TRUE" => {
                     PushParser::rec_push(&mut push_state.exec_stack, Item::bool(true), depth);
@@ -191,11 +342,13 @@ impl PushParser {
                     continue;
                 }
                 &_ => {
-                    PushParser::rec_push(
-                        &mut push_state.exec_stack,
-                        Item::name(token.to_string()),
-                        depth,
-                    );
+                    // Quoted tokens are NAME literals, all others identifiers
+                    let item = if token.len() > 1 && token.starts_with('\'') {
+                        Item::quoted_name(token[1..].to_string())
+                    } else {
+                        Item::id(token.to_string())
+                    };
+                    PushParser::rec_push(&mut push_state.exec_stack, item, depth);
                 }
             }
         }
@@ -215,6 +368,21 @@ mod tests {
         assert_eq!(push_state.exec_stack.to_string(), "( 2 3 INTEGER.* 4.100 5.200 FLOAT.+ TRUE FALSE BOOLEAN.OR )");
     }
 
+    #[test]
+    fn parse_names_and_identifiers() {
+        let input = "( 'X X ' )";
+        let mut push_state = PushState::new();
+        let instruction_set = InstructionSet::new();
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        assert_eq!(push_state.exec_stack.to_string(), "( 'X X ' )");
+        let mut program = push_state.exec_stack.pop().unwrap();
+        if let Item::List { items } = &mut program {
+            assert!(Item::equals(&items.pop().unwrap(), &Item::quoted_name("X".to_string())));
+            assert!(Item::equals(&items.pop().unwrap(), &Item::id("X".to_string())));
+            assert!(Item::equals(&items.pop().unwrap(), &Item::id("'".to_string())));
+        }
+    }
+
     #[test]
     pub fn parse_potentiation_program() {
         let input = "( ARG FLOAT.DEFINE EXEC.Y ( ARG FLOAT.* 1 INTEGER.- INTEGER.DUP 0 INTEGER.> EXEC.IF ( ) EXEC.POP ) ) ";
@@ -267,4 +435,28 @@ mod tests {
             "( [1,2,3] )"
         );
     }
+
+    #[test]
+    fn check_program_locates_syntax_errors() {
+        let program = "( 1 2 INTEGER.+ )\n( INT[1,a] ) )\n( BOOL[1,0]";
+        let errors = PushParser::check_program(program);
+        let kinds: Vec<ParseErrorKind> = errors.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ParseErrorKind::InvalidVector,
+                ParseErrorKind::UnmatchedClose,
+                ParseErrorKind::UnclosedList
+            ]
+        );
+        assert_eq!(
+            errors[0].render_diagnostic(program),
+            "error: invalid vector literal\n --> 2:3\n  |\n2 | ( INT[1,a] ) )\n  |   ^^^^^^^^\n"
+        );
+        assert_eq!(
+            errors[1].render_diagnostic(program),
+            "error: unmatched closing parenthesis\n --> 2:14\n  |\n2 | ( INT[1,a] ) )\n  |              ^\n"
+        );
+        assert!(PushParser::check_program("( 1 ( 2.0 FLOAT[1.5] X ) )").is_empty());
+    }
 }
@@ -0,0 +1,479 @@
+use crate::push::ancestry::{Ancestry, Lineage};
+use crate::push::configuration::PushConfiguration;
+use crate::push::evaluator::{derive_seed, program_hash};
+use crate::push::experiment::InstructionOptions;
+use crate::push::instructions::{InstructionCache, InstructionSet};
+use crate::push::interpreter::PushInterpreter;
+use crate::push::item::Item;
+use crate::push::random::CodeGenerator;
+use crate::push::selection::{Selection, Selector};
+use crate::push::state::PushState;
+use crate::push::vector::FloatVector;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Parameters of a PushGP run, e.g.
+///
+/// ```toml
+/// population_size = 200
+/// generations = 50
+/// selection = { Tournament = { size = 7 } }
+///
+/// [instructions]
+/// include = ["INTEGER.", "EXEC."]
+/// ```
+///
+/// The probabilities of the variation operators should sum up to at most 1. The
+/// remaining children are copies of their parents (reproduction).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PushGpConfig {
+    pub population_size: usize,
+    // Number of generations after the initial population
+    pub generations: usize,
+    // The maximum number of points of the programs of the initial population
+    pub max_initial_points: usize,
+    // The maximum number of points of the programs created by variation
+    pub max_points: usize,
+    pub selection: Selection,
+    // Probabilities of the variation operators (see CodeGenerator)
+    pub subtree_crossover: f32,
+    pub alternation_crossover: f32,
+    pub point_mutation: f32,
+    pub uniform_mutation: f32,
+    // Probability to switch the parent and standard deviation of the switch position
+    // of the alternation crossover
+    pub alternation_rate: f32,
+    pub alignment_deviation: f32,
+    // Probability to replace a point by the uniform mutation
+    pub uniform_mutation_rate: f32,
+    // The run stops as soon as a program has a total error of at most this threshold
+    pub error_threshold: f64,
+    // Seed of the variation and of the evaluations (see evaluator::derive_seed)
+    pub seed: u64,
+    // Number of threads that evaluate the population
+    pub threads: usize,
+    // Interpreter configuration of the evaluations
+    pub configuration: PushConfiguration,
+    // Instructions of the programs. The input instructions IN0..INn are added for the
+    // inputs of the test cases.
+    pub instructions: InstructionOptions,
+}
+
+impl PushGpConfig {
+    pub fn new() -> Self {
+        Self {
+            population_size: 200,
+            generations: 50,
+            max_initial_points: 50,
+            max_points: 100,
+            selection: Selection::Tournament { size: 7 },
+            subtree_crossover: 0.4,
+            alternation_crossover: 0.2,
+            point_mutation: 0.2,
+            uniform_mutation: 0.1,
+            alternation_rate: 0.05,
+            alignment_deviation: 1.0,
+            uniform_mutation_rate: 0.05,
+            error_threshold: 0.0,
+            seed: 0,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            configuration: PushConfiguration::new(),
+            instructions: InstructionOptions::default(),
+        }
+    }
+}
+
+impl Default for PushGpConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Test case with the inputs that are available to the program through the input
+/// instructions (see registers.rs) and the expected outputs.
+#[derive(Clone, Debug, Default)]
+pub struct TestCase {
+    pub inputs: Vec<Item>,
+    pub outputs: Vec<Item>,
+}
+
+/// Evaluated program.
+#[derive(Clone, Debug)]
+pub struct Individual {
+    pub program: Item,
+    // Error per test case
+    pub errors: FloatVector,
+    pub total_error: f64,
+}
+
+/// Statistics of an evaluated generation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenerationStatistics {
+    pub generation: usize,
+    pub best_total_error: f64,
+    pub average_total_error: f64,
+    pub average_size: f64,
+    // The program with the lowest total error
+    pub best_program: String,
+}
+
+/// Outcome of a PushGP run.
+#[derive(Debug)]
+pub struct PushGpResult {
+    // The individual with the lowest total error of all generations
+    pub best: Individual,
+    // True if the best individual reached the error threshold
+    pub solved: bool,
+    pub generations: Vec<GenerationStatistics>,
+    // Ancestry of the programs created by the variation operators
+    pub lineage: Lineage,
+}
+
+/// Evolution loop of PushGP: Creates a random population, evaluates it on the test
+/// cases, selects parents and creates the next generation with the variation
+/// operators of CodeGenerator until the error threshold or the number of generations
+/// is reached.
+pub struct PushGp<E>
+where
+    E: Fn(&TestCase, &PushState) -> f64 + Sync,
+{
+    pub config: PushGpConfig,
+    pub cases: Vec<TestCase>,
+    // Maps a test case and the final state of the program to the error of the
+    // program on this case
+    error: E,
+}
+
+impl<E> PushGp<E>
+where
+    E: Fn(&TestCase, &PushState) -> f64 + Sync,
+{
+    pub fn new(config: PushGpConfig, cases: Vec<TestCase>, error: E) -> Self {
+        Self {
+            config,
+            cases,
+            error,
+        }
+    }
+
+    /// Returns the instruction set of the programs.
+    fn instruction_set(&self) -> InstructionSet {
+        let mut instruction_set = self.config.instructions.instruction_set();
+        let inputs = self.cases.iter().map(|c| c.inputs.len()).max().unwrap_or(0);
+        instruction_set.load_input_instructions(inputs);
+        instruction_set
+    }
+
+    /// Runs the program on each test case and returns the errors. The state of each
+    /// run is seeded with derive_seed, so that the errors are reproducible. An error
+    /// that is not a number counts as infinite error.
+    fn errors(&self, program: &Item, instruction_set: &mut InstructionSet) -> FloatVector {
+        let hash = program_hash(&program.to_string());
+        let errors = self
+            .cases
+            .iter()
+            .enumerate()
+            .map(|(index, case)| {
+                let mut push_state = PushState::new();
+                push_state.configuration = self.config.configuration.clone();
+                push_state.seed(derive_seed(self.config.seed, index, hash));
+                push_state.inputs = case.inputs.clone();
+                push_state.exec_stack.push(program.clone());
+                PushInterpreter::run(&mut push_state, instruction_set);
+                let error = (self.error)(case, &push_state) as f32;
+                if error.is_nan() {
+                    f32::INFINITY
+                } else {
+                    error
+                }
+            })
+            .collect();
+        FloatVector::new(errors)
+    }
+
+    /// Evaluates the programs on a pool of worker threads. Each worker claims the
+    /// next program that has not been evaluated yet. If the evaluation of a program
+    /// panics, the program gets infinite error on all test cases and the worker
+    /// continues with a fresh instruction set.
+    pub fn evaluate(&self, programs: Vec<Item>) -> Vec<Individual> {
+        let threads = usize::min(usize::max(self.config.threads, 1), usize::max(programs.len(), 1));
+        let next_program = AtomicUsize::new(0);
+        let errors = Mutex::new(Vec::with_capacity(programs.len()));
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    let mut instruction_set = self.instruction_set();
+                    loop {
+                        let index = next_program.fetch_add(1, Ordering::SeqCst);
+                        if index >= programs.len() {
+                            break;
+                        }
+                        let program_errors = panic::catch_unwind(AssertUnwindSafe(|| {
+                            self.errors(&programs[index], &mut instruction_set)
+                        }))
+                        .unwrap_or_else(|_| {
+                            instruction_set = self.instruction_set();
+                            FloatVector::new(vec![f32::INFINITY; self.cases.len()])
+                        });
+                        errors.lock().unwrap().push((index, program_errors));
+                    }
+                });
+            }
+        });
+        let mut errors = errors.into_inner().unwrap();
+        errors.sort_by_key(|(index, _)| *index);
+        programs
+            .into_iter()
+            .zip(errors)
+            .map(|(program, (_, errors))| Individual {
+                total_error: errors.values.iter().map(|e| *e as f64).sum(),
+                program,
+                errors,
+            })
+            .collect()
+    }
+
+    /// Creates a child by applying a randomly chosen variation operator to selected
+    /// parents. Returns the child with its ancestry.
+    fn breed(
+        &self,
+        push_state: &mut PushState,
+        instructions: &InstructionCache,
+        population: &[Individual],
+        selector: &Selector,
+    ) -> (Item, Ancestry) {
+        let config = &self.config;
+        let select = |push_state: &mut PushState| {
+            selector
+                .select(&mut push_state.rng)
+                .map_or_else(Item::empty_list, |i| population[i].program.clone())
+        };
+        let parent = select(push_state);
+        let mut operator = push_state.rng.gen::<f32>();
+        if operator < config.subtree_crossover {
+            let other = select(push_state);
+            let (child, points) =
+                CodeGenerator::subtree_crossover_with_points(push_state, &parent, &other, config.max_points);
+            return (child, Ancestry::new(&[&parent, &other], "subtree_crossover", points));
+        }
+        operator -= config.subtree_crossover;
+        if operator < config.alternation_crossover {
+            let other = select(push_state);
+            let child = CodeGenerator::alternation_crossover(
+                push_state,
+                &parent,
+                &other,
+                config.alternation_rate,
+                config.alignment_deviation,
+                config.max_points,
+            );
+            return (child, Ancestry::new(&[&parent, &other], "alternation_crossover", vec![]));
+        }
+        operator -= config.alternation_crossover;
+        if operator < config.point_mutation {
+            let (child, points) =
+                CodeGenerator::point_mutation_with_points(push_state, instructions, &parent, config.max_points);
+            return (child, Ancestry::new(&[&parent], "point_mutation", points));
+        }
+        operator -= config.point_mutation;
+        if operator < config.uniform_mutation {
+            let child = CodeGenerator::uniform_mutation(
+                push_state,
+                instructions,
+                &parent,
+                config.uniform_mutation_rate,
+            );
+            return (child, Ancestry::new(&[&parent], "uniform_mutation", vec![]));
+        }
+        let ancestry = Ancestry::new(&[&parent], "reproduction", vec![]);
+        (parent, ancestry)
+    }
+
+    /// Runs the evolution and returns the best individual with the statistics of
+    /// each generation. The callback is invoked after each evaluated generation,
+    /// e.g. to report the progress.
+    pub fn run<F>(&self, mut on_generation: F) -> PushGpResult
+    where
+        F: FnMut(&GenerationStatistics),
+    {
+        let instruction_set = self.instruction_set();
+        let instructions = instruction_set.cache();
+        // The state only serves as random number generator of the variation
+        let mut push_state = PushState::new();
+        push_state.configuration = self.config.configuration.clone();
+        push_state.seed(self.config.seed);
+        let mut programs: Vec<Item> = (0..self.config.population_size)
+            .map(|_| {
+                CodeGenerator::random_code(&mut push_state, &instructions, self.config.max_initial_points)
+                    .unwrap_or_else(Item::empty_list)
+            })
+            .collect();
+        let mut best: Option<Individual> = None;
+        let mut statistics = vec![];
+        let mut lineage = Lineage::new();
+        for generation in 0..=self.config.generations {
+            let population = self.evaluate(programs);
+            if population.is_empty() {
+                break;
+            }
+            let generation_best = population
+                .iter()
+                .min_by(|a, b| a.total_error.total_cmp(&b.total_error))
+                .unwrap();
+            let n = population.len() as f64;
+            let generation_statistics = GenerationStatistics {
+                generation,
+                best_total_error: generation_best.total_error,
+                average_total_error: population.iter().map(|i| i.total_error).sum::<f64>() / n,
+                average_size: population.iter().map(|i| Item::size(&i.program) as f64).sum::<f64>() / n,
+                best_program: generation_best.program.to_string(),
+            };
+            on_generation(&generation_statistics);
+            statistics.push(generation_statistics);
+            if best
+                .as_ref()
+                .map_or(true, |b| generation_best.total_error < b.total_error)
+            {
+                best = Some(generation_best.clone());
+            }
+            if generation_best.total_error <= self.config.error_threshold
+                || generation == self.config.generations
+            {
+                break;
+            }
+            let selector = Selector::new(
+                self.config.selection,
+                population.iter().map(|i| i.errors.clone()).collect(),
+            );
+            programs = (0..self.config.population_size)
+                .map(|_| {
+                    let (child, ancestry) =
+                        self.breed(&mut push_state, &instructions, &population, &selector);
+                    lineage.record(&child, ancestry);
+                    child
+                })
+                .collect();
+        }
+        let best = best.unwrap_or(Individual {
+            program: Item::empty_list(),
+            errors: FloatVector::new(vec![]),
+            total_error: f64::INFINITY,
+        });
+        PushGpResult {
+            solved: best.total_error <= self.config.error_threshold,
+            best,
+            generations: statistics,
+            lineage,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::item::PushType;
+
+    fn cases() -> Vec<TestCase> {
+        (0..5)
+            .map(|x| TestCase {
+                inputs: vec![Item::int(x)],
+                outputs: vec![Item::int(3 * x)],
+            })
+            .collect()
+    }
+
+    fn int_error(case: &TestCase, push_state: &PushState) -> f64 {
+        match (case.outputs.first(), push_state.int_stack.get(0)) {
+            (
+                Some(Item::Literal {
+                    push_type: PushType::Int { val },
+                }),
+                Some(actual),
+            ) => (*val as f64 - *actual as f64).abs(),
+            _ => 1000.0,
+        }
+    }
+
+    #[test]
+    fn evolution_finds_tripling_function() {
+        let mut config = PushGpConfig::new();
+        config.population_size = 100;
+        config.generations = 30;
+        config.max_initial_points = 10;
+        config.max_points = 30;
+        config.threads = 2;
+        config.seed = 11;
+        config.instructions.include = vec!["INTEGER.+".to_string(), "INTEGER.DUP".to_string()];
+        let push_gp = PushGp::new(config, cases(), int_error);
+        let mut reported = 0;
+        let result = push_gp.run(|_| reported += 1);
+        assert_eq!(reported, result.generations.len());
+        assert!(result.solved, "{:?}", result.generations.last());
+        assert_eq!(result.best.errors, FloatVector::new(vec![0.0; 5]));
+        // The best total error of a generation is never below the overall best
+        assert!(result
+            .generations
+            .iter()
+            .all(|g| g.best_total_error >= result.best.total_error));
+        assert!(result.lineage.size() > 0);
+    }
+
+    #[test]
+    fn bred_children_carry_their_ancestry() {
+        let mut config = PushGpConfig::new();
+        config.subtree_crossover = 1.0;
+        config.instructions.include = vec!["INTEGER.+".to_string()];
+        let push_gp = PushGp::new(config, cases(), int_error);
+        let instructions = push_gp.instruction_set().cache();
+        let mut push_state = PushState::new();
+        push_state.seed(461);
+        let population = push_gp.evaluate(vec![
+            Item::list(vec![Item::int(1), Item::int(2)]),
+            Item::list(vec![Item::int(3), Item::int(4)]),
+        ]);
+        let selector = Selector::new(
+            Selection::Tournament { size: 1 },
+            population.iter().map(|i| i.errors.clone()).collect(),
+        );
+        let hashes: Vec<u64> = population
+            .iter()
+            .map(|i| i.program.hash_structural())
+            .collect();
+        for _ in 0..10 {
+            let (_, ancestry) = push_gp.breed(&mut push_state, &instructions, &population, &selector);
+            assert_eq!(ancestry.operator, "subtree_crossover");
+            assert_eq!(ancestry.parents.len(), 2);
+            assert!(ancestry.parents.iter().all(|p| hashes.contains(p)));
+            assert_eq!(ancestry.crossover_points.len(), 2);
+        }
+    }
+
+    #[test]
+    fn panics_and_nan_errors_count_as_infinite_error() {
+        let mut config = PushGpConfig::new();
+        config.threads = 2;
+        let push_gp = PushGp::new(config, cases(), |_: &TestCase, push_state: &PushState| {
+            match push_state.int_stack.get(0) {
+                Some(1) => f64::NAN,
+                Some(_) => 0.0,
+                None => panic!("no result"),
+            }
+        });
+        let programs = vec![
+            Item::empty_list(),
+            Item::list(vec![Item::int(1)]),
+            Item::list(vec![Item::int(2)]),
+        ];
+        let total_errors: Vec<f64> = push_gp
+            .evaluate(programs)
+            .iter()
+            .map(|i| i.total_error)
+            .collect();
+        assert_eq!(total_errors, vec![f64::INFINITY, f64::INFINITY, 0.0]);
+    }
+}
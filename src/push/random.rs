@@ -1,13 +1,17 @@
 extern crate names;
 
+use crate::push::genome::Genome;
 use crate::push::instructions::InstructionCache;
 use crate::push::item::Item;
+use crate::push::stack::PushStack;
 use crate::push::state::PushState;
+use crate::push::tag::TagInstruction;
 use crate::push::vector::{BoolVector, FloatVector, IntVector};
-use names::Generator;
-use rand::distributions::{Distribution, Standard, Uniform};
+use names::{ADJECTIVES, NOUNS};
+use rand::distributions::weighted::WeightedError;
+use rand::distributions::{Bernoulli, Distribution, Standard, Uniform, WeightedIndex};
 use rand::Rng;
-use rand_distr::Normal;
+use rand_distr::{Exp, Normal, Poisson};
 
 /// Item types without list
 pub enum ItemType {
@@ -33,18 +37,47 @@ impl Distribution<ItemType> for Standard {
     }
 }
 
+/// Instruction distribution that is built once per generated program
+enum InstructionSelection {
+    Weighted(WeightedIndex<f32>),
+    // Weights are unusable: every instruction is equally likely
+    Uniform,
+    // No instructions or all weights zero: no instruction can be selected
+    Nothing,
+}
+
+impl InstructionSelection {
+    fn new(push_state: &PushState, instructions: &InstructionCache) -> Self {
+        let weights: Vec<f32> = instructions
+            .list
+            .iter()
+            .map(|name| instructions.weight(push_state, name))
+            .collect();
+        // WeightedIndex panics if the sum of the weights overflows
+        if !weights.iter().sum::<f32>().is_finite() {
+            return InstructionSelection::Uniform;
+        }
+        match WeightedIndex::new(weights) {
+            Ok(dist) => InstructionSelection::Weighted(dist),
+            Err(WeightedError::NoItem) | Err(WeightedError::AllWeightsZero) => {
+                InstructionSelection::Nothing
+            }
+            Err(_) => InstructionSelection::Uniform,
+        }
+    }
+}
+
 pub struct CodeGenerator {}
 
 impl CodeGenerator {
     /// Returns random code of random size but smaller than max_points
     pub fn random_code<'a>(
-        push_state: &PushState,
+        push_state: &mut PushState,
         instructions: &InstructionCache,
         max_points: usize,
     ) -> Option<Item> {
-        if max_points > 0 {
-            let mut rng = rand::thread_rng();
-            let actual_points = Uniform::from(1..max_points).sample(&mut rng);
+        if max_points > 1 {
+            let actual_points = Uniform::from(1..max_points).sample(&mut push_state.rng);
             Some(CodeGenerator::random_code_with_size(
                 push_state,
                 instructions,
@@ -56,11 +89,10 @@ impl CodeGenerator {
     }
 
     /// Returns a random boolean vector of given size and sparcity
-    pub fn random_bool_vector(size: i32, sparsity: f32) -> Option<BoolVector> {
+    pub fn random_bool_vector<R: Rng>(rng: &mut R, size: i32, sparsity: f32) -> Option<BoolVector> {
         if size < 0 || sparsity < 0.0 || sparsity > 1.0 {
             None
         } else {
-            let mut rng = rand::thread_rng();
             // default = false when less than half of the bits should be active
             // sparcity = portion of non-default values
             let default = sparsity > 0.5;
@@ -83,41 +115,86 @@ impl CodeGenerator {
 
     /// Returns a random float vector. Its elements are independent and identically distributed
     /// random variables drawn from the normal distribution with given mean and standard
-    /// deviation.
-    pub fn random_float_vector(size: i32, mean: f32, stddev: f32) -> Option<FloatVector> {
+    /// deviation. Returns None if the standard deviation is negative or not finite.
+    pub fn random_float_vector<R: Rng>(
+        rng: &mut R,
+        size: i32,
+        mean: f32,
+        stddev: f32,
+    ) -> Option<FloatVector> {
         if size < 0 || stddev < 0.0 {
-            None
-        } else {
-            let mut float_vector = Vec::with_capacity(size as usize);
-            let mut r = rand::thread_rng();
-            let n = Normal::new(mean, stddev).unwrap();
-            for _i in 0..size {
-                float_vector.push(n.sample(&mut r));
-            }
-            Some(FloatVector::new(float_vector))
+            return None;
         }
+        // Fails for a standard deviation that is not finite
+        let d = Normal::new(mean, stddev).ok()?;
+        Some(FloatVector::new(d.sample_iter(rng).take(size as usize).collect()))
     }
 
     /// Returns a random integer vector. Its elements are independent and identically distributed
     /// random variables drawn from the uniform distribution with given min and max values.
-    pub fn random_int_vector(size: i32, min: i32, max: i32) -> Option<IntVector> {
+    pub fn random_int_vector<R: Rng>(rng: &mut R, size: i32, min: i32, max: i32) -> Option<IntVector> {
         if size < 0 || max <= min {
             None
         } else {
             let mut int_vector = Vec::with_capacity(size as usize);
-            let mut r = rand::thread_rng();
             for _i in 0..size {
-                int_vector.push(r.gen_range(min..max));
+                int_vector.push(rng.gen_range(min..max));
             }
             Some(IntVector::new(int_vector))
         }
     }
 
+    /// Returns a random boolean vector. Its elements are independent Bernoulli trials with
+    /// success probability p.
+    pub fn random_bernoulli_bool_vector<R: Rng>(rng: &mut R, size: i32, p: f32) -> Option<BoolVector> {
+        if size < 0 {
+            return None;
+        }
+        let d = Bernoulli::new(p as f64).ok()?;
+        Some(BoolVector::new(d.sample_iter(rng).take(size as usize).collect()))
+    }
+
+    /// Returns a random float vector. Its elements are independent and identically distributed
+    /// random variables drawn from the exponential distribution with given rate lambda.
+    pub fn random_exp_float_vector<R: Rng>(rng: &mut R, size: i32, lambda: f32) -> Option<FloatVector> {
+        if size < 0 || lambda <= 0.0 {
+            return None;
+        }
+        let d = Exp::new(lambda).ok()?;
+        Some(FloatVector::new(d.sample_iter(rng).take(size as usize).collect()))
+    }
+
+    /// Returns a random float vector. Its elements are independent and identically distributed
+    /// random variables drawn from the uniform distribution over [min, max).
+    pub fn random_uniform_float_vector<R: Rng>(
+        rng: &mut R,
+        size: i32,
+        min: f32,
+        max: f32,
+    ) -> Option<FloatVector> {
+        if size < 0 || !min.is_finite() || !max.is_finite() || min >= max {
+            return None;
+        }
+        let d = Uniform::new(min, max);
+        Some(FloatVector::new(d.sample_iter(rng).take(size as usize).collect()))
+    }
+
+    /// Returns a random integer vector. Its elements are independent and identically distributed
+    /// random variables drawn from the Poisson distribution with given mean lambda.
+    pub fn random_poisson_int_vector<R: Rng>(rng: &mut R, size: i32, lambda: f32) -> Option<IntVector> {
+        if size < 0 || lambda <= 0.0 || !lambda.is_finite() {
+            return None;
+        }
+        let d = Poisson::new(lambda as f64).ok()?;
+        Some(IntVector::new(
+            d.sample_iter(rng).take(size as usize).map(|x: f64| x as i32).collect(),
+        ))
+    }
+
     /// Returns random float value within the bounds given by configuration
-    pub fn random_float(push_state: &PushState) -> Option<f32> {
-        let mut rng = rand::thread_rng();
+    pub fn random_float(push_state: &mut PushState) -> Option<f32> {
         if push_state.configuration.min_random_float < push_state.configuration.max_random_float {
-            Some(rng.gen_range(
+            Some(push_state.rng.gen_range(
                 push_state.configuration.min_random_float
                     ..push_state.configuration.max_random_float,
             ))
@@ -127,11 +204,10 @@ impl CodeGenerator {
     }
 
     /// Returns random integer value within the bounds given by configuration
-    pub fn random_integer(push_state: &PushState) -> Option<i32> {
-        let mut rng = rand::thread_rng();
+    pub fn random_integer(push_state: &mut PushState) -> Option<i32> {
         if push_state.configuration.min_random_integer < push_state.configuration.max_random_integer
         {
-            Some(rng.gen_range(
+            Some(push_state.rng.gen_range(
                 push_state.configuration.min_random_integer
                     ..push_state.configuration.max_random_integer,
             ))
@@ -141,80 +217,112 @@ impl CodeGenerator {
     }
 
     /// Returns a random name that is not being used yet
-    pub fn new_random_name() -> String {
-        let mut generator = Generator::default();
-        let rand_name = generator.next().unwrap();
-        return rand_name;
+    pub fn new_random_name<R: Rng>(rng: &mut R) -> String {
+        format!(
+            "{}-{}",
+            ADJECTIVES[rng.gen_range(0..ADJECTIVES.len())],
+            NOUNS[rng.gen_range(0..NOUNS.len())]
+        )
     }
 
     /// Selects a random item from the name bindings or a new
     /// name if there is not name binding yet.
-    pub fn existing_random_name(push_state: &PushState) -> String {
+    pub fn existing_random_name(push_state: &mut PushState) -> String {
         let name_size = push_state.name_bindings.len();
         if name_size == 0 {
-            CodeGenerator::new_random_name()
+            CodeGenerator::new_random_name(&mut push_state.rng)
         } else {
-            let mut rng = rand::thread_rng();
-            let name_idx = rng.gen_range(0..name_size);
-            let names: Vec<String> = push_state.name_bindings.keys().cloned().collect();
+            let name_idx = push_state.rng.gen_range(0..name_size);
+            // Sorted to be independent of the iteration order of the bindings
+            let mut names: Vec<&String> = push_state.name_bindings.keys().collect();
+            names.sort();
             names[name_idx].to_string()
         }
     }
 
     /// Return random code of size points
     pub fn random_code_with_size<'a>(
-        push_state: &PushState,
+        push_state: &mut PushState,
+        instructions: &InstructionCache,
+        points: usize,
+    ) -> Item {
+        let selection = InstructionSelection::new(push_state, instructions);
+        CodeGenerator::random_code_with_selection(push_state, instructions, &selection, points)
+    }
+
+    fn random_code_with_selection(
+        push_state: &mut PushState,
         instructions: &InstructionCache,
+        selection: &InstructionSelection,
         points: usize,
     ) -> Item {
-        let number_instructions = instructions.list.len();
         if points == 1 {
-            let mut rng = rand::thread_rng();
-            let item_type: ItemType = rand::random();
+            let tag_probability = push_state.configuration.tag_erc_probability;
+            if tag_probability > 0.0 && push_state.rng.gen_bool(f64::min(tag_probability as f64, 1.0)) {
+                let tag_limit = push_state.configuration.tag_limit;
+                let tag_instruction = TagInstruction::random(&mut push_state.rng, tag_limit);
+                return Item::instruction(tag_instruction.name());
+            }
+            let item_type: ItemType = push_state.rng.gen();
             match item_type {
-                ItemType::Boolean => Item::bool(rng.gen::<bool>()),
-                ItemType::Float => Item::float(rng.gen::<f32>()),
+                ItemType::Boolean => Item::bool(push_state.rng.gen::<bool>()),
+                ItemType::Float => Item::float(push_state.rng.gen::<f32>()),
                 ItemType::Instruction => {
-                    if number_instructions > 0 {
-                        let instruction_idx = rng.gen_range(0..number_instructions);
-                        let selected_instruction =
-                            instructions.list.get(instruction_idx).unwrap().clone();
-                        Item::instruction(selected_instruction)
-                    } else {
-                        Item::noop()
-                    }
+                    let index = match selection {
+                        InstructionSelection::Weighted(dist) => dist.sample(&mut push_state.rng),
+                        InstructionSelection::Uniform => {
+                            push_state.rng.gen_range(0..instructions.list.len())
+                        }
+                        InstructionSelection::Nothing => return Item::noop(),
+                    };
+                    Item::instruction(instructions.list[index].clone())
                 }
-                ItemType::Integer => Item::int(rng.gen::<i32>()),
+                ItemType::Integer => Item::int(push_state.rng.gen::<i32>()),
                 ItemType::Name => {
                     let rand_name;
                     let pnew_name = push_state.configuration.new_erc_name_probability;
                     let n_total = 10000;
                     let n_event_new_name = (pnew_name * n_total as f32) as u32;
-                    if rng.gen_range(0..n_total) < n_event_new_name {
-                        rand_name = CodeGenerator::new_random_name();
+                    if push_state.rng.gen_range(0..n_total) < n_event_new_name {
+                        rand_name = CodeGenerator::new_random_name(&mut push_state.rng);
                     } else {
                         rand_name = CodeGenerator::existing_random_name(push_state);
                     }
-                    Item::name(rand_name)
+                    Item::id(rand_name)
                 }
                 ItemType::BoolVector => {
-                    let sparsity = rng.gen_range(0.0..1.0);
-                    let size = rng.gen_range(0..push_state.configuration.max_random_integer);
-                    Item::boolvec(CodeGenerator::random_bool_vector(size, sparsity).unwrap())
+                    let sparsity = push_state.rng.gen_range(0.0..1.0);
+                    let size = push_state
+                        .rng
+                        .gen_range(0..push_state.configuration.max_random_integer);
+                    Item::boolvec(
+                        CodeGenerator::random_bool_vector(&mut push_state.rng, size, sparsity)
+                            .unwrap(),
+                    )
                 }
                 ItemType::FloatVector => {
-                    let size = rng.gen_range(0..push_state.configuration.max_random_integer);
-                    let mean = rng.gen_range(
+                    let size = push_state
+                        .rng
+                        .gen_range(0..push_state.configuration.max_random_integer);
+                    let mean = push_state.rng.gen_range(
                         push_state.configuration.min_random_float
                             ..push_state.configuration.max_random_float,
                     );
-                    let stddev = rng.gen_range(0.0..push_state.configuration.max_random_float);
-                    Item::floatvec(CodeGenerator::random_float_vector(size, mean, stddev).unwrap())
+                    let stddev = push_state
+                        .rng
+                        .gen_range(0.0..push_state.configuration.max_random_float);
+                    Item::floatvec(
+                        CodeGenerator::random_float_vector(&mut push_state.rng, size, mean, stddev)
+                            .unwrap(),
+                    )
                 }
                 ItemType::IntVector => {
-                    let size = rng.gen_range(0..push_state.configuration.max_random_integer);
+                    let size = push_state
+                        .rng
+                        .gen_range(0..push_state.configuration.max_random_integer);
                     Item::intvec(
                         CodeGenerator::random_int_vector(
+                            &mut push_state.rng,
                             size,
                             push_state.configuration.min_random_integer,
                             push_state.configuration.max_random_integer,
@@ -225,12 +333,13 @@ impl CodeGenerator {
             }
         } else {
             let mut item_distribution: Vec<usize> = vec![];
-            CodeGenerator::decompose(&mut item_distribution, points - 1);
+            CodeGenerator::decompose(&mut push_state.rng, &mut item_distribution, points - 1);
             let mut items_this_level: Vec<Item> = Vec::with_capacity(item_distribution.len());
             for i in 0..item_distribution.len() {
-                items_this_level.push(CodeGenerator::random_code_with_size(
+                items_this_level.push(CodeGenerator::random_code_with_selection(
                     push_state,
                     instructions,
+                    selection,
                     item_distribution[i],
                 ));
             }
@@ -240,36 +349,269 @@ impl CodeGenerator {
 
     /// Returns a vector of random size whose elements sum up to
     /// remaining_item
-    pub fn decompose(elements: &mut Vec<usize>, remaining_items: usize) {
+    pub fn decompose<R: Rng>(rng: &mut R, elements: &mut Vec<usize>, remaining_items: usize) {
         if remaining_items == 1 {
             elements.push(1);
             return;
         }
-        let mut rng = rand::thread_rng();
         let items_this_level = rng.gen_range(1..remaining_items) as usize;
         elements.push(items_this_level);
-        CodeGenerator::decompose(elements, remaining_items - items_this_level);
+        CodeGenerator::decompose(rng, elements, remaining_items - items_this_level);
+    }
+
+    /// Replaces a random point of the program with random code (subtree mutation). The
+    /// size of the new code is limited by max_points_in_random_expressions and by the
+    /// points that are left until the program has max_points. Returns a copy of the
+    /// program if there are no points left.
+    pub fn point_mutation(
+        push_state: &mut PushState,
+        instructions: &InstructionCache,
+        program: &Item,
+        max_points: usize,
+    ) -> Item {
+        CodeGenerator::point_mutation_with_points(push_state, instructions, program, max_points).0
+    }
+
+    /// Like point_mutation but also returns the replaced point (see Ancestry). The
+    /// point is missing if a copy of the program is returned.
+    pub fn point_mutation_with_points(
+        push_state: &mut PushState,
+        instructions: &InstructionCache,
+        program: &Item,
+        max_points: usize,
+    ) -> (Item, Vec<usize>) {
+        let size = Item::size(program);
+        let point = push_state.rng.gen_range(0..size);
+        if let Ok(subtree) = Item::traverse(program, point) {
+            let remaining = size - Item::size(&subtree);
+            let random_points = i32::abs(push_state.configuration.max_points_in_random_expressions);
+            let budget = usize::min(max_points.saturating_sub(remaining), random_points as usize);
+            if budget > 0 {
+                let points = push_state.rng.gen_range(1..=budget);
+                let code = CodeGenerator::random_code_with_size(push_state, instructions, points);
+                let mut mutant = program.clone();
+                replace_point(&mut mutant, &mut point.clone(), &code);
+                return (mutant, vec![point]);
+            }
+        }
+        (program.clone(), vec![])
+    }
+
+    /// Replaces each literal and instruction of the program with a random literal or
+    /// instruction with the given probability. The structure and the size of the program
+    /// are preserved.
+    pub fn uniform_mutation(
+        push_state: &mut PushState,
+        instructions: &InstructionCache,
+        program: &Item,
+        rate: f32,
+    ) -> Item {
+        match program {
+            Item::List { items } => Item::List {
+                items: PushStack::from_vec(
+                    items
+                        .as_slice()
+                        .iter()
+                        .map(|item| {
+                            CodeGenerator::uniform_mutation(push_state, instructions, item, rate)
+                        })
+                        .collect(),
+                ),
+            },
+            _ => {
+                if push_state.rng.gen::<f32>() < rate {
+                    CodeGenerator::random_code_with_size(push_state, instructions, 1)
+                } else {
+                    program.clone()
+                }
+            }
+        }
+    }
+
+    /// Replaces each instruction of the genome with a random literal or instruction
+    /// with the given probability. The close counts are preserved.
+    pub fn genome_uniform_mutation(
+        push_state: &mut PushState,
+        instructions: &InstructionCache,
+        genome: &Genome,
+        rate: f32,
+    ) -> Genome {
+        let mut mutant = genome.clone();
+        for gene in mutant.genes.iter_mut() {
+            if push_state.rng.gen::<f32>() < rate {
+                gene.instruction = CodeGenerator::random_code_with_size(push_state, instructions, 1);
+            }
+        }
+        mutant
+    }
+
+    /// Replaces a random point of the first parent with a random point of the second
+    /// parent. Returns a copy of the first parent if the offspring has more than
+    /// max_points.
+    pub fn subtree_crossover(
+        push_state: &mut PushState,
+        first: &Item,
+        second: &Item,
+        max_points: usize,
+    ) -> Item {
+        CodeGenerator::subtree_crossover_with_points(push_state, first, second, max_points).0
+    }
+
+    /// Like subtree_crossover but also returns the replaced point of the first parent and
+    /// the donated point of the second parent (see Ancestry). The points are missing if
+    /// a copy of the first parent is returned.
+    pub fn subtree_crossover_with_points(
+        push_state: &mut PushState,
+        first: &Item,
+        second: &Item,
+        max_points: usize,
+    ) -> (Item, Vec<usize>) {
+        let point = push_state.rng.gen_range(0..Item::size(first));
+        let donor_point = push_state.rng.gen_range(0..Item::size(second));
+        if let Ok(donor) = Item::traverse(second, donor_point) {
+            let mut offspring = first.clone();
+            replace_point(&mut offspring, &mut point.clone(), &donor);
+            if Item::size(&offspring) <= max_points {
+                return (offspring, vec![point, donor_point]);
+            }
+        }
+        (first.clone(), vec![])
+    }
+
+    /// Alternation crossover of the top-level items of the parents (in program order).
+    /// See alternate for the parameters. Returns a copy of the first parent if the
+    /// offspring has more than max_points.
+    pub fn alternation_crossover(
+        push_state: &mut PushState,
+        first: &Item,
+        second: &Item,
+        rate: f32,
+        alignment_deviation: f32,
+        max_points: usize,
+    ) -> Item {
+        let mut offspring = alternate(
+            &mut push_state.rng,
+            &program_items(first),
+            &program_items(second),
+            rate,
+            alignment_deviation,
+        );
+        offspring.reverse();
+        let offspring = Item::list(offspring);
+        if Item::size(&offspring) <= max_points {
+            offspring
+        } else {
+            first.clone()
+        }
+    }
+
+    /// Alternation crossover of the genes of Plush genomes. See alternate for the
+    /// parameters. Returns a copy of the first parent if the translation of the
+    /// offspring has more than max_points.
+    pub fn genome_alternation_crossover(
+        push_state: &mut PushState,
+        instructions: &InstructionCache,
+        first: &Genome,
+        second: &Genome,
+        rate: f32,
+        alignment_deviation: f32,
+        max_points: usize,
+    ) -> Genome {
+        let offspring = Genome::new(alternate(
+            &mut push_state.rng,
+            &first.genes,
+            &second.genes,
+            rate,
+            alignment_deviation,
+        ));
+        if Item::size(&offspring.translate(instructions)) <= max_points {
+            offspring
+        } else {
+            first.clone()
+        }
     }
 }
 
+/// Replaces the point with the given index in depth first order where the item itself
+/// is point 0 (see Item::traverse). Returns true if the point was found.
+fn replace_point(item: &mut Item, point: &mut usize, replacement: &Item) -> bool {
+    if *point == 0 {
+        *item = replacement.clone();
+        return true;
+    }
+    *point -= 1;
+    if let Item::List { items } = item {
+        for i in 0..items.size() {
+            if let Some(child) = items.get_mut(i) {
+                if replace_point(child, point, replacement) {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Returns the top-level items of a program in program order. Items that are not
+/// lists are programs with a single item.
+fn program_items(program: &Item) -> Vec<Item> {
+    match program {
+        Item::List { items } => items.as_slice().iter().rev().cloned().collect(),
+        _ => vec![program.clone()],
+    }
+}
+
+/// Alternation crossover (Spector and Helmuth, Uniform linear transformation with
+/// repair and alternation in genetic programming, 2014): Copies the elements of a
+/// randomly selected parent from left to right. With probability rate it switches to
+/// the other parent instead of copying, at an index that deviates from the current
+/// one by a normally distributed offset with the given standard deviation. Ends when
+/// the index passes the end of the current parent or after as many steps as both
+/// parents have elements.
+fn alternate<T: Clone, R: Rng>(
+    rng: &mut R,
+    first: &[T],
+    second: &[T],
+    rate: f32,
+    alignment_deviation: f32,
+) -> Vec<T> {
+    let deviation = Normal::new(0.0, f32::abs(alignment_deviation)).unwrap();
+    let mut offspring = vec![];
+    let mut use_first = rng.gen::<bool>();
+    let mut i = 0;
+    for _ in 0..first.len() + second.len() {
+        let parent = if use_first { first } else { second };
+        if i >= parent.len() {
+            break;
+        }
+        if rng.gen::<f32>() < rate {
+            let offset = deviation.sample(rng).round() as i64;
+            i = i64::max(0, i as i64 + offset) as usize;
+            use_first = !use_first;
+        } else {
+            offspring.push(parent[i].clone());
+            i += 1;
+        }
+    }
+    offspring
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::push::genome::Gene;
     use crate::push::instructions::InstructionSet;
+    use crate::push::parser::PushParser;
 
     #[test]
     fn random_bool_vector_is_generated() {
         let test_size = 100;
         let test_sparsity = vec![0.0, 0.12, 0.5, 0.85, 1.0];
         for test_sp in test_sparsity {
-            if let Some(rand_bool_vector) = CodeGenerator::random_bool_vector(test_size, test_sp) {
-                assert_eq!(rand_bool_vector.values.len(), test_size as usize);
+            if let Some(rand_bool_vector) = CodeGenerator::random_bool_vector(&mut rand::thread_rng(), test_size, test_sp) {
+                assert_eq!(rand_bool_vector.len(), test_size as usize);
                 assert_eq!(
-                    rand_bool_vector
-                        .values
-                        .iter()
-                        .filter(|&n| *n == true)
-                        .count(),
+                    rand_bool_vector.bits.count_ones(),
                     (test_sp * test_size as f32) as usize
                 );
             } else {
@@ -284,7 +626,7 @@ mod tests {
         let test_mean = 0.5;
         let test_stddev = 0.01;
         if let Some(rand_vector) =
-            CodeGenerator::random_float_vector(test_size, test_mean, test_stddev)
+            CodeGenerator::random_float_vector(&mut rand::thread_rng(), test_size, test_mean, test_stddev)
         {
             assert_eq!(rand_vector.values.len(), test_size as usize);
         } else {
@@ -297,41 +639,233 @@ mod tests {
         let test_size = 100;
         let test_min = 5;
         let test_max = 11;
-        if let Some(rand_vector) = CodeGenerator::random_int_vector(test_size, test_min, test_max) {
+        if let Some(rand_vector) = CodeGenerator::random_int_vector(&mut rand::thread_rng(), test_size, test_min, test_max) {
             assert_eq!(rand_vector.values.len(), test_size as usize);
         } else {
             assert!(false, "Expected to get int vector");
         }
     }
 
+    #[test]
+    fn distribution_vectors_are_reproducible() {
+        let mut test_state = PushState::new();
+        test_state.seed(7);
+        let fv1 = CodeGenerator::random_exp_float_vector(&mut test_state.rng, 50, 2.0).unwrap();
+        let iv1 = CodeGenerator::random_poisson_int_vector(&mut test_state.rng, 50, 3.0).unwrap();
+        test_state.seed(7);
+        let fv2 = CodeGenerator::random_exp_float_vector(&mut test_state.rng, 50, 2.0).unwrap();
+        let iv2 = CodeGenerator::random_poisson_int_vector(&mut test_state.rng, 50, 3.0).unwrap();
+        assert_eq!(fv1, fv2);
+        assert_eq!(iv1, iv2);
+        assert!(fv1.values.iter().all(|x| *x >= 0.0));
+        assert!(iv1.values.iter().all(|x| *x >= 0));
+        let uv = CodeGenerator::random_uniform_float_vector(&mut test_state.rng, 50, -1.0, 1.0).unwrap();
+        assert!(uv.values.iter().all(|x| *x >= -1.0 && *x < 1.0));
+        let bv = CodeGenerator::random_bernoulli_bool_vector(&mut test_state.rng, 50, 1.0).unwrap();
+        assert!(bv.bits.iter().all(|x| x));
+        assert!(CodeGenerator::random_exp_float_vector(&mut test_state.rng, 5, 0.0).is_none());
+        assert!(CodeGenerator::random_uniform_float_vector(&mut test_state.rng, 5, 1.0, 1.0).is_none());
+        assert!(CodeGenerator::random_bernoulli_bool_vector(&mut test_state.rng, 5, 1.5).is_none());
+    }
+
     #[test]
     fn random_code_is_generated() {
-        let push_state = PushState::new();
+        let mut push_state = PushState::new();
         let test_size = 1034;
         let mut instruction_set = InstructionSet::new();
         instruction_set.load();
         let instructions = instruction_set.cache();
-        let random_item = CodeGenerator::random_code(&push_state, &instructions, test_size);
+        let random_item = CodeGenerator::random_code(&mut push_state, &instructions, test_size);
         assert!(Item::size(&random_item.unwrap()) <= test_size);
     }
 
     #[test]
     fn random_code_with_size_is_generated() {
-        let push_state = PushState::new();
+        let mut push_state = PushState::new();
         let test_size = 235;
         let mut instruction_set = InstructionSet::new();
         instruction_set.load();
         let instructions = instruction_set.cache();
         let random_item =
-            CodeGenerator::random_code_with_size(&push_state, &instructions, test_size);
+            CodeGenerator::random_code_with_size(&mut push_state, &instructions, test_size);
         assert_eq!(Item::size(&random_item), test_size);
     }
 
+    #[test]
+    fn random_instructions_follow_weights() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        for name in instruction_set.names_with_prefix("") {
+            instruction_set.set_weight(&name, 0.0);
+        }
+        instruction_set.set_weight("INTEGER.+", 2.0);
+        let instructions = instruction_set.cache();
+        let sample = |push_state: &mut PushState| {
+            (0..200)
+                .map(|_| CodeGenerator::random_code_with_size(push_state, &instructions, 1))
+                .filter_map(|item| match item {
+                    Item::InstructionMeta { name, .. } => Some(name),
+                    _ => None,
+                })
+                .collect::<Vec<String>>()
+        };
+        let selected = sample(&mut push_state);
+        assert!(!selected.is_empty());
+        assert!(selected.iter().all(|name| name == "INTEGER.+"));
+        // Weights set at runtime take precedence
+        for (name, weight) in [("INTEGER.+", 0.0), ("FLOAT.+", 1.0)] {
+            push_state.name_stack.push(name.to_string());
+            push_state.float_stack.push(weight);
+            let instruction = instruction_set.get_instruction("INSTRUCTION.WEIGHT").unwrap();
+            (instruction.execute)(&mut push_state, &instructions);
+        }
+        assert_eq!(push_state.name_stack.size(), 0);
+        assert!(sample(&mut push_state).iter().all(|name| name == "FLOAT.+"));
+    }
+
+    #[test]
+    fn random_code_contains_tag_instructions() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let instructions = instruction_set.cache();
+        push_state.configuration.tag_erc_probability = 1.0;
+        push_state.configuration.tag_limit = 100;
+        let code = CodeGenerator::random_code_with_size(&mut push_state, &instructions, 20);
+        let leaves = match code.flatten() {
+            Item::List { items } => items.as_slice().to_vec(),
+            item => vec![item],
+        };
+        assert!(!leaves.is_empty());
+        for leaf in leaves {
+            match leaf {
+                Item::InstructionMeta { name, .. } => match TagInstruction::parse(&name) {
+                    Some(TagInstruction::Tag { tag, .. })
+                    | Some(TagInstruction::Tagged { tag, .. })
+                    | Some(TagInstruction::Untag { tag }) => assert!((0..100).contains(&tag)),
+                    None => panic!("{} is not a tag instruction", name),
+                },
+                item => panic!("{} is not a tag instruction", item),
+            }
+        }
+    }
+
+    #[test]
+    fn random_instructions_are_uniform_for_unusable_weights() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let instructions = instruction_set.cache();
+        // The sum of the weights is not finite
+        push_state.instruction_weights.insert("INTEGER.+".to_string(), f32::MAX);
+        push_state.instruction_weights.insert("FLOAT.+".to_string(), f32::MAX);
+        let selected: Vec<Item> = (0..200)
+            .map(|_| CodeGenerator::random_code_with_size(&mut push_state, &instructions, 1))
+            .filter(|item| matches!(item, Item::InstructionMeta { .. }))
+            .collect();
+        assert!(!selected.is_empty());
+        let code = CodeGenerator::random_code_with_size(&mut push_state, &instructions, 20);
+        assert_eq!(Item::size(&code), 20);
+    }
+
+    fn parse(code: &str) -> Item {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, code);
+        push_state.exec_stack.pop().unwrap()
+    }
+
+    #[test]
+    fn mutations_respect_max_points() {
+        let mut push_state = PushState::new();
+        push_state.seed(3);
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let instructions = instruction_set.cache();
+        let program = parse("( 1 ( 2 INTEGER.+ ) FLOAT.* ( ( TRUE ) ) )");
+        for _ in 0..100 {
+            let mutant =
+                CodeGenerator::point_mutation(&mut push_state, &instructions, &program, 12);
+            assert!(Item::size(&mutant) <= 12);
+        }
+        let mutant = CodeGenerator::point_mutation(&mut push_state, &instructions, &program, 3);
+        assert!(Item::equals(&mutant, &program));
+        let mutant = CodeGenerator::uniform_mutation(&mut push_state, &instructions, &program, 1.0);
+        assert_eq!(Item::size(&mutant), Item::size(&program));
+        assert!(!Item::equals(&mutant, &program));
+        let mutant = CodeGenerator::uniform_mutation(&mut push_state, &instructions, &program, 0.0);
+        assert!(Item::equals(&mutant, &program));
+    }
+
+    #[test]
+    fn crossovers_combine_parents() {
+        let mut push_state = PushState::new();
+        push_state.seed(5);
+        let first = parse("( 1 2 3 4 5 6 )");
+        let second = parse("( ( 7 8 ) 9 10 )");
+        for _ in 0..100 {
+            let offspring = CodeGenerator::subtree_crossover(&mut push_state, &first, &second, 9);
+            assert!(Item::size(&offspring) <= 9);
+        }
+        // Without alternation the offspring is a copy of one of the parents
+        let offspring =
+            CodeGenerator::alternation_crossover(&mut push_state, &first, &second, 0.0, 1.0, 100);
+        assert!(Item::equals(&offspring, &first) || Item::equals(&offspring, &second));
+        for _ in 0..100 {
+            let offspring = CodeGenerator::alternation_crossover(
+                &mut push_state,
+                &first,
+                &second,
+                0.3,
+                1.0,
+                8,
+            );
+            assert!(Item::size(&offspring) <= 8);
+        }
+    }
+
+    #[test]
+    fn genome_operators_preserve_genes() {
+        let mut push_state = PushState::new();
+        push_state.seed(9);
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let instructions = instruction_set.cache();
+        let genome = Genome::new(
+            (0..10)
+                .map(|i| Gene::new(Item::int(i), (i % 3) as usize))
+                .collect(),
+        );
+        let mutant =
+            CodeGenerator::genome_uniform_mutation(&mut push_state, &instructions, &genome, 0.5);
+        assert_eq!(mutant.genes.len(), genome.genes.len());
+        assert!(mutant
+            .genes
+            .iter()
+            .zip(genome.genes.iter())
+            .all(|(m, g)| m.close == g.close));
+        let other = Genome::new(vec![Gene::new(Item::bool(true), 0); 4]);
+        for _ in 0..100 {
+            let offspring = CodeGenerator::genome_alternation_crossover(
+                &mut push_state,
+                &instructions,
+                &genome,
+                &other,
+                0.5,
+                2.0,
+                100,
+            );
+            assert!(offspring.genes.len() <= 14);
+        }
+    }
+
     #[test]
     fn decompose_generates_valid_distribution() {
         let test_size = 11;
         let mut test_distribution: Vec<usize> = vec![];
-        CodeGenerator::decompose(&mut test_distribution, test_size);
+        CodeGenerator::decompose(&mut rand::thread_rng(), &mut test_distribution, test_size);
         assert_eq!(test_distribution.iter().sum::<usize>(), test_size);
     }
 }
@@ -0,0 +1,127 @@
+use crate::push::instructions::{Instruction, InstructionCache, InstructionSet};
+use crate::push::state::*;
+use std::collections::HashMap;
+
+// Input registers and output values for the evaluation of programs on test cases
+// (like the input instructions of Clojush). The inputs of a test case are set on
+// the state before the execution, the fitness function reads the outputs after it:
+//
+//     push_state.inputs = vec![Item::int(3), Item::float(0.5)];
+//     instruction_set.load_input_instructions(2);
+//     PushInterpreter::run(&mut push_state, &mut instruction_set);
+//     let result = push_state.outputs.first();
+
+impl InstructionSet {
+    /// Adds the instructions IN0 to IN<n-1>. INi pushes a copy of the input register i
+    /// of the state onto the EXEC stack, so that literals end up on the stack of their
+    /// type. It acts as a NOOP if the state has less than i+1 inputs.
+    pub fn load_input_instructions(&mut self, n: usize) {
+        for i in 0..n {
+            self.add(
+                format!("IN{}", i),
+                Instruction::new(move |push_state, _| {
+                    if let Some(item) = push_state.inputs.get(i).cloned() {
+                        push_state.exec_stack.push(item);
+                    }
+                }),
+            );
+        }
+    }
+}
+
+pub fn load_output_instructions(map: &mut HashMap<String, Instruction>) {
+    map.insert(String::from("OUT.BOOLEAN"), Instruction::new(out_boolean));
+    map.insert(
+        String::from("OUT.BOOLVECTOR"),
+        Instruction::new(out_bool_vector),
+    );
+    map.insert(String::from("OUT.CHAR"), Instruction::new(out_char));
+    map.insert(String::from("OUT.CODE"), Instruction::new(out_code));
+    map.insert(String::from("OUT.FLOAT"), Instruction::new(out_float));
+    map.insert(
+        String::from("OUT.FLOATVECTOR"),
+        Instruction::new(out_float_vector),
+    );
+    map.insert(String::from("OUT.INTEGER"), Instruction::new(out_integer));
+    map.insert(
+        String::from("OUT.INTVECTOR"),
+        Instruction::new(out_int_vector),
+    );
+    map.insert(String::from("OUT.NAME"), Instruction::new(out_name));
+}
+
+/// Pops the top item of the stack and appends it to the outputs of the state.
+fn write_output(push_state: &mut PushState, stack_id: i32) {
+    if let Some(item) = push_state.pop_item(stack_id) {
+        push_state.outputs.push(item);
+    }
+}
+
+/// OUT.BOOLEAN: Pops the top BOOLEAN and appends it to the outputs.
+fn out_boolean(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    write_output(push_state, BOOL_STACK_ID);
+}
+
+/// OUT.BOOLVECTOR: Pops the top BOOLVECTOR and appends it to the outputs.
+fn out_bool_vector(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    write_output(push_state, BOOL_VECTOR_STACK_ID);
+}
+
+/// OUT.CHAR: Pops the top CHAR and appends it to the outputs.
+fn out_char(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    write_output(push_state, CHAR_STACK_ID);
+}
+
+/// OUT.CODE: Pops the top CODE item and appends it to the outputs.
+fn out_code(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    write_output(push_state, CODE_STACK_ID);
+}
+
+/// OUT.FLOAT: Pops the top FLOAT and appends it to the outputs.
+fn out_float(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    write_output(push_state, FLOAT_STACK_ID);
+}
+
+/// OUT.FLOATVECTOR: Pops the top FLOATVECTOR and appends it to the outputs.
+fn out_float_vector(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    write_output(push_state, FLOAT_VECTOR_STACK_ID);
+}
+
+/// OUT.INTEGER: Pops the top INTEGER and appends it to the outputs.
+fn out_integer(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    write_output(push_state, INT_STACK_ID);
+}
+
+/// OUT.INTVECTOR: Pops the top INTVECTOR and appends it to the outputs.
+fn out_int_vector(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    write_output(push_state, INT_VECTOR_STACK_ID);
+}
+
+/// OUT.NAME: Pops the top NAME and appends it to the outputs.
+fn out_name(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    write_output(push_state, NAME_STACK_ID);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::interpreter::PushInterpreter;
+    use crate::push::item::Item;
+
+    #[test]
+    fn inputs_are_pushed_and_outputs_collected() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        instruction_set.load_input_instructions(3);
+        push_state.inputs = vec![Item::int(3), Item::float(0.5)];
+        let result = PushInterpreter::run_program(
+            "( IN0 IN0 INTEGER.* OUT.INTEGER IN1 OUT.FLOAT IN2 OUT.BOOLEAN )",
+            &mut push_state,
+            &mut instruction_set,
+        );
+        assert_eq!(push_state.outputs, vec![Item::int(9), Item::float(0.5)]);
+        assert_eq!(result.stack("int"), Some(""));
+        assert!(!instruction_set.is_instruction("IN3"));
+    }
+}
@@ -0,0 +1,362 @@
+use crate::push::buffer::{BufferType, PushBuffer};
+use crate::push::instructions::{InstructionCache, InstructionSet};
+use crate::push::interpreter::{Breakpoint, PushInterpreter};
+use crate::push::item::Item;
+use crate::push::stack::{PushPrint, PushStack};
+use crate::push::state::PushState;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+
+/// Default number of steps that can be undone.
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// Stack, buffer or other large part of the state that is recorded as a whole in
+/// the inverse delta of a step if the step modifies it.
+trait StatePart {
+    /// Returns true if the part equals the part of the current state.
+    fn unchanged(&self, current: &Self) -> bool;
+
+    /// Returns an empty part that takes the place of an unchanged part in a delta.
+    fn placeholder() -> Self;
+}
+
+impl<T> StatePart for PushStack<T>
+where
+    T: Clone + fmt::Display + PartialEq + PushPrint,
+{
+    /// Stacks are copy-on-write, so a stack that still shares its elements with the
+    /// stack before the step has not been modified.
+    fn unchanged(&self, current: &Self) -> bool {
+        self.shares_elements(current)
+    }
+
+    fn placeholder() -> Self {
+        PushStack::new()
+    }
+}
+
+impl<T> StatePart for PushBuffer<T>
+where
+    T: Clone + fmt::Display + Default + PartialEq + fmt::Debug,
+{
+    fn unchanged(&self, current: &Self) -> bool {
+        self.capacity() == current.capacity()
+            && self.size() == current.size()
+            && (0..self.size()).all(|i| self.get(i) == current.get(i))
+    }
+
+    fn placeholder() -> Self {
+        PushBuffer::new(BufferType::Stack, 0)
+    }
+}
+
+impl StatePart for HashMap<String, Item> {
+    fn unchanged(&self, current: &Self) -> bool {
+        self.len() == current.len()
+            && self
+                .iter()
+                .all(|(name, item)| matches!(current.get(name), Some(other) if Item::equals(item, other)))
+    }
+
+    fn placeholder() -> Self {
+        HashMap::new()
+    }
+}
+
+/// Operation on the parts of the state before and after a step (see visit_parts).
+trait PartVisitor {
+    fn visit<P: StatePart>(&mut self, previous: &mut P, current: &mut P);
+}
+
+/// Applies the visitor to the stacks, buffers and name bindings of the state before
+/// and after a step in a fixed order.
+fn visit_parts<V: PartVisitor>(previous: &mut PushState, current: &mut PushState, visitor: &mut V) {
+    visitor.visit(&mut previous.bool_stack, &mut current.bool_stack);
+    visitor.visit(&mut previous.char_stack, &mut current.char_stack);
+    visitor.visit(&mut previous.code_stack, &mut current.code_stack);
+    visitor.visit(&mut previous.exec_stack, &mut current.exec_stack);
+    visitor.visit(&mut previous.float_stack, &mut current.float_stack);
+    visitor.visit(&mut previous.index_stack, &mut current.index_stack);
+    visitor.visit(&mut previous.int_stack, &mut current.int_stack);
+    visitor.visit(&mut previous.name_stack, &mut current.name_stack);
+    visitor.visit(&mut previous.string_stack, &mut current.string_stack);
+    #[cfg(feature = "bignum")]
+    visitor.visit(&mut previous.bigint_stack, &mut current.bigint_stack);
+    visitor.visit(
+        &mut previous.bool_vector_stack,
+        &mut current.bool_vector_stack,
+    );
+    visitor.visit(
+        &mut previous.float_vector_stack,
+        &mut current.float_vector_stack,
+    );
+    visitor.visit(
+        &mut previous.int_vector_stack,
+        &mut current.int_vector_stack,
+    );
+    visitor.visit(&mut previous.bitmap_stack, &mut current.bitmap_stack);
+    visitor.visit(&mut previous.matrix_stack, &mut current.matrix_stack);
+    visitor.visit(
+        &mut previous.vector_list_stack,
+        &mut current.vector_list_stack,
+    );
+    visitor.visit(&mut previous.input_stack, &mut current.input_stack);
+    visitor.visit(&mut previous.output_stack, &mut current.output_stack);
+    visitor.visit(&mut previous.graph_stack, &mut current.graph_stack);
+    visitor.visit(&mut previous.error_stack, &mut current.error_stack);
+    visitor.visit(&mut previous.name_bindings, &mut current.name_bindings);
+}
+
+/// Replaces the unchanged parts of the previous state by placeholders.
+struct DropUnchanged {
+    unchanged: Vec<bool>,
+}
+
+impl PartVisitor for DropUnchanged {
+    fn visit<P: StatePart>(&mut self, previous: &mut P, current: &mut P) {
+        let unchanged = previous.unchanged(current);
+        if unchanged {
+            *previous = P::placeholder();
+        }
+        self.unchanged.push(unchanged);
+    }
+}
+
+/// Moves the unchanged parts of the current state to the previous state.
+struct TakeUnchanged<'a> {
+    unchanged: std::slice::Iter<'a, bool>,
+}
+
+impl<'a> PartVisitor for TakeUnchanged<'a> {
+    fn visit<P: StatePart>(&mut self, previous: &mut P, current: &mut P) {
+        if self.unchanged.next() == Some(&true) {
+            std::mem::swap(previous, current);
+        }
+    }
+}
+
+/// Inverse delta of a step: the stacks, buffers and name bindings that the step
+/// modified as they were before the step and the remaining fields of the state
+/// before the step, e.g. the configuration and the random number generator. The
+/// unchanged parts are placeholders and are taken from the current state on undo.
+struct StepDelta {
+    previous: PushState,
+    // Flags of the unchanged parts in the order of visit_parts
+    unchanged: Vec<bool>,
+}
+
+/// Debug runner that executes a program step by step. Each step records its
+/// inverse delta in a bounded history so that the step can be undone.
+///
+/// A delta only keeps the stacks, buffers and name bindings that its step modified,
+/// i.e. usually one or two stacks. The stacks are copy-on-write (see PushStack), so
+/// finding the modified stacks takes constant time per stack. For states with large
+/// items the limit should be lowered with Runner::with_history_limit, a limit of 0
+/// disables the history.
+pub struct Runner {
+    pub push_state: PushState,
+    pub instruction_set: InstructionSet,
+    icache: InstructionCache,
+    history: VecDeque<StepDelta>,
+    history_limit: usize,
+    step_counter: usize,
+}
+
+impl Runner {
+    pub fn new(push_state: PushState, instruction_set: InstructionSet) -> Self {
+        Runner::with_history_limit(push_state, instruction_set, DEFAULT_HISTORY_LIMIT)
+    }
+
+    /// Creates a runner that keeps at most history_limit steps for undo.
+    pub fn with_history_limit(
+        push_state: PushState,
+        instruction_set: InstructionSet,
+        history_limit: usize,
+    ) -> Self {
+        let icache = instruction_set.cache();
+        Self {
+            push_state,
+            instruction_set,
+            icache,
+            history: VecDeque::with_capacity(history_limit),
+            history_limit,
+            step_counter: 0,
+        }
+    }
+
+    /// Sets the constants that programs can read with CONST.GET. The table is shared
+    /// with the state instead of copied.
+    pub fn set_constants(&mut self, constants: Arc<HashMap<String, Item>>) {
+        self.push_state.constants = constants;
+    }
+
+    /// Adds a constant that programs can read with CONST.GET.
+    pub fn set_constant(&mut self, name: &str, item: Item) {
+        Arc::make_mut(&mut self.push_state.constants).insert(name.to_string(), item);
+    }
+
+    /// Executes a single step. Returns true if the execution stack is empty.
+    pub fn step(&mut self) -> bool {
+        if self.push_state.exec_stack.size() == 0 {
+            return true;
+        }
+        let previous = if self.history_limit > 0 {
+            Some(self.push_state.clone())
+        } else {
+            None
+        };
+        self.step_counter += 1;
+        let halted = PushInterpreter::step(
+            &mut self.push_state,
+            &mut self.instruction_set,
+            &self.icache,
+        );
+        if let Some(mut previous) = previous {
+            let mut drop_unchanged = DropUnchanged { unchanged: vec![] };
+            visit_parts(&mut previous, &mut self.push_state, &mut drop_unchanged);
+            if self.history.len() == self.history_limit {
+                self.history.pop_front();
+            }
+            self.history.push_back(StepDelta {
+                previous,
+                unchanged: drop_unchanged.unchanged,
+            });
+        }
+        halted
+    }
+
+    /// Executes up to max_steps steps and stops before a step if one of the
+    /// breakpoints is hit. The first step is executed without checking the
+    /// breakpoints so that the execution can continue from a breakpoint. Returns the
+    /// number of executed steps.
+    pub fn run_steps(&mut self, max_steps: usize, breakpoints: &[Breakpoint]) -> usize {
+        let mut steps = 0;
+        while steps < max_steps && self.push_state.exec_stack.size() > 0 {
+            if steps > 0 && breakpoints.iter().any(|b| b.is_hit(&self.push_state)) {
+                break;
+            }
+            self.step();
+            steps += 1;
+        }
+        steps
+    }
+
+    /// Reverts the state to the point before the last step. Returns false
+    /// if there is no recorded step left to undo.
+    pub fn step_back(&mut self) -> bool {
+        match self.history.pop_back() {
+            Some(mut delta) => {
+                let mut take_unchanged = TakeUnchanged {
+                    unchanged: delta.unchanged.iter(),
+                };
+                visit_parts(
+                    &mut delta.previous,
+                    &mut self.push_state,
+                    &mut take_unchanged,
+                );
+                self.push_state = delta.previous;
+                self.step_counter -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of steps that can currently be undone.
+    pub fn history_size(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Number of steps executed since the start, excluding undone steps.
+    pub fn step_counter(&self) -> usize {
+        self.step_counter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::parser::PushParser;
+
+    fn runner(input: &str, history_limit: usize) -> Runner {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        Runner::with_history_limit(push_state, instruction_set, history_limit)
+    }
+
+    #[test]
+    fn step_back_restores_previous_stacks() {
+        let mut runner = runner("( 2 3 INTEGER.* INTEGER.DUP )", 10);
+        while !runner.step() {}
+        assert_eq!(runner.push_state.int_stack.to_string(), "6 6");
+        assert!(runner.step_back());
+        assert_eq!(runner.push_state.int_stack.to_string(), "6");
+        assert!(runner.step_back());
+        assert_eq!(runner.push_state.int_stack.to_string(), "3 2");
+        assert_eq!(
+            runner.push_state.exec_stack.to_string(),
+            "INTEGER.* INTEGER.DUP"
+        );
+        assert!(!runner.step());
+        assert_eq!(runner.push_state.int_stack.to_string(), "6");
+    }
+
+    #[test]
+    fn step_back_is_bounded_by_history_limit() {
+        let mut runner = runner("( 1 2 3 4 5 )", 2);
+        while !runner.step() {}
+        assert_eq!(runner.history_size(), 2);
+        assert!(runner.step_back());
+        assert!(runner.step_back());
+        assert!(!runner.step_back());
+        assert_eq!(runner.push_state.int_stack.to_string(), "3 2 1");
+        assert_eq!(runner.step_counter(), 4);
+    }
+
+    #[test]
+    fn history_keeps_only_modified_parts() {
+        let mut runner = runner("( 1 2 3 )", 1);
+        PushParser::copy_to_code_stack(&mut runner.push_state);
+        runner.step();
+        runner.step();
+        let delta = runner.history.back().unwrap();
+        assert_eq!(delta.previous.code_stack.size(), 0);
+        assert_eq!(delta.previous.int_stack.size(), 0);
+        assert!(!runner.push_state.code_stack.is_shared());
+        assert_eq!(delta.unchanged.iter().filter(|u| !**u).count(), 2);
+        assert!(runner.step_back());
+        assert_eq!(runner.push_state.code_stack.to_string(), "( 1 2 3 )");
+        assert_eq!(runner.push_state.exec_stack.to_string(), "1 2 3");
+    }
+
+    #[test]
+    fn step_back_restores_name_bindings_and_graphs() {
+        let mut runner = runner("( 5 x INTEGER.DEFINE GRAPH.ADD )", 10);
+        while !runner.step() {}
+        assert_eq!(runner.push_state.graph_stack.size(), 1);
+        assert!(runner.step_back());
+        assert_eq!(runner.push_state.graph_stack.size(), 0);
+        assert!(runner.push_state.name_bindings.contains_key("x"));
+        assert!(runner.step_back());
+        assert!(runner.push_state.name_bindings.is_empty());
+        assert_eq!(runner.push_state.int_stack.to_string(), "5");
+    }
+
+    #[test]
+    fn run_steps_stops_at_breakpoints() {
+        let mut runner = runner("( 1 2 INTEGER.+ 3 INTEGER.+ INTEGER.DUP )", 10);
+        let breakpoints = vec![Breakpoint::Instruction("INTEGER.+".to_string())];
+        assert_eq!(runner.run_steps(usize::MAX, &breakpoints), 3);
+        assert_eq!(runner.push_state.int_stack.to_string(), "2 1");
+        assert_eq!(runner.run_steps(usize::MAX, &breakpoints), 2);
+        assert_eq!(runner.push_state.int_stack.to_string(), "3 3");
+        assert_eq!(runner.run_steps(1, &breakpoints), 1);
+        assert_eq!(runner.run_steps(5, &[]), 1);
+        assert_eq!(runner.push_state.int_stack.to_string(), "6 6");
+        assert_eq!(runner.run_steps(5, &[]), 0);
+        assert_eq!(runner.step_counter(), 7);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::push::instructions::InstructionSet;
+use crate::push::interpreter::{PushInterpreter, PushInterpreterState};
+use crate::push::item::Item;
+use crate::push::parser::PushParser;
+use crate::push::state::PushState;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Instructions with side effects outside of the PushState that are not available
+/// to untrusted programs.
+pub const IO_INSTRUCTION_PREFIXES: [&str; 4] = ["EXEC.CMD", "INPUT.", "OUTPUT.", "NAME.SEND"];
+
+/// Resource limits for the execution of an untrusted program.
+#[derive(Clone, Debug)]
+pub struct SandboxLimits {
+    // Maximum number of executed steps
+    pub max_steps: i32,
+    // Maximum execution time in milliseconds
+    pub max_time_ms: u64,
+    // Maximum number of elements held by the state, see PushState::memory_size
+    pub max_items: usize,
+    // Maximum number of elements of a single vector, bitmap or matrix item
+    pub max_item_size: usize,
+    // Maximum nesting depth of the parsed program
+    pub max_depth: usize,
+    // Maximum number of points of the parsed program and any CODE item
+    pub max_points: i32,
+}
+
+impl SandboxLimits {
+    pub fn new() -> Self {
+        Self {
+            max_steps: 1000,
+            max_time_ms: 1000,
+            max_items: 1000,
+            max_item_size: 100,
+            max_depth: 32,
+            max_points: 100,
+        }
+    }
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SandboxError {
+    ProgramTooLarge,
+    ProgramTooDeep,
+    StepLimitExceeded,
+    TimeLimitExceeded,
+    MemoryLimitExceeded,
+    Panicked(String),
+}
+
+/// Parses and executes an untrusted program within the given limits. IO instructions
+/// are disabled and panics that unwind are caught and returned as SandboxError::Panicked.
+/// Aborts, e.g. after a failed allocation, cannot be caught. Returns the final state if
+/// the program halted normally.
+pub fn run_untrusted(program: &str, limits: &SandboxLimits) -> Result<PushState, SandboxError> {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        for prefix in IO_INSTRUCTION_PREFIXES.iter() {
+            for name in instruction_set.names_with_prefix(prefix) {
+                instruction_set.remove(&name);
+            }
+        }
+        let mut push_state = PushState::new();
+        push_state.configuration.eval_push_limit = limits.max_steps;
+        push_state.configuration.eval_time_limit = limits.max_time_ms;
+        push_state.configuration.max_points_in_program = limits.max_points;
+        push_state.configuration.growth_cap = limits.max_items;
+        push_state.configuration.max_vector_size = limits.max_item_size;
+        PushParser::parse_program(&mut push_state, &instruction_set, program);
+        for i in 0..push_state.exec_stack.size() {
+            let item = push_state.exec_stack.get(i).unwrap();
+            if Item::size(item) > limits.max_points as usize {
+                return Err(SandboxError::ProgramTooLarge);
+            }
+            if Item::depth(item) > limits.max_depth {
+                return Err(SandboxError::ProgramTooDeep);
+            }
+        }
+        let max_items = limits.max_items;
+        match PushInterpreter::run_until(&mut push_state, &mut instruction_set, |state| {
+            state.memory_size() > max_items
+        }) {
+            // No breakpoints are set so the execution never pauses
+            PushInterpreterState::NoErrors | PushInterpreterState::Paused => Ok(push_state),
+            PushInterpreterState::StepLimitExceeded => Err(SandboxError::StepLimitExceeded),
+            PushInterpreterState::TimeLimitExceeded => Err(SandboxError::TimeLimitExceeded),
+            PushInterpreterState::ProgramTooLarge => Err(SandboxError::ProgramTooLarge),
+            PushInterpreterState::GrowthCapExceeded
+            | PushInterpreterState::TerminatedByPredicate => {
+                Err(SandboxError::MemoryLimitExceeded)
+            }
+        }
+    }));
+    match result {
+        Ok(outcome) => outcome,
+        Err(cause) => {
+            let message = if let Some(msg) = cause.downcast_ref::<&str>() {
+                msg.to_string()
+            } else if let Some(msg) = cause.downcast_ref::<String>() {
+                msg.clone()
+            } else {
+                "Unknown panic".to_string()
+            };
+            Err(SandboxError::Panicked(message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_untrusted_returns_final_state() {
+        let push_state = run_untrusted("( 2 3 INTEGER.* )", &SandboxLimits::new()).unwrap();
+        assert_eq!(push_state.int_stack.to_string(), "6");
+    }
+
+    #[test]
+    fn run_untrusted_disables_io_instructions() {
+        let push_state = run_untrusted("( 0 EXEC.CMD INPUT.AVAILABLE )", &SandboxLimits::new()).unwrap();
+        assert_eq!(push_state.bool_stack.size(), 0);
+        assert_eq!(push_state.name_stack.to_string(), "INPUT.AVAILABLE EXEC.CMD");
+    }
+
+    #[test]
+    fn run_untrusted_enforces_limits() {
+        let mut limits = SandboxLimits::new();
+        assert_eq!(
+            run_untrusted("( EXEC.Y ( 1 ) )", &limits).err(),
+            Some(SandboxError::StepLimitExceeded)
+        );
+        limits.max_items = 10;
+        assert_eq!(
+            run_untrusted("( 1 2 3 4 5 6 7 8 9 10 11 12 )", &limits).err(),
+            Some(SandboxError::MemoryLimitExceeded)
+        );
+        limits.max_depth = 2;
+        assert_eq!(
+            run_untrusted("( ( ( 1 ) ) )", &limits).err(),
+            Some(SandboxError::ProgramTooDeep)
+        );
+        limits.max_points = 3;
+        assert_eq!(
+            run_untrusted("( 1 2 3 )", &limits).err(),
+            Some(SandboxError::ProgramTooLarge)
+        );
+    }
+
+    #[test]
+    fn run_untrusted_counts_elements_and_caps_items() {
+        let mut limits = SandboxLimits::new();
+        let push_state = run_untrusted("( 1000 INTVECTOR.ONES )", &limits).unwrap();
+        assert_eq!(push_state.int_vector_stack.get(0).unwrap().values.len(), 100);
+        limits.max_items = 150;
+        assert_eq!(
+            run_untrusted("( 100 INTVECTOR.ONES 100 INTVECTOR.ONES )", &limits).err(),
+            Some(SandboxError::MemoryLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn run_untrusted_catches_panics() {
+        assert!(matches!(
+            run_untrusted("( INT[ )", &SandboxLimits::new()),
+            Err(SandboxError::Panicked(_))
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::push::audit::instruction_table;
+use crate::push::configuration::{PushConfiguration, PROFILE_NAMES};
+use crate::push::instructions::InstructionSet;
+use crate::push::sandbox::IO_INSTRUCTION_PREFIXES;
+use crate::push::state::{PushState, STACK_NAMES};
+use crate::push::statistics::{pad_stack, PROBED_STACKS};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Returns the short names of the stacks with the given IDs.
+fn stack_names(ids: &[i32]) -> Vec<&'static str> {
+    STACK_NAMES
+        .iter()
+        .filter(|(_, id)| ids.contains(id))
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+/// Returns the doc comment without the instruction name at its beginning.
+fn description(name: &str, doc: &str) -> String {
+    doc.strip_prefix(name)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .unwrap_or(doc)
+        .trim()
+        .to_string()
+}
+
+impl InstructionSet {
+    /// Executes the instruction on a copy of the state where the given stacks are padded
+    /// with default items. Returns the padded state and the state after the execution.
+    fn probe(&mut self, name: &str, state: &PushState, padded: &[i32]) -> (PushState, PushState) {
+        let icache = self.cache();
+        let mut before = state.clone();
+        for id in padded.iter() {
+            pad_stack(&mut before, *id);
+        }
+        let mut after = before.clone();
+        if let Some(instruction) = self.get_instruction(name) {
+            (instruction.execute)(&mut after, &icache);
+        }
+        (before, after)
+    }
+
+    /// Determines the stacks the instruction takes its arguments from and the stacks it
+    /// modifies under the given configuration. The modified stacks are the stacks that
+    /// change when the instruction is executed with all stacks padded (see
+    /// statistics::pad_stack). A stack is an argument if leaving it empty changes the
+    /// effect on the other stacks or if the instruction does not modify it anymore.
+    /// IO instructions are not executed.
+    fn stack_signature(
+        &mut self,
+        name: &str,
+        configuration: &PushConfiguration,
+    ) -> (Vec<&'static str>, Vec<&'static str>) {
+        if IO_INSTRUCTION_PREFIXES.iter().any(|p| name.starts_with(p)) {
+            return (vec![], vec![]);
+        }
+        let mut empty_state = PushState::new();
+        empty_state.configuration = configuration.clone();
+        empty_state.seed(0);
+        let (padded_before, padded_after) = self.probe(name, &empty_state, PROBED_STACKS);
+        let modified = |before: &PushState, after: &PushState, id: i32| {
+            before.stack_to_string(id) != after.stack_to_string(id)
+        };
+        let outputs: Vec<i32> = STACK_NAMES
+            .iter()
+            .map(|(_, id)| *id)
+            .filter(|id| modified(&padded_before, &padded_after, *id))
+            .collect();
+        let mut inputs = vec![];
+        for id in PROBED_STACKS.iter() {
+            let others: Vec<i32> = PROBED_STACKS.iter().cloned().filter(|o| o != id).collect();
+            let (before, after) = self.probe(name, &empty_state, &others);
+            let other_effect_changed =
+                STACK_NAMES
+                    .iter()
+                    .filter(|(_, other)| other != id)
+                    .any(|(_, other)| {
+                        after.stack_to_string(*other) != padded_after.stack_to_string(*other)
+                    });
+            if other_effect_changed || (outputs.contains(id) && !modified(&before, &after, *id)) {
+                inputs.push(*id);
+            }
+        }
+        (stack_names(&inputs), stack_names(&outputs))
+    }
+
+    /// Exports the instruction set as JSON for external tools like program editors or
+    /// grammar-guided generators. Each instruction is described by its name, its type
+    /// (the prefix of the name), its doc comment and its stack signature per semantics
+    /// profile. The signatures are determined by probing (see stack_signature), so that
+    /// they also cover custom instructions. IO instructions and instructions that only
+    /// take effect under special conditions have empty signatures.
+    pub fn to_schema_json(&mut self) -> String {
+        let docs: HashMap<String, Option<String>> = instruction_table()
+            .into_iter()
+            .map(|entry| (entry.name, entry.doc))
+            .collect();
+        let configurations: Vec<(&str, PushConfiguration)> = PROFILE_NAMES
+            .iter()
+            .filter_map(|p| PushConfiguration::profile(p).map(|c| (*p, c)))
+            .collect();
+        let mut names = self.names_with_prefix("");
+        names.sort();
+        let mut instructions = vec![];
+        for name in names.iter() {
+            let mut profiles = Map::new();
+            for (profile, configuration) in configurations.iter() {
+                let (inputs, outputs) = self.stack_signature(name, configuration);
+                profiles.insert(
+                    profile.to_string(),
+                    json!({ "inputs": inputs, "outputs": outputs }),
+                );
+            }
+            let doc = docs
+                .get(name)
+                .cloned()
+                .flatten()
+                .map(|doc| description(name, &doc));
+            instructions.push(json!({
+                "name": name,
+                "type": name.split('.').next().unwrap_or(name),
+                "doc": doc,
+                "profiles": Value::Object(profiles),
+            }));
+        }
+        let stacks: Vec<Value> = STACK_NAMES
+            .iter()
+            .map(|(name, id)| json!({ "name": name, "id": id }))
+            .collect();
+        json!({
+            "profiles": PROFILE_NAMES,
+            "stacks": stacks,
+            "instructions": instructions,
+        })
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::Instruction;
+
+    fn instruction<'a>(schema: &'a Value, name: &str) -> &'a Value {
+        schema["instructions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|i| i["name"] == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn schema_describes_signatures_and_docs() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        instruction_set.add(
+            String::from("CUSTOM.ADDONE"),
+            Instruction::new(|push_state, _| {
+                if let Some(ival) = push_state.int_stack.pop() {
+                    push_state.float_stack.push(ival as f32 + 1.0);
+                }
+            }),
+        );
+        let schema: Value = serde_json::from_str(&instruction_set.to_schema_json()).unwrap();
+        assert_eq!(
+            schema["instructions"].as_array().unwrap().len(),
+            instruction_set.names_with_prefix("").len()
+        );
+        let less = instruction(&schema, "FLOAT.<");
+        assert_eq!(less["type"], "FLOAT");
+        assert!(less["doc"].as_str().unwrap().starts_with("Pushes TRUE"));
+        assert_eq!(less["profiles"]["pushr"]["inputs"], json!(["float"]));
+        assert_eq!(
+            less["profiles"]["pushr"]["outputs"],
+            json!(["bool", "float"])
+        );
+        // Element-wise vector instructions take an offset only if vector_offsets is set
+        let add = instruction(&schema, "INTVECTOR.+");
+        assert_eq!(
+            add["profiles"]["pushr"]["inputs"],
+            json!(["int", "intvector"])
+        );
+        assert_eq!(add["profiles"]["clojush"]["inputs"], json!(["intvector"]));
+        let custom = instruction(&schema, "CUSTOM.ADDONE");
+        assert_eq!(custom["doc"], Value::Null);
+        assert_eq!(custom["profiles"]["strict"]["inputs"], json!(["int"]));
+        assert_eq!(
+            custom["profiles"]["strict"]["outputs"],
+            json!(["float", "int"])
+        );
+    }
+}
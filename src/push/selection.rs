@@ -0,0 +1,187 @@
+use crate::push::vector::FloatVector;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Method to select parents by their errors on the test cases.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Selection {
+    // The individual with the lowest total error out of size random individuals
+    Tournament { size: usize },
+    // Filters the population by the errors on the test cases in random order and
+    // returns a random survivor (Helmuth et al., Solving uncompromising problems with
+    // lexicase selection, 2015)
+    Lexicase,
+    // Lexicase selection that keeps all individuals whose error is within epsilon of the
+    // best error on a case, where epsilon is the median absolute deviation of the errors
+    // on the case (La Cava et al., Epsilon-lexicase selection for regression, 2016)
+    EpsilonLexicase,
+}
+
+/// Selects individuals of a population by their error vectors, i.e. the errors of each
+/// individual on the test cases as FLOATVECTOR. Values that depend on the whole
+/// population like the epsilons of epsilon-lexicase selection are computed once when
+/// the selector is created.
+pub struct Selector {
+    method: Selection,
+    errors: Vec<FloatVector>,
+    total_errors: Vec<f32>,
+    epsilons: FloatVector,
+}
+
+impl Selector {
+    pub fn new(method: Selection, errors: Vec<FloatVector>) -> Self {
+        let total_errors = errors.iter().map(|e| e.values.iter().sum()).collect();
+        let epsilons = match method {
+            Selection::EpsilonLexicase => median_absolute_deviations(&errors),
+            _ => FloatVector::new(vec![]),
+        };
+        Self {
+            method,
+            errors,
+            total_errors,
+            epsilons,
+        }
+    }
+
+    /// Returns the index of the selected individual or None if the population is empty.
+    pub fn select<R: Rng>(&self, rng: &mut R) -> Option<usize> {
+        if self.errors.is_empty() {
+            return None;
+        }
+        Some(match self.method {
+            Selection::Tournament { size } => tournament(rng, &self.total_errors, size),
+            Selection::Lexicase => lexicase(rng, &self.errors, None),
+            Selection::EpsilonLexicase => lexicase(rng, &self.errors, Some(&self.epsilons)),
+        })
+    }
+}
+
+/// Returns the index of the lowest total error out of size randomly chosen indices.
+fn tournament<R: Rng>(rng: &mut R, total_errors: &[f32], size: usize) -> usize {
+    let mut winner = rng.gen_range(0..total_errors.len());
+    for _ in 1..size {
+        let competitor = rng.gen_range(0..total_errors.len());
+        if total_errors[competitor] < total_errors[winner] {
+            winner = competitor;
+        }
+    }
+    winner
+}
+
+/// Keeps the individuals with the lowest error (plus epsilon of the case) on each test
+/// case in random order until a single individual is left or all cases are used.
+/// A case on which no candidate has a comparable error (e.g. all errors are NaN) keeps
+/// all candidates. Returns the index of a random survivor.
+fn lexicase<R: Rng>(rng: &mut R, errors: &[FloatVector], epsilons: Option<&FloatVector>) -> usize {
+    let cases = errors.iter().map(|e| e.values.len()).min().unwrap_or(0);
+    let mut order: Vec<usize> = (0..cases).collect();
+    order.shuffle(rng);
+    let mut candidates: Vec<usize> = (0..errors.len()).collect();
+    for case in order {
+        if candidates.len() == 1 {
+            break;
+        }
+        let best = candidates
+            .iter()
+            .map(|i| errors[*i].values[case])
+            .fold(f32::INFINITY, f32::min);
+        let epsilon = epsilons
+            .and_then(|e| e.values.get(case))
+            .copied()
+            .unwrap_or(0.0);
+        let survivors: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|i| errors[*i].values[case] <= best + epsilon)
+            .collect();
+        if !survivors.is_empty() {
+            candidates = survivors;
+        }
+    }
+    candidates[rng.gen_range(0..candidates.len())]
+}
+
+/// Returns the median of the values. The values must not be empty.
+pub(crate) fn median(values: &mut [f32]) -> f32 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+/// Returns the median absolute deviation of the errors on each test case across the
+/// population.
+pub fn median_absolute_deviations(errors: &[FloatVector]) -> FloatVector {
+    let cases = errors.iter().map(|e| e.values.len()).min().unwrap_or(0);
+    FloatVector::new(
+        (0..cases)
+            .map(|case| {
+                let mut case_errors: Vec<f32> = errors.iter().map(|e| e.values[case]).collect();
+                let case_median = median(&mut case_errors);
+                let mut deviations: Vec<f32> =
+                    case_errors.iter().map(|e| (e - case_median).abs()).collect();
+                median(&mut deviations)
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn errors(values: &[[f32; 2]]) -> Vec<FloatVector> {
+        values.iter().map(|v| FloatVector::new(v.to_vec())).collect()
+    }
+
+    #[test]
+    fn lexicase_selects_specialists() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let population = errors(&[[0.0, 9.0], [9.0, 0.0], [4.0, 4.0]]);
+        let lexicase = Selector::new(Selection::Lexicase, population.clone());
+        let tournament = Selector::new(Selection::Tournament { size: 100 }, population);
+        let mut selected = vec![];
+        for _ in 0..50 {
+            selected.push(lexicase.select(&mut rng).unwrap());
+            assert_eq!(tournament.select(&mut rng), Some(2));
+        }
+        assert!(selected.contains(&0) && selected.contains(&1) && !selected.contains(&2));
+        assert_eq!(Selector::new(Selection::Lexicase, vec![]).select(&mut rng), None);
+    }
+
+    #[test]
+    fn epsilon_lexicase_keeps_nearly_best_errors() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let population = errors(&[[0.0, 4.0], [1.0, 0.0], [2.0, 9.0], [10.0, 9.0], [11.0, 9.0]]);
+        assert_eq!(
+            median_absolute_deviations(&population),
+            FloatVector::new(vec![2.0, 0.0])
+        );
+        // Plain lexicase selects individual 0 whenever case 0 comes first
+        let lexicase = Selector::new(Selection::Lexicase, population.clone());
+        let epsilon_lexicase = Selector::new(Selection::EpsilonLexicase, population);
+        let selected: Vec<usize> = (0..50).map(|_| lexicase.select(&mut rng).unwrap()).collect();
+        assert!(selected.contains(&0));
+        for _ in 0..50 {
+            assert_eq!(epsilon_lexicase.select(&mut rng), Some(1));
+        }
+    }
+
+    #[test]
+    fn lexicase_skips_cases_without_comparable_errors() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let population = errors(&[[f32::NAN, 1.0], [f32::NAN, 2.0]]);
+        for method in [Selection::Lexicase, Selection::EpsilonLexicase].iter() {
+            let selector = Selector::new(*method, population.clone());
+            for _ in 0..20 {
+                assert_eq!(selector.select(&mut rng), Some(0));
+            }
+        }
+    }
+}
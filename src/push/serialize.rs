@@ -0,0 +1,673 @@
+use crate::push::buffer::PushBuffer;
+use crate::push::char::char_literal;
+use crate::push::instructions::InstructionSet;
+use crate::push::item::{Item, PushType};
+use crate::push::parser::PushParser;
+use crate::push::stack::{PushPrint, PushStack};
+use crate::push::state::*;
+use crate::push::vector::{BoolVector, FloatVector, IntVector};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+/// Selects the parts of a PushState that are serialized.
+#[derive(Clone, Debug)]
+pub struct SerializeOptions {
+    // IDs of the stacks to serialize (e.g. INT_STACK_ID)
+    pub stacks: Vec<i32>,
+    // Maximum number of items that are kept from the top of each stack
+    pub max_items_per_stack: Option<usize>,
+    // Serialize name bindings and configuration
+    pub include_bindings: bool,
+    // Serialize the state of the random number generator so that a restored
+    // state continues the random sequence
+    pub include_rng: bool,
+}
+
+impl SerializeOptions {
+    /// Options that serialize the complete state.
+    pub fn all() -> Self {
+        #[allow(unused_mut)]
+        let mut stacks = vec![
+            BOOL_STACK_ID,
+            BOOL_VECTOR_STACK_ID,
+            CODE_STACK_ID,
+            EXEC_STACK_ID,
+            FLOAT_STACK_ID,
+            FLOAT_VECTOR_STACK_ID,
+            INDEX_STACK_ID,
+            INPUT_STACK_ID,
+            INT_STACK_ID,
+            INT_VECTOR_STACK_ID,
+            NAME_STACK_ID,
+            OUTPUT_STACK_ID,
+            GRAPH_STACK_ID,
+            ERROR_STACK_ID,
+            BITMAP_STACK_ID,
+            CHAR_STACK_ID,
+            MATRIX_STACK_ID,
+            VECTOR_LIST_STACK_ID,
+            STRING_STACK_ID,
+        ];
+        #[cfg(feature = "bignum")]
+        stacks.push(BIGINT_STACK_ID);
+        Self {
+            stacks,
+            max_items_per_stack: None,
+            include_bindings: true,
+            include_rng: true,
+        }
+    }
+
+    /// Options that serialize the given stacks only.
+    pub fn stacks(stacks: Vec<i32>) -> Self {
+        Self {
+            stacks,
+            max_items_per_stack: None,
+            include_bindings: false,
+            include_rng: false,
+        }
+    }
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// Serializes the top items of the stack (at most max_items).
+fn stack_value<T>(stack: &PushStack<T>, max_items: Option<usize>) -> Value
+where
+    T: Clone + fmt::Display + PartialEq + PushPrint + Serialize,
+{
+    let elements = stack.as_slice();
+    let start = elements.len() - usize::min(elements.len(), max_items.unwrap_or(elements.len()));
+    json!({ "elements": &elements[start..] })
+}
+
+/// Serializes the first items of the buffer (at most max_items).
+fn buffer_value<T>(buffer: &PushBuffer<T>, max_items: Option<usize>) -> Value
+where
+    T: Clone + fmt::Display + Default + PartialEq + fmt::Debug + Serialize,
+{
+    match max_items {
+        Some(n) if n < buffer.size() => json!(buffer.truncated(n)),
+        _ => json!(buffer),
+    }
+}
+
+/// Stacks whose items have no literal syntax with their keys in the JSON
+/// serialization. They are written as JSON in fixtures.
+const JSON_FIXTURE_STACKS: &[(i32, &str)] = &[
+    (INDEX_STACK_ID, "index_stack"),
+    (INPUT_STACK_ID, "input_stack"),
+    (OUTPUT_STACK_ID, "output_stack"),
+    (GRAPH_STACK_ID, "graph_stack"),
+    (ERROR_STACK_ID, "error_stack"),
+    (BITMAP_STACK_ID, "bitmap_stack"),
+    (MATRIX_STACK_ID, "matrix_stack"),
+    (VECTOR_LIST_STACK_ID, "vector_list_stack"),
+    (STRING_STACK_ID, "string_stack"),
+    #[cfg(feature = "bignum")]
+    (BIGINT_STACK_ID, "bigint_stack"),
+];
+
+fn bool_literal(val: bool) -> String {
+    val.to_string().to_uppercase()
+}
+
+fn bool_vector_literal(val: &BoolVector) -> String {
+    let elements: Vec<&str> = val.bits.iter().map(|b| if b { "1" } else { "0" }).collect();
+    format!("BOOL[{}]", elements.join(","))
+}
+
+fn int_vector_literal(val: &IntVector) -> String {
+    let elements: Vec<String> = val.values.iter().map(|i| i.to_string()).collect();
+    format!("INT[{}]", elements.join(","))
+}
+
+// Floats are written with Debug, the shortest representation that is parsed to the
+// same value
+fn float_vector_literal(val: &FloatVector) -> String {
+    let elements: Vec<String> = val.values.iter().map(|f| format!("{:?}", f)).collect();
+    format!("FLOAT[{}]", elements.join(","))
+}
+
+/// Returns true if all floats of the item are finite. serde_json writes other floats
+/// as null which cannot be restored.
+fn is_finite_item(item: &Item) -> bool {
+    match item {
+        Item::List { items } => items.as_slice().iter().all(is_finite_item),
+        Item::Literal { push_type } => match push_type {
+            PushType::Float { val } => val.is_finite(),
+            PushType::FloatVector { val } => is_finite_slice(&val.values),
+            PushType::Graph { val } => val.has_finite_weights(),
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+fn is_finite_slice(values: &[f32]) -> bool {
+    values.iter().all(|f| f.is_finite())
+}
+
+/// Writes the item in Push syntax such that parsing restores it exactly. Fails for
+/// literals without Push syntax (INDEX and GRAPH).
+fn fixture_item(item: &Item) -> Result<String, String> {
+    Ok(match item {
+        Item::List { items } => {
+            let mut elements = vec!["(".to_string()];
+            for i in 0..items.size() {
+                elements.push(fixture_item(items.get(i).unwrap())?);
+            }
+            elements.push(")".to_string());
+            elements.join(" ")
+        }
+        Item::InstructionMeta { name, .. } | Item::Identifier { name } => name.clone(),
+        Item::Name { name } => format!("'{}", name),
+        Item::Literal { push_type } => match push_type {
+            PushType::Bool { val } => bool_literal(*val),
+            PushType::Int { val } => val.to_string(),
+            PushType::Float { val } => format!("{:?}", val),
+            PushType::Char { val } => char_literal(*val),
+            PushType::BoolVector { val } => bool_vector_literal(val),
+            PushType::IntVector { val } => int_vector_literal(val),
+            PushType::FloatVector { val } => float_vector_literal(val),
+            #[cfg(feature = "bignum")]
+            PushType::BigInt { val } => val.to_string(),
+            _ => return Err(format!("{} has no literal syntax", item)),
+        },
+    })
+}
+
+/// Writes the items of the stack top first.
+fn fixture_stack<T, F>(stack: &PushStack<T>, literal: F) -> Result<String, String>
+where
+    T: Clone + fmt::Display + PartialEq + PushPrint,
+    F: Fn(&T) -> Result<String, String>,
+{
+    let items: Result<Vec<String>, String> = stack.as_slice().iter().rev().map(literal).collect();
+    Ok(items?.join(" "))
+}
+
+/// Parses the code and returns its top-level items in program order.
+fn fixture_items(instruction_set: &InstructionSet, code: &str) -> Vec<Item> {
+    let mut push_state = PushState::new();
+    PushParser::parse_program(&mut push_state, instruction_set, code);
+    push_state.exec_stack.as_slice().iter().rev().cloned().collect()
+}
+
+impl PushState {
+    /// Serializes the parts of the state selected by the options to JSON. Stacks
+    /// that are not selected are omitted and restored as empty stacks by
+    /// PushState::deserialize.
+    pub fn serialize(&self, options: &SerializeOptions) -> String {
+        let max = options.max_items_per_stack;
+        let mut map = Map::new();
+        for stack_id in options.stacks.iter() {
+            let (key, value) = match *stack_id {
+                BOOL_STACK_ID => ("bool_stack", stack_value(&self.bool_stack, max)),
+                BOOL_VECTOR_STACK_ID => ("bool_vector_stack", stack_value(&self.bool_vector_stack, max)),
+                CODE_STACK_ID => ("code_stack", stack_value(&self.code_stack, max)),
+                EXEC_STACK_ID => ("exec_stack", stack_value(&self.exec_stack, max)),
+                FLOAT_STACK_ID => ("float_stack", stack_value(&self.float_stack, max)),
+                FLOAT_VECTOR_STACK_ID => ("float_vector_stack", stack_value(&self.float_vector_stack, max)),
+                INDEX_STACK_ID => ("index_stack", stack_value(&self.index_stack, max)),
+                INPUT_STACK_ID => ("input_stack", buffer_value(&self.input_stack, max)),
+                INT_STACK_ID => ("int_stack", stack_value(&self.int_stack, max)),
+                INT_VECTOR_STACK_ID => ("int_vector_stack", stack_value(&self.int_vector_stack, max)),
+                NAME_STACK_ID => ("name_stack", stack_value(&self.name_stack, max)),
+                OUTPUT_STACK_ID => ("output_stack", buffer_value(&self.output_stack, max)),
+                GRAPH_STACK_ID => ("graph_stack", buffer_value(&self.graph_stack, max)),
+                ERROR_STACK_ID => ("error_stack", stack_value(&self.error_stack, max)),
+                BITMAP_STACK_ID => ("bitmap_stack", stack_value(&self.bitmap_stack, max)),
+                CHAR_STACK_ID => ("char_stack", stack_value(&self.char_stack, max)),
+                MATRIX_STACK_ID => ("matrix_stack", stack_value(&self.matrix_stack, max)),
+                VECTOR_LIST_STACK_ID => ("vector_list_stack", stack_value(&self.vector_list_stack, max)),
+                STRING_STACK_ID => ("string_stack", stack_value(&self.string_stack, max)),
+                #[cfg(feature = "bignum")]
+                BIGINT_STACK_ID => ("bigint_stack", stack_value(&self.bigint_stack, max)),
+                _ => continue,
+            };
+            map.insert(key.to_string(), value);
+        }
+        if options.include_bindings {
+            map.insert("name_bindings".to_string(), json!(self.name_bindings));
+            map.insert("tag_space".to_string(), json!(self.tag_space));
+            map.insert("configuration".to_string(), json!(self.configuration));
+            map.insert("quote_name".to_string(), json!(self.quote_name));
+            map.insert("send_name".to_string(), json!(self.send_name));
+        }
+        if options.include_rng {
+            map.insert("rng".to_string(), json!(self.rng));
+        }
+        Value::Object(map).to_string()
+    }
+
+    /// Restores a state from JSON. Omitted parts are initialized
+    /// as in PushState::new.
+    pub fn deserialize(json: &str) -> Result<PushState, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    /// Serializes the complete state to JSON, e.g. to checkpoint a long-running
+    /// experiment. Unlike serialize, this includes the binding scopes, the
+    /// instruction weights and the counters. The constants are not serialized.
+    /// Fails if a stack, a binding, the tag space, the input and output registers or
+    /// the instruction weights contain a float that is not finite.
+    pub fn to_json(&self) -> Result<String, String> {
+        let mut bindings = self
+            .name_bindings
+            .values()
+            .chain(self.binding_scopes.iter().flat_map(|(_, scope)| scope.values()));
+        let finite_parts = [
+            ("FLOAT stack", is_finite_slice(self.float_stack.as_slice())),
+            (
+                "FLOATVECTOR stack",
+                self.float_vector_stack.as_slice().iter().all(|v| is_finite_slice(&v.values)),
+            ),
+            (
+                "MATRIX stack",
+                self.matrix_stack.as_slice().iter().all(|m| is_finite_slice(&m.values)),
+            ),
+            (
+                "VECTORLIST stack",
+                self.vector_list_stack
+                    .as_slice()
+                    .iter()
+                    .all(|l| l.rows.iter().all(|v| is_finite_slice(&v.values))),
+            ),
+            ("GRAPH stack", self.graph_stack.iter().all(|g| g.has_finite_weights())),
+            ("CODE stack", self.code_stack.as_slice().iter().all(is_finite_item)),
+            ("EXEC stack", self.exec_stack.as_slice().iter().all(is_finite_item)),
+            ("Name bindings", bindings.all(is_finite_item)),
+            ("Tag space", self.tag_space.values().all(is_finite_item)),
+            (
+                "Registers",
+                self.inputs.iter().chain(self.outputs.iter()).all(is_finite_item),
+            ),
+            (
+                "Instruction weights",
+                self.instruction_weights.values().all(|w| w.is_finite()),
+            ),
+        ];
+        if let Some((part, _)) = finite_parts.iter().find(|(_, finite)| !finite) {
+            return Err(format!("{} contains a value that is not finite", part));
+        }
+        serde_json::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// Restores a state written by to_json.
+    pub fn from_json(json: &str) -> Result<PushState, String> {
+        PushState::deserialize(json)
+    }
+
+    /// Writes the stacks, the bindings, the tag space and the input and output registers
+    /// in the fixture format, a line based text format for golden files and bug
+    /// reports:
+    ///
+    /// ```text
+    /// int: 3 2 1
+    /// float: 0.5
+    /// exec: ( 1 INTEGER.+ ) X
+    /// graph: {"elements":[...]}
+    /// bind X: ( 2 INTEGER.* )
+    /// tag 10: FLOAT.+
+    /// inputs: 3 TRUE
+    /// ```
+    ///
+    /// Each line holds the items of a stack top first in Push syntax. Floats are written
+    /// exactly. Stacks without literal syntax (e.g. GRAPH) are written as JSON. Empty
+    /// stacks are omitted, stacks are written in the order of STACK_NAMES and bindings
+    /// and tags are sorted, so equal states give equal fixtures. The configuration and
+    /// the random number generator are not part of the fixture. Fails if a CODE or EXEC
+    /// item contains an INDEX or GRAPH literal.
+    pub fn to_fixture(&self) -> Result<String, String> {
+        let mut lines = vec![];
+        for (name, id) in STACK_NAMES.iter() {
+            let value = match *id {
+                BOOL_STACK_ID => fixture_stack(&self.bool_stack, |b| Ok(bool_literal(*b)))?,
+                BOOL_VECTOR_STACK_ID => {
+                    fixture_stack(&self.bool_vector_stack, |v| Ok(bool_vector_literal(v)))?
+                }
+                CODE_STACK_ID => fixture_stack(&self.code_stack, fixture_item)?,
+                EXEC_STACK_ID => fixture_stack(&self.exec_stack, fixture_item)?,
+                FLOAT_STACK_ID => fixture_stack(&self.float_stack, |f| Ok(format!("{:?}", f)))?,
+                FLOAT_VECTOR_STACK_ID => {
+                    fixture_stack(&self.float_vector_stack, |v| Ok(float_vector_literal(v)))?
+                }
+                INT_STACK_ID => fixture_stack(&self.int_stack, |i| Ok(i.to_string()))?,
+                INT_VECTOR_STACK_ID => {
+                    fixture_stack(&self.int_vector_stack, |v| Ok(int_vector_literal(v)))?
+                }
+                NAME_STACK_ID => fixture_stack(&self.name_stack, |n| Ok(format!("'{}", n)))?,
+                CHAR_STACK_ID => fixture_stack(&self.char_stack, |c| Ok(char_literal(*c)))?,
+                _ if self.stack_size(*id) != Some(0) => {
+                    let json: Value = serde_json::from_str(&self.serialize(&SerializeOptions::stacks(vec![*id])))
+                        .map_err(|e| e.to_string())?;
+                    json.as_object()
+                        .and_then(|stacks| stacks.values().next())
+                        .map_or(String::new(), |stack| stack.to_string())
+                }
+                _ => String::new(),
+            };
+            if !value.is_empty() {
+                lines.push(format!("{}: {}", name, value));
+            }
+        }
+        let mut bindings: Vec<(&String, &Item)> = self.name_bindings.iter().collect();
+        bindings.sort_by_key(|(name, _)| *name);
+        for (name, item) in bindings {
+            lines.push(format!("bind {}: {}", name, fixture_item(item)?));
+        }
+        for (tag, item) in self.tag_space.iter() {
+            lines.push(format!("tag {}: {}", tag, fixture_item(item)?));
+        }
+        for (name, registers) in [("inputs", &self.inputs), ("outputs", &self.outputs)] {
+            if !registers.is_empty() {
+                let items: Result<Vec<String>, String> = registers.iter().map(fixture_item).collect();
+                lines.push(format!("{}: {}", name, items?.join(" ")));
+            }
+        }
+        Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+    }
+
+    /// Restores a state from the fixture format (see to_fixture). Empty lines and lines
+    /// starting with '#' are ignored. Instruction names are recognized with the default
+    /// instruction set. Parts that are not given are initialized as in PushState::new.
+    pub fn from_fixture(fixture: &str) -> Result<PushState, String> {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut push_state = PushState::new();
+        for (number, line) in fixture.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", number + 1, message);
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| error("missing ':'".to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+            let single_item = || {
+                let mut items = fixture_items(&instruction_set, value);
+                if items.len() == 1 {
+                    items.pop().unwrap()
+                } else {
+                    Item::list(items.into_iter().rev().collect())
+                }
+            };
+            if let Some(name) = key.strip_prefix("bind ") {
+                push_state.name_bindings.insert(name.trim().to_string(), single_item());
+            } else if let Some(tag) = key.strip_prefix("tag ") {
+                let tag = tag.trim().parse().map_err(|_| error(format!("invalid tag {}", tag)))?;
+                push_state.tag_space.insert(tag, single_item());
+            } else if key == "inputs" {
+                push_state.inputs = fixture_items(&instruction_set, value);
+            } else if key == "outputs" {
+                push_state.outputs = fixture_items(&instruction_set, value);
+            } else {
+                let id = stack_id(key).ok_or_else(|| error(format!("unknown stack {}", key)))?;
+                push_state
+                    .load_fixture_stack(id, value, &instruction_set)
+                    .map_err(error)?;
+            }
+        }
+        Ok(push_state)
+    }
+
+    /// Replaces the stack with the items of a fixture line.
+    fn load_fixture_stack(
+        &mut self,
+        id: i32,
+        value: &str,
+        instruction_set: &InstructionSet,
+    ) -> Result<(), String> {
+        if let Some((_, key)) = JSON_FIXTURE_STACKS.iter().find(|(i, _)| *i == id) {
+            let json = format!("{{\"{}\": {}}}", key, value);
+            let restored = PushState::deserialize(&json)?;
+            match id {
+                INDEX_STACK_ID => self.index_stack = restored.index_stack,
+                INPUT_STACK_ID => self.input_stack = restored.input_stack,
+                OUTPUT_STACK_ID => self.output_stack = restored.output_stack,
+                GRAPH_STACK_ID => self.graph_stack = restored.graph_stack,
+                ERROR_STACK_ID => self.error_stack = restored.error_stack,
+                BITMAP_STACK_ID => self.bitmap_stack = restored.bitmap_stack,
+                MATRIX_STACK_ID => self.matrix_stack = restored.matrix_stack,
+                VECTOR_LIST_STACK_ID => self.vector_list_stack = restored.vector_list_stack,
+                STRING_STACK_ID => self.string_stack = restored.string_stack,
+                #[cfg(feature = "bignum")]
+                BIGINT_STACK_ID => self.bigint_stack = restored.bigint_stack,
+                _ => (),
+            }
+            return Ok(());
+        }
+        // Bottom item first
+        for item in fixture_items(instruction_set, value).into_iter().rev() {
+            let invalid = || format!("invalid item {} for stack {}", item, id);
+            match (id, &item) {
+                (CODE_STACK_ID, _) => self.code_stack.push(item.clone()),
+                (EXEC_STACK_ID, _) => self.exec_stack.push(item.clone()),
+                (NAME_STACK_ID, Item::Name { name }) | (NAME_STACK_ID, Item::Identifier { name }) => {
+                    self.name_stack.push(name.clone())
+                }
+                (_, Item::Literal { push_type }) => match (id, push_type) {
+                    (BOOL_STACK_ID, PushType::Bool { val }) => self.bool_stack.push(*val),
+                    (INT_STACK_ID, PushType::Int { val }) => self.int_stack.push(*val),
+                    (FLOAT_STACK_ID, PushType::Float { val }) => self.float_stack.push(*val),
+                    (CHAR_STACK_ID, PushType::Char { val }) => self.char_stack.push(*val),
+                    (BOOL_VECTOR_STACK_ID, PushType::BoolVector { val }) => {
+                        self.bool_vector_stack.push(val.clone())
+                    }
+                    (INT_VECTOR_STACK_ID, PushType::IntVector { val }) => {
+                        self.int_vector_stack.push(val.clone())
+                    }
+                    (FLOAT_VECTOR_STACK_ID, PushType::FloatVector { val }) => {
+                        self.float_vector_stack.push(val.clone())
+                    }
+                    _ => return Err(invalid()),
+                },
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::bitmap::Bitmap;
+    use crate::push::error::PushError;
+    use crate::push::graph::Graph;
+    use crate::push::index::Index;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::interpreter::PushInterpreter;
+    use crate::push::item::Item;
+    use crate::push::io::PushMessage;
+    use crate::push::parser::PushParser;
+    use crate::push::vector::{BoolVector, FloatVector, IntVector};
+    use std::collections::HashMap;
+
+    #[test]
+    fn serialize_selected_stacks_only() {
+        let mut push_state = PushState::new();
+        push_state.int_stack.push_vec(vec![1, 2, 3]);
+        push_state.float_stack.push(1.5);
+        push_state.exec_stack.push(Item::list(vec![Item::int(1), Item::noop()]));
+        push_state.name_bindings.insert("X".to_string(), Item::int(2));
+        let mut options = SerializeOptions::stacks(vec![INT_STACK_ID, EXEC_STACK_ID]);
+        options.max_items_per_stack = Some(2);
+        let restored = PushState::deserialize(&push_state.serialize(&options)).unwrap();
+        assert_eq!(restored.int_stack.to_string(), "3 2");
+        assert_eq!(restored.exec_stack.to_string(), "( NOOP 1 )");
+        assert_eq!(restored.float_stack.size(), 0);
+        assert_eq!(restored.name_bindings.len(), 0);
+        assert_eq!(restored.graph_stack.capacity(), GRAPH_BUFFER_SIZE);
+    }
+
+    #[test]
+    fn to_json_round_trip_restores_complete_state() {
+        let mut push_state = PushState::new();
+        push_state.seed(505);
+        push_state.bool_stack.push(true);
+        push_state.char_stack.push('x');
+        push_state.float_stack.push(-0.25);
+        push_state.index_stack.push(Index::new(3));
+        push_state.int_stack.push_vec(vec![1, 2]);
+        push_state.name_stack.push("A".to_string());
+        push_state.code_stack.push(Item::list(vec![Item::int(1), Item::noop()]));
+        push_state.exec_stack.push(Item::char('y'));
+        push_state.bool_vector_stack.push(BoolVector::new(vec![true, false]));
+        push_state.int_vector_stack.push(IntVector::new(vec![4, 5]));
+        push_state.float_vector_stack.push(FloatVector::new(vec![0.5]));
+        push_state.bitmap_stack.push(Bitmap::zeros(2, 3));
+        push_state.input_stack.push(PushMessage::new(
+            IntVector::new(vec![1]),
+            BoolVector::new(vec![true]),
+        ));
+        let mut graph = Graph::new();
+        let a = graph.add_node(1);
+        let b = graph.add_node(2);
+        graph.add_edge(a, b, 0.5);
+        push_state.graph_stack.push(graph);
+        push_state.error_stack.push(PushError::IndexOutOfRange { index: 3, len: 2 });
+        push_state.name_bindings.insert("X".to_string(), Item::int(2));
+        push_state.name_bindings.insert("Y".to_string(), Item::instruction("INTEGER.+".to_string()));
+        push_state.binding_scopes.push((1, HashMap::new()));
+        push_state.instruction_weights.insert("INTEGER.+".to_string(), 2.0);
+        push_state.div_zero_count = 3;
+        push_state.configuration.strict_indexing = true;
+
+        let json = push_state.to_json().unwrap();
+        let restored = PushState::from_json(&json).unwrap();
+        let as_value = |s: &str| serde_json::from_str::<Value>(s).unwrap();
+        assert_eq!(as_value(&restored.to_json().unwrap()), as_value(&json));
+        assert_eq!(restored.code_stack.to_string(), "( NOOP 1 )");
+        assert_eq!(restored.graph_stack.get(0).unwrap().get_weight(&a, &b), Some(0.5));
+        assert_eq!(restored.name_bindings.get("Y"), push_state.name_bindings.get("Y"));
+        assert_eq!(restored.binding_scopes.len(), 1);
+        assert_eq!(restored.div_zero_count, 3);
+        assert!(restored.configuration.strict_indexing);
+        push_state.float_stack.push(f32::NAN);
+        assert!(push_state.to_json().is_err());
+    }
+
+    #[test]
+    fn to_json_rejects_non_finite_floats_of_all_stacks() {
+        let mut push_state = PushState::new();
+        push_state.float_vector_stack.push(FloatVector::new(vec![0.5, f32::NAN]));
+        assert_eq!(
+            push_state.to_json(),
+            Err("FLOATVECTOR stack contains a value that is not finite".to_string())
+        );
+        push_state.float_vector_stack.pop();
+        let json = push_state.to_json().unwrap();
+        assert!(PushState::from_json(&json).is_ok());
+        push_state.exec_stack.push(Item::list(vec![Item::floatvec(FloatVector::new(vec![
+            f32::INFINITY,
+        ]))]));
+        assert!(push_state.to_json().is_err());
+        push_state.exec_stack.pop();
+        let mut graph = Graph::new();
+        let a = graph.add_node(1);
+        graph.add_edge(a, a, f32::NAN);
+        push_state.graph_stack.push(graph);
+        assert!(push_state.to_json().is_err());
+    }
+
+    #[test]
+    fn fixture_round_trip_restores_stacks_and_bindings() {
+        let mut push_state = PushState::new();
+        push_state.bool_stack.push_vec(vec![false, true]);
+        push_state.char_stack.push(' ');
+        push_state.float_stack.push_vec(vec![0.1, -2.5e-8, f32::INFINITY]);
+        push_state.int_stack.push_vec(vec![1, 2, 3]);
+        push_state.name_stack.push("A".to_string());
+        push_state.int_vector_stack.push(IntVector::new(vec![]));
+        push_state.float_vector_stack.push(FloatVector::new(vec![0.1, 2.0]));
+        push_state.bool_vector_stack.push(BoolVector::new(vec![true, false]));
+        push_state.exec_stack.push(Item::list(vec![
+            Item::quoted_name("B".to_string()),
+            Item::list(vec![]),
+            Item::instruction("INTEGER.+".to_string()),
+            Item::float(1.0),
+        ]));
+        push_state.exec_stack.push(Item::id("X".to_string()));
+        push_state.bitmap_stack.push(Bitmap::zeros(1, 2));
+        push_state.name_bindings.insert("X".to_string(), Item::int(2));
+        push_state.name_bindings.insert("Y".to_string(), Item::list(vec![Item::int(1), Item::int(2)]));
+        push_state.tag_space.insert(10, Item::char('x'));
+        push_state.inputs = vec![Item::int(3), Item::bool(true)];
+        push_state.string_stack.push("a b\n".to_string());
+
+        let fixture = push_state.to_fixture().unwrap();
+        assert!(fixture.starts_with("bool: TRUE FALSE\nboolvector: BOOL[1,0]\nexec: X ( 1.0 INTEGER.+ ( ) 'B )\n"));
+        assert!(fixture.contains("\nfloat: inf -2.5e-8 0.1\nfloatvector: FLOAT[0.1,2.0]\nint: 3 2 1\nintvector: INT[]\n"));
+        assert!(fixture.ends_with("\nbind X: 2\nbind Y: ( 2 1 )\ntag 10: \\x\ninputs: 3 TRUE\n"));
+        let restored = PushState::from_fixture(&format!("# Reproduction\n\n{}", fixture)).unwrap();
+        assert_eq!(restored.to_fixture().unwrap(), fixture);
+        assert_eq!(restored.float_stack.get(1), Some(&-2.5e-8));
+        assert_eq!(restored.bitmap_stack.get(0), Some(&Bitmap::zeros(1, 2)));
+        assert_eq!(restored.string_stack.get(0), Some(&"a b\n".to_string()));
+
+        assert_eq!(
+            PushState::from_fixture("int: 1\nint 2").err(),
+            Some("line 2: missing ':'".to_string())
+        );
+        assert!(PushState::from_fixture("int: TRUE").is_err());
+        assert!(PushState::from_fixture("stack: 1").is_err());
+        push_state.code_stack.push(Item::list(vec![Item::index(Index::new(1))]));
+        assert!(push_state.to_fixture().is_err());
+    }
+
+    #[test]
+    fn serialize_truncates_buffers_from_top() {
+        let mut push_state = PushState::new();
+        for i in 1..4 {
+            let mut graph = Graph::new();
+            graph.add_node(i);
+            push_state.graph_stack.push(graph);
+        }
+        let mut options = SerializeOptions::all();
+        options.max_items_per_stack = Some(2);
+        let restored = PushState::deserialize(&push_state.serialize(&options)).unwrap();
+        assert_eq!(restored.graph_stack.size(), 2);
+        assert_eq!(restored.graph_stack.get(0).unwrap().get_state(&1), Some(3));
+        assert_eq!(restored.graph_stack.get(1).unwrap().get_state(&1), Some(2));
+    }
+
+    #[test]
+    fn resumed_checkpoint_continues_random_sequence() {
+        let program = "( INTEGER.RAND FLOAT.RAND BOOLEAN.RAND NAME.RAND 5 CODE.RAND \
+                       8 0.3 BOOLVECTOR.RAND 4 -5 5 INTVECTOR.RAND 6 0.0 1.0 FLOATVECTOR.RAND \
+                       INTEGER.RAND FLOAT.RAND BOOLEAN.RAND NAME.RAND 5 CODE.RAND )";
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let icache = instruction_set.cache();
+        let mut initial_state = PushState::new();
+        initial_state.seed(42);
+        PushParser::parse_program(&mut initial_state, &instruction_set, program);
+        let mut uninterrupted = initial_state.clone();
+        while !PushInterpreter::step(&mut uninterrupted, &mut instruction_set, &icache) {}
+        for checkpoint_step in [1, 8, 20] {
+            let mut interrupted = initial_state.clone();
+            for _ in 0..checkpoint_step {
+                PushInterpreter::step(&mut interrupted, &mut instruction_set, &icache);
+            }
+            let checkpoint = interrupted.serialize(&SerializeOptions::all());
+            let mut resumed = PushState::deserialize(&checkpoint).unwrap();
+            while !PushInterpreter::step(&mut resumed, &mut instruction_set, &icache) {}
+            assert_eq!(
+                resumed.serialize(&SerializeOptions::all()),
+                uninterrupted.serialize(&SerializeOptions::all())
+            );
+        }
+        // Without the state of the generator the sequence is not continued
+        let mut options = SerializeOptions::all();
+        options.include_rng = false;
+        assert!(!initial_state.serialize(&options).contains("\"rng\""));
+    }
+}
@@ -0,0 +1,198 @@
+use crate::push::item::Item;
+
+/// Minimizes a program with delta debugging (ddmin). Points and sublists are removed as
+/// long as the predicate holds for the reduced program, e.g. "still reproduces the crash"
+/// or "error stays below X". Sublists with a single element are replaced by this element
+/// if the predicate allows it.
+///
+/// The lists of the program are reduced top-down, so that large parts are removed first,
+/// and the passes are repeated until no further point can be removed. The predicate
+/// should be deterministic. If it does not hold for the original program, the program
+/// is returned unchanged.
+pub fn shrink<F>(program: &Item, mut predicate: F) -> Item
+where
+    F: FnMut(&Item) -> bool,
+{
+    let mut current = program.clone();
+    if !predicate(&current) {
+        return current;
+    }
+    loop {
+        let size_before_pass = Item::size(&current);
+        let mut i = 0;
+        loop {
+            // Reducing the list at position i of the pre-order only changes its
+            // subtree, the positions of the lists before it are stable.
+            let paths = list_paths(&current);
+            if i >= paths.len() {
+                break;
+            }
+            current = ddmin(&current, &paths[i], &mut predicate);
+            if let Some(hoisted) = hoist(&current, &paths[i]) {
+                if predicate(&hoisted) {
+                    current = hoisted;
+                    continue;
+                }
+            }
+            i += 1;
+        }
+        if Item::size(&current) == size_before_pass {
+            return current;
+        }
+    }
+}
+
+/// Returns the elements of the list at the given path. Elements and paths use the
+/// storage order of the list stack.
+fn children_at(item: &Item, path: &[usize]) -> Vec<Item> {
+    match (item, path.split_first()) {
+        (Item::List { items }, None) => items.as_slice().to_vec(),
+        (Item::List { items }, Some((first, rest))) => items
+            .as_slice()
+            .get(*first)
+            .map_or(vec![], |child| children_at(child, rest)),
+        _ => vec![],
+    }
+}
+
+/// Returns a copy of the item where the node at the given path is replaced.
+fn replace_at(item: &Item, path: &[usize], replacement: Item) -> Item {
+    match (item, path.split_first()) {
+        (_, None) => replacement,
+        (Item::List { items }, Some((first, rest))) => Item::list(
+            items
+                .as_slice()
+                .iter()
+                .enumerate()
+                .map(|(i, child)| {
+                    if i == *first {
+                        replace_at(child, rest, replacement.clone())
+                    } else {
+                        child.clone()
+                    }
+                })
+                .collect(),
+        ),
+        _ => item.clone(),
+    }
+}
+
+/// Returns the paths of all lists of the item in pre-order.
+fn list_paths(item: &Item) -> Vec<Vec<usize>> {
+    let mut paths = vec![];
+    collect_list_paths(item, &mut vec![], &mut paths);
+    paths
+}
+
+fn collect_list_paths(item: &Item, path: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+    if let Item::List { items } = item {
+        paths.push(path.clone());
+        // Top element first, i.e. in the order of the printed program
+        for (i, child) in items.as_slice().iter().enumerate().rev() {
+            path.push(i);
+            collect_list_paths(child, path, paths);
+            path.pop();
+        }
+    }
+}
+
+/// Removes elements of the list at the given path while the predicate holds. The
+/// elements are split into chunks of decreasing size and the first chunk whose
+/// removal keeps the predicate is dropped.
+fn ddmin<F>(program: &Item, path: &[usize], predicate: &mut F) -> Item
+where
+    F: FnMut(&Item) -> bool,
+{
+    let mut current = program.clone();
+    let mut children = children_at(&current, path);
+    let mut granularity = 2;
+    while !children.is_empty() {
+        let chunk_size = (children.len() + granularity - 1) / granularity;
+        let mut reduced = false;
+        for start in (0..children.len()).step_by(chunk_size) {
+            let remaining: Vec<Item> = children
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i < start || *i >= start + chunk_size)
+                .map(|(_, child)| child.clone())
+                .collect();
+            let candidate = replace_at(&current, path, Item::list(remaining.clone()));
+            if predicate(&candidate) {
+                current = candidate;
+                children = remaining;
+                granularity = usize::max(granularity - 1, 2);
+                reduced = true;
+                break;
+            }
+        }
+        if !reduced {
+            if granularity >= children.len() {
+                break;
+            }
+            granularity = usize::min(granularity * 2, children.len());
+        }
+    }
+    current
+}
+
+/// Returns a copy of the program where the list at the given path is replaced by its
+/// only element. Returns None for the top-level list and lists with more elements.
+fn hoist(program: &Item, path: &[usize]) -> Option<Item> {
+    if path.is_empty() {
+        return None;
+    }
+    let mut children = children_at(program, path);
+    if children.len() != 1 {
+        return None;
+    }
+    Some(replace_at(program, path, children.pop().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::interpreter::PushInterpreter;
+    use crate::push::parser::PushParser;
+    use crate::push::state::PushState;
+
+    fn parse(instruction_set: &InstructionSet, code: &str) -> Item {
+        let mut push_state = PushState::new();
+        PushParser::parse_program(&mut push_state, instruction_set, code);
+        push_state.exec_stack.pop().unwrap()
+    }
+
+    #[test]
+    fn shrink_removes_points_and_sublists() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program = parse(
+            &instruction_set,
+            "( 1 ( 2 3 INTEGER.+ ) ( 4 ( 5 INTEGER./ ) ) 6 )",
+        );
+        let shrunk = shrink(&program, |p| p.to_string().contains("INTEGER./"));
+        assert_eq!(shrunk.to_string(), "( INTEGER./ )");
+        // The program is unchanged if the predicate does not hold
+        let unchanged = shrink(&program, |p| p.to_string().contains("INTEGER.-"));
+        assert_eq!(unchanged, program);
+    }
+
+    #[test]
+    fn shrink_keeps_behavior_of_interest() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program = parse(
+            &instruction_set,
+            "( 3 INTEGER.DUP 4 ( FLOAT.DUP 1.0 ) INTEGER.* 7 BOOLEAN.NOT )",
+        );
+        let mut calls = 0;
+        let shrunk = shrink(&program, |p| {
+            calls += 1;
+            let mut push_state = PushState::new();
+            PushInterpreter::run_program(&p.to_string(), &mut push_state, &mut instruction_set);
+            push_state.int_stack.as_slice().contains(&12)
+        });
+        assert_eq!(shrunk.to_string(), "( 3 4 INTEGER.* )");
+        assert!(calls > 1);
+    }
+}
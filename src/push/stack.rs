@@ -1,8 +1,15 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
-
-#[derive(Clone, Debug)]
+use std::sync::Arc;
+
+/// Stack of items. The elements are shared between clones of the stack and copied
+/// when a clone is modified (copy-on-write), so that cloning a stack, e.g. a CODE
+/// item that is duplicated or quoted, takes constant time. Since lists are stacks of
+/// items, nested lists are shared as well and a modification only copies the path
+/// to the modified list.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PushStack<T> {
-    elements: Vec<T>,
+    elements: Arc<Vec<T>>,
 }
 
 pub trait PushPrint {
@@ -39,14 +46,43 @@ where
 {
     pub fn new() -> Self {
         Self {
-            elements: Vec::new(),
+            elements: Arc::new(Vec::new()),
         }
     }
 
     /// Initializes the stack with the argument. Its last
     /// element becomes the top element of the stack.
     pub fn from_vec(elements: Vec<T>) -> Self {
-        Self { elements: elements }
+        Self {
+            elements: Arc::new(elements),
+        }
+    }
+
+    /// Returns the elements of the stack from bottom to top.
+    pub fn as_slice(&self) -> &[T] {
+        &self.elements
+    }
+
+    /// Returns the elements of the stack from bottom to top for modification.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self.elements_mut()
+    }
+
+    /// Returns true if the elements are shared with another stack.
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.elements) > 1
+    }
+
+    /// Returns true if the elements are shared with the other stack, i.e. neither
+    /// stack has been modified since one was cloned from the other.
+    pub fn shares_elements(&self, other: &PushStack<T>) -> bool {
+        Arc::ptr_eq(&self.elements, &other.elements)
+    }
+
+    /// Returns the elements for modification. They are copied first if they are
+    /// shared with another stack.
+    fn elements_mut(&mut self) -> &mut Vec<T> {
+        Arc::make_mut(&mut self.elements)
     }
 
     /// Prints the stack from top to bottom enumerating its elements.
@@ -85,7 +121,7 @@ where
     /// of the stack.
     pub fn bottom_mut(&mut self) -> Option<&mut T> {
         if self.size() > 0 {
-            self.elements.first_mut()
+            self.elements_mut().first_mut()
         } else {
             None
         }
@@ -93,7 +129,7 @@ where
 
     /// Removes all elements from the stack.
     pub fn flush(&mut self) {
-        self.elements = Vec::new();
+        self.elements = Arc::new(Vec::new());
     }
 
     /// Replace element at position i counting from the top. In case the index does not
@@ -102,7 +138,7 @@ where
         let size = &mut self.size();
         match i.checked_sub(*size) {
             None => {
-                let _ = std::mem::replace(&mut self.elements[*size - (i + 1)], new_el);
+                let _ = std::mem::replace(&mut self.elements_mut()[*size - (i + 1)], new_el);
                 Ok(())
             }
             Some(diff) => Err(diff + 1),
@@ -113,13 +149,13 @@ where
     pub fn remove(&mut self, i: usize) {
         let size = self.size();
         if i < size {
-            self.elements.remove(size - (i + 1));
+            self.elements_mut().remove(size - (i + 1));
         }
     }
 
     /// Reverse elements of stack.
     pub fn reverse(&mut self) {
-        self.elements.reverse();
+        self.elements_mut().reverse();
     }
 
     /// Returns a mutable reference to the element at stack position i counting
@@ -127,7 +163,7 @@ where
     pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
         let size = &mut self.size();
         if i < *size {
-            Some(&mut self.elements[*size - (i + 1)])
+            Some(&mut self.elements_mut()[*size - (i + 1)])
         } else {
             None
         }
@@ -146,20 +182,22 @@ where
 
     /// Pushes element to the top of the stack.
     pub fn push(&mut self, value: T) {
-        self.elements.push(value);
+        self.elements_mut().push(value);
     }
 
     /// Pushes element to the bottom of the stack.
     pub fn push_front(&mut self, value: T) {
-        self.elements.insert(0, value);
+        self.elements_mut().insert(0, value);
     }
 
     /// Removes an indexed item from stack position i counting from the top and
     /// pushes it on top of the stack.
     pub fn yank(&mut self, index: usize) {
         if index > 0 && index < self.size() {
-            let el = self.elements.remove(self.size() - (index + 1));
-            self.elements.push(el);
+            let top_down_index = self.size() - (index + 1);
+            let elements = self.elements_mut();
+            let el = elements.remove(top_down_index);
+            elements.push(el);
         }
     }
 
@@ -167,16 +205,16 @@ where
     /// position index counting from the top.
     pub fn shove(&mut self, index: usize) {
         if index > 0 && index < self.size() {
-            if let Some(el) = self.elements.pop() {
+            if let Some(el) = self.elements_mut().pop() {
                 let top_down_index = self.size() - index;
-                self.elements.insert(top_down_index, el);
+                self.elements_mut().insert(top_down_index, el);
             }
         }
     }
 
     /// Swaps vector elements
     pub fn swap(&mut self, i: usize, j: usize) {
-        self.elements.swap(i, j);
+        self.elements_mut().swap(i, j);
     }
 
     /// Removes the bottom element from the stack and returns it.
@@ -184,12 +222,26 @@ where
         if self.elements.is_empty() {
             return None;
         }
-        Some(self.elements.remove(0))
+        Some(self.elements_mut().remove(0))
     }
 
     /// Removes the top element from the stack and returns it.
     pub fn pop(&mut self) -> Option<T> {
-        self.elements.pop()
+        if self.elements.is_empty() {
+            return None;
+        }
+        self.elements_mut().pop()
+    }
+
+    /// Returns a read-only view of the n top-most elements without copying them. The
+    /// elements are ordered as returned by pop_vec, i.e. the last element is the top
+    /// element of the stack.
+    pub fn top_slice(&self, n: usize) -> Option<&[T]> {
+        if n > self.elements.len() {
+            None
+        } else {
+            Some(&self.elements[self.elements.len() - n..])
+        }
     }
 
     /// Pops and returns the n top-most elements of the stack.
@@ -199,12 +251,8 @@ where
         if n > self.elements.len() {
             None
         } else {
-            Some(
-                self.elements
-                    .split_off(self.elements.len() - n)
-                    .into_iter()
-                    .collect(),
-            )
+            let len = self.elements.len();
+            Some(self.elements_mut().split_off(len - n))
         }
     }
 
@@ -236,7 +284,7 @@ where
     /// Pushes the argument to the stack where the last
     /// element of the argument will at the top of the stack.
     pub fn push_vec(&mut self, to_push: Vec<T>) {
-        self.elements.extend(to_push);
+        self.elements_mut().extend(to_push);
     }
 }
 
@@ -247,7 +295,7 @@ mod tests {
     #[test]
     fn pop_vec_in_right_order() {
         let mut test_stack = PushStack {
-            elements: vec![1, 2, 3],
+            elements: Arc::new(vec![1, 2, 3]),
         };
 
         match test_stack.pop_vec(2) {
@@ -259,7 +307,7 @@ mod tests {
     #[test]
     fn pop_vec_max_index() {
         let mut test_stack = PushStack {
-            elements: vec![1, 2, 3],
+            elements: Arc::new(vec![1, 2, 3]),
         };
         match test_stack.pop_vec(4) {
             None => assert!(true),
@@ -270,17 +318,27 @@ mod tests {
     #[test]
     fn push_vec_in_right_order() {
         let mut test_stack = PushStack {
-            elements: vec![1, 2, 3],
+            elements: Arc::new(vec![1, 2, 3]),
         };
         let test_vec = vec![4, 5];
         test_stack.push_vec(test_vec);
-        assert_eq!(test_stack.elements, [1, 2, 3, 4, 5]);
+        assert_eq!(*test_stack.elements, [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn top_slice_views_top_elements() {
+        let test_stack = PushStack {
+            elements: Arc::new(vec![1, 2, 3]),
+        };
+        assert_eq!(test_stack.top_slice(2), Some(&[2, 3][..]));
+        assert_eq!(test_stack.top_slice(0), Some(&[][..]));
+        assert_eq!(test_stack.top_slice(4), None);
     }
 
     #[test]
     fn copy_vec_preserves_stack() {
         let test_stack = PushStack {
-            elements: vec![1, 2, 3],
+            elements: Arc::new(vec![1, 2, 3]),
         };
 
         match test_stack.copy_vec(2) {
@@ -301,7 +359,7 @@ mod tests {
     #[test]
     fn equal_at_checks_equality_at_right_index() {
         let test_stack = PushStack {
-            elements: vec![1, 2, 3, 4, 5],
+            elements: Arc::new(vec![1, 2, 3, 4, 5]),
         };
         assert_eq!(test_stack.equal_at(0, &5), Some(true));
         assert_eq!(test_stack.equal_at(3, &2), Some(true));
@@ -311,45 +369,45 @@ mod tests {
     #[test]
     fn yank_vec_returns_right_order() {
         let mut test_stack = PushStack {
-            elements: vec![1, 2, 3, 4, 5],
+            elements: Arc::new(vec![1, 2, 3, 4, 5]),
         };
         let mut test_idx = 1;
         test_stack.yank(test_idx);
-        assert_eq!(test_stack.elements, [1, 2, 3, 5, 4]);
+        assert_eq!(*test_stack.elements, [1, 2, 3, 5, 4]);
         test_idx = 5; // No change
         test_stack.yank(test_idx);
-        assert_eq!(test_stack.elements, [1, 2, 3, 5, 4]);
+        assert_eq!(*test_stack.elements, [1, 2, 3, 5, 4]);
         test_idx = 3;
         test_stack.yank(test_idx);
-        assert_eq!(test_stack.elements, [1, 3, 5, 4, 2]);
+        assert_eq!(*test_stack.elements, [1, 3, 5, 4, 2]);
         test_idx = 0; // No change
         test_stack.yank(test_idx);
-        assert_eq!(test_stack.elements, [1, 3, 5, 4, 2]);
+        assert_eq!(*test_stack.elements, [1, 3, 5, 4, 2]);
     }
 
     #[test]
     fn shove_vec_returns_right_order() {
         let mut test_stack = PushStack {
-            elements: vec![1, 2, 3, 4, 5],
+            elements: Arc::new(vec![1, 2, 3, 4, 5]),
         };
         let mut test_idx = 1;
         test_stack.shove(test_idx);
-        assert_eq!(test_stack.elements, [1, 2, 3, 5, 4]);
+        assert_eq!(*test_stack.elements, [1, 2, 3, 5, 4]);
         test_idx = 4; // Push to bottom
         test_stack.shove(test_idx);
-        assert_eq!(test_stack.elements, [4, 1, 2, 3, 5]);
+        assert_eq!(*test_stack.elements, [4, 1, 2, 3, 5]);
         test_idx = 3;
         test_stack.shove(test_idx);
-        assert_eq!(test_stack.elements, [4, 5, 1, 2, 3]);
+        assert_eq!(*test_stack.elements, [4, 5, 1, 2, 3]);
         test_idx = 0; // No change
         test_stack.shove(test_idx);
-        assert_eq!(test_stack.elements, [4, 5, 1, 2, 3]);
+        assert_eq!(*test_stack.elements, [4, 5, 1, 2, 3]);
     }
 
     #[test]
     fn last_eq_preserves_vector() {
         let test_stack = PushStack {
-            elements: vec![1, 2, 3, 4, 5],
+            elements: Arc::new(vec![1, 2, 3, 4, 5]),
         };
         let candidate = 5;
         assert_eq!(test_stack.last_eq(&candidate), true);
@@ -357,7 +415,7 @@ mod tests {
         assert_eq!(test_stack.last_eq(&candidate), false);
         assert_eq!(test_stack.size(), 5);
         let test_stack = PushStack {
-            elements: Vec::new(),
+            elements: Arc::new(Vec::new()),
         };
         assert_eq!(test_stack.last_eq(&candidate), false);
     }
@@ -365,7 +423,7 @@ mod tests {
     #[test]
     fn replace_returns_right_offset() {
         let mut test_stack = PushStack {
-            elements: vec![1, 2, 3, 4, 5],
+            elements: Arc::new(vec![1, 2, 3, 4, 5]),
         };
         assert_eq!(test_stack.replace(1, 19), Ok(()));
         assert_eq!(test_stack.replace(5, 19), Err(1));
@@ -378,9 +436,27 @@ mod tests {
     #[test]
     fn reverse_elements() {
         let mut test_stack = PushStack {
-            elements: vec![1, 2, 3, 4, 5],
+            elements: Arc::new(vec![1, 2, 3, 4, 5]),
         };
         test_stack.reverse();
-        assert_eq!(test_stack.elements, [5, 4, 3, 2, 1]);
+        assert_eq!(*test_stack.elements, [5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn clones_share_elements_until_modified() {
+        use crate::push::item::Item;
+        let inner = Item::list(vec![Item::int(1), Item::int(2)]);
+        let mut original = PushStack::from_vec(vec![inner, Item::int(3)]);
+        let mut copy = original.clone();
+        assert!(original.is_shared() && copy.is_shared());
+        // Modifying the nested list of the copy copies the path to it
+        if let Some(Item::List { items }) = copy.get_mut(1) {
+            items.push(Item::int(4));
+        }
+        assert!(!original.is_shared());
+        assert_eq!(copy.to_string(), "3 ( 4 2 1 )");
+        assert_eq!(original.to_string(), "3 ( 2 1 )");
+        original.pop();
+        assert_eq!(copy.size(), 2);
     }
 }
@@ -1,13 +1,25 @@
-use crate::push::configuration::PushConfiguration;
+use crate::push::configuration::{BindingEviction, PushConfiguration};
+use crate::push::error::PushError;
 use crate::push::graph::Graph;
+use crate::push::heatmap::HeatMap;
 use crate::push::index::Index;
 use crate::push::item::Item;
-use crate::push::stack::PushStack;
+use crate::push::stack::{PushPrint, PushStack};
+use crate::push::bitmap::Bitmap;
 use crate::push::buffer::{PushBuffer, BufferType};
 use crate::push::io::{PushMessage};
+use crate::push::matrix::FloatMatrix;
+use crate::push::string::string_literal;
 use crate::push::vector::{BoolVector, FloatVector, IntVector};
-use std::collections::HashMap;
+use crate::push::vector_list::VectorList;
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
+use std::sync::Arc;
 
 pub const BOOL_STACK_ID: i32 = 1;
 pub const BOOL_VECTOR_STACK_ID: i32 = 2;
@@ -21,27 +33,77 @@ pub const INT_STACK_ID: i32 = 9;
 pub const INT_VECTOR_STACK_ID: i32 = 10;
 pub const NAME_STACK_ID: i32 = 11;
 pub const OUTPUT_STACK_ID: i32 = 12;
+pub const GRAPH_STACK_ID: i32 = 13;
+pub const ERROR_STACK_ID: i32 = 14;
+pub const BITMAP_STACK_ID: i32 = 15;
+#[cfg(feature = "bignum")]
+pub const BIGINT_STACK_ID: i32 = 16;
+pub const CHAR_STACK_ID: i32 = 17;
+pub const MATRIX_STACK_ID: i32 = 18;
+pub const VECTOR_LIST_STACK_ID: i32 = 19;
+pub const STRING_STACK_ID: i32 = 20;
+
+/// Short names of the stacks, e.g. for the selection of stacks on the command line.
+pub const STACK_NAMES: &[(&str, i32)] = &[
+    ("bool", BOOL_STACK_ID),
+    ("boolvector", BOOL_VECTOR_STACK_ID),
+    ("code", CODE_STACK_ID),
+    ("exec", EXEC_STACK_ID),
+    ("float", FLOAT_STACK_ID),
+    ("floatvector", FLOAT_VECTOR_STACK_ID),
+    ("index", INDEX_STACK_ID),
+    ("input", INPUT_STACK_ID),
+    ("int", INT_STACK_ID),
+    ("intvector", INT_VECTOR_STACK_ID),
+    ("name", NAME_STACK_ID),
+    ("output", OUTPUT_STACK_ID),
+    ("graph", GRAPH_STACK_ID),
+    ("error", ERROR_STACK_ID),
+    ("bitmap", BITMAP_STACK_ID),
+    ("char", CHAR_STACK_ID),
+    ("matrix", MATRIX_STACK_ID),
+    ("vectorlist", VECTOR_LIST_STACK_ID),
+    ("string", STRING_STACK_ID),
+    #[cfg(feature = "bignum")]
+    ("bigint", BIGINT_STACK_ID),
+];
+
+/// Returns the ID of the stack with the given short name.
+pub fn stack_id(name: &str) -> Option<i32> {
+    STACK_NAMES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, id)| *id)
+}
 
 
 pub const INPUT_BUFFER_SIZE: usize = 10;
 pub const OUTPUT_BUFFER_SIZE: usize = 3;
 pub const GRAPH_BUFFER_SIZE: usize = 100;
 
-
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PushState {
     // Scalar Types
     pub bool_stack: PushStack<bool>,
+    pub char_stack: PushStack<char>,
     pub code_stack: PushStack<Item>, // Change this to reference for speedup
     pub exec_stack: PushStack<Item>,
     pub float_stack: PushStack<f32>,
     pub index_stack: PushStack<Index>,
     pub int_stack: PushStack<i32>,
     pub name_stack: PushStack<String>,
+    pub string_stack: PushStack<String>,
+    #[cfg(feature = "bignum")]
+    pub bigint_stack: PushStack<BigInt>,
 
     // Vector Types
     pub bool_vector_stack: PushStack<BoolVector>,
     pub float_vector_stack: PushStack<FloatVector>,
     pub int_vector_stack: PushStack<IntVector>,
+    pub bitmap_stack: PushStack<Bitmap>,
+    pub matrix_stack: PushStack<FloatMatrix>,
+    pub vector_list_stack: PushStack<VectorList>,
 
     // IO
     pub input_stack: PushBuffer<PushMessage>,
@@ -50,34 +112,339 @@ pub struct PushState {
     // Graph
     pub graph_stack: PushBuffer<Graph>,
 
+    // Errors reported in strict mode
+    pub error_stack: PushStack<PushError>,
+
     // Bindings
     pub name_bindings: HashMap<String, Item>,
 
+    // Logical time of the last definition or use of each binding if the number
+    // of bindings is limited (see bind) and the names ordered by this time
+    pub binding_last_used: HashMap<String, u64>,
+    pub binding_lru: BTreeMap<u64, String>,
+    pub binding_clock: u64,
+
+    // Bindings saved when entering a binding scope (innermost last) with the depth of the
+    // EXEC stack below the item that the scope executes
+    pub binding_scopes: Vec<(usize, HashMap<String, Item>)>,
+
+    // Instruction selection weights set by INSTRUCTION.WEIGHT
+    pub instruction_weights: HashMap<String, f32>,
+
+    // Items stored under integer tags by the tag instructions (see tag.rs)
+    pub tag_space: BTreeMap<i32, Item>,
+
+    // Input values of a test case that are pushed by the IN0..INn instructions and
+    // values written by the OUT instructions (see registers.rs)
+    pub inputs: Vec<Item>,
+    pub outputs: Vec<Item>,
+
+    // Execution counts of the EXEC items if enabled (see heatmap.rs)
+    #[serde(skip)]
+    pub heat_map: Option<HeatMap>,
+
+    // Read-only constants of the run (see CONST.GET). They are shared between
+    // clones of the state and not serialized.
+    #[serde(skip)]
+    pub constants: Arc<HashMap<String, Item>>,
+
     pub configuration: PushConfiguration,
     pub quote_name: bool,
     pub send_name: bool,
+
+    // Number of divisions by zero during execution
+    pub div_zero_count: usize,
+
+    // Random number generator for stochastic instructions
+    pub rng: ChaCha8Rng,
 }
 
 impl PushState {
     pub fn new() -> Self {
         Self {
             bool_stack: PushStack::new(),
+            char_stack: PushStack::new(),
             code_stack: PushStack::new(),
             exec_stack: PushStack::new(),
             float_stack: PushStack::new(),
             index_stack: PushStack::new(),
             int_stack: PushStack::new(),
             name_stack: PushStack::new(),
+            string_stack: PushStack::new(),
+            #[cfg(feature = "bignum")]
+            bigint_stack: PushStack::new(),
             bool_vector_stack: PushStack::new(),
             float_vector_stack: PushStack::new(),
             int_vector_stack: PushStack::new(),
+            bitmap_stack: PushStack::new(),
+            matrix_stack: PushStack::new(),
+            vector_list_stack: PushStack::new(),
             input_stack: PushBuffer::new(BufferType::Queue, INPUT_BUFFER_SIZE),
             output_stack: PushBuffer::new(BufferType::Queue, OUTPUT_BUFFER_SIZE),
             graph_stack: PushBuffer::new(BufferType::Stack, GRAPH_BUFFER_SIZE),
+            error_stack: PushStack::new(),
             name_bindings: HashMap::new(),
+            binding_last_used: HashMap::new(),
+            binding_lru: BTreeMap::new(),
+            binding_clock: 0,
+            binding_scopes: Vec::new(),
+            instruction_weights: HashMap::new(),
+            tag_space: BTreeMap::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            heat_map: None,
+            constants: Arc::new(HashMap::new()),
             configuration: PushConfiguration::new(),
             quote_name: false,
             send_name: false,
+            div_zero_count: 0,
+            rng: ChaCha8Rng::from_entropy(),
+        }
+    }
+
+    /// Reseeds the random number generator of the state. Stochastic
+    /// instructions are reproducible for the same seed.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    /// Prepends the prefix to the names of all bindings. Identifiers inside the bound
+    /// items that refer to other bindings are renamed as well so that the bindings
+    /// stay consistent. This isolates the bindings of modules that are composed
+    /// into a single program.
+    pub fn namespace_bindings(&mut self, prefix: &str) {
+        let names: HashSet<String> = self.name_bindings.keys().cloned().collect();
+        self.name_bindings = self
+            .name_bindings
+            .drain()
+            .map(|(name, mut item)| {
+                prefix_identifiers(&mut item, prefix, &names);
+                (format!("{}{}", prefix, name), item)
+            })
+            .collect();
+    }
+
+    /// Binds the item to the name as done by the DEFINE instructions. If the number of
+    /// bindings is limited by the configuration and a new name would exceed the limit,
+    /// the definition is either rejected or the least recently used binding is evicted.
+    /// In strict mode both cases are reported to the ERROR stack.
+    pub fn bind(&mut self, name: String, item: Item) {
+        if let Some(cap) = self.configuration.max_name_bindings {
+            if !self.name_bindings.contains_key(&name) && self.name_bindings.len() >= cap {
+                if self.configuration.strict {
+                    self.error_stack.push(PushError::NameBindingCap {
+                        name: name.clone(),
+                        cap,
+                    });
+                }
+                match self.configuration.binding_eviction {
+                    BindingEviction::RejectNew => return,
+                    BindingEviction::EvictLru => match self.least_recently_used_binding() {
+                        Some(lru) => {
+                            self.name_bindings.remove(&lru);
+                            if let Some(last_used) = self.binding_last_used.remove(&lru) {
+                                self.binding_lru.remove(&last_used);
+                            }
+                        }
+                        None => return,
+                    },
+                }
+            }
+            self.name_bindings.insert(name.clone(), item);
+            self.touch_binding(&name);
+        } else {
+            self.name_bindings.insert(name, item);
+        }
+    }
+
+    /// Records the use of the binding for LRU eviction. Only tracked if the number of
+    /// bindings is limited.
+    pub fn touch_binding(&mut self, name: &str) {
+        if self.configuration.max_name_bindings.is_some() {
+            self.binding_clock += 1;
+            let now = self.binding_clock;
+            match self.binding_last_used.get_mut(name) {
+                Some(last_used) => {
+                    let previous = std::mem::replace(last_used, now);
+                    let name = self
+                        .binding_lru
+                        .remove(&previous)
+                        .unwrap_or_else(|| name.to_string());
+                    self.binding_lru.insert(now, name);
+                }
+                None => {
+                    self.binding_last_used.insert(name.to_string(), now);
+                    self.binding_lru.insert(now, name.to_string());
+                }
+            }
+        }
+    }
+
+    /// Returns the name of the least recently used binding. Bindings that have never been
+    /// used, e.g. because they were inserted into name_bindings directly, count as least
+    /// recently used and are ordered by name. Only then all bindings are scanned, otherwise
+    /// the name is taken from the ordered index.
+    fn least_recently_used_binding(&self) -> Option<String> {
+        if self.name_bindings.len() > self.binding_last_used.len() {
+            let unused = self
+                .name_bindings
+                .keys()
+                .filter(|n| !self.binding_last_used.contains_key(*n))
+                .min();
+            if unused.is_some() {
+                return unused.cloned();
+            }
+        }
+        self.binding_lru
+            .values()
+            .find(|n| self.name_bindings.contains_key(*n))
+            .cloned()
+    }
+
+    /// Removes the usage times of names that are no longer bound.
+    fn prune_binding_usage(&mut self) {
+        let bindings = &self.name_bindings;
+        let binding_lru = &mut self.binding_lru;
+        self.binding_last_used.retain(|name, last_used| {
+            let bound = bindings.contains_key(name);
+            if !bound {
+                binding_lru.remove(last_used);
+            }
+            bound
+        });
+    }
+
+    /// Saves the bindings before an item is pushed to the EXEC stack for execution. The
+    /// bindings are restored by leave_binding_scopes as soon as the EXEC stack is no deeper
+    /// than before the push, i.e. when the item and everything it expanded to has been
+    /// executed or removed. Acts as NOOP if scoped bindings are disabled.
+    pub fn enter_binding_scope(&mut self) {
+        if self.configuration.scoped_bindings {
+            self.binding_scopes
+                .push((self.exec_stack.size(), self.name_bindings.clone()));
+        }
+    }
+
+    /// Restores the bindings of all binding scopes whose items have left the EXEC stack.
+    pub fn leave_binding_scopes(&mut self) {
+        while let Some((depth, _)) = self.binding_scopes.last() {
+            if self.exec_stack.size() > *depth {
+                break;
+            }
+            if let Some((_, saved_bindings)) = self.binding_scopes.pop() {
+                self.name_bindings = saved_bindings;
+                self.prune_binding_usage();
+            }
+        }
+    }
+
+    /// Returns the number of items on the stack with the given ID.
+    pub fn stack_size(&self, stack_id: i32) -> Option<usize> {
+        match stack_id {
+            BOOL_STACK_ID => Some(self.bool_stack.size()),
+            BOOL_VECTOR_STACK_ID => Some(self.bool_vector_stack.size()),
+            CODE_STACK_ID => Some(self.code_stack.size()),
+            EXEC_STACK_ID => Some(self.exec_stack.size()),
+            FLOAT_STACK_ID => Some(self.float_stack.size()),
+            FLOAT_VECTOR_STACK_ID => Some(self.float_vector_stack.size()),
+            INDEX_STACK_ID => Some(self.index_stack.size()),
+            INPUT_STACK_ID => Some(self.input_stack.size()),
+            INT_STACK_ID => Some(self.int_stack.size()),
+            INT_VECTOR_STACK_ID => Some(self.int_vector_stack.size()),
+            NAME_STACK_ID => Some(self.name_stack.size()),
+            OUTPUT_STACK_ID => Some(self.output_stack.size()),
+            GRAPH_STACK_ID => Some(self.graph_stack.size()),
+            ERROR_STACK_ID => Some(self.error_stack.size()),
+            BITMAP_STACK_ID => Some(self.bitmap_stack.size()),
+            CHAR_STACK_ID => Some(self.char_stack.size()),
+            MATRIX_STACK_ID => Some(self.matrix_stack.size()),
+            VECTOR_LIST_STACK_ID => Some(self.vector_list_stack.size()),
+            STRING_STACK_ID => Some(self.string_stack.size()),
+            #[cfg(feature = "bignum")]
+            BIGINT_STACK_ID => Some(self.bigint_stack.size()),
+            _ => None,
+        }
+    }
+
+    /// Pops the top item of the stack with the given ID as item of the EXEC stack. Returns
+    /// None if the stack is empty or its items cannot be represented as EXEC items.
+    pub fn pop_item(&mut self, stack_id: i32) -> Option<Item> {
+        match stack_id {
+            BOOL_STACK_ID => self.bool_stack.pop().map(Item::bool),
+            BOOL_VECTOR_STACK_ID => self.bool_vector_stack.pop().map(Item::boolvec),
+            CHAR_STACK_ID => self.char_stack.pop().map(Item::char),
+            CODE_STACK_ID => self.code_stack.pop(),
+            EXEC_STACK_ID => self.exec_stack.pop(),
+            FLOAT_STACK_ID => self.float_stack.pop().map(Item::float),
+            FLOAT_VECTOR_STACK_ID => self.float_vector_stack.pop().map(Item::floatvec),
+            INT_STACK_ID => self.int_stack.pop().map(Item::int),
+            INT_VECTOR_STACK_ID => self.int_vector_stack.pop().map(Item::intvec),
+            NAME_STACK_ID => self.name_stack.pop().map(Item::quoted_name),
+            _ => None,
+        }
+    }
+
+    /// Returns the printed items of the stack with the given ID (top first).
+    pub fn stack_to_string(&self, stack_id: i32) -> Option<String> {
+        match stack_id {
+            BOOL_STACK_ID => Some(self.bool_stack.to_string()),
+            BOOL_VECTOR_STACK_ID => Some(self.bool_vector_stack.to_string()),
+            CODE_STACK_ID => Some(self.code_stack.to_string()),
+            EXEC_STACK_ID => Some(self.exec_stack.to_string()),
+            FLOAT_STACK_ID => Some(self.float_stack.to_string()),
+            FLOAT_VECTOR_STACK_ID => Some(self.float_vector_stack.to_string()),
+            INDEX_STACK_ID => Some(self.index_stack.to_string()),
+            INPUT_STACK_ID => Some(self.input_stack.to_string()),
+            INT_STACK_ID => Some(self.int_stack.to_string()),
+            INT_VECTOR_STACK_ID => Some(self.int_vector_stack.to_string()),
+            NAME_STACK_ID => Some(self.name_stack.to_string()),
+            OUTPUT_STACK_ID => Some(self.output_stack.to_string()),
+            GRAPH_STACK_ID => Some(self.graph_stack.to_string()),
+            ERROR_STACK_ID => Some(self.error_stack.to_string()),
+            BITMAP_STACK_ID => Some(self.bitmap_stack.to_string()),
+            CHAR_STACK_ID => Some(self.char_stack.to_string()),
+            MATRIX_STACK_ID => Some(self.matrix_stack.to_string()),
+            VECTOR_LIST_STACK_ID => Some(self.vector_list_stack.to_string()),
+            STRING_STACK_ID => Some(
+                self.string_stack
+                    .as_slice()
+                    .iter()
+                    .rev()
+                    .map(|s| string_literal(s))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+            ),
+            #[cfg(feature = "bignum")]
+            BIGINT_STACK_ID => Some(self.bigint_stack.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Returns the printed item at the given position (0 = top) of the stack with the given ID.
+    pub fn stack_item_to_string(&self, stack_id: i32, index: usize) -> Option<String> {
+        match stack_id {
+            BOOL_STACK_ID => self.bool_stack.get(index).map(|x| x.to_pstring()),
+            BOOL_VECTOR_STACK_ID => self.bool_vector_stack.get(index).map(|x| x.to_pstring()),
+            CODE_STACK_ID => self.code_stack.get(index).map(|x| x.to_pstring()),
+            EXEC_STACK_ID => self.exec_stack.get(index).map(|x| x.to_pstring()),
+            FLOAT_STACK_ID => self.float_stack.get(index).map(|x| x.to_pstring()),
+            FLOAT_VECTOR_STACK_ID => self.float_vector_stack.get(index).map(|x| x.to_pstring()),
+            INDEX_STACK_ID => self.index_stack.get(index).map(|x| x.to_pstring()),
+            INPUT_STACK_ID => self.input_stack.get(index).map(|x| x.to_pstring()),
+            INT_STACK_ID => self.int_stack.get(index).map(|x| x.to_pstring()),
+            INT_VECTOR_STACK_ID => self.int_vector_stack.get(index).map(|x| x.to_pstring()),
+            NAME_STACK_ID => self.name_stack.get(index).map(|x| x.to_pstring()),
+            OUTPUT_STACK_ID => self.output_stack.get(index).map(|x| x.to_pstring()),
+            GRAPH_STACK_ID => self.graph_stack.get(index).map(|x| x.to_pstring()),
+            ERROR_STACK_ID => self.error_stack.get(index).map(|x| x.to_pstring()),
+            BITMAP_STACK_ID => self.bitmap_stack.get(index).map(|x| x.to_pstring()),
+            CHAR_STACK_ID => self.char_stack.get(index).map(|x| x.to_pstring()),
+            MATRIX_STACK_ID => self.matrix_stack.get(index).map(|x| x.to_pstring()),
+            VECTOR_LIST_STACK_ID => self.vector_list_stack.get(index).map(|x| x.to_pstring()),
+            STRING_STACK_ID => self.string_stack.get(index).map(|x| string_literal(x)),
+            #[cfg(feature = "bignum")]
+            BIGINT_STACK_ID => self.bigint_stack.get(index).map(|x| x.to_pstring()),
+            _ => None,
         }
     }
 
@@ -92,6 +459,69 @@ impl PushState {
             + self.bool_vector_stack.size()
             + self.float_vector_stack.size()
             + self.int_vector_stack.size()
+            + self.bitmap_stack.size()
+            + self.char_stack.size()
+            + self.matrix_stack.size()
+            + self.vector_list_stack.size()
+            + self.string_stack.size()
+            + self.outputs.len()
+    }
+
+    /// Returns the number of elements held by the state. In contrast to size, vector,
+    /// bitmap, matrix and string items count with their elements, CODE and EXEC items and
+    /// bindings with their points and graphs with their nodes and edges.
+    pub fn memory_size(&self) -> usize {
+        let points = |items: &[Item]| items.iter().map(Item::size).sum::<usize>();
+        let mut size = self.bool_stack.size()
+            + self.char_stack.size()
+            + self.float_stack.size()
+            + self.index_stack.size()
+            + self.int_stack.size()
+            + self.name_stack.size()
+            + points(self.code_stack.as_slice())
+            + points(self.exec_stack.as_slice())
+            + points(&self.outputs);
+        #[cfg(feature = "bignum")]
+        {
+            size += self.bigint_stack.size();
+        }
+        size += self.bool_vector_stack.as_slice().iter().map(|bv| bv.len().max(1)).sum::<usize>();
+        size += self.float_vector_stack.as_slice().iter().map(|fv| fv.values.len().max(1)).sum::<usize>();
+        size += self.int_vector_stack.as_slice().iter().map(|iv| iv.values.len().max(1)).sum::<usize>();
+        size += self.bitmap_stack.as_slice().iter().map(|bm| bm.values.len().max(1)).sum::<usize>();
+        size += self.matrix_stack.as_slice().iter().map(|m| m.values.len().max(1)).sum::<usize>();
+        size += self.string_stack.as_slice().iter().map(|s| s.chars().count().max(1)).sum::<usize>();
+        for vector_list in self.vector_list_stack.as_slice() {
+            size += vector_list.rows.iter().map(|row| row.values.len().max(1)).sum::<usize>().max(1);
+        }
+        for graph in self.graph_stack.iter() {
+            size += 1 + graph.node_size() + graph.edge_size();
+        }
+        size += self.name_bindings.values().map(Item::size).sum::<usize>();
+        size
+    }
+}
+
+/// Prepends the prefix to all identifiers of the item that are contained in names.
+fn prefix_identifiers(item: &mut Item, prefix: &str, names: &HashSet<String>) {
+    match item {
+        Item::Identifier { name } if names.contains(name.as_str()) => {
+            *name = format!("{}{}", prefix, name);
+        }
+        Item::List { items } => {
+            for i in 0..items.size() {
+                if let Some(child) = items.get_mut(i) {
+                    prefix_identifiers(child, prefix, names);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+impl Default for PushState {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -127,6 +557,71 @@ impl fmt::Display for PushState {
 mod tests {
     use super::*;
 
+    #[test]
+    fn bind_respects_name_binding_cap() {
+        let mut test_state = PushState::new();
+        test_state.configuration.max_name_bindings = Some(2);
+        test_state.configuration.strict = true;
+        test_state.bind("A".to_string(), Item::int(1));
+        test_state.bind("B".to_string(), Item::int(2));
+        test_state.bind("C".to_string(), Item::int(3));
+        assert!(!test_state.name_bindings.contains_key("C"));
+        test_state.bind("A".to_string(), Item::int(4));
+        assert_eq!(test_state.error_stack.size(), 1);
+        test_state.configuration.binding_eviction = BindingEviction::EvictLru;
+        test_state.touch_binding("B");
+        test_state.bind("C".to_string(), Item::int(3));
+        let mut names: Vec<&String> = test_state.name_bindings.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["B", "C"]);
+        assert_eq!(
+            test_state.error_stack.to_string(),
+            "NAME*BINDING*CAP(C,2) NAME*BINDING*CAP(C,2)"
+        );
+        assert_eq!(test_state.binding_last_used.len(), 2);
+        assert_eq!(test_state.binding_lru.len(), 2);
+        // Bindings that were never used are evicted first
+        test_state.name_bindings.insert("D".to_string(), Item::int(5));
+        test_state.configuration.max_name_bindings = Some(3);
+        test_state.bind("E".to_string(), Item::int(6));
+        let mut names: Vec<&String> = test_state.name_bindings.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["B", "C", "E"]);
+    }
+
+    #[test]
+    fn leaving_binding_scopes_prunes_binding_usage() {
+        let mut test_state = PushState::new();
+        test_state.configuration.max_name_bindings = Some(10);
+        test_state.configuration.scoped_bindings = true;
+        test_state.bind("A".to_string(), Item::int(1));
+        test_state.enter_binding_scope();
+        test_state.bind("B".to_string(), Item::int(2));
+        test_state.touch_binding("A");
+        assert_eq!(test_state.binding_lru.len(), 2);
+        test_state.leave_binding_scopes();
+        assert!(!test_state.name_bindings.contains_key("B"));
+        assert_eq!(test_state.binding_last_used.len(), 1);
+        assert_eq!(
+            test_state.binding_lru.values().collect::<Vec<&String>>(),
+            vec!["A"]
+        );
+    }
+
+    #[test]
+    fn namespace_bindings_renames_names_and_references() {
+        let mut test_state = PushState::new();
+        test_state.name_bindings.insert("X".to_string(), Item::int(1));
+        test_state.name_bindings.insert(
+            "F".to_string(),
+            Item::list(vec![Item::id("X".to_string()), Item::id("Y".to_string())]),
+        );
+        test_state.namespace_bindings("M1.");
+        assert_eq!(test_state.name_bindings.len(), 2);
+        assert_eq!(test_state.name_bindings["M1.X"].to_string(), "1");
+        assert_eq!(test_state.name_bindings["M1.F"].to_string(), "( Y M1.X )");
+    }
+
     #[test]
     fn push_state_prints_name_bindings_in_alphabetical_order() {
         let mut test_state = PushState::new();
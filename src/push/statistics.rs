@@ -0,0 +1,430 @@
+use crate::push::bitmap::Bitmap;
+use crate::push::graph::Graph;
+use crate::push::index::Index;
+use crate::push::instructions::{InstructionCache, InstructionSet};
+use crate::push::item::Item;
+use crate::push::matrix::FloatMatrix;
+use crate::push::sandbox::IO_INSTRUCTION_PREFIXES;
+use crate::push::stack::{PushPrint, PushStack};
+use crate::push::state::*;
+use crate::push::vector::{BoolVector, FloatVector, IntVector};
+use crate::push::vector_list::VectorList;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Aggregated usage statistics of a single instruction.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InstructionStatistics {
+    // Number of times the instruction was executed
+    pub executions: u64,
+    // Number of executions that did not change the state (failed preconditions)
+    pub noops: u64,
+    // Number of evaluated programs that executed the instruction at least once
+    pub programs: u64,
+    // Sum of the error reductions of these programs
+    pub error_reduction_sum: f64,
+}
+
+impl InstructionStatistics {
+    /// Returns the portion of executions that acted as NOOP.
+    pub fn noop_rate(&self) -> f64 {
+        if self.executions == 0 {
+            0.0
+        } else {
+            self.noops as f64 / self.executions as f64
+        }
+    }
+
+    /// Returns the average error reduction of the programs that used the instruction.
+    pub fn average_error_reduction(&self) -> f64 {
+        if self.programs == 0 {
+            0.0
+        } else {
+            self.error_reduction_sum / self.programs as f64
+        }
+    }
+}
+
+/// Minimum number of items per stack that is assumed to satisfy the
+/// preconditions of any instruction when NOOPs are explained.
+const PROBE_STACK_SIZE: usize = 3;
+
+/// Stacks that are padded with default items to probe the arguments of an instruction.
+pub const PROBED_STACKS: &[i32] = &[
+    BOOL_STACK_ID,
+    BOOL_VECTOR_STACK_ID,
+    CODE_STACK_ID,
+    EXEC_STACK_ID,
+    FLOAT_STACK_ID,
+    FLOAT_VECTOR_STACK_ID,
+    INDEX_STACK_ID,
+    INT_STACK_ID,
+    INT_VECTOR_STACK_ID,
+    NAME_STACK_ID,
+    CHAR_STACK_ID,
+    BITMAP_STACK_ID,
+    MATRIX_STACK_ID,
+    VECTOR_LIST_STACK_ID,
+    STRING_STACK_ID,
+    GRAPH_STACK_ID,
+];
+
+/// Explains why an instruction acted as NOOP.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoopExplanation {
+    // Step at which the instruction was executed
+    pub step: usize,
+    pub instruction: String,
+    // IDs of the stacks that did not hold enough arguments. If empty the
+    // arguments were present but invalid (e.g. division by zero).
+    pub missing_stacks: Vec<i32>,
+}
+
+/// Instruction executions of a single program run. The tally is filled by
+/// PushInterpreter::run_with_tally and merged into a StatisticsSink afterwards.
+#[derive(Clone, Debug, Default)]
+pub struct ExecutionTally {
+    // Instruction name => (executions, noops)
+    counts: HashMap<String, (u64, u64)>,
+    explain_noops: bool,
+    noop_explanations: Vec<NoopExplanation>,
+}
+
+impl ExecutionTally {
+    pub fn new() -> Self {
+        Self {
+            counts: HashMap::new(),
+            explain_noops: false,
+            noop_explanations: vec![],
+        }
+    }
+
+    /// Creates a tally that additionally records which stacks were missing
+    /// arguments whenever an instruction acts as NOOP.
+    pub fn with_noop_explanations() -> Self {
+        Self {
+            explain_noops: true,
+            ..Self::new()
+        }
+    }
+
+    /// Returns true if NOOPs should be explained.
+    pub fn explains_noops(&self) -> bool {
+        self.explain_noops
+    }
+
+    /// Records the explanation of a NOOP.
+    pub fn explain(&mut self, explanation: NoopExplanation) {
+        self.noop_explanations.push(explanation);
+    }
+
+    /// Returns the recorded NOOP explanations in execution order.
+    pub fn noop_explanations(&self) -> &[NoopExplanation] {
+        &self.noop_explanations
+    }
+
+    /// Records one execution of the named instruction.
+    pub fn record(&mut self, name: &str, noop: bool) {
+        let count = self.counts.entry(name.to_string()).or_insert((0, 0));
+        count.0 += 1;
+        if noop {
+            count.1 += 1;
+        }
+    }
+
+    /// Returns the number of executions and NOOPs of the named instruction.
+    pub fn get(&self, name: &str) -> Option<(u64, u64)> {
+        self.counts.get(name).cloned()
+    }
+
+    /// Returns true if the instruction changed any stack or binding of the state.
+    /// The IO buffers are not compared.
+    pub fn changed(before: &PushState, after: &PushState) -> bool {
+        before.bool_stack.as_slice() != after.bool_stack.as_slice()
+            || items_changed(before.code_stack.as_slice(), after.code_stack.as_slice())
+            || items_changed(before.exec_stack.as_slice(), after.exec_stack.as_slice())
+            || before.float_stack.as_slice() != after.float_stack.as_slice()
+            || before.index_stack.as_slice() != after.index_stack.as_slice()
+            || before.int_stack.as_slice() != after.int_stack.as_slice()
+            || before.name_stack.as_slice() != after.name_stack.as_slice()
+            || before.bool_vector_stack.as_slice() != after.bool_vector_stack.as_slice()
+            || before.float_vector_stack.as_slice() != after.float_vector_stack.as_slice()
+            || before.int_vector_stack.as_slice() != after.int_vector_stack.as_slice()
+            || before.char_stack.as_slice() != after.char_stack.as_slice()
+            || before.bitmap_stack.as_slice() != after.bitmap_stack.as_slice()
+            || before.matrix_stack.as_slice() != after.matrix_stack.as_slice()
+            || before.vector_list_stack.as_slice() != after.vector_list_stack.as_slice()
+            || before.string_stack.as_slice() != after.string_stack.as_slice()
+            || before.graph_stack.size() != after.graph_stack.size()
+            || before.graph_stack.get(0) != after.graph_stack.get(0)
+            || before.name_bindings.len() != after.name_bindings.len()
+            || before.name_bindings.iter().any(|(name, item)| {
+                after
+                    .name_bindings
+                    .get(name)
+                    .map_or(true, |other| !Item::equals(item, other))
+            })
+    }
+}
+
+/// Returns true if the items differ. Items are compared deeply since the equality of
+/// items only compares their kind.
+fn items_changed(before: &[Item], after: &[Item]) -> bool {
+    before.len() != after.len()
+        || before
+            .iter()
+            .zip(after.iter())
+            .any(|(b, a)| !Item::equals(b, a))
+}
+
+/// Inserts the item at the bottom of the stack so that the top items are unchanged.
+fn push_bottom<T>(stack: &mut PushStack<T>, item: T)
+where
+    T: Clone + fmt::Display + PartialEq + PushPrint,
+{
+    stack.push(item);
+    stack.shove(stack.size() - 1);
+}
+
+/// Inserts default items at the bottom of the stack until it holds PROBE_STACK_SIZE items.
+/// The items differ by their position so that reordering instructions like SWAP have an
+/// effect. Integers start at 2 to avoid degenerate sizes and indices.
+pub fn pad_stack(push_state: &mut PushState, stack_id: i32) {
+    while push_state.stack_size(stack_id).unwrap_or(PROBE_STACK_SIZE) < PROBE_STACK_SIZE {
+        let k = push_state.stack_size(stack_id).unwrap_or(0);
+        let even = k % 2 == 0;
+        match stack_id {
+            BOOL_STACK_ID => push_bottom(&mut push_state.bool_stack, even),
+            BOOL_VECTOR_STACK_ID => push_bottom(&mut push_state.bool_vector_stack, BoolVector::new(vec![even, true])),
+            CODE_STACK_ID => push_bottom(&mut push_state.code_stack, Item::int(k as i32 + 2)),
+            EXEC_STACK_ID => push_bottom(&mut push_state.exec_stack, Item::int(k as i32 + 2)),
+            FLOAT_STACK_ID => push_bottom(&mut push_state.float_stack, k as f32 + 2.0),
+            FLOAT_VECTOR_STACK_ID => push_bottom(&mut push_state.float_vector_stack, FloatVector::new(vec![k as f32 + 2.0, k as f32 + 3.0])),
+            INDEX_STACK_ID => push_bottom(&mut push_state.index_stack, Index::new(k + 1)),
+            INT_STACK_ID => push_bottom(&mut push_state.int_stack, k as i32 + 2),
+            INT_VECTOR_STACK_ID => push_bottom(&mut push_state.int_vector_stack, IntVector::new(vec![k as i32 + 2, k as i32 + 3])),
+            NAME_STACK_ID => push_bottom(&mut push_state.name_stack, format!("X{}", k)),
+            CHAR_STACK_ID => push_bottom(&mut push_state.char_stack, (b'a' + k as u8) as char),
+            BITMAP_STACK_ID => {
+                let mut bitmap = Bitmap::zeros(2, 2);
+                bitmap.set(0, 0, even);
+                push_bottom(&mut push_state.bitmap_stack, bitmap)
+            }
+            MATRIX_STACK_ID => {
+                let values = (0..4).map(|i| (k + i) as f32 + 2.0).collect();
+                if let Some(matrix) = FloatMatrix::from_values(2, values) {
+                    push_bottom(&mut push_state.matrix_stack, matrix)
+                }
+            }
+            VECTOR_LIST_STACK_ID => {
+                let row = FloatVector::new(vec![k as f32 + 2.0, k as f32 + 3.0]);
+                push_bottom(&mut push_state.vector_list_stack, VectorList::new(vec![row]))
+            }
+            STRING_STACK_ID => push_bottom(&mut push_state.string_stack, format!("s{}", k)),
+            // Only probed if the GRAPH stack is empty
+            GRAPH_STACK_ID => push_state.graph_stack.push(Graph::new()),
+            _ => return,
+        }
+    }
+}
+
+/// Determines the stacks that did not hold enough arguments for the instruction
+/// by executing it again on padded copies of the state before the NOOP. A stack
+/// is reported if the instruction has an effect when all short stacks are padded
+/// but not when this stack is left as it was. IO instructions are not probed.
+pub fn missing_stacks(
+    instruction_set: &mut InstructionSet,
+    icache: &InstructionCache,
+    name: &str,
+    state_before_step: &PushState,
+) -> Vec<i32> {
+    if IO_INSTRUCTION_PREFIXES.iter().any(|p| name.starts_with(p)) {
+        return vec![];
+    }
+    let short_stacks: Vec<i32> = PROBED_STACKS
+        .iter()
+        .cloned()
+        .filter(|id| match *id {
+            GRAPH_STACK_ID => state_before_step.graph_stack.size() == 0,
+            _ => state_before_step.stack_size(*id).unwrap_or(0) < PROBE_STACK_SIZE,
+        })
+        .collect();
+    let mut has_effect = |padded: &[i32]| -> bool {
+        let mut probe = state_before_step.clone();
+        for id in padded {
+            pad_stack(&mut probe, *id);
+        }
+        let padded_state = probe.clone();
+        match instruction_set.get_instruction(name) {
+            Some(instruction) => (instruction.execute)(&mut probe, icache),
+            None => return false,
+        }
+        ExecutionTally::changed(&padded_state, &probe)
+    };
+    if !has_effect(&short_stacks) {
+        return vec![];
+    }
+    let mut missing = vec![];
+    for id in short_stacks.iter() {
+        let others: Vec<i32> = short_stacks.iter().cloned().filter(|o| o != id).collect();
+        if !has_effect(&others) {
+            missing.push(*id);
+        }
+    }
+    missing
+}
+
+/// Thread-safe sink that aggregates instruction statistics across the
+/// evaluations of a population run. Clones share the same statistics so
+/// that each evaluation thread can hold its own handle.
+#[derive(Clone, Debug, Default)]
+pub struct StatisticsSink {
+    statistics: Arc<Mutex<HashMap<String, InstructionStatistics>>>,
+}
+
+impl StatisticsSink {
+    pub fn new() -> Self {
+        Self {
+            statistics: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Adds the tally of a program run. If the error reduction of the program
+    /// (e.g. compared to its parent) is known, it is credited to each instruction
+    /// that the program executed.
+    pub fn record(&self, tally: &ExecutionTally, error_reduction: Option<f64>) {
+        let mut statistics = self.statistics.lock().unwrap();
+        for (name, (executions, noops)) in tally.counts.iter() {
+            let entry = statistics.entry(name.clone()).or_default();
+            entry.executions += executions;
+            entry.noops += noops;
+            if let Some(reduction) = error_reduction {
+                entry.programs += 1;
+                entry.error_reduction_sum += reduction;
+            }
+        }
+    }
+
+    /// Returns the statistics of the named instruction.
+    pub fn get(&self, name: &str) -> Option<InstructionStatistics> {
+        self.statistics.lock().unwrap().get(name).cloned()
+    }
+
+    /// Removes all collected statistics.
+    pub fn clear(&self) {
+        self.statistics.lock().unwrap().clear();
+    }
+
+    /// Exports the statistics as CSV with one line per instruction in
+    /// alphabetical order.
+    pub fn to_csv(&self) -> String {
+        let statistics = self.statistics.lock().unwrap();
+        let mut names: Vec<&String> = statistics.keys().collect();
+        names.sort();
+        let mut csv = String::from("instruction,executions,noops,noop_rate,avg_error_reduction\n");
+        for name in names {
+            let s = &statistics[name];
+            csv += &format!(
+                "{},{},{},{:.4},{:.4}\n",
+                name,
+                s.executions,
+                s.noops,
+                s.noop_rate(),
+                s.average_error_reduction()
+            );
+        }
+        csv
+    }
+
+    /// Writes the CSV export to the given file.
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::interpreter::PushInterpreter;
+    use crate::push::parser::PushParser;
+    use std::thread;
+
+    fn tally(input: &str) -> ExecutionTally {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, input);
+        let mut tally = ExecutionTally::new();
+        PushInterpreter::run_with_tally(&mut push_state, &mut instruction_set, &mut tally);
+        tally
+    }
+
+    #[test]
+    fn tally_counts_executions_and_noops() {
+        let tally = tally("( 1 INTEGER.+ 2 INTEGER.+ INTEGER.DUP )");
+        assert_eq!(tally.get("INTEGER.+"), Some((2, 1)));
+        assert_eq!(tally.get("INTEGER.DUP"), Some((1, 0)));
+        assert_eq!(tally.get("FLOAT.+"), None);
+    }
+
+    #[test]
+    fn tally_compares_code_and_bindings_deeply() {
+        let tally = tally(
+            "( CODE.QUOTE ( 1 ) CODE.QUOTE ( 2 ) CODE.SWAP CODE.QUOTE ( 3 ) CODE.QUOTE ( 3 ) CODE.SWAP )",
+        );
+        assert_eq!(tally.get("CODE.SWAP"), Some((2, 1)));
+        let mut before = PushState::new();
+        before.name_bindings.insert("X".to_string(), Item::int(5));
+        let mut after = before.clone();
+        after.name_bindings.insert("X".to_string(), Item::int(6));
+        assert!(ExecutionTally::changed(&before, &after));
+        assert!(!ExecutionTally::changed(&before, &before.clone()));
+    }
+
+    #[test]
+    fn noop_explanations_name_missing_stacks() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, "( 1 INTEGER.+ 1.0 0.0 FLOAT./ GRAPH.NODE*ADD )");
+        let mut tally = ExecutionTally::with_noop_explanations();
+        PushInterpreter::run_with_tally(&mut push_state, &mut instruction_set, &mut tally);
+        let explanations = tally.noop_explanations();
+        assert_eq!(explanations.len(), 3);
+        assert_eq!(explanations[0].instruction, "INTEGER.+");
+        assert_eq!(explanations[0].missing_stacks, vec![INT_STACK_ID]);
+        // Arguments present but invalid
+        assert_eq!(explanations[1].instruction, "FLOAT./");
+        assert_eq!(explanations[1].missing_stacks, Vec::<i32>::new());
+        assert_eq!(explanations[2].missing_stacks, vec![GRAPH_STACK_ID]);
+    }
+
+    #[test]
+    fn sink_aggregates_across_threads() {
+        let sink = StatisticsSink::new();
+        let handles: Vec<_> = [1.0, 3.0]
+            .iter()
+            .map(|reduction| {
+                let sink = sink.clone();
+                let reduction = *reduction;
+                thread::spawn(move || sink.record(&tally("( 1 INTEGER.+ )"), Some(reduction)))
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let stats = sink.get("INTEGER.+").unwrap();
+        assert_eq!(stats.executions, 2);
+        assert_eq!(stats.noop_rate(), 1.0);
+        assert_eq!(stats.average_error_reduction(), 2.0);
+        assert_eq!(
+            sink.to_csv(),
+            "instruction,executions,noops,noop_rate,avg_error_reduction\nINTEGER.+,2,2,1.0000,2.0000\n"
+        );
+    }
+}
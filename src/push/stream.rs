@@ -0,0 +1,207 @@
+use crate::push::instructions::InstructionSet;
+use crate::push::interpreter::{PushInterpreter, PushInterpreterState};
+use crate::push::io::PushMessage;
+use crate::push::item::Item;
+use crate::push::state::PushState;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+
+/// Item that is fed to a streaming program. Each variant is pushed to its
+/// designated stack.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamInput {
+    // Pushed to the INPUT stack
+    Message(PushMessage),
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    // Pushed to the EXEC stack to be executed before the program
+    Exec(Item),
+}
+
+impl StreamInput {
+    fn push_to(self, push_state: &mut PushState) {
+        match self {
+            StreamInput::Message(msg) => push_state.input_stack.push(msg),
+            StreamInput::Bool(val) => push_state.bool_stack.push(val),
+            StreamInput::Int(val) => push_state.int_stack.push(val),
+            StreamInput::Float(val) => push_state.float_stack.push(val),
+            StreamInput::Exec(item) => push_state.exec_stack.push(item),
+        }
+    }
+}
+
+/// Decides how a streaming program waits for the next input item.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StreamMode {
+    // Wait until the next item arrives or the input channel is closed
+    Blocking,
+    // Return if no item is pending
+    Polling,
+}
+
+/// Turns a Push program into a long-running stream processor. The program is
+/// executed once when the stream is started and again for every item that is
+/// received from the input channel. The state persists between the activations.
+/// Messages written by OUTPUT.WRITE are removed from the OUTPUT stack and sent to
+/// the output channel after the step that wrote them.
+pub struct StreamExecution {
+    pub push_state: PushState,
+    program: Vec<Item>,
+    input: Receiver<StreamInput>,
+    output: Sender<PushMessage>,
+    activation_counter: usize,
+}
+
+impl StreamExecution {
+    /// Creates a stream execution for the program on the EXEC stack of the state.
+    pub fn new(
+        push_state: PushState,
+        input: Receiver<StreamInput>,
+        output: Sender<PushMessage>,
+    ) -> Self {
+        let program = push_state
+            .exec_stack
+            .copy_vec(push_state.exec_stack.size())
+            .unwrap_or_default();
+        Self {
+            push_state,
+            program,
+            input,
+            output,
+            activation_counter: 0,
+        }
+    }
+
+    /// Creates a stream execution together with the sending end of its input
+    /// channel and the receiving end of its output channel.
+    pub fn with_channels(
+        push_state: PushState,
+    ) -> (Self, Sender<StreamInput>, Receiver<PushMessage>) {
+        let (input_sender, input_receiver) = channel();
+        let (output_sender, output_receiver) = channel();
+        (
+            StreamExecution::new(push_state, input_receiver, output_sender),
+            input_sender,
+            output_receiver,
+        )
+    }
+
+    /// Returns the number of activations that have been started so far.
+    pub fn activation_counter(&self) -> usize {
+        self.activation_counter
+    }
+
+    /// Waits for the next input item according to the mode and pushes it to its
+    /// stack on top of the program. Returns false if no item is available.
+    fn activate(&mut self, mode: StreamMode) -> bool {
+        let input = match mode {
+            StreamMode::Blocking => self.input.recv().ok(),
+            StreamMode::Polling => match self.input.try_recv() {
+                Ok(input) => Some(input),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None,
+            },
+        };
+        if let Some(input) = input {
+            self.push_state.exec_stack.push_vec(self.program.clone());
+            input.push_to(&mut self.push_state);
+            self.activation_counter += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Executes the program until the input channel is closed (Blocking) or no
+    /// input item is pending (Polling). In the latter case run can be called again
+    /// to process items that arrive later. The step limit of the configuration
+    /// applies to each activation separately.
+    pub fn run(
+        &mut self,
+        instruction_set: &mut InstructionSet,
+        mode: StreamMode,
+    ) -> PushInterpreterState {
+        let icache = instruction_set.cache();
+        if self.activation_counter == 0 {
+            self.activation_counter = 1;
+        } else if !self.activate(mode) {
+            return PushInterpreterState::NoErrors;
+        }
+        let mut step_counter = 0;
+        loop {
+            if step_counter > self.push_state.configuration.eval_push_limit {
+                return PushInterpreterState::StepLimitExceeded;
+            }
+            if PushInterpreter::step(&mut self.push_state, instruction_set, &icache) {
+                if !self.activate(mode) {
+                    return PushInterpreterState::NoErrors;
+                }
+                step_counter = 0;
+                continue;
+            }
+            while let Some(msg) = self.push_state.output_stack.pop() {
+                // Outputs are dropped if nobody listens
+                let _ = self.output.send(msg);
+            }
+            step_counter += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::parser::PushParser;
+    use crate::push::vector::{BoolVector, IntVector};
+    use std::thread;
+
+    fn stream(program: &str) -> (StreamExecution, Sender<StreamInput>, Receiver<PushMessage>) {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut push_state = PushState::new();
+        PushParser::parse_program(&mut push_state, &instruction_set, program);
+        StreamExecution::with_channels(push_state)
+    }
+
+    fn message(id: i32) -> PushMessage {
+        PushMessage::new(
+            IntVector::new(vec![id]),
+            BoolVector::from_int_array(vec![1, 0]),
+        )
+    }
+
+    #[test]
+    fn blocking_stream_echoes_messages() {
+        let (mut execution, input, output) = stream("( INPUT.READ OUTPUT.WRITE INPUT.NEXT )");
+        let producer = thread::spawn(move || {
+            for id in 0..3 {
+                input.send(StreamInput::Message(message(id))).unwrap();
+            }
+        });
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        assert_eq!(
+            execution.run(&mut instruction_set, StreamMode::Blocking),
+            PushInterpreterState::NoErrors
+        );
+        producer.join().unwrap();
+        let echoed: Vec<PushMessage> = output.try_iter().collect();
+        assert_eq!(echoed, vec![message(0), message(1), message(2)]);
+        assert_eq!(execution.activation_counter(), 4);
+        assert_eq!(execution.push_state.input_stack.size(), 0);
+    }
+
+    #[test]
+    fn polling_stream_returns_when_idle() {
+        let (mut execution, input, _output) = stream("( INTEGER.+ )");
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        input.send(StreamInput::Int(2)).unwrap();
+        input.send(StreamInput::Int(3)).unwrap();
+        execution.run(&mut instruction_set, StreamMode::Polling);
+        assert_eq!(execution.push_state.int_stack.to_string(), "5");
+        input.send(StreamInput::Int(4)).unwrap();
+        execution.run(&mut instruction_set, StreamMode::Polling);
+        assert_eq!(execution.push_state.int_stack.to_string(), "9");
+        assert_eq!(execution.activation_counter(), 4);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::push::instructions::Instruction;
+use crate::push::instructions::InstructionCache;
+use crate::push::state::PushState;
+use crate::push::vector::report_vector_size_cap;
+use std::collections::HashMap;
+
+/// Returns the string in quotes with special characters escaped as it is printed for
+/// the STRING stack.
+pub fn string_literal(sval: &str) -> String {
+    format!("{:?}", sval)
+}
+
+/// Strings for Program Synthesis Benchmark problems. There is no STRING literal syntax,
+/// strings are built from CHAR items. Their length in characters is limited by the
+/// maximum vector size.
+pub fn load_string_instructions(map: &mut HashMap<String, Instruction>) {
+    map.insert(String::from("STRING.="), Instruction::new(string_equal));
+    map.insert(String::from("STRING.CONCAT"), Instruction::new(string_concat));
+    map.insert(
+        String::from("STRING.CONJCHAR"),
+        Instruction::new(string_conj_char),
+    );
+    map.insert(String::from("STRING.DUP"), Instruction::new(string_dup));
+    map.insert(String::from("STRING.FLUSH"), Instruction::new(string_flush));
+    map.insert(
+        String::from("STRING.FROMCHAR"),
+        Instruction::new(string_from_char),
+    );
+    map.insert(String::from("STRING.LENGTH"), Instruction::new(string_length));
+    map.insert(String::from("STRING.NTH"), Instruction::new(string_nth));
+    map.insert(String::from("STRING.POP"), Instruction::new(string_pop));
+    map.insert(
+        String::from("STRING.STACKDEPTH"),
+        Instruction::new(string_stack_depth),
+    );
+    map.insert(String::from("STRING.SWAP"), Instruction::new(string_swap));
+}
+
+/// Returns true if a string with the given number of characters exceeds the maximum
+/// vector size. The violation is reported in strict mode.
+fn exceeds_size_cap(push_state: &mut PushState, len: usize) -> bool {
+    if len > push_state.configuration.max_vector_size {
+        report_vector_size_cap(push_state, len);
+        true
+    } else {
+        false
+    }
+}
+
+/// STRING.=: Pushes TRUE onto the BOOLEAN stack if the top two STRING items are equal, or
+/// FALSE otherwise.
+fn string_equal(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(svals) = push_state.string_stack.pop_vec(2) {
+        push_state.bool_stack.push(svals[0] == svals[1]);
+    }
+}
+
+/// STRING.CONCAT: Pops the top two STRING items and pushes the second item followed by the
+/// top item. If the result would exceed the maximum vector size this acts as a NOOP.
+fn string_concat(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    let len = match (
+        push_state.string_stack.get(1),
+        push_state.string_stack.get(0),
+    ) {
+        (Some(second), Some(top)) => second.chars().count() + top.chars().count(),
+        _ => return,
+    };
+    if exceeds_size_cap(push_state, len) {
+        return;
+    }
+    if let Some(top) = push_state.string_stack.pop() {
+        if let Some(second) = push_state.string_stack.get_mut(0) {
+            second.push_str(&top);
+        }
+    }
+}
+
+/// STRING.CONJCHAR: Pops the top CHAR and appends it to the top STRING. If the result would
+/// exceed the maximum vector size this acts as a NOOP.
+fn string_conj_char(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    let len = match (push_state.string_stack.get(0), push_state.char_stack.get(0)) {
+        (Some(sval), Some(_)) => sval.chars().count() + 1,
+        _ => return,
+    };
+    if exceeds_size_cap(push_state, len) {
+        return;
+    }
+    if let Some(cval) = push_state.char_stack.pop() {
+        if let Some(sval) = push_state.string_stack.get_mut(0) {
+            sval.push(cval);
+        }
+    }
+}
+
+/// STRING.DUP: Duplicates the top item on the STRING stack. Does not pop its argument.
+fn string_dup(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(sval) = push_state.string_stack.copy(0) {
+        push_state.string_stack.push(sval);
+    }
+}
+
+/// STRING.FLUSH: Empties the STRING stack.
+fn string_flush(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.string_stack.flush();
+}
+
+/// STRING.FROMCHAR: Pops the top CHAR and pushes a STRING that consists of it.
+fn string_from_char(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(cval) = push_state.char_stack.pop() {
+        push_state.string_stack.push(cval.to_string());
+    }
+}
+
+/// STRING.LENGTH: Pushes the number of characters of the top STRING to the INTEGER stack.
+/// Does not pop its argument.
+fn string_length(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(sval) = push_state.string_stack.get(0) {
+        let len = sval.chars().count() as i32;
+        push_state.int_stack.push(len);
+    }
+}
+
+/// STRING.NTH: Pops the top STRING and pushes its nth character to the CHAR stack. The
+/// index n is taken from the INTEGER stack modulo the length of the string. Acts as NOOP
+/// if the string is empty.
+fn string_nth(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    let cval = match (push_state.string_stack.get(0), push_state.int_stack.get(0)) {
+        (Some(sval), Some(index)) if !sval.is_empty() => {
+            let len = sval.chars().count() as i32;
+            sval.chars().nth(index.rem_euclid(len) as usize)
+        }
+        _ => None,
+    };
+    if let Some(cval) = cval {
+        push_state.string_stack.pop();
+        push_state.int_stack.pop();
+        push_state.char_stack.push(cval);
+    }
+}
+
+/// STRING.POP: Pops the STRING stack.
+fn string_pop(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.string_stack.pop();
+}
+
+/// STRING.STACKDEPTH: Pushes the stack depth onto the INTEGER stack.
+fn string_stack_depth(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state
+        .int_stack
+        .push(push_state.string_stack.size() as i32);
+}
+
+/// STRING.SWAP: Swaps the top two STRINGs.
+fn string_swap(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.string_stack.shove(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub fn icache() -> InstructionCache {
+        InstructionCache::new(vec![])
+    }
+
+    #[test]
+    fn strings_are_built_from_chars() {
+        let mut test_state = PushState::new();
+        for c in ['a', 'ß', 'c'].iter() {
+            test_state.char_stack.push(*c);
+        }
+        string_from_char(&mut test_state, &icache());
+        string_from_char(&mut test_state, &icache());
+        string_swap(&mut test_state, &icache());
+        string_concat(&mut test_state, &icache());
+        string_conj_char(&mut test_state, &icache());
+        assert_eq!(test_state.string_stack.to_string(), "ßca");
+        assert_eq!(test_state.char_stack.size(), 0);
+        string_length(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.to_string(), "3");
+        string_dup(&mut test_state, &icache());
+        string_dup(&mut test_state, &icache());
+        string_equal(&mut test_state, &icache());
+        assert_eq!(test_state.bool_stack.to_string(), "TRUE");
+        test_state.configuration.max_vector_size = 3;
+        test_state.char_stack.push('d');
+        string_conj_char(&mut test_state, &icache());
+        assert_eq!(test_state.string_stack.to_string(), "ßca");
+        assert_eq!(test_state.char_stack.size(), 1);
+    }
+
+    #[test]
+    fn string_nth_wraps_index() {
+        let mut test_state = PushState::new();
+        test_state.string_stack.push("aßc".to_string());
+        test_state.int_stack.push(-2);
+        string_nth(&mut test_state, &icache());
+        assert_eq!(test_state.char_stack.to_string(), "\\ß");
+        assert_eq!(test_state.string_stack.size(), 0);
+        test_state.string_stack.push(String::new());
+        test_state.int_stack.push(0);
+        string_nth(&mut test_state, &icache());
+        assert_eq!(test_state.string_stack.size(), 1);
+        assert_eq!(test_state.int_stack.size(), 1);
+        assert_eq!(test_state.char_stack.size(), 1);
+    }
+}
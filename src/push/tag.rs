@@ -0,0 +1,237 @@
+use crate::push::item::Item;
+use crate::push::state::*;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+/// Types whose items can be stored in the tag space with the IDs of their stacks.
+const TAG_TYPES: &[(&str, i32)] = &[
+    ("BOOLEAN", BOOL_STACK_ID),
+    ("BOOLVECTOR", BOOL_VECTOR_STACK_ID),
+    ("CHAR", CHAR_STACK_ID),
+    ("CODE", CODE_STACK_ID),
+    ("EXEC", EXEC_STACK_ID),
+    ("FLOAT", FLOAT_STACK_ID),
+    ("FLOATVECTOR", FLOAT_VECTOR_STACK_ID),
+    ("INTEGER", INT_STACK_ID),
+    ("INTVECTOR", INT_VECTOR_STACK_ID),
+    ("NAME", NAME_STACK_ID),
+];
+
+/// Instructions of the tag space (Spector et al., Tag-based modules in genetic
+/// programming, 2011). Unlike other instructions the tag is part of the name, e.g.
+/// TAG.INTEGER.42 or TAGGED.42, so that the instructions are not registered in the
+/// instruction set but recognized by their names.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TagInstruction {
+    // TAG.<TYPE>.<N>: Pops the top item of the stack of the type and stores it under
+    // tag N. An item stored under the same tag is replaced.
+    Tag { stack_id: i32, tag: i32 },
+    // TAGGED.<N>: Pushes a copy of the item with the closest matching tag onto the EXEC
+    // stack. TAGGED.CODE.<N> pushes it onto the CODE stack.
+    Tagged { stack_id: i32, tag: i32 },
+    // UNTAG.<N>: Removes the item with the closest matching tag from the tag space.
+    Untag { tag: i32 },
+}
+
+impl TagInstruction {
+    /// Parses the name of a tag instruction. Returns None if the name does not
+    /// denote a tag instruction.
+    pub fn parse(name: &str) -> Option<Self> {
+        let parts: Vec<&str> = name.split('.').collect();
+        match parts.as_slice() {
+            ["TAG", type_name, tag] => {
+                let (_, stack_id) = TAG_TYPES.iter().find(|(t, _)| t == type_name)?;
+                Some(TagInstruction::Tag {
+                    stack_id: *stack_id,
+                    tag: tag.parse().ok()?,
+                })
+            }
+            ["TAGGED", tag] => Some(TagInstruction::Tagged {
+                stack_id: EXEC_STACK_ID,
+                tag: tag.parse().ok()?,
+            }),
+            ["TAGGED", "CODE", tag] => Some(TagInstruction::Tagged {
+                stack_id: CODE_STACK_ID,
+                tag: tag.parse().ok()?,
+            }),
+            ["UNTAG", tag] => Some(TagInstruction::Untag {
+                tag: tag.parse().ok()?,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the instruction (see parse).
+    pub fn name(&self) -> String {
+        match *self {
+            TagInstruction::Tag { stack_id, tag } => {
+                let type_name = TAG_TYPES
+                    .iter()
+                    .find(|(_, id)| *id == stack_id)
+                    .map_or("EXEC", |(t, _)| t);
+                format!("TAG.{}.{}", type_name, tag)
+            }
+            TagInstruction::Tagged { stack_id, tag } if stack_id == CODE_STACK_ID => {
+                format!("TAGGED.CODE.{}", tag)
+            }
+            TagInstruction::Tagged { tag, .. } => format!("TAGGED.{}", tag),
+            TagInstruction::Untag { tag } => format!("UNTAG.{}", tag),
+        }
+    }
+
+    /// Returns a random tag instruction (ERC) with a tag from 0 (inclusive) to
+    /// tag_limit (exclusive). All variants, including TAG for each type, are equally
+    /// likely.
+    pub fn random<R: Rng>(rng: &mut R, tag_limit: i32) -> Self {
+        let tag = rng.gen_range(0..i32::max(tag_limit, 1));
+        let variant = rng.gen_range(0..TAG_TYPES.len() + 3);
+        match variant.checked_sub(TAG_TYPES.len()) {
+            None => TagInstruction::Tag {
+                stack_id: TAG_TYPES[variant].1,
+                tag,
+            },
+            Some(0) => TagInstruction::Tagged {
+                stack_id: EXEC_STACK_ID,
+                tag,
+            },
+            Some(1) => TagInstruction::Tagged {
+                stack_id: CODE_STACK_ID,
+                tag,
+            },
+            _ => TagInstruction::Untag { tag },
+        }
+    }
+
+    /// Executes the instruction. Acts as a NOOP if the argument stack or the tag
+    /// space is empty or if TAG would exceed the maximum number of tags.
+    pub fn execute(&self, push_state: &mut PushState) {
+        match *self {
+            TagInstruction::Tag { stack_id, tag } => {
+                if !push_state.tag_space.contains_key(&tag)
+                    && push_state.tag_space.len() >= push_state.configuration.max_tags
+                {
+                    return;
+                }
+                if let Some(item) = push_state.pop_item(stack_id) {
+                    push_state.tag_space.insert(tag, item);
+                }
+            }
+            TagInstruction::Tagged { stack_id, tag } => {
+                if let Some(closest) = closest_tag(&push_state.tag_space, tag) {
+                    if let Some(item) = push_state.tag_space.get(&closest).cloned() {
+                        if stack_id == CODE_STACK_ID {
+                            push_state.code_stack.push(item);
+                        } else {
+                            push_state.exec_stack.push(item);
+                        }
+                    }
+                }
+            }
+            TagInstruction::Untag { tag } => {
+                if let Some(closest) = closest_tag(&push_state.tag_space, tag) {
+                    push_state.tag_space.remove(&closest);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the closest match of the tag in the tag space: the smallest tag that is
+/// greater than or equal to the given tag. If there is no such tag, the search wraps
+/// around to the smallest tag. Returns None if the tag space is empty.
+pub fn closest_tag(tag_space: &BTreeMap<i32, Item>, tag: i32) -> Option<i32> {
+    tag_space
+        .range(tag..)
+        .next()
+        .or_else(|| tag_space.iter().next())
+        .map(|(closest, _)| *closest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::interpreter::PushInterpreter;
+
+    #[test]
+    fn tag_names_are_parsed() {
+        assert_eq!(
+            TagInstruction::parse("TAG.INTEGER.42"),
+            Some(TagInstruction::Tag {
+                stack_id: INT_STACK_ID,
+                tag: 42
+            })
+        );
+        assert_eq!(
+            TagInstruction::parse("TAGGED.CODE.7"),
+            Some(TagInstruction::Tagged {
+                stack_id: CODE_STACK_ID,
+                tag: 7
+            })
+        );
+        assert_eq!(
+            TagInstruction::parse("UNTAG.3"),
+            Some(TagInstruction::Untag { tag: 3 })
+        );
+        assert_eq!(TagInstruction::parse("TAG.GRAPH.1"), None);
+        assert_eq!(TagInstruction::parse("TAGGED.X"), None);
+        assert_eq!(TagInstruction::parse("INTEGER.+"), None);
+    }
+
+    #[test]
+    fn closest_tag_wraps_around() {
+        let mut tag_space = BTreeMap::new();
+        assert_eq!(closest_tag(&tag_space, 5), None);
+        tag_space.insert(10, Item::int(1));
+        tag_space.insert(20, Item::int(2));
+        assert_eq!(closest_tag(&tag_space, 5), Some(10));
+        assert_eq!(closest_tag(&tag_space, 10), Some(10));
+        assert_eq!(closest_tag(&tag_space, 11), Some(20));
+        assert_eq!(closest_tag(&tag_space, 21), Some(10));
+    }
+
+    #[test]
+    fn tagged_code_is_executed_as_module() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        // The module doubles the top INTEGER and is called twice via a near tag
+        PushInterpreter::run_program(
+            "( TAG.EXEC.10 ( INTEGER.DUP INTEGER.+ ) 3 TAGGED.8 TAGGED.9 5 TAG.INTEGER.30 TAGGED.CODE.25 UNTAG.0 TAGGED.0 )",
+            &mut push_state,
+            &mut instruction_set,
+        );
+        assert_eq!(push_state.int_stack.to_string(), "5 12");
+        assert_eq!(push_state.code_stack.get(0), Some(&Item::int(5)));
+        assert_eq!(push_state.tag_space.len(), 1);
+    }
+
+    #[test]
+    fn random_tag_instructions_are_parsed() {
+        let mut push_state = PushState::new();
+        for _ in 0..200 {
+            let instruction = TagInstruction::random(&mut push_state.rng, 5);
+            assert_eq!(TagInstruction::parse(&instruction.name()), Some(instruction));
+            match instruction {
+                TagInstruction::Tag { tag, .. }
+                | TagInstruction::Tagged { tag, .. }
+                | TagInstruction::Untag { tag } => assert!((0..5).contains(&tag)),
+            }
+        }
+    }
+
+    #[test]
+    fn tag_space_is_bounded() {
+        let mut push_state = PushState::new();
+        push_state.configuration.max_tags = 2;
+        push_state.int_stack.push_vec(vec![1, 2, 3, 4]);
+        for tag in [1, 2, 3].iter() {
+            TagInstruction::Tag { stack_id: INT_STACK_ID, tag: *tag }.execute(&mut push_state);
+        }
+        assert_eq!(push_state.tag_space.keys().copied().collect::<Vec<i32>>(), vec![1, 2]);
+        assert_eq!(push_state.int_stack.to_string(), "2 1");
+        // Items under existing tags can still be replaced
+        TagInstruction::Tag { stack_id: INT_STACK_ID, tag: 1 }.execute(&mut push_state);
+        assert_eq!(push_state.tag_space.get(&1).map(|item| item.to_string()), Some("2".to_string()));
+    }
+}
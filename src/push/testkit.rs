@@ -0,0 +1,113 @@
+use crate::push::instructions::InstructionSet;
+use crate::push::state::PushState;
+use crate::push::vector::{BoolVector, FloatVector, IntVector};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+use std::cell::RefCell;
+
+pub const MAX_STACK_SIZE: usize = 5;
+pub const MAX_VECTOR_SIZE: usize = 8;
+pub const MAX_ABS_VALUE: i32 = 100;
+
+/// Strategy that generates push states with random items on the BOOLEAN, INTEGER,
+/// FLOAT, BOOLVECTOR, INTVECTOR and FLOATVECTOR stacks. Each stack holds up to
+/// MAX_STACK_SIZE items and each vector up to MAX_VECTOR_SIZE elements.
+pub fn arb_push_state() -> impl Strategy<Value = PushState> {
+    let int = -MAX_ABS_VALUE..MAX_ABS_VALUE;
+    let float = -MAX_ABS_VALUE as f32..MAX_ABS_VALUE as f32;
+    (
+        vec(any::<bool>(), 0..MAX_STACK_SIZE),
+        vec(int.clone(), 0..MAX_STACK_SIZE),
+        vec(float.clone(), 0..MAX_STACK_SIZE),
+        vec(vec(any::<bool>(), 0..MAX_VECTOR_SIZE), 0..MAX_STACK_SIZE),
+        vec(vec(int, 0..MAX_VECTOR_SIZE), 0..MAX_STACK_SIZE),
+        vec(vec(float, 0..MAX_VECTOR_SIZE), 0..MAX_STACK_SIZE),
+    )
+        .prop_map(|(bools, ints, floats, bool_vectors, int_vectors, float_vectors)| {
+            let mut push_state = PushState::new();
+            push_state.bool_stack.push_vec(bools);
+            push_state.int_stack.push_vec(ints);
+            push_state.float_stack.push_vec(floats);
+            push_state
+                .bool_vector_stack
+                .push_vec(bool_vectors.into_iter().map(BoolVector::new).collect());
+            push_state
+                .int_vector_stack
+                .push_vec(int_vectors.into_iter().map(IntVector::new).collect());
+            push_state
+                .float_vector_stack
+                .push_vec(float_vectors.into_iter().map(FloatVector::new).collect());
+            push_state
+        })
+}
+
+/// Executes the instruction with the given name on randomly generated pre-states
+/// and checks the postcondition for every pair of pre- and post-state. Returns
+/// a description of the first (shrunk) counterexample if the postcondition fails.
+pub fn check_instruction<F>(name: &str, cases: u32, postcondition: F) -> Result<(), String>
+where
+    F: Fn(&PushState, &PushState) -> bool,
+{
+    let mut instruction_set = InstructionSet::new();
+    instruction_set.load();
+    if !instruction_set.is_instruction(name) {
+        return Err(format!("Unknown instruction {}", name));
+    }
+    let icache = instruction_set.cache();
+    let instruction_set = RefCell::new(instruction_set);
+    let mut runner = TestRunner::new(Config::with_cases(cases));
+    runner
+        .run(&arb_push_state(), |pre_state| {
+            let mut post_state = pre_state.clone();
+            if let Some(instruction) = instruction_set.borrow_mut().get_instruction(name) {
+                (instruction.execute)(&mut post_state, &icache);
+            }
+            if postcondition(&pre_state, &post_state) {
+                Ok(())
+            } else {
+                Err(TestCaseError::fail(format!(
+                    "Postcondition of {} failed\nPRE:\n{}\nPOST:\n{}",
+                    name, pre_state, post_state
+                )))
+            }
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_add_consumes_one_item() {
+        assert_eq!(
+            check_instruction("INTEGER.+", 64, |pre, post| {
+                if pre.int_stack.size() < 2 {
+                    post.int_stack.size() == pre.int_stack.size()
+                } else {
+                    post.int_stack.size() == pre.int_stack.size() - 1
+                }
+            }),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn boolvector_dup_copies_top_item() {
+        assert_eq!(
+            check_instruction("BOOLVECTOR.DUP", 64, |pre, post| {
+                pre.bool_vector_stack.size() == 0
+                    || post.bool_vector_stack.get(0).unwrap()
+                        == post.bool_vector_stack.get(1).unwrap()
+            }),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn failing_postcondition_is_reported() {
+        assert!(check_instruction("INTEGER.POP", 64, |_pre, post| post.int_stack.size() > 0).is_err());
+        assert!(check_instruction("UNKNOWN", 64, |_pre, _post| true).is_err());
+    }
+}
@@ -1,29 +1,70 @@
+use crate::push::bitset::BitSet;
+use crate::push::error::PushError;
 use crate::push::instructions::Instruction;
 use crate::push::instructions::InstructionCache;
 use crate::push::item::Item;
 use crate::push::random::CodeGenerator;
+use crate::push::selection::median;
 use crate::push::state::PushState;
 use crate::push::state::*;
 use crate::push::stack::PushPrint;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 
-#[derive(Clone, Debug, Default)]
+/// BOOLVECTOR item. The elements are packed into a BitSet, so that the bitwise
+/// instructions and COUNT process 64 elements at once. It is serialized as the list of
+/// its elements.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(from = "BoolVectorValues", into = "BoolVectorValues")]
 pub struct BoolVector {
-    pub values: Vec<bool>,
+    pub bits: BitSet,
+}
+
+/// Serialized form of a BoolVector.
+#[derive(Clone, Serialize, Deserialize)]
+struct BoolVectorValues {
+    values: Vec<bool>,
+}
+
+impl From<BoolVectorValues> for BoolVector {
+    fn from(data: BoolVectorValues) -> Self {
+        BoolVector::new(data.values)
+    }
+}
+
+impl From<BoolVector> for BoolVectorValues {
+    fn from(bv: BoolVector) -> Self {
+        BoolVectorValues {
+            values: bv.to_bools(),
+        }
+    }
 }
 
 impl BoolVector {
     pub fn new(arg: Vec<bool>) -> Self {
-        Self { values: arg }
+        Self {
+            bits: BitSet::from_bools(&arg),
+        }
     }
 
     pub fn from_int_array(arg: Vec<usize>) -> Self {
-        let mut bv = vec![false; arg.len()];
-        for (i, ival) in arg.iter().enumerate() {
-            bv[i] = ival == &1;
+        Self {
+            bits: arg.iter().map(|ival| *ival == 1).collect(),
         }
-        Self { values: bv }
+    }
+
+    /// Returns the elements as a vector of bools.
+    pub fn to_bools(&self) -> Vec<bool> {
+        self.bits.to_bools()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
     }
 }
 
@@ -36,9 +77,8 @@ impl PushPrint for BoolVector {
 impl fmt::Display for BoolVector {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut s = self
-            .values
-            .clone()
-            .into_iter()
+            .bits
+            .iter()
             .fold(String::new(), |acc, num| {
                 acc + &num.to_string().to_uppercase() + ","
             });
@@ -49,11 +89,11 @@ impl fmt::Display for BoolVector {
 
 impl PartialEq for BoolVector {
     fn eq(&self, other: &Self) -> bool {
-        self.values == other.values
+        self.bits == other.bits
     }
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct IntVector {
     pub values: Vec<i32>,
 }
@@ -88,7 +128,7 @@ impl PartialEq for IntVector {
     }
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct FloatVector {
     pub values: Vec<f32>,
 }
@@ -132,6 +172,10 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("BOOLVECTOR.SET"),
         Instruction::new(bool_vector_set),
     );
+    map.insert(
+        String::from("BOOLVECTOR.ALIGN"),
+        Instruction::new(bool_vector_align),
+    );
     map.insert(
         String::from("BOOLVECTOR.AND"),
         Instruction::new(bool_vector_and),
@@ -148,10 +192,18 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("BOOLVECTOR.COUNT"),
         Instruction::new(bool_vector_count),
     );
+    map.insert(
+        String::from("BOOLVECTOR.CONCAT"),
+        Instruction::new(bool_vector_concat),
+    );
     map.insert(
         String::from("BOOLVECTOR.DEFINE"),
         Instruction::new(bool_vector_define),
     );
+    map.insert(
+        String::from("BOOLVECTOR.DROP"),
+        Instruction::new(bool_vector_drop),
+    );
     map.insert(
         String::from("BOOLVECTOR.DUP"),
         Instruction::new(bool_vector_dup),
@@ -164,10 +216,30 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("BOOLVECTOR.FLUSH"),
         Instruction::new(bool_vector_flush),
     );
+    map.insert(
+        String::from("BOOLVECTOR.FIND"),
+        Instruction::new(bool_vector_find),
+    );
+    map.insert(
+        String::from("BOOLVECTOR.FIRST"),
+        Instruction::new(bool_vector_first),
+    );
+    map.insert(
+        String::from("BOOLVECTOR.FROMINTVECTOR"),
+        Instruction::new(bool_vector_from_int_vector),
+    );
+    map.insert(
+        String::from("BOOLVECTOR.HAMMING"),
+        Instruction::new(bool_vector_hamming),
+    );
     map.insert(
         String::from("BOOLVECTOR.ID"),
         Instruction::new(bool_vector_id),
     );
+    map.insert(
+        String::from("BOOLVECTOR.LAST"),
+        Instruction::new(bool_vector_last),
+    );
     map.insert(
         String::from("BOOLVECTOR.LENGTH"),
         Instruction::new(bool_vector_length),
@@ -176,6 +248,10 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("BOOLVECTOR.ONES"),
         Instruction::new(bool_vector_ones),
     );
+    map.insert(
+        String::from("BOOLVECTOR.OVERLAP"),
+        Instruction::new(bool_vector_overlap),
+    );
     map.insert(
         String::from("BOOLVECTOR.POP"),
         Instruction::new(bool_vector_pop),
@@ -184,14 +260,26 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("BOOLVECTOR.RAND"),
         Instruction::new(bool_vector_rand),
     );
+    map.insert(
+        String::from("BOOLVECTOR.RAND*BERNOULLI"),
+        Instruction::new(bool_vector_rand_bernoulli),
+    );
+    map.insert(
+        String::from("BOOLVECTOR.REVERSE"),
+        Instruction::new(bool_vector_reverse),
+    );
     map.insert(
         String::from("BOOLVECTOR.ROTATE"),
-        Instruction::new(bool_vector_rand),
+        Instruction::new(bool_vector_rotate),
     );
     map.insert(
         String::from("BOOLVECTOR.SHOVE"),
         Instruction::new(bool_vector_shove),
     );
+    map.insert(
+        String::from("BOOLVECTOR.SLICE"),
+        Instruction::new(bool_vector_slice),
+    );
     map.insert(
         String::from("BOOLVECTOR.SORT*ASC"),
         Instruction::new(bool_vector_sort_asc),
@@ -208,6 +296,18 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("BOOLVECTOR.STACKDEPTH"),
         Instruction::new(bool_vector_stack_depth),
     );
+    map.insert(
+        String::from("BOOLVECTOR.STARTSWITH"),
+        Instruction::new(bool_vector_starts_with),
+    );
+    map.insert(
+        String::from("BOOLVECTOR.TAKE"),
+        Instruction::new(bool_vector_take),
+    );
+    map.insert(
+        String::from("BOOLVECTOR.XOR"),
+        Instruction::new(bool_vector_xor),
+    );
     map.insert(
         String::from("BOOLVECTOR.YANK"),
         Instruction::new(bool_vector_yank),
@@ -221,10 +321,22 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         Instruction::new(bool_vector_zeros),
     );
 
+    map.insert(
+        String::from("INTVECTOR.ALIGN"),
+        Instruction::new(int_vector_align),
+    );
     map.insert(
         String::from("INTVECTOR.APPEND"),
         Instruction::new(int_vector_append),
     );
+    map.insert(
+        String::from("INTVECTOR.ARGMAX"),
+        Instruction::new(int_vector_argmax),
+    );
+    map.insert(
+        String::from("INTVECTOR.ARGMIN"),
+        Instruction::new(int_vector_argmin),
+    );
     map.insert(
         String::from("INTVECTOR.BOOLINDEX"),
         Instruction::new(int_vector_bool_index),
@@ -245,6 +357,14 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("INTVECTOR.-"),
         Instruction::new(int_vector_subtract),
     );
+    map.insert(
+        String::from("INTVECTOR.+PAD"),
+        Instruction::new(int_vector_add_pad),
+    );
+    map.insert(
+        String::from("INTVECTOR.-PAD"),
+        Instruction::new(int_vector_subtract_pad),
+    );
 //    map.insert(
 //        String::from("INTVECTOR.*"),
 //        Instruction::new(int_vector_multiply),
@@ -257,10 +377,18 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("INTVECTOR.CONTAINS"),
         Instruction::new(int_vector_contains),
     );
+    map.insert(
+        String::from("INTVECTOR.CONCAT"),
+        Instruction::new(int_vector_concat),
+    );
     map.insert(
         String::from("INTVECTOR.DEFINE"),
         Instruction::new(int_vector_define),
     );
+    map.insert(
+        String::from("INTVECTOR.DROP"),
+        Instruction::new(int_vector_drop),
+    );
     map.insert(
         String::from("INTVECTOR.DUP"),
         Instruction::new(int_vector_dup),
@@ -277,10 +405,26 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("INTVECTOR.FLUSH"),
         Instruction::new(int_vector_flush),
     );
+    map.insert(
+        String::from("INTVECTOR.FIND"),
+        Instruction::new(int_vector_find),
+    );
+    map.insert(
+        String::from("INTVECTOR.FROMFLOATVECTOR"),
+        Instruction::new(int_vector_from_float_vector),
+    );
     map.insert(
         String::from("INTVECTOR.FROMINT"),
         Instruction::new(int_vector_from_int),
     );
+    map.insert(
+        String::from("INTVECTOR.HISTOGRAM"),
+        Instruction::new(int_vector_histogram),
+    );
+    map.insert(
+        String::from("INTVECTOR.FIRST"),
+        Instruction::new(int_vector_first),
+    );
     map.insert(
         String::from("INTVECTOR.ID"),
         Instruction::new(int_vector_id),
@@ -293,13 +437,29 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("INTVECTOR.MEAN"),
         Instruction::new(int_vector_mean),
     );
+    map.insert(
+        String::from("INTVECTOR.MAX"),
+        Instruction::new(int_vector_max),
+    );
+    map.insert(
+        String::from("INTVECTOR.MEDIAN"),
+        Instruction::new(int_vector_median),
+    );
+    map.insert(
+        String::from("INTVECTOR.MIN"),
+        Instruction::new(int_vector_min),
+    );
+    map.insert(
+        String::from("INTVECTOR.LAST"),
+        Instruction::new(int_vector_last),
+    );
     map.insert(
         String::from("INTVECTOR.LENGTH"),
         Instruction::new(int_vector_length),
     );
     map.insert(
         String::from("INTVECTOR.LOOP"),
-        Instruction::new(int_vector_loop),
+        Instruction::new(int_vector_loop).with_blocks(1),
     );
     map.insert(
         String::from("INTVECTOR.POP"),
@@ -313,6 +473,14 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("INTVECTOR.RAND"),
         Instruction::new(int_vector_rand),
     );
+    map.insert(
+        String::from("INTVECTOR.RAND*POISSON"),
+        Instruction::new(int_vector_rand_poisson),
+    );
+    map.insert(
+        String::from("INTVECTOR.REVERSE"),
+        Instruction::new(int_vector_reverse),
+    );
     map.insert(
         String::from("INTVECTOR.ROTATE"),
         Instruction::new(int_vector_rotate),
@@ -321,6 +489,10 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("INTVECTOR.SHOVE"),
         Instruction::new(int_vector_shove),
     );
+    map.insert(
+        String::from("INTVECTOR.SLICE"),
+        Instruction::new(int_vector_slice),
+    );
     map.insert(
         String::from("INTVECTOR.SORT*ASC"),
         Instruction::new(int_vector_sort_asc),
@@ -337,6 +509,14 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("INTVECTOR.STACKDEPTH"),
         Instruction::new(int_vector_stack_depth),
     );
+    map.insert(
+        String::from("INTVECTOR.STARTSWITH"),
+        Instruction::new(int_vector_starts_with),
+    );
+    map.insert(
+        String::from("INTVECTOR.STDDEV"),
+        Instruction::new(int_vector_stddev),
+    );
     map.insert(
         String::from("INTVECTOR.SET*INSERT"),
         Instruction::new(int_vector_set_insert),
@@ -345,6 +525,14 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("INTVECTOR.SUM"),
         Instruction::new(int_vector_sum),
     );
+    map.insert(
+        String::from("INTVECTOR.TAKE"),
+        Instruction::new(int_vector_take),
+    );
+    map.insert(
+        String::from("INTVECTOR.TOFLOAT"),
+        Instruction::new(int_vector_to_float),
+    );
     map.insert(
         String::from("INTVECTOR.YANK"),
         Instruction::new(int_vector_yank),
@@ -386,14 +574,66 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("FLOATVECTOR./"),
         Instruction::new(float_vector_divide),
     );
+    map.insert(
+        String::from("FLOATVECTOR.+PAD"),
+        Instruction::new(float_vector_add_pad),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.-PAD"),
+        Instruction::new(float_vector_subtract_pad),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.*PAD"),
+        Instruction::new(float_vector_multiply_pad),
+    );
+    map.insert(
+        String::from("FLOATVECTOR./PAD"),
+        Instruction::new(float_vector_divide_pad),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.ABS"),
+        Instruction::new(float_vector_abs),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.ALIGN"),
+        Instruction::new(float_vector_align),
+    );
     map.insert(
         String::from("FLOATVECTOR.APPEND"),
         Instruction::new(float_vector_append),
     );
+    map.insert(
+        String::from("FLOATVECTOR.ARGMAX"),
+        Instruction::new(float_vector_argmax),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.ARGMIN"),
+        Instruction::new(float_vector_argmin),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.ARGSORT"),
+        Instruction::new(float_vector_argsort),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.COSINE"),
+        Instruction::new(float_vector_cosine),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.CONCAT"),
+        Instruction::new(float_vector_concat),
+    );
     map.insert(
         String::from("FLOATVECTOR.DEFINE"),
         Instruction::new(float_vector_define),
     );
+    map.insert(
+        String::from("FLOATVECTOR.DOT"),
+        Instruction::new(float_vector_dot),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.DROP"),
+        Instruction::new(float_vector_drop),
+    );
     map.insert(
         String::from("FLOATVECTOR.DUP"),
         Instruction::new(float_vector_dup),
@@ -406,22 +646,82 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("FLOATVECTOR.EQUAL"),
         Instruction::new(float_vector_equal),
     );
+    map.insert(
+        String::from("FLOATVECTOR.EXP"),
+        Instruction::new(float_vector_exp),
+    );
     map.insert(
         String::from("FLOATVECTOR.FLUSH"),
         Instruction::new(float_vector_flush),
     );
+    map.insert(
+        String::from("FLOATVECTOR.FROMINTVECTOR"),
+        Instruction::new(float_vector_from_int_vector),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.HISTOGRAM"),
+        Instruction::new(float_vector_histogram),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.FIRST"),
+        Instruction::new(float_vector_first),
+    );
     map.insert(
         String::from("FLOATVECTOR.ID"),
         Instruction::new(float_vector_id),
     );
+    map.insert(
+        String::from("FLOATVECTOR.INTERP"),
+        Instruction::new(float_vector_interp),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.LAST"),
+        Instruction::new(float_vector_last),
+    );
     map.insert(
         String::from("FLOATVECTOR.LENGTH"),
         Instruction::new(float_vector_length),
     );
+    map.insert(
+        String::from("FLOATVECTOR.LOG"),
+        Instruction::new(float_vector_log),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.MAP"),
+        Instruction::new(float_vector_map).with_blocks(1),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.MAP*STEP"),
+        Instruction::new(float_vector_map_step),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.MAX"),
+        Instruction::new(float_vector_max),
+    );
     map.insert(
         String::from("FLOATVECTOR.MEAN"),
         Instruction::new(float_vector_mean),
     );
+    map.insert(
+        String::from("FLOATVECTOR.MEDIAN"),
+        Instruction::new(float_vector_median),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.MIN"),
+        Instruction::new(float_vector_min),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.NEAREST"),
+        Instruction::new(float_vector_nearest),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.NORM*L1"),
+        Instruction::new(float_vector_norm_l1),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.NORM*L2"),
+        Instruction::new(float_vector_norm_l2),
+    );
     map.insert(
         String::from("FLOATVECTOR.ONES"),
         Instruction::new(float_vector_ones),
@@ -434,10 +734,30 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("FLOATVECTOR.RAND"),
         Instruction::new(float_vector_rand),
     );
+    map.insert(
+        String::from("FLOATVECTOR.RAND*EXP"),
+        Instruction::new(float_vector_rand_exp),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.RAND*UNIFORM"),
+        Instruction::new(float_vector_rand_uniform),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.RESAMPLE"),
+        Instruction::new(float_vector_resample),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.REVERSE"),
+        Instruction::new(float_vector_reverse),
+    );
     map.insert(
         String::from("FLOATVECTOR.ROTATE"),
         Instruction::new(float_vector_rotate),
     );
+    map.insert(
+        String::from("FLOATVECTOR.SIGMOID"),
+        Instruction::new(float_vector_sigmoid),
+    );
     map.insert(
         String::from("FLOATVECTOR.SINE"),
         Instruction::new(float_vector_sine),
@@ -446,6 +766,10 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("FLOATVECTOR.SHOVE"),
         Instruction::new(float_vector_shove),
     );
+    map.insert(
+        String::from("FLOATVECTOR.SLICE"),
+        Instruction::new(float_vector_slice),
+    );
     map.insert(
         String::from("FLOATVECTOR.SORT*ASC"),
         Instruction::new(float_vector_sort_asc),
@@ -454,6 +778,10 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("FLOATVECTOR.SORT*DESC"),
         Instruction::new(float_vector_sort_desc),
     );
+    map.insert(
+        String::from("FLOATVECTOR.SQRT"),
+        Instruction::new(float_vector_sqrt),
+    );
     map.insert(
         String::from("FLOATVECTOR.SWAP"),
         Instruction::new(float_vector_swap),
@@ -462,9 +790,25 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("FLOATVECTOR.STACKDEPTH"),
         Instruction::new(float_vector_stack_depth),
     );
+    map.insert(
+        String::from("FLOATVECTOR.STDDEV"),
+        Instruction::new(float_vector_stddev),
+    );
     map.insert(
         String::from("FLOATVECTOR.SUM"),
-        Instruction::new(float_vector_stack_depth),
+        Instruction::new(float_vector_sum),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.TANH"),
+        Instruction::new(float_vector_tanh),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.XCORR"),
+        Instruction::new(float_vector_xcorr),
+    );
+    map.insert(
+        String::from("FLOATVECTOR.TAKE"),
+        Instruction::new(float_vector_take),
     );
     map.insert(
         String::from("FLOATVECTOR.YANK"),
@@ -480,59 +824,239 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
     );
 }
 
+/// Counts the values in equally sized bins between lower and upper. Values
+/// at the upper bound are counted in the last bin.
+fn histogram(values: &[f64], lower: f64, upper: f64, bins: usize) -> Vec<i32> {
+    let mut counts = vec![0; bins];
+    for v in values {
+        let mut bin = 0;
+        if upper > lower {
+            bin = ((v - lower) / (upper - lower) * bins as f64) as usize;
+        }
+        counts[usize::min(bin, bins - 1)] += 1;
+    }
+    counts
+}
+
+/// Returns the index of the first occurrence of pattern within values or -1 if
+/// pattern is not contained. An empty pattern is found at index 0.
+fn find_subsequence<T: PartialEq>(values: &[T], pattern: &[T]) -> i32 {
+    if pattern.is_empty() {
+        return 0;
+    }
+    match values.windows(pattern.len()).position(|w| w == pattern) {
+        Some(idx) => idx as i32,
+        None => -1,
+    }
+}
+
+/// Pops the index offset of an element-wise vector instruction from the INTEGER stack.
+/// The offset is zero if vector offsets are disabled by the configuration.
+fn pop_offset(push_state: &mut PushState) -> Option<i32> {
+    if push_state.configuration.vector_offsets {
+        push_state.int_stack.pop()
+    } else {
+        Some(0)
+    }
+}
+
+/// Pushes back an offset that was popped by pop_offset.
+fn restore_offset(push_state: &mut PushState, offset: i32) {
+    if push_state.configuration.vector_offsets {
+        push_state.int_stack.push(offset);
+    }
+}
+
+/// Returns the values shifted to the right by the offset (to the left if negative).
+/// The length is preserved and vacated positions are filled with the default value.
+fn align<T: Clone + Default>(values: &[T], offset: i32) -> Vec<T> {
+    let len = values.len() as i64;
+    (0..len)
+        .map(|i| {
+            let source = i - offset as i64;
+            if source >= 0 && source < len {
+                values[source as usize].clone()
+            } else {
+                T::default()
+            }
+        })
+        .collect()
+}
+
+/// Combines the elements of both vectors pairwise. The shorter vector is extended with the
+/// fill value so that the result has the length of the longer vector. Returns None if the
+/// operation fails for any pair of elements.
+fn pad_zip<T: Copy, F: FnMut(T, T) -> Option<T>>(
+    first: &[T],
+    second: &[T],
+    fill: T,
+    mut op: F,
+) -> Option<Vec<T>> {
+    (0..usize::max(first.len(), second.len()))
+        .map(|i| {
+            op(
+                *first.get(i).unwrap_or(&fill),
+                *second.get(i).unwrap_or(&fill),
+            )
+        })
+        .collect()
+}
+
+/// Reports a vector that would exceed the configured maximum size to the ERROR
+/// stack if strict mode is enabled.
+pub(crate) fn report_vector_size_cap(push_state: &mut PushState, requested: usize) {
+    if push_state.configuration.strict {
+        let cap = push_state.configuration.max_vector_size;
+        push_state.error_stack.push(PushError::VectorSizeCap { requested, cap });
+    }
+}
+
+/// Returns the range of a vector of the given length between the start (inclusive) and end
+/// (exclusive) index. Both indices are bound to the valid range, and the range is empty if
+/// start is not smaller than end.
+fn slice_range(len: usize, start: i32, end: i32) -> std::ops::Range<usize> {
+    let start = i32::max(start, 0) as usize;
+    let end = i32::max(end, 0) as usize;
+    usize::min(start, len)..usize::min(usize::max(start, end), len)
+}
+
+/// Returns the position of the index in a vector of the given length. By default the
+/// index is bound to the valid range. With strict indexing an out-of-range index is
+/// reported to the ERROR stack and None is returned.
+fn vector_index(push_state: &mut PushState, index: i32, len: usize) -> Option<usize> {
+    if push_state.configuration.strict_indexing {
+        if index < 0 || index as usize >= len {
+            push_state
+                .error_stack
+                .push(PushError::IndexOutOfRange { index, len });
+            return None;
+        }
+        Some(index as usize)
+    } else if len > 0 {
+        Some(i32::max(i32::min(index, len as i32 - 1), 0) as usize)
+    } else {
+        None
+    }
+}
+
+/// Limits the requested number of vector elements to the configured maximum
+/// vector size.
+pub fn cap_vector_size(push_state: &mut PushState, requested: i32) -> i32 {
+    let cap = usize::min(push_state.configuration.max_vector_size, i32::MAX as usize) as i32;
+    if requested > cap {
+        report_vector_size_cap(push_state, requested as usize);
+        cap
+    } else {
+        requested
+    }
+}
+
+/// Pops the size of a new vector from the INTEGER stack limited to the configured
+/// maximum vector size.
+fn pop_vector_size(push_state: &mut PushState) -> Option<i32> {
+    let size = push_state.int_stack.pop()?;
+    Some(cap_vector_size(push_state, size))
+}
+
 /////////////////////////////////////// BOOLVECTOR //////////////////////////////////////////
 
+/// BOOLVECTOR.FROMINTVECTOR: Pops the top INTVECTOR and pushes a BOOLVECTOR of the same
+/// length whose elements are TRUE where the integer is not zero.
+pub fn bool_vector_from_int_vector(
+    push_state: &mut PushState,
+    _instruction_cache: &InstructionCache,
+) {
+    if let Some(iv) = push_state.int_vector_stack.pop() {
+        push_state
+            .bool_vector_stack
+            .push(BoolVector::new(iv.values.iter().map(|x| *x != 0).collect()));
+    }
+}
+
 /// BOOLVECTOR.ID: Pushes the ID of the BOOLVECTOR stack to the INTEGER stack.
 pub fn bool_vector_id(push_state: &mut PushState, _instruction_set: &InstructionCache) {
     push_state.int_stack.push(BOOL_VECTOR_STACK_ID);
 }
 
 /// BOOLVECTOR.SET: Replaces the ith element of the top BOOLVECTOR item by the top item of the
-/// BOOLEAN stack. The index i is taken from the INTEGER stack.
+/// BOOLEAN stack. The index i is taken from the INTEGER stack. With strict indexing an
+/// out-of-range index is reported to the ERROR stack and the arguments are left on their stacks.
 pub fn bool_vector_set(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(index) = push_state.int_stack.pop() {
         if let Some(new_element) = push_state.bool_stack.pop() {
-            if let Some(item_to_change) = push_state.bool_vector_stack.get_mut(0) {
-                if item_to_change.values.len() > 0 {
-                    let i =
-                        i32::max(i32::min(index, item_to_change.values.len() as i32 - 1), 0) as usize;
-                    item_to_change.values[i] = new_element;
+            if let Some(len) = push_state.bool_vector_stack.get(0).map(|bv| bv.len()) {
+                if let Some(i) = vector_index(push_state, index, len) {
+                    if let Some(item_to_change) = push_state.bool_vector_stack.get_mut(0) {
+                        item_to_change.bits.set(i, new_element);
+                    }
+                } else if push_state.configuration.strict_indexing {
+                    push_state.bool_stack.push(new_element);
+                    push_state.int_stack.push(index);
                 }
             }
         }
     }
 }
 
+/// BOOLVECTOR.ALIGN: Shifts the elements of the top BOOLVECTOR item by the offset taken from
+/// the INTEGER stack. Positive offsets shift to higher indices. The length is preserved and
+/// vacated positions are set to FALSE.
+pub fn bool_vector_align(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvec) = push_state.bool_vector_stack.get_mut(0) {
+        if let Some(offset) = push_state.int_stack.pop() {
+            bvec.bits = bvec.bits.shifted(offset, bvec.len());
+        }
+    }
+}
+
+/// Returns the range of indices of a vector of the given length that an item of length
+/// other_len covers if its indices are shifted by the offset.
+fn offset_range(len: usize, other_len: usize, offset: i32) -> std::ops::Range<usize> {
+    let shift = |i: usize| (i as i64 + offset as i64).clamp(0, len as i64) as usize;
+    shift(0)..shift(other_len)
+}
+
+/// Replaces the top two BOOLVECTOR items by the result of applying the bitwise operation to
+/// the second item and the top item shifted by the offset from the INTEGER stack. Elements
+/// that the shifted top item does not cover are kept.
+fn bool_vector_bitwise<F>(push_state: &mut PushState, op: F)
+where
+    F: Fn(&BitSet, &BitSet) -> BitSet,
+{
+    if let Some(mut bv) = push_state.bool_vector_stack.pop_vec(2) {
+        if let Some(offset) = pop_offset(push_state) {
+            let top = bv.pop().unwrap();
+            let mut second = bv.pop().unwrap();
+            let len = second.len();
+            let covered = BitSet::range(len, offset_range(len, top.len(), offset));
+            let result = op(&second.bits, &top.bits.shifted(offset, len));
+            second.bits = result.and(&covered).or(&second.bits.and(&covered.not()));
+            push_state.bool_vector_stack.push(second);
+        }
+    }
+}
+
 /// BOOLVECTOR.AND: Pushes the result of applying element-wise AND of the top item to the
 /// second item on the BOOLVECTOR stack. It applies an offset to the indices of the top
 /// item. The offset is taken from the INTEGER stack. Indices that are outside of the valid
 /// range of the second item are ignored. If there is no overlap of indices the second item of
 /// the stack is pushed as a result.
 pub fn bool_vector_and(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    if let Some(mut bv) = push_state.bool_vector_stack.pop_vec(2) {
-        if let Some(offset) = push_state.int_stack.pop() {
-            // Loop through indices of second item
-            let scd_size = bv[0].values.len();
-            for i in 0..scd_size {
-                let ofs_idx = (i as i32 + offset) as usize;
-                if ofs_idx > scd_size - 1 {
-                    continue; // Out of bounds
-                }
-                bv[0].values[ofs_idx] &= bv[1].values[i];
-            }
-            push_state.bool_vector_stack.push(bv[0].clone());
-        }
-    }
+    bool_vector_bitwise(push_state, BitSet::and);
 }
 
 /// BOOLVECTOR.GET: Copies the element at index i of the top BOOLVECTOR item to the BOOLEAN stack
-/// where i taken from the INTEGER stack limited to valid range.
+/// where i taken from the INTEGER stack limited to valid range. With strict indexing an
+/// out-of-range index is reported to the ERROR stack and left on the INTEGER stack.
 pub fn bool_vector_get(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(index) = push_state.int_stack.pop() {
-        if let Some(element) = push_state.bool_vector_stack.get(0) {
-            if element.values.len() >0 {
-                let i = i32::max(i32::min(index, element.values.len() as i32 - 1), 0) as usize;
-                push_state.bool_stack.push(element.values[i].clone());
+        if let Some(len) = push_state.bool_vector_stack.get(0).map(|v| v.len()) {
+            if let Some(i) = vector_index(push_state, index, len) {
+                if let Some(element) = push_state.bool_vector_stack.get(0) {
+                    push_state.bool_stack.push(element.bits.get(i));
+                }
+            } else if push_state.configuration.strict_indexing {
+                push_state.int_stack.push(index);
             }
         }
     }
@@ -544,45 +1068,54 @@ pub fn bool_vector_get(push_state: &mut PushState, _instruction_cache: &Instruct
 /// range of the second item are ignored. If there is no overlap of indices the second item of
 /// the stack is pushed as a result.
 pub fn bool_vector_or(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    if let Some(mut bv) = push_state.bool_vector_stack.pop_vec(2) {
-        if let Some(offset) = push_state.int_stack.pop() {
-            // Loop through indices of second item
-            let scd_size = bv[0].values.len();
-            for i in 0..scd_size {
-                let ofs_idx = (i as i32 + offset) as usize;
-                if ofs_idx > scd_size - 1 {
-                    continue; // Out of bounds
-                }
-                bv[0].values[ofs_idx] |= bv[1].values[i];
-            }
-            push_state.bool_vector_stack.push(bv[0].clone());
-        }
-    }
+    bool_vector_bitwise(push_state, BitSet::or);
 }
 
 /// BOOLVECTOR.NOT Applies the negation operator for the elements of the top item. It only considers
 /// indices larger than the offset. The offset is taken from the INTEGER stack.
 pub fn bool_vector_not(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(mut bvval) = push_state.bool_vector_stack.pop() {
-        if let Some(offset) = push_state.int_stack.pop() {
-            for i in 0..bvval.values.len() {
-                let ofs_idx = (i as i32 + offset) as usize;
-                if ofs_idx > bvval.values.len() - 1 {
-                    continue; // Out of bounds
-                }
-                bvval.values[ofs_idx] = !bvval.values[ofs_idx];
-            }
-            push_state.bool_vector_stack.push(bvval.clone());
+        if let Some(offset) = pop_offset(push_state) {
+            let len = bvval.len();
+            bvval.bits = bvval.bits.xor(&BitSet::range(len, offset_range(len, len, offset)));
+            push_state.bool_vector_stack.push(bvval);
         }
     }
 }
 
+/// BOOLVECTOR.XOR: Pushes the result of applying element-wise XOR of the top item to the
+/// second item on the BOOLVECTOR stack. It applies an offset to the indices of the top
+/// item. The offset is taken from the INTEGER stack. Indices that are outside of the valid
+/// range of the second item are ignored. If there is no overlap of indices the second item of
+/// the stack is pushed as a result.
+pub fn bool_vector_xor(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    bool_vector_bitwise(push_state, BitSet::xor);
+}
+
+/// BOOLVECTOR.HAMMING: Pops the top two BOOLVECTOR items and pushes the number of positions
+/// at which they differ to the INTEGER stack. The shorter vector is padded with FALSE.
+pub fn bool_vector_hamming(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvvals) = push_state.bool_vector_stack.pop_vec(2) {
+        let distance = BitSet::from(&bvvals[0]).hamming(&BitSet::from(&bvvals[1]));
+        push_state.int_stack.push(distance as i32);
+    }
+}
+
+/// BOOLVECTOR.OVERLAP: Pops the top two BOOLVECTOR items and pushes the number of positions
+/// at which both are TRUE to the INTEGER stack.
+pub fn bool_vector_overlap(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvvals) = push_state.bool_vector_stack.pop_vec(2) {
+        let overlap = BitSet::from(&bvvals[0]).overlap(&BitSet::from(&bvvals[1]));
+        push_state.int_stack.push(overlap as i32);
+    }
+}
+
 /// BOOLVECTOR.DEFINE: Defines the name on top of the NAME stack as an instruction that will
 /// push the top item of the BOOLVECTOR stack onto the EXEC stack.
 pub fn bool_vector_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(bvval) = push_state.bool_vector_stack.pop() {
-            push_state.name_bindings.insert(name, Item::boolvec(bvval));
+            push_state.bind(name, Item::boolvec(bvval));
         }
     }
 }
@@ -595,7 +1128,7 @@ pub fn bool_vector_dup(push_state: &mut PushState, _instruction_cache: &Instruct
     }
 }
 
-/// BOOLVECTOR.=: Pushes TRUE onto the BOOLEAN stack if the top two items are equal, or FALSE
+/// BOOLVECTOR.EQUAL: Pushes TRUE onto the BOOLEAN stack if the top two items are equal, or FALSE
 /// otherwise.
 fn bool_vector_equal(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(bvvals) = push_state.bool_vector_stack.pop_vec(2) {
@@ -608,17 +1141,28 @@ pub fn bool_vector_flush(push_state: &mut PushState, _instruction_cache: &Instru
     push_state.bool_vector_stack.flush();
 }
 
+/// BOOLVECTOR.FIND: Pushes the index of the first occurrence of the top BOOLVECTOR item within
+/// the second item to the INTEGER stack or -1 if the top item is not contained. Both items are
+/// popped.
+pub fn bool_vector_find(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvvals) = push_state.bool_vector_stack.pop_vec(2) {
+        push_state
+            .int_stack
+            .push(find_subsequence(&bvvals[0].to_bools(), &bvvals[1].to_bools()));
+    }
+}
+
 /// BOOLVECTOR.LENGTH: Pushes the length of the top BOOLVECTOR item to the INTEGER stack.
 pub fn bool_vector_length(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(bv) = push_state.bool_vector_stack.get(0) {
-        push_state.int_stack.push(bv.values.len() as i32);
+        push_state.int_stack.push(bv.len() as i32);
     }
 }
 
 /// BOOLVECTOR.ONES: Pushes a newly generated BOOLVECTOR with all elements set to true. The size
 /// is taken from the INTEGER stack
 pub fn bool_vector_ones(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    if let Some(size) = push_state.int_stack.pop() {
+    if let Some(size) = pop_vector_size(push_state) {
         if size > 0 {
             push_state
                 .bool_vector_stack
@@ -636,23 +1180,121 @@ pub fn bool_vector_pop(push_state: &mut PushState, _instruction_cache: &Instruct
 /// stack, the sparsity from the FLOAT stack. If the size is <0 or the sparcity not in [0,1] this
 /// acts as a NOOP.
 pub fn bool_vector_rand(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    if let Some(size) = push_state.int_stack.pop() {
+    if let Some(size) = pop_vector_size(push_state) {
         if let Some(sparsity) = push_state.float_stack.pop() {
-            if let Some(rbvval) = CodeGenerator::random_bool_vector(size, sparsity) {
+            if let Some(rbvval) = CodeGenerator::random_bool_vector(&mut push_state.rng, size, sparsity) {
                 push_state.bool_vector_stack.push(rbvval);
             }
         }
     }
 }
 
+/// BOOLVECTOR.RAND*BERNOULLI: Pushes a newly generated random BOOLVECTOR whose elements are
+/// TRUE with probability p. The size is taken from the INTEGER stack, p from the FLOAT stack.
+/// If the size is <0 or p not in [0,1] this acts as a NOOP.
+pub fn bool_vector_rand_bernoulli(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(size) = pop_vector_size(push_state) {
+        if let Some(p) = push_state.float_stack.pop() {
+            if let Some(rbvval) = CodeGenerator::random_bernoulli_bool_vector(&mut push_state.rng, size, p) {
+                push_state.bool_vector_stack.push(rbvval);
+            }
+        }
+    }
+}
+
+/// BOOLVECTOR.CONCAT: Pops the top two BOOLVECTOR items and pushes the elements of the second item
+/// followed by the elements of the top item. If the result would exceed the maximum vector
+/// size this acts as a NOOP.
+pub fn bool_vector_concat(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    let len = match (
+        push_state.bool_vector_stack.get(1),
+        push_state.bool_vector_stack.get(0),
+    ) {
+        (Some(second), Some(top)) => second.len() + top.len(),
+        _ => return,
+    };
+    if len > push_state.configuration.max_vector_size {
+        report_vector_size_cap(push_state, len);
+        return;
+    }
+    if let Some(top) = push_state.bool_vector_stack.pop() {
+        if let Some(second) = push_state.bool_vector_stack.get_mut(0) {
+            let shifted_top = top.bits.shifted(second.len() as i32, len);
+            second.bits = second.bits.shifted(0, len).or(&shifted_top);
+        }
+    }
+}
+
+/// BOOLVECTOR.DROP: Removes the first n elements of the top BOOLVECTOR item. The number n is taken
+/// from the INTEGER stack and bound to the valid range.
+pub fn bool_vector_drop(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.bool_vector_stack.size() > 0 {
+        if let Some(n) = push_state.int_stack.pop() {
+            let bv = push_state.bool_vector_stack.get_mut(0).unwrap();
+            let n = usize::min(n.max(0) as usize, bv.len());
+            bv.bits = bv.bits.shifted(-(n as i32), bv.len() - n);
+        }
+    }
+}
+
+/// BOOLVECTOR.FIRST: Copies the first element of the top BOOLVECTOR item to the BOOLEAN stack.
+pub fn bool_vector_first(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bv) = push_state.bool_vector_stack.get(0) {
+        if !bv.is_empty() {
+            push_state.bool_stack.push(bv.bits.get(0));
+        }
+    }
+}
+
+/// BOOLVECTOR.LAST: Copies the last element of the top BOOLVECTOR item to the BOOLEAN stack.
+pub fn bool_vector_last(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bv) = push_state.bool_vector_stack.get(0) {
+        if let Some(last) = bv.len().checked_sub(1) {
+            push_state.bool_stack.push(bv.bits.get(last));
+        }
+    }
+}
+
+/// BOOLVECTOR.REVERSE: Reverses the order of the elements of the top BOOLVECTOR item.
+pub fn bool_vector_reverse(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bv) = push_state.bool_vector_stack.get_mut(0) {
+        bv.bits = (0..bv.len()).rev().map(|i| bv.bits.get(i)).collect();
+    }
+}
+
+/// BOOLVECTOR.SLICE: Replaces the top BOOLVECTOR item by its elements from index start (inclusive)
+/// to index end (exclusive). The end is taken from the top and the start from the second item
+/// of the INTEGER stack. See slice_range for the handling of invalid indices.
+pub fn bool_vector_slice(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.bool_vector_stack.size() > 0 {
+        if let Some(bounds) = push_state.int_stack.pop_vec(2) {
+            let bv = push_state.bool_vector_stack.get_mut(0).unwrap();
+            let range = slice_range(bv.len(), bounds[0], bounds[1]);
+            bv.bits = bv.bits.shifted(-(range.start as i32), range.len());
+        }
+    }
+}
+
+/// BOOLVECTOR.TAKE: Keeps the first n elements of the top BOOLVECTOR item. The number n is taken
+/// from the INTEGER stack and bound to the valid range.
+pub fn bool_vector_take(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.bool_vector_stack.size() > 0 {
+        if let Some(n) = push_state.int_stack.pop() {
+            let bv = push_state.bool_vector_stack.get_mut(0).unwrap();
+            bv.bits.truncate(n.max(0) as usize);
+        }
+    }
+}
+
 /// BOOLVECTOR.ROTATE: Moves all elements of the top item to the adjacent position on the left.
 /// The first item is removed while the last element of the vector is taken from the BOOLEAN stack.
 pub fn bool_vector_rotate(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(b) = push_state.bool_stack.pop() {
         if let Some(bv) = push_state.bool_vector_stack.get_mut(0) {
-            bv.values.rotate_left(1);
-            let n = bv.values.len();
-            bv.values[n - 1] = b;
+            if let Some(last) = bv.len().checked_sub(1) {
+                bv.bits = bv.bits.shifted(-1, bv.len());
+                bv.bits.set(last, b);
+            }
         }
     }
 }
@@ -660,15 +1302,15 @@ pub fn bool_vector_rotate(push_state: &mut PushState, _instruction_cache: &Instr
 /// BOOLVECTOR.SORT*ASC: Sorts the top BOOLVECTOR item in ascending order.
 pub fn bool_vector_sort_asc(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(bvec) = push_state.bool_vector_stack.get_mut(0) {
-        bvec.values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let (len, ones) = (bvec.len(), bvec.bits.count_ones());
+        bvec.bits = BitSet::range(len, len - ones..len);
     }
 }
 
 /// BOOLVECTOR.SORT*DESC: Sorts the top BOOLVECTOR item in descending order.
 pub fn bool_vector_sort_desc(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(bvec) = push_state.bool_vector_stack.get_mut(0) {
-        bvec.values.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        bvec.values.reverse();
+        bvec.bits = BitSet::range(bvec.len(), 0..bvec.bits.count_ones());
     }
 }
 
@@ -677,7 +1319,7 @@ pub fn bool_vector_count(push_state: &mut PushState, _instruction_cache: &Instru
     if let Some(bvec) = push_state.bool_vector_stack.get(0) {
         push_state
             .int_stack
-            .push(bvec.values.iter().filter(|&n| *n == true).count() as i32);
+            .push(bvec.bits.count_ones() as i32);
     }
 }
 
@@ -703,6 +1345,16 @@ pub fn bool_vector_stack_depth(push_state: &mut PushState, _instruction_cache: &
         .push(push_state.bool_vector_stack.size() as i32);
 }
 
+/// BOOLVECTOR.STARTSWITH: Pushes TRUE to the BOOLEAN stack if the second BOOLVECTOR item starts
+/// with the elements of the top item and FALSE otherwise. Both items are popped.
+pub fn bool_vector_starts_with(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bvvals) = push_state.bool_vector_stack.pop_vec(2) {
+        push_state
+            .bool_stack
+            .push(bvvals[0].len() >= bvvals[1].len() && bvvals[0].bits.shifted(0, bvvals[1].len()) == bvvals[1].bits);
+    }
+}
+
 /// BOOLVECTOR.SWAP: Swaps the top two BOOLVECTORs.
 pub fn bool_vector_swap(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     push_state.bool_vector_stack.shove(1);
@@ -739,7 +1391,7 @@ pub fn bool_vector_yank_dup(push_state: &mut PushState, _instruction_cache: &Ins
 /// BOOLVECTOR.ZEROS: Pushes a newly generated BOOLVECTOR with all elements set to false. The size
 /// is taken from the INTEGER stack.
 pub fn bool_vector_zeros(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    if let Some(size) = push_state.int_stack.pop() {
+    if let Some(size) = pop_vector_size(push_state) {
         if size > 0 {
             push_state
                 .bool_vector_stack
@@ -750,11 +1402,43 @@ pub fn bool_vector_zeros(push_state: &mut PushState, _instruction_cache: &Instru
 
 /////////////////////////////////////// INTVECTOR //////////////////////////////////////////
 
-/// INTVECTOR.APPEND: Appends the top integer item to the top intvector item.
+/// INTVECTOR.APPEND: Appends the top integer item to the top intvector item. If the
+/// vector already has the maximum size the integer is dropped.
 pub fn int_vector_append(push_state: &mut PushState, _instruction_set: &InstructionCache) {
+    let cap = push_state.configuration.max_vector_size;
+    let mut rejected_size = None;
     if let Some(item) = push_state.int_vector_stack.get_mut(0) {
         if let Some(to_append) = push_state.int_stack.pop() {
-            item.values.push(to_append);
+            if item.values.len() < cap {
+                item.values.push(to_append);
+            } else {
+                rejected_size = Some(item.values.len() + 1);
+            }
+        }
+    }
+    if let Some(requested) = rejected_size {
+        report_vector_size_cap(push_state, requested);
+    }
+}
+
+/// INTVECTOR.HISTOGRAM: Pops the top INTVECTOR and pushes the number of its elements in each
+/// of k equally sized bins between its minimum and maximum value as INTVECTOR. The number of
+/// bins k is taken from the INTEGER stack and limited to the maximum vector size. Acts as NOOP
+/// if k < 1.
+pub fn int_vector_histogram(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bins) = pop_vector_size(push_state) {
+        if bins > 0 {
+            if let Some(iv) = push_state.int_vector_stack.pop() {
+                let values: Vec<f64> = iv.values.iter().map(|x| *x as f64).collect();
+                let lower = values.iter().cloned().fold(f64::INFINITY, f64::min);
+                let upper = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max) + 1.0;
+                push_state.int_vector_stack.push(IntVector::new(histogram(
+                    &values,
+                    lower,
+                    upper,
+                    bins as usize,
+                )));
+            }
         }
     }
 }
@@ -764,14 +1448,67 @@ pub fn int_vector_id(push_state: &mut PushState, _instruction_set: &InstructionC
     push_state.int_stack.push(INT_VECTOR_STACK_ID);
 }
 
+/// Returns the index of the first largest element or None if the values are empty.
+fn argmax<T: PartialOrd>(values: &[T]) -> Option<usize> {
+    let mut best: Option<usize> = None;
+    for (i, v) in values.iter().enumerate() {
+        if best.map_or(true, |b| *v > values[b]) {
+            best = Some(i);
+        }
+    }
+    best
+}
+
+/// Returns the index of the first smallest element or None if the values are empty.
+fn argmin<T: PartialOrd>(values: &[T]) -> Option<usize> {
+    let mut best: Option<usize> = None;
+    for (i, v) in values.iter().enumerate() {
+        if best.map_or(true, |b| *v < values[b]) {
+            best = Some(i);
+        }
+    }
+    best
+}
+
+/// Returns the population standard deviation of the values. The values must not be empty.
+fn standard_deviation(values: &[f32]) -> f32 {
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let squared_deviations = values.iter().map(|x| (x - mean) * (x - mean));
+    (squared_deviations.sum::<f32>() / values.len() as f32).sqrt()
+}
+
+/// INTVECTOR.ARGMAX: Pushes the index of the largest element of the top INTVECTOR to the
+/// INTEGER stack. If the maximum occurs more than once the first index is pushed.
+pub fn int_vector_argmax(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = push_state
+        .int_vector_stack
+        .get(0)
+        .and_then(|iv| argmax(&iv.values))
+    {
+        push_state.int_stack.push(index as i32);
+    }
+}
+
+/// INTVECTOR.ARGMIN: Pushes the index of the smallest element of the top INTVECTOR to the
+/// INTEGER stack. If the minimum occurs more than once the first index is pushed.
+pub fn int_vector_argmin(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = push_state
+        .int_vector_stack
+        .get(0)
+        .and_then(|iv| argmin(&iv.values))
+    {
+        push_state.int_stack.push(index as i32);
+    }
+}
+
 /// INTVECTOR.BOOLINDEX: Pushes an INTVECTOR item that contains the indices of all true values
 /// of the top BOOLVECTOR item. For example, this instruction pushes INT[0,2] if the top
 /// item on the BOOLVECTOR stack is BOOL[1,0,1]. The BOOLVECTOR item is popped.
 pub fn int_vector_bool_index(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(bvval) = push_state.bool_vector_stack.pop() {
         let mut index_vector = vec![];
-        for (i, bval) in bvval.values.iter().enumerate() {
-            if *bval {
+        for (i, bval) in bvval.bits.iter().enumerate() {
+            if bval {
                 index_vector.push(i as i32);
             }
         }
@@ -782,34 +1519,55 @@ pub fn int_vector_bool_index(push_state: &mut PushState, _instruction_cache: &In
 }
 
 /// INTVECTOR.GET: Copies the element at index i of the top INTVECTOR item to the INTEGER stack
-/// where i taken from the INTEGER stack and bound to valid range.
+/// where i taken from the INTEGER stack and bound to valid range. With strict indexing an
+/// out-of-range index is reported to the ERROR stack and left on the INTEGER stack.
 pub fn int_vector_get(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(index) = push_state.int_stack.pop() {
-        if let Some(element) = push_state.int_vector_stack.get(0) {
-            if element.values.len() >0 {
-                let i = i32::max(i32::min(index, element.values.len() as i32 - 1), 0) as usize;
-                push_state.int_stack.push(element.values[i].clone());
+        if let Some(len) = push_state.int_vector_stack.get(0).map(|v| v.values.len()) {
+            if let Some(i) = vector_index(push_state, index, len) {
+                if let Some(element) = push_state.int_vector_stack.get(0) {
+                    push_state.int_stack.push(element.values[i]);
+                }
+            } else if push_state.configuration.strict_indexing {
+                push_state.int_stack.push(index);
             }
         }
     }
 }
 
 /// INTVECTOR.SET: Replaces the ith element of the top INTVECTOR item by the second item of the
-/// INTVECTOR stack. The top item of the INTEGER stack is the index i bound to valid range.
+/// INTVECTOR stack. The top item of the INTEGER stack is the index i bound to valid range. With
+/// strict indexing an out-of-range index is reported to the ERROR stack and the arguments are
+/// left on the INTEGER stack.
 pub fn int_vector_set(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(index) = push_state.int_stack.pop() {
         if let Some(new_element) = push_state.int_stack.pop() {
-            if let Some(item_to_change) = push_state.int_vector_stack.get_mut(0) {
-                if item_to_change.values.len() >0 {
-                    let i =
-                        i32::max(i32::min(index, item_to_change.values.len() as i32 - 1), 0) as usize;
-                    item_to_change.values[i] = new_element;
+            if let Some(len) = push_state.int_vector_stack.get(0).map(|iv| iv.values.len()) {
+                if let Some(i) = vector_index(push_state, index, len) {
+                    if let Some(item_to_change) = push_state.int_vector_stack.get_mut(0) {
+                        item_to_change.values[i] = new_element;
+                    }
+                } else if push_state.configuration.strict_indexing {
+                    push_state.int_stack.push(new_element);
+                    push_state.int_stack.push(index);
                 }
             }
         }
     }
 }
 
+/// INTVECTOR.ALIGN: Shifts the elements of the top INTVECTOR item by the offset taken from
+/// the INTEGER stack. Positive offsets shift to higher indices. The length is preserved and
+/// vacated positions are set to 0, so that e.g. ALIGN followed by INTVECTOR.+ with zero
+/// offset is equivalent to INTVECTOR.+ with the offset.
+pub fn int_vector_align(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(ivec) = push_state.int_vector_stack.get_mut(0) {
+        if let Some(offset) = push_state.int_stack.pop() {
+            ivec.values = align(&ivec.values, offset);
+        }
+    }
+}
+
 /// INTVECTOR.+: Pushes the result of applying element-wise ADD of the top item to the
 /// second item on the INTVECTOR stack. It applies an offset to the indices of the top
 /// item. The offset is taken from the INTEGER stack. Indices that are outside of the valid
@@ -817,7 +1575,7 @@ pub fn int_vector_set(push_state: &mut PushState, _instruction_cache: &Instructi
 /// the stack is pushed as a result.
 pub fn int_vector_add(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(mut iv) = push_state.int_vector_stack.pop_vec(2) {
-        if let Some(offset) = push_state.int_stack.pop() {
+        if let Some(offset) = pop_offset(push_state) {
             // Loop through indices of second item
             let scd_size = iv[0].values.len();
             for i in 0..scd_size {
@@ -839,7 +1597,7 @@ pub fn int_vector_add(push_state: &mut PushState, _instruction_cache: &Instructi
 /// the stack is pushed as a result.
 pub fn int_vector_subtract(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(mut iv) = push_state.int_vector_stack.pop_vec(2) {
-        if let Some(offset) = push_state.int_stack.pop() {
+        if let Some(offset) = pop_offset(push_state) {
             // Loop through indices of second item
             let scd_size = iv[0].values.len();
             for i in 0..scd_size {
@@ -861,7 +1619,7 @@ pub fn int_vector_subtract(push_state: &mut PushState, _instruction_cache: &Inst
 /// the stack is pushed as a result.
 pub fn int_vector_multiply(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(mut iv) = push_state.int_vector_stack.pop_vec(2) {
-        if let Some(offset) = push_state.int_stack.pop() {
+        if let Some(offset) = pop_offset(push_state) {
             // Loop through indices of second item
             let scd_size = iv[0].values.len();
             for i in 0..scd_size {
@@ -880,11 +1638,14 @@ pub fn int_vector_multiply(push_state: &mut PushState, _instruction_cache: &Inst
 /// top item on the INTVECTOR stack. It applies an offset to the indices of the top
 /// item. The offset is taken from the INTEGER stack. Indices that are outside of the valid
 /// range of the second item are ignored. If there is no overlap of indices the second item of
-/// the stack is pushed as a result. If at least one divisor is zero the instruction acts
-/// as NOOP.
+/// the stack is pushed as a result. Divisions by zero are resolved by the configured
+/// divide-by-zero policy. With the NOOP policy a single zero divisor makes the whole
+/// instruction a NOOP.
 pub fn int_vector_divide(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(mut iv) = push_state.int_vector_stack.pop_vec(2) {
-        if let Some(offset) = push_state.int_stack.pop() {
+        if let Some(offset) = pop_offset(push_state) {
+            let policy = push_state.configuration.div_zero_policy;
+            let dividend = iv[0].clone();
             let mut invalid = false;
             // Loop through indices of second item
             let scd_size = iv[0].values.len();
@@ -894,18 +1655,57 @@ pub fn int_vector_divide(push_state: &mut PushState, _instruction_cache: &Instru
                     continue; // Out of bounds
                 }
                 if iv[1].values[i] == 0 {
-                    invalid = true;
+                    push_state.div_zero_count += 1;
+                    match policy.resolve(iv[0].values[ofs_idx]) {
+                        Some(result) => iv[0].values[ofs_idx] = result,
+                        None => invalid = true,
+                    }
                 } else {
-                    iv[0].values[ofs_idx] /= iv[1].values[i];
+                    // Only i32::MIN / -1 overflows, it wraps around to i32::MIN
+                    let quotient = iv[0].values[ofs_idx].checked_div(iv[1].values[i]);
+                    iv[0].values[ofs_idx] = quotient.unwrap_or(i32::MIN);
                 }
             }
-            if !invalid {
+            if invalid {
+                iv[0] = dividend;
+                push_state.int_vector_stack.push_vec(iv);
+                restore_offset(push_state, offset);
+            } else {
                 push_state.int_vector_stack.push(iv[0].clone());
             }
         }
     }
 }
 
+/// INTVECTOR.+PAD: Pushes the result of element-wise ADD of the top item to the second item
+/// on the INTVECTOR stack. Unlike INTVECTOR.+ the shorter item is extended with a fill value
+/// taken from the INTEGER stack, so that the result has the length of the longer item.
+pub fn int_vector_add_pad(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    int_vector_pad_op(push_state, |a, b| Some(a.wrapping_add(b)));
+}
+
+/// INTVECTOR.-PAD: Pushes the result of element-wise SUBTRACT of the top item from the second
+/// item on the INTVECTOR stack. Unlike INTVECTOR.- the shorter item is extended with a fill
+/// value taken from the INTEGER stack, so that the result has the length of the longer item.
+pub fn int_vector_subtract_pad(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    int_vector_pad_op(push_state, |a, b| Some(a.wrapping_sub(b)));
+}
+
+/// Applies the operation to the elements of the second and the top INTVECTOR item padded
+/// with the top INTEGER. Acts as a NOOP if one of the items is missing.
+fn int_vector_pad_op<F: FnMut(i32, i32) -> Option<i32>>(push_state: &mut PushState, op: F) {
+    if push_state.int_vector_stack.size() < 2 {
+        return;
+    }
+    if let Some(fill) = push_state.int_stack.pop() {
+        if let Some(iv) = push_state.int_vector_stack.pop_vec(2) {
+            if let Some(values) = pad_zip(&iv[0].values, &iv[1].values, fill, op) {
+                push_state.int_vector_stack.push(IntVector::new(values));
+            }
+        }
+    }
+}
+
 /// INTVECTOR.CONTAINS: Pushes true to the BOOLEAN stack if the top INTEGER is included in the
 /// top INTVECTOR item. This instruction acts as a NOOP if there is no INTEGER or INTVECTOR.
 /// The INTVECTOR item is popped.
@@ -922,7 +1722,7 @@ pub fn int_vector_contains(push_state: &mut PushState, _instruction_cache: &Inst
 pub fn int_vector_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(ivval) = push_state.int_vector_stack.pop() {
-            push_state.name_bindings.insert(name, Item::intvec(ivval));
+            push_state.bind(name, Item::intvec(ivval));
         }
     }
 }
@@ -940,7 +1740,7 @@ fn int_vector_empty(push_state: &mut PushState, _instruction_cache: &Instruction
     push_state.int_vector_stack.push(IntVector::new(vec![]));
 }
 
-/// INTVECTOR.=: Pushes TRUE onto the BOOLEAN stack if the top two items are equal, or FALSE
+/// INTVECTOR.EQUAL: Pushes TRUE onto the BOOLEAN stack if the top two items are equal, or FALSE
 /// otherwise.
 fn int_vector_equal(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(ivvals) = push_state.int_vector_stack.pop_vec(2) {
@@ -953,6 +1753,38 @@ pub fn int_vector_flush(push_state: &mut PushState, _instruction_cache: &Instruc
     push_state.int_vector_stack.flush();
 }
 
+/// INTVECTOR.FIND: Pushes the index of the first occurrence of the top INTVECTOR item within
+/// the second item to the INTEGER stack or -1 if the top item is not contained. Both items are
+/// popped.
+pub fn int_vector_find(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(ivvals) = push_state.int_vector_stack.pop_vec(2) {
+        push_state
+            .int_stack
+            .push(find_subsequence(&ivvals[0].values, &ivvals[1].values));
+    }
+}
+
+/// INTVECTOR.FROMFLOATVECTOR: Pops the top FLOATVECTOR and pushes an INTVECTOR with its
+/// elements converted to integers. The conversion mode is taken from the BOOLEAN stack: TRUE
+/// rounds to the nearest integer, FALSE rounds down. Values outside of the integer range
+/// saturate and NaN becomes 0.
+pub fn int_vector_from_float_vector(
+    push_state: &mut PushState,
+    _instruction_cache: &InstructionCache,
+) {
+    if push_state.float_vector_stack.size() > 0 {
+        if let Some(round) = push_state.bool_stack.pop() {
+            let fv = push_state.float_vector_stack.pop().unwrap();
+            let values = fv
+                .values
+                .iter()
+                .map(|x| if round { x.round() } else { x.floor() } as i32)
+                .collect();
+            push_state.int_vector_stack.push(IntVector::new(values));
+        }
+    }
+}
+
 /// INTVECTOR.FROMINT: Create an INTVECTOR from the elements of the INTEGER stack. The top
 /// element (min-max corrected) describes the number of elements. The elements 1..n of
 /// the INTEGER stack are pushed as vector to the INTVECTOR stack.
@@ -1003,10 +1835,43 @@ pub fn int_vector_mean(push_state: &mut PushState, _instruction_cache: &Instruct
     }
 }
 
+/// INTVECTOR.MAX: Pushes the largest element of the top INTVECTOR to the INTEGER stack.
+pub fn int_vector_max(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(max) = push_state
+        .int_vector_stack
+        .get(0)
+        .and_then(|iv| iv.values.iter().max().copied())
+    {
+        push_state.int_stack.push(max);
+    }
+}
+
+/// INTVECTOR.MEDIAN: Pushes the median of the top INTVECTOR to the FLOAT stack. For an even
+/// number of elements this is the mean of the two middle elements.
+pub fn int_vector_median(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(iv) = push_state.int_vector_stack.get(0) {
+        if !iv.values.is_empty() {
+            let mut values: Vec<f32> = iv.values.iter().map(|x| *x as f32).collect();
+            push_state.float_stack.push(median(&mut values));
+        }
+    }
+}
+
+/// INTVECTOR.MIN: Pushes the smallest element of the top INTVECTOR to the INTEGER stack.
+pub fn int_vector_min(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(min) = push_state
+        .int_vector_stack
+        .get(0)
+        .and_then(|iv| iv.values.iter().min().copied())
+    {
+        push_state.int_stack.push(min);
+    }
+}
+
 /// INTVECTOR.ONES: Pushes a newly generated INTVECTOR with all elements set to 1. The size
 /// is taken from the INTEGER stack
 pub fn int_vector_ones(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    if let Some(size) = push_state.int_stack.pop() {
+    if let Some(size) = pop_vector_size(push_state) {
         if size > 0 {
             push_state
                 .int_vector_stack
@@ -1027,18 +1892,126 @@ pub fn int_vector_rand(push_state: &mut PushState, _instruction_cache: &Instruct
         // 1 params[2] -> size
         // 2 params[1] -> max
         // 3 params[0] -> min
-        if let Some(rbvval) = CodeGenerator::random_int_vector(params[2], params[0], params[1]) {
+        let size = cap_vector_size(push_state, params[2]);
+        if let Some(rbvval) = CodeGenerator::random_int_vector(&mut push_state.rng, size, params[0], params[1]) {
             push_state.int_vector_stack.push(rbvval);
         }
     }
 }
 
-/// INTVECTOR.REMOVE: Removes any occurance of the top element from the INTEGER stack from 
-/// the top element of INTVECTOR if it is contained.
-pub fn int_vector_remove(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    if let Some(item) = push_state.int_vector_stack.get_mut(0) {
-        if let Some(to_remove) = push_state.int_stack.pop() {
-            item.values.retain(|x| *x != to_remove);
+/// INTVECTOR.RAND*POISSON: Pushes a newly generated random INTVECTOR with Poisson distributed
+/// elements. The size is taken from the INTEGER stack, the mean lambda from the FLOAT stack.
+/// If the size is <0 or lambda <= 0 this acts as a NOOP.
+pub fn int_vector_rand_poisson(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(size) = pop_vector_size(push_state) {
+        if let Some(lambda) = push_state.float_stack.pop() {
+            if let Some(rivval) = CodeGenerator::random_poisson_int_vector(&mut push_state.rng, size, lambda) {
+                push_state.int_vector_stack.push(rivval);
+            }
+        }
+    }
+}
+
+/// INTVECTOR.REMOVE: Removes any occurance of the top element from the INTEGER stack from 
+/// the top element of INTVECTOR if it is contained.
+pub fn int_vector_remove(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(item) = push_state.int_vector_stack.get_mut(0) {
+        if let Some(to_remove) = push_state.int_stack.pop() {
+            item.values.retain(|x| *x != to_remove);
+        }
+    }
+}
+
+/// INTVECTOR.CONCAT: Pops the top two INTVECTOR items and pushes the elements of the second item
+/// followed by the elements of the top item. If the result would exceed the maximum vector
+/// size this acts as a NOOP.
+pub fn int_vector_concat(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    let len = match (
+        push_state.int_vector_stack.get(1),
+        push_state.int_vector_stack.get(0),
+    ) {
+        (Some(second), Some(top)) => second.values.len() + top.values.len(),
+        _ => return,
+    };
+    if len > push_state.configuration.max_vector_size {
+        report_vector_size_cap(push_state, len);
+        return;
+    }
+    if let Some(top) = push_state.int_vector_stack.pop() {
+        if let Some(second) = push_state.int_vector_stack.get_mut(0) {
+            second.values.extend(top.values);
+        }
+    }
+}
+
+/// INTVECTOR.DROP: Removes the first n elements of the top INTVECTOR item. The number n is taken
+/// from the INTEGER stack and bound to the valid range.
+pub fn int_vector_drop(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.int_vector_stack.size() > 0 {
+        if let Some(n) = push_state.int_stack.pop() {
+            let iv = push_state.int_vector_stack.get_mut(0).unwrap();
+            let n = usize::min(n.max(0) as usize, iv.values.len());
+            iv.values.drain(..n);
+        }
+    }
+}
+
+/// INTVECTOR.FIRST: Copies the first element of the top INTVECTOR item to the INTEGER stack.
+pub fn int_vector_first(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(iv) = push_state.int_vector_stack.get(0) {
+        if let Some(element) = iv.values.first() {
+            push_state.int_stack.push(*element);
+        }
+    }
+}
+
+/// INTVECTOR.LAST: Copies the last element of the top INTVECTOR item to the INTEGER stack.
+pub fn int_vector_last(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(iv) = push_state.int_vector_stack.get(0) {
+        if let Some(element) = iv.values.last() {
+            push_state.int_stack.push(*element);
+        }
+    }
+}
+
+/// INTVECTOR.REVERSE: Reverses the order of the elements of the top INTVECTOR item.
+pub fn int_vector_reverse(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(iv) = push_state.int_vector_stack.get_mut(0) {
+        iv.values.reverse();
+    }
+}
+
+/// INTVECTOR.SLICE: Replaces the top INTVECTOR item by its elements from index start (inclusive)
+/// to index end (exclusive). The end is taken from the top and the start from the second item
+/// of the INTEGER stack. See slice_range for the handling of invalid indices.
+pub fn int_vector_slice(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.int_vector_stack.size() > 0 {
+        if let Some(bounds) = push_state.int_stack.pop_vec(2) {
+            let iv = push_state.int_vector_stack.get_mut(0).unwrap();
+            let range = slice_range(iv.values.len(), bounds[0], bounds[1]);
+            iv.values = iv.values[range].to_vec();
+        }
+    }
+}
+
+/// INTVECTOR.TOFLOAT: Pops the top INTVECTOR and pushes its elements converted to floats to
+/// the FLOAT stack. The last element of the vector ends up on top (the reverse of
+/// INTVECTOR.FROMINT).
+pub fn int_vector_to_float(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(iv) = push_state.int_vector_stack.pop() {
+        push_state
+            .float_stack
+            .push_vec(iv.values.iter().map(|x| *x as f32).collect());
+    }
+}
+
+/// INTVECTOR.TAKE: Keeps the first n elements of the top INTVECTOR item. The number n is taken
+/// from the INTEGER stack and bound to the valid range.
+pub fn int_vector_take(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.int_vector_stack.size() > 0 {
+        if let Some(n) = push_state.int_stack.pop() {
+            let iv = push_state.int_vector_stack.get_mut(0).unwrap();
+            iv.values.truncate(n.max(0) as usize);
         }
     }
 }
@@ -1062,13 +2035,22 @@ pub fn int_vector_set_insert(push_state: &mut PushState, _instruction_set: &Inst
     if push_state.int_vector_stack.size() == 0 {
         push_state.int_vector_stack.push(IntVector::new(vec![]));
     }
+    let cap = push_state.configuration.max_vector_size;
+    let mut rejected_size = None;
     if let Some(item) = push_state.int_vector_stack.get_mut(0) {
         if let Some(to_insert) = push_state.int_stack.pop() {
             if !item.values.contains(&to_insert) {
-                item.values.push(to_insert);
+                if item.values.len() < cap {
+                    item.values.push(to_insert);
+                } else {
+                    rejected_size = Some(item.values.len() + 1);
+                }
             }
         }
     }
+    if let Some(requested) = rejected_size {
+        report_vector_size_cap(push_state, requested);
+    }
 }
 
 /// INTVECTOR.SHOVE: Inserts the second INTEGER "deep" in the stack, at the position indexed by the
@@ -1105,6 +2087,27 @@ pub fn int_vector_stack_depth(push_state: &mut PushState, _instruction_cache: &I
         .push(push_state.int_vector_stack.size() as i32);
 }
 
+/// INTVECTOR.STARTSWITH: Pushes TRUE to the BOOLEAN stack if the second INTVECTOR item starts
+/// with the elements of the top item and FALSE otherwise. Both items are popped.
+pub fn int_vector_starts_with(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(ivvals) = push_state.int_vector_stack.pop_vec(2) {
+        push_state
+            .bool_stack
+            .push(ivvals[0].values.starts_with(&ivvals[1].values));
+    }
+}
+
+/// INTVECTOR.STDDEV: Pushes the (population) standard deviation of the top INTVECTOR to the
+/// FLOAT stack.
+pub fn int_vector_stddev(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(iv) = push_state.int_vector_stack.get(0) {
+        if !iv.values.is_empty() {
+            let values: Vec<f32> = iv.values.iter().map(|x| *x as f32).collect();
+            push_state.float_stack.push(standard_deviation(&values));
+        }
+    }
+}
+
 /// INTVECTOR.SUM Pushes the sum of the elements to the INTEGER stack.
 pub fn int_vector_sum(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(ivec) = push_state.int_vector_stack.get(0) {
@@ -1148,7 +2151,7 @@ pub fn int_vector_yank_dup(push_state: &mut PushState, _instruction_cache: &Inst
 /// INTVECTOR.ZEROS: Pushes a newly generated INTVECTOR with all elements set to 0. The size
 /// is taken from the INTEGER stack
 pub fn int_vector_zeros(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    if let Some(size) = push_state.int_stack.pop() {
+    if let Some(size) = pop_vector_size(push_state) {
         if size > 0 {
             push_state
                 .int_vector_stack
@@ -1159,11 +2162,91 @@ pub fn int_vector_zeros(push_state: &mut PushState, _instruction_cache: &Instruc
 
 ////////////////////////////////////// FLOATVECTOR //////////////////////////////////////////
 
-/// FLOATVECTOR.APPEND: Appends the top FLOAT item to the top FLOATVECTOR item.
+/// FLOATVECTOR.APPEND: Appends the top FLOAT item to the top FLOATVECTOR item. If the
+/// vector already has the maximum size the float is dropped.
 pub fn float_vector_append(push_state: &mut PushState, _instruction_set: &InstructionCache) {
+    let cap = push_state.configuration.max_vector_size;
+    let mut rejected_size = None;
     if let Some(item) = push_state.float_vector_stack.get_mut(0) {
         if let Some(to_append) = push_state.float_stack.pop() {
-            item.values.push(to_append);
+            if item.values.len() < cap {
+                item.values.push(to_append);
+            } else {
+                rejected_size = Some(item.values.len() + 1);
+            }
+        }
+    }
+    if let Some(requested) = rejected_size {
+        report_vector_size_cap(push_state, requested);
+    }
+}
+
+/// FLOATVECTOR.ARGMAX: Pushes the index of the largest element of the top FLOATVECTOR to the
+/// INTEGER stack. If the maximum occurs more than once the first index is pushed.
+pub fn float_vector_argmax(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = push_state
+        .float_vector_stack
+        .get(0)
+        .and_then(|fv| argmax(&fv.values))
+    {
+        push_state.int_stack.push(index as i32);
+    }
+}
+
+/// FLOATVECTOR.ARGMIN: Pushes the index of the smallest element of the top FLOATVECTOR to the
+/// INTEGER stack. If the minimum occurs more than once the first index is pushed.
+pub fn float_vector_argmin(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = push_state
+        .float_vector_stack
+        .get(0)
+        .and_then(|fv| argmin(&fv.values))
+    {
+        push_state.int_stack.push(index as i32);
+    }
+}
+
+/// FLOATVECTOR.ARGSORT: Pushes an INTVECTOR with the indices that sort the top FLOATVECTOR
+/// item in ascending order. Equal elements keep their relative order. The elements are
+/// ordered by f32::total_cmp, which ranks NaN above infinity.
+pub fn float_vector_argsort(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fvec) = push_state.float_vector_stack.get(0) {
+        let mut indices: Vec<i32> = (0..fvec.values.len() as i32).collect();
+        indices.sort_by(|a, b| fvec.values[*a as usize].total_cmp(&fvec.values[*b as usize]));
+        push_state.int_vector_stack.push(IntVector::new(indices));
+    }
+}
+
+/// FLOATVECTOR.FROMINTVECTOR: Pops the top INTVECTOR and pushes a FLOATVECTOR with its
+/// elements converted to floats.
+pub fn float_vector_from_int_vector(
+    push_state: &mut PushState,
+    _instruction_cache: &InstructionCache,
+) {
+    if let Some(iv) = push_state.int_vector_stack.pop() {
+        push_state
+            .float_vector_stack
+            .push(FloatVector::new(iv.values.iter().map(|x| *x as f32).collect()));
+    }
+}
+
+/// FLOATVECTOR.HISTOGRAM: Pops the top FLOATVECTOR and pushes the number of its elements in
+/// each of k equally sized bins between its minimum and maximum value as INTVECTOR. The number
+/// of bins k is taken from the INTEGER stack and limited to the maximum vector size. Acts as
+/// NOOP if k < 1.
+pub fn float_vector_histogram(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(bins) = pop_vector_size(push_state) {
+        if bins > 0 {
+            if let Some(fv) = push_state.float_vector_stack.pop() {
+                let values: Vec<f64> = fv.values.iter().map(|x| *x as f64).collect();
+                let lower = values.iter().cloned().fold(f64::INFINITY, f64::min);
+                let upper = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                push_state.int_vector_stack.push(IntVector::new(histogram(
+                    &values,
+                    lower,
+                    upper,
+                    bins as usize,
+                )));
+            }
         }
     }
 }
@@ -1174,33 +2257,76 @@ pub fn float_vector_id(push_state: &mut PushState, _instruction_set: &Instructio
 }
 
 /// FLOATVECTOR.GET: Copies the element at index i of the top FLOATVECTOR item to the FLOAT stack
-/// where i is taken from the FLOAT stack limited to valid range.
+/// where i is taken from the INTEGER stack limited to valid range. With strict indexing an
+/// out-of-range index is reported to the ERROR stack and left on the INTEGER stack.
 pub fn float_vector_get(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(index) = push_state.int_stack.pop() {
-        if let Some(element) = push_state.float_vector_stack.get(0) {
-            if element.values.len() > 0 {
-                let i = i32::max(i32::min(index, element.values.len() as i32 - 1), 0) as usize;
-                push_state.float_stack.push(element.values[i].clone());
+        if let Some(len) = push_state.float_vector_stack.get(0).map(|v| v.values.len()) {
+            if let Some(i) = vector_index(push_state, index, len) {
+                if let Some(element) = push_state.float_vector_stack.get(0) {
+                    push_state.float_stack.push(element.values[i]);
+                }
+            } else if push_state.configuration.strict_indexing {
+                push_state.int_stack.push(index);
             }
         }
     }
 }
 
 /// FLOATVECTOR.SET: Replaces the ith element of the top FLOATVECTOR item by the top item of the
-/// FLOAT stack. The top item of the INTEGER stack is the index i limited to valid range.
+/// FLOAT stack. The top item of the INTEGER stack is the index i limited to valid range. With
+/// strict indexing an out-of-range index is reported to the ERROR stack and the arguments are
+/// left on their stacks.
 pub fn float_vector_set(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(index) = push_state.int_stack.pop() {
         if let Some(new_element) = push_state.float_stack.pop() {
-            if let Some(item_to_change) = push_state.float_vector_stack.get_mut(0) {
-                if item_to_change.values.len() > 0 {
-                    let i = i32::max(i32::min(index, item_to_change.values.len() as i32 - 1), 0) as usize;
-                    item_to_change.values[i] = new_element;
+            if let Some(len) = push_state.float_vector_stack.get(0).map(|fv| fv.values.len()) {
+                if let Some(i) = vector_index(push_state, index, len) {
+                    if let Some(item_to_change) = push_state.float_vector_stack.get_mut(0) {
+                        item_to_change.values[i] = new_element;
+                    }
+                } else if push_state.configuration.strict_indexing {
+                    push_state.float_stack.push(new_element);
+                    push_state.int_stack.push(index);
                 }
             }
         }
     }
 }
 
+/// Applies the function to each element of the top FLOATVECTOR item if all elements are in
+/// the domain of the function. Otherwise this acts as a NOOP.
+fn float_vector_apply(
+    push_state: &mut PushState,
+    in_domain: fn(f32) -> bool,
+    function: fn(f32) -> f32,
+) {
+    if let Some(fv) = push_state.float_vector_stack.get(0) {
+        if !fv.values.iter().all(|x| in_domain(*x)) {
+            return;
+        }
+    }
+    if let Some(fv) = push_state.float_vector_stack.get_mut(0) {
+        fv.values.iter_mut().for_each(|x| *x = function(*x));
+    }
+}
+
+/// FLOATVECTOR.ABS: Replaces each element of the top FLOATVECTOR with its absolute value.
+pub fn float_vector_abs(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    float_vector_apply(push_state, |_| true, f32::abs);
+}
+
+/// FLOATVECTOR.ALIGN: Shifts the elements of the top FLOATVECTOR item by the offset taken
+/// from the INTEGER stack. Positive offsets shift to higher indices. The length is preserved
+/// and vacated positions are set to 0.0.
+pub fn float_vector_align(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fvec) = push_state.float_vector_stack.get_mut(0) {
+        if let Some(offset) = push_state.int_stack.pop() {
+            fvec.values = align(&fvec.values, offset);
+        }
+    }
+}
+
 /// FLOATVECTOR.+: Pushes the result of applying element-wise ADD of the top item to the
 /// second item on the FLOATVECTOR stack. It applies an offset to the indices of the top
 /// item. The offset is taken from the INTEGER stack. Indices that are outside of the valid
@@ -1208,7 +2334,7 @@ pub fn float_vector_set(push_state: &mut PushState, _instruction_cache: &Instruc
 /// the stack is pushed as a result.
 pub fn float_vector_add(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(mut iv) = push_state.float_vector_stack.pop_vec(2) {
-        if let Some(offset) = push_state.int_stack.pop() {
+        if let Some(offset) = pop_offset(push_state) {
             // Loop through indices of second item
             let scd_size = iv[0].values.len();
             for i in 0..scd_size {
@@ -1230,7 +2356,7 @@ pub fn float_vector_add(push_state: &mut PushState, _instruction_cache: &Instruc
 /// the stack is pushed as a result.
 pub fn float_vector_subtract(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(mut iv) = push_state.float_vector_stack.pop_vec(2) {
-        if let Some(offset) = push_state.int_stack.pop() {
+        if let Some(offset) = pop_offset(push_state) {
             // Loop through indices of second item
             let scd_size = iv[0].values.len();
             for i in 0..scd_size {
@@ -1252,7 +2378,7 @@ pub fn float_vector_subtract(push_state: &mut PushState, _instruction_cache: &In
 /// the stack is pushed as a result.
 pub fn float_vector_multiply(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(mut iv) = push_state.float_vector_stack.pop_vec(2) {
-        if let Some(offset) = push_state.int_stack.pop() {
+        if let Some(offset) = pop_offset(push_state) {
             // Loop through indices of second item
             let scd_size = iv[0].values.len();
             for i in 0..scd_size {
@@ -1271,11 +2397,14 @@ pub fn float_vector_multiply(push_state: &mut PushState, _instruction_cache: &In
 /// top item on the FLOATVECTOR stack. It applies an offset to the indices of the top
 /// item. The offset is taken from the INTEGER stack. Indices that are outside of the valid
 /// range of the second item are ignored. If there is no overlap of indices the second item of
-/// the stack is pushed as a result. If at least one divisor is zero the instruction acts
-/// as NOOP.
+/// the stack is pushed as a result. Divisions by zero are resolved by the configured
+/// divide-by-zero policy. With the NOOP policy a single zero divisor makes the whole
+/// instruction a NOOP.
 pub fn float_vector_divide(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(mut iv) = push_state.float_vector_stack.pop_vec(2) {
-        if let Some(offset) = push_state.int_stack.pop() {
+        if let Some(offset) = pop_offset(push_state) {
+            let policy = push_state.configuration.div_zero_policy;
+            let dividend = iv[0].clone();
             let mut invalid = false;
             // Loop through indices of second item
             let scd_size = iv[0].values.len();
@@ -1285,24 +2414,92 @@ pub fn float_vector_divide(push_state: &mut PushState, _instruction_cache: &Inst
                     continue; // Out of bounds
                 }
                 if iv[1].values[i] == 0.0 {
-                    invalid = true;
+                    push_state.div_zero_count += 1;
+                    match policy.resolve(iv[0].values[ofs_idx]) {
+                        Some(result) => iv[0].values[ofs_idx] = result,
+                        None => invalid = true,
+                    }
                 } else {
                     iv[0].values[ofs_idx] /= iv[1].values[i];
                 }
             }
-            if !invalid {
+            if invalid {
+                iv[0] = dividend;
+                push_state.float_vector_stack.push_vec(iv);
+                restore_offset(push_state, offset);
+            } else {
                 push_state.float_vector_stack.push(iv[0].clone());
             }
         }
     }
 }
 
+/// FLOATVECTOR.+PAD: Pushes the result of element-wise ADD of the top item to the second item
+/// on the FLOATVECTOR stack. Unlike FLOATVECTOR.+ the shorter item is extended with a fill
+/// value taken from the FLOAT stack, so that the result has the length of the longer item.
+pub fn float_vector_add_pad(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    float_vector_pad_op(push_state, |a, b| Some(a + b));
+}
+
+/// FLOATVECTOR.-PAD: Pushes the result of element-wise SUBTRACT of the top item from the
+/// second item on the FLOATVECTOR stack. The shorter item is extended with a fill value taken
+/// from the FLOAT stack.
+pub fn float_vector_subtract_pad(
+    push_state: &mut PushState,
+    _instruction_cache: &InstructionCache,
+) {
+    float_vector_pad_op(push_state, |a, b| Some(a - b));
+}
+
+/// FLOATVECTOR.*PAD: Pushes the result of element-wise MULTIPLY of the top item to the second
+/// item on the FLOATVECTOR stack. The shorter item is extended with a fill value taken from
+/// the FLOAT stack.
+pub fn float_vector_multiply_pad(
+    push_state: &mut PushState,
+    _instruction_cache: &InstructionCache,
+) {
+    float_vector_pad_op(push_state, |a, b| Some(a * b));
+}
+
+/// FLOATVECTOR./PAD: Pushes the result of element-wise DIVIDE of the second item by the top
+/// item on the FLOATVECTOR stack. The shorter item is extended with a fill value taken from
+/// the FLOAT stack. Divisions by zero are resolved by the configured divide-by-zero policy.
+/// With the NOOP policy a single zero divisor makes the whole instruction a NOOP.
+pub fn float_vector_divide_pad(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    let policy = push_state.configuration.div_zero_policy;
+    let mut div_zero_count = 0;
+    float_vector_pad_op(push_state, |a, b| {
+        if b == 0.0 {
+            div_zero_count += 1;
+            policy.resolve(a)
+        } else {
+            Some(a / b)
+        }
+    });
+    push_state.div_zero_count += div_zero_count;
+}
+
+/// Applies the operation to the elements of the second and the top FLOATVECTOR item padded
+/// with the top FLOAT. Acts as a NOOP if one of the items is missing or the operation fails.
+/// The items are only read until the result is known, so they are not copied.
+fn float_vector_pad_op<F: FnMut(f32, f32) -> Option<f32>>(push_state: &mut PushState, op: F) {
+    if let Some(fill) = push_state.float_stack.get(0) {
+        if let Some(fv) = push_state.float_vector_stack.top_slice(2) {
+            if let Some(values) = pad_zip(&fv[0].values, &fv[1].values, *fill, op) {
+                push_state.float_stack.pop();
+                push_state.float_vector_stack.pop_vec(2);
+                push_state.float_vector_stack.push(FloatVector::new(values));
+            }
+        }
+    }
+}
+
 /// FLOATVECTOR.DEFINE: Defines the name on top of the NAME stack as an instruction that will
 /// push the top item of the FLOATVECTOR stack onto the EXEC stack.
 pub fn float_vector_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(fvval) = push_state.float_vector_stack.pop() {
-            push_state.name_bindings.insert(name, Item::floatvec(fvval));
+            push_state.bind(name, Item::floatvec(fvval));
         }
     }
 }
@@ -1320,7 +2517,7 @@ fn float_vector_empty(push_state: &mut PushState, _instruction_cache: &Instructi
     push_state.float_vector_stack.push(FloatVector::new(vec![]));
 }
 
-/// FLOATVECTOR.=: Pushes TRUE onto the BOOLEAN stack if the top two items are equal, or FALSE
+/// FLOATVECTOR.EQUAL: Pushes TRUE onto the BOOLEAN stack if the top two items are equal, or FALSE
 /// otherwise.
 fn float_vector_equal(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(fvvals) = push_state.float_vector_stack.pop_vec(2) {
@@ -1328,11 +2525,44 @@ fn float_vector_equal(push_state: &mut PushState, _instruction_cache: &Instructi
     }
 }
 
+/// FLOATVECTOR.EXP: Replaces each element x of the top FLOATVECTOR with exp(x).
+pub fn float_vector_exp(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    float_vector_apply(push_state, |_| true, f32::exp);
+}
+
 /// FLOATVECTOR.FLUSH: Empties the FLOATVECTOR stack.
 pub fn float_vector_flush(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     push_state.float_vector_stack.flush();
 }
 
+/// Evaluates the piecewise-linear function through the points (i, values[i]) at x. The
+/// argument is clamped to the interval [0, values.len() - 1].
+fn interpolate(values: &[f32], x: f32) -> f32 {
+    let x = x.max(0.0).min((values.len() - 1) as f32);
+    let lower = x.floor() as usize;
+    let upper = (lower + 1).min(values.len() - 1);
+    let fraction = x - lower as f32;
+    values[lower] + fraction * (values[upper] - values[lower])
+}
+
+/// FLOATVECTOR.INTERP: Treats the elements of the top FLOATVECTOR item as samples of a
+/// piecewise-linear function at the positions 0, 1, 2, ... and pushes the function value
+/// at the position given by the top FLOAT item. Positions outside of the vector are clamped
+/// to the first or last element. Acts as NOOP if the vector is empty or the position is NaN.
+pub fn float_vector_interp(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fvec) = push_state.float_vector_stack.get(0) {
+        if !fvec.values.is_empty() {
+            if let Some(x) = push_state.float_stack.get(0) {
+                if !x.is_nan() {
+                    let y = interpolate(&fvec.values, *x);
+                    push_state.float_stack.pop();
+                    push_state.float_stack.push(y);
+                }
+            }
+        }
+    }
+}
+
 /// FLOATVECTOR.LENGTH: Pushes the length of the top FLOATVECTOR item to the INTEGER stack.
 pub fn float_vector_length(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(fv) = push_state.float_vector_stack.get(0) {
@@ -1340,6 +2570,80 @@ pub fn float_vector_length(push_state: &mut PushState, _instruction_cache: &Inst
     }
 }
 
+/// FLOATVECTOR.LOG: Replaces each element x of the top FLOATVECTOR with the natural logarithm
+/// ln(x). If an element is not positive this acts as a NOOP.
+pub fn float_vector_log(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    float_vector_apply(push_state, |x| x > 0.0, f32::ln);
+}
+
+/// FLOATVECTOR.MAP: Applies the top element of the EXEC stack to each element of the top
+/// FLOATVECTOR item. Before each application the element is pushed to the FLOAT stack. The
+/// top FLOAT item after the application is collected into a new FLOATVECTOR that is pushed
+/// when all elements have been processed. The map is aborted if an application leaves
+/// the FLOAT stack empty. Acts as NOOP if the FLOATVECTOR is empty.
+pub fn float_vector_map(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fv) = push_state.float_vector_stack.get(0) {
+        if !fv.values.is_empty() {
+            if let Some(body) = push_state.exec_stack.pop() {
+                let mut remaining = push_state.float_vector_stack.pop().unwrap();
+                remaining.values.reverse();
+                push_map_application(push_state, body, remaining, FloatVector::new(vec![]));
+            }
+        }
+    }
+}
+
+/// FLOATVECTOR.MAP*STEP: Continuation of FLOATVECTOR.MAP. Pops the collected results (second
+/// item) and the remaining elements (top item, in reverse order) from the FLOATVECTOR stack
+/// and the map body from the EXEC stack. Appends the top FLOAT item to the results and applies the body to
+/// the next element or pushes the results if no elements are remaining.
+pub fn float_vector_map_step(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(mut fvals) = push_state.float_vector_stack.pop_vec(2) {
+        if let Some(body) = push_state.exec_stack.pop() {
+            if let Some(result) = push_state.float_stack.pop() {
+                let remaining = fvals.pop().unwrap();
+                let mut collected = fvals.pop().unwrap();
+                collected.values.push(result);
+                if remaining.values.is_empty() {
+                    push_state.float_vector_stack.push(collected);
+                } else {
+                    push_map_application(push_state, body, remaining, collected);
+                }
+            }
+        }
+    }
+}
+
+/// Pushes the next element of the remaining elements to the FLOAT stack and schedules the
+/// application of the body followed by FLOATVECTOR.MAP*STEP. The remaining elements are kept
+/// in reverse order so that the next element is taken from the back.
+fn push_map_application(
+    push_state: &mut PushState,
+    body: Item,
+    mut remaining: FloatVector,
+    collected: FloatVector,
+) {
+    let next_element = remaining.values.pop().unwrap();
+    let continuation = Item::list(vec![
+        body.clone(),
+        Item::instruction("FLOATVECTOR.MAP*STEP".to_string()),
+        Item::floatvec(remaining),
+        Item::floatvec(collected),
+    ]);
+    push_state.exec_stack.push(continuation);
+    push_state.exec_stack.push(body);
+    push_state.float_stack.push(next_element);
+}
+
+/// FLOATVECTOR.MAX: Pushes the largest element of the top FLOATVECTOR to the FLOAT stack.
+pub fn float_vector_max(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fv) = push_state.float_vector_stack.get(0) {
+        if let Some(index) = argmax(&fv.values) {
+            push_state.float_stack.push(fv.values[index]);
+        }
+    }
+}
+
 /// FLOATVECTOR.MEAN: Pushes the mean of the top FLOATVECTOR to the float stack
 pub fn float_vector_mean(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(numbers) = push_state.float_vector_stack.get(0) {
@@ -1349,6 +2653,26 @@ pub fn float_vector_mean(push_state: &mut PushState, _instruction_cache: &Instru
     }
 }
 
+/// FLOATVECTOR.MEDIAN: Pushes the median of the top FLOATVECTOR to the FLOAT stack. For an
+/// even number of elements this is the mean of the two middle elements.
+pub fn float_vector_median(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fv) = push_state.float_vector_stack.get(0) {
+        if !fv.values.is_empty() {
+            let mut values = fv.values.clone();
+            push_state.float_stack.push(median(&mut values));
+        }
+    }
+}
+
+/// FLOATVECTOR.MIN: Pushes the smallest element of the top FLOATVECTOR to the FLOAT stack.
+pub fn float_vector_min(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fv) = push_state.float_vector_stack.get(0) {
+        if let Some(index) = argmin(&fv.values) {
+            push_state.float_stack.push(fv.values[index]);
+        }
+    }
+}
+
 /// FLOATVECTOR.*SCALAR: Multiplies the top item of the FLOAT stack with each element of the
 /// top FLOATVECTOR element.
 pub fn float_vector_multiply_scalar(
@@ -1362,10 +2686,123 @@ pub fn float_vector_multiply_scalar(
     }
 }
 
+/// Returns the cosine similarity of a and b or None if one of them has zero norm.
+fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a > 0.0 && norm_b > 0.0 {
+        Some(dot / (norm_a * norm_b))
+    } else {
+        None
+    }
+}
+
+/// FLOATVECTOR.COSINE: Pops the top two FLOATVECTOR items and pushes their cosine similarity
+/// to the FLOAT stack. If the lengths of the vectors differ or one of them has zero norm
+/// this acts as a NOOP.
+pub fn float_vector_cosine(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let (Some(second), Some(top)) = (
+        push_state.float_vector_stack.get(1),
+        push_state.float_vector_stack.get(0),
+    ) {
+        if second.values.len() != top.values.len() {
+            return;
+        }
+        if let Some(sim) = cosine_similarity(&second.values, &top.values) {
+            push_state.float_vector_stack.pop_vec(2);
+            push_state.float_stack.push(sim);
+        }
+    }
+}
+
+/// FLOATVECTOR.DOT: Pops the top two FLOATVECTOR items and pushes their dot product to the
+/// FLOAT stack. If the lengths of the vectors differ this acts as a NOOP.
+pub fn float_vector_dot(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let (Some(second), Some(top)) = (
+        push_state.float_vector_stack.get(1),
+        push_state.float_vector_stack.get(0),
+    ) {
+        if second.values.len() != top.values.len() {
+            return;
+        }
+        let dot = second
+            .values
+            .iter()
+            .zip(top.values.iter())
+            .map(|(x, y)| x * y)
+            .sum();
+        push_state.float_vector_stack.pop_vec(2);
+        push_state.float_stack.push(dot);
+    }
+}
+
+/// FLOATVECTOR.NEAREST: Pops the top FLOATVECTOR item as query and searches the remaining
+/// items of the FLOATVECTOR stack for the most similar vector of equal length. The search mode
+/// is taken from the INTEGER stack: 0 selects the vector with the highest cosine similarity,
+/// any other value the vector with the smallest euclidean distance. The stack position of the
+/// match (after popping the query) is pushed to the INTEGER stack and the similarity
+/// (or distance) to the FLOAT stack. If no vector is comparable this acts as a NOOP.
+pub fn float_vector_nearest(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(query) = push_state.float_vector_stack.get(0) {
+        if let Some(mode) = push_state.int_stack.copy(0) {
+            let mut best: Option<(usize, f32)> = None;
+            for i in 1..push_state.float_vector_stack.size() {
+                let candidate = &push_state.float_vector_stack.get(i).unwrap().values;
+                if candidate.len() != query.values.len() {
+                    continue;
+                }
+                if mode == 0 {
+                    if let Some(sim) = cosine_similarity(&query.values, candidate) {
+                        if best.map_or(true, |b| sim > b.1) {
+                            best = Some((i - 1, sim));
+                        }
+                    }
+                } else {
+                    let dist = query
+                        .values
+                        .iter()
+                        .zip(candidate.iter())
+                        .map(|(x, y)| (x - y) * (x - y))
+                        .sum::<f32>()
+                        .sqrt();
+                    if best.map_or(true, |b| dist < b.1) {
+                        best = Some((i - 1, dist));
+                    }
+                }
+            }
+            if let Some((index, score)) = best {
+                push_state.float_vector_stack.pop();
+                push_state.int_stack.pop();
+                push_state.int_stack.push(index as i32);
+                push_state.float_stack.push(score);
+            }
+        }
+    }
+}
+
+/// FLOATVECTOR.NORM*L1: Pushes the L1 norm (sum of absolute values) of the top FLOATVECTOR to
+/// the FLOAT stack.
+pub fn float_vector_norm_l1(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fvec) = push_state.float_vector_stack.get(0) {
+        let norm = fvec.values.iter().map(|x| x.abs()).sum();
+        push_state.float_stack.push(norm);
+    }
+}
+
+/// FLOATVECTOR.NORM*L2: Pushes the L2 (euclidean) norm of the top FLOATVECTOR to the FLOAT
+/// stack.
+pub fn float_vector_norm_l2(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fvec) = push_state.float_vector_stack.get(0) {
+        let norm = fvec.values.iter().map(|x| x * x).sum::<f32>().sqrt();
+        push_state.float_stack.push(norm);
+    }
+}
+
 /// FLOATVECTOR.ONES: Pushes a newly generated FLOATVECTOR with all elements set to 1. The size
 /// is taken from the INTEGER stack
 pub fn float_vector_ones(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    if let Some(size) = push_state.int_stack.pop() {
+    if let Some(size) = pop_vector_size(push_state) {
         if size > 0 {
             push_state
                 .float_vector_stack
@@ -1381,14 +2818,46 @@ pub fn float_vector_pop(push_state: &mut PushState, _instruction_cache: &Instruc
 
 /// FLOATVECTOR.RAND: Pushes a newly generated random INTVECTOR. The size is taken from the
 /// INTEGER stack while the parameters for mean and standard deviation are the first (top) and
-/// second item on the FLOAT stack. If size < 0 or standard deviation < 0 this act as a NOOP.
+/// second item on the FLOAT stack. If size < 0 or standard deviation < 0 or not finite this
+/// act as a NOOP.
 pub fn float_vector_rand(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    if let Some(size) = push_state.int_stack.pop() {
+    if let Some(size) = pop_vector_size(push_state) {
         if let Some(gauss_params) = push_state.float_stack.pop_vec(2) {
             // 1 gauss_params[1]: mean
             // 2 gauss_params[0]: stddev
             if let Some(rfvval) =
-                CodeGenerator::random_float_vector(size, gauss_params[1], gauss_params[0])
+                CodeGenerator::random_float_vector(&mut push_state.rng, size, gauss_params[1], gauss_params[0])
+            {
+                push_state.float_vector_stack.push(rfvval);
+            }
+        }
+    }
+}
+
+/// FLOATVECTOR.RAND*EXP: Pushes a newly generated random FLOATVECTOR with exponentially
+/// distributed elements. The size is taken from the INTEGER stack, the rate lambda from the FLOAT
+/// stack. If the size is <0 or lambda <= 0 this acts as a NOOP.
+pub fn float_vector_rand_exp(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(size) = pop_vector_size(push_state) {
+        if let Some(lambda) = push_state.float_stack.pop() {
+            if let Some(rfvval) = CodeGenerator::random_exp_float_vector(&mut push_state.rng, size, lambda) {
+                push_state.float_vector_stack.push(rfvval);
+            }
+        }
+    }
+}
+
+/// FLOATVECTOR.RAND*UNIFORM: Pushes a newly generated random FLOATVECTOR with uniformly
+/// distributed elements. The size is taken from the INTEGER stack while the upper and lower
+/// bound are the first (top) and second item on the FLOAT stack. If size < 0 or the upper
+/// bound is not greater than the lower bound this acts as a NOOP.
+pub fn float_vector_rand_uniform(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(size) = pop_vector_size(push_state) {
+        if let Some(bounds) = push_state.float_stack.pop_vec(2) {
+            // 1 bounds[1]: max
+            // 2 bounds[0]: min
+            if let Some(rfvval) =
+                CodeGenerator::random_uniform_float_vector(&mut push_state.rng, size, bounds[0], bounds[1])
             {
                 push_state.float_vector_stack.push(rfvval);
             }
@@ -1396,6 +2865,114 @@ pub fn float_vector_rand(push_state: &mut PushState, _instruction_cache: &Instru
     }
 }
 
+/// FLOATVECTOR.RESAMPLE: Replaces the top FLOATVECTOR item by a vector of the length given
+/// by the top INTEGER item. The new elements are linearly interpolated between the original
+/// elements so that the first and last elements are preserved.
+pub fn float_vector_resample(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fvec) = push_state.float_vector_stack.get(0) {
+        if !fvec.values.is_empty() {
+            if let Some(size) = pop_vector_size(push_state) {
+                if size > 0 {
+                    let fvec = push_state.float_vector_stack.pop().unwrap();
+                    let size = size as usize;
+                    let scale = if size > 1 {
+                        (fvec.values.len() - 1) as f32 / (size - 1) as f32
+                    } else {
+                        0.0
+                    };
+                    let values = (0..size)
+                        .map(|i| interpolate(&fvec.values, i as f32 * scale))
+                        .collect();
+                    push_state.float_vector_stack.push(FloatVector::new(values));
+                }
+            }
+        }
+    }
+}
+
+/// FLOATVECTOR.CONCAT: Pops the top two FLOATVECTOR items and pushes the elements of the second item
+/// followed by the elements of the top item. If the result would exceed the maximum vector
+/// size this acts as a NOOP.
+pub fn float_vector_concat(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    let len = match (
+        push_state.float_vector_stack.get(1),
+        push_state.float_vector_stack.get(0),
+    ) {
+        (Some(second), Some(top)) => second.values.len() + top.values.len(),
+        _ => return,
+    };
+    if len > push_state.configuration.max_vector_size {
+        report_vector_size_cap(push_state, len);
+        return;
+    }
+    if let Some(top) = push_state.float_vector_stack.pop() {
+        if let Some(second) = push_state.float_vector_stack.get_mut(0) {
+            second.values.extend(top.values);
+        }
+    }
+}
+
+/// FLOATVECTOR.DROP: Removes the first n elements of the top FLOATVECTOR item. The number n is taken
+/// from the INTEGER stack and bound to the valid range.
+pub fn float_vector_drop(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.float_vector_stack.size() > 0 {
+        if let Some(n) = push_state.int_stack.pop() {
+            let fv = push_state.float_vector_stack.get_mut(0).unwrap();
+            let n = usize::min(n.max(0) as usize, fv.values.len());
+            fv.values.drain(..n);
+        }
+    }
+}
+
+/// FLOATVECTOR.FIRST: Copies the first element of the top FLOATVECTOR item to the FLOAT stack.
+pub fn float_vector_first(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fv) = push_state.float_vector_stack.get(0) {
+        if let Some(element) = fv.values.first() {
+            push_state.float_stack.push(*element);
+        }
+    }
+}
+
+/// FLOATVECTOR.LAST: Copies the last element of the top FLOATVECTOR item to the FLOAT stack.
+pub fn float_vector_last(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fv) = push_state.float_vector_stack.get(0) {
+        if let Some(element) = fv.values.last() {
+            push_state.float_stack.push(*element);
+        }
+    }
+}
+
+/// FLOATVECTOR.REVERSE: Reverses the order of the elements of the top FLOATVECTOR item.
+pub fn float_vector_reverse(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fv) = push_state.float_vector_stack.get_mut(0) {
+        fv.values.reverse();
+    }
+}
+
+/// FLOATVECTOR.SLICE: Replaces the top FLOATVECTOR item by its elements from index start (inclusive)
+/// to index end (exclusive). The end is taken from the top and the start from the second item
+/// of the INTEGER stack. See slice_range for the handling of invalid indices.
+pub fn float_vector_slice(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.float_vector_stack.size() > 0 {
+        if let Some(bounds) = push_state.int_stack.pop_vec(2) {
+            let fv = push_state.float_vector_stack.get_mut(0).unwrap();
+            let range = slice_range(fv.values.len(), bounds[0], bounds[1]);
+            fv.values = fv.values[range].to_vec();
+        }
+    }
+}
+
+/// FLOATVECTOR.TAKE: Keeps the first n elements of the top FLOATVECTOR item. The number n is taken
+/// from the INTEGER stack and bound to the valid range.
+pub fn float_vector_take(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.float_vector_stack.size() > 0 {
+        if let Some(n) = push_state.int_stack.pop() {
+            let fv = push_state.float_vector_stack.get_mut(0).unwrap();
+            fv.values.truncate(n.max(0) as usize);
+        }
+    }
+}
+
 /// FLOATVECTOR.ROTATE: Moves all elements of the top item to the adjacent position on the left.
 /// The first item is removed while the last element of the vector is taken from the FLOAT stack.
 pub fn float_vector_rotate(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
@@ -1408,13 +2985,23 @@ pub fn float_vector_rotate(push_state: &mut PushState, _instruction_cache: &Inst
     }
 }
 
+/// FLOATVECTOR.SIGMOID: Replaces each element x of the top FLOATVECTOR with the logistic
+/// function 1 / (1 + exp(-x)).
+pub fn float_vector_sigmoid(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    float_vector_apply(push_state, |_| true, |x| 1.0 / (1.0 + (-x).exp()));
+}
+
 /// FLOATVECTOR.SINE: Pushes a FLOATVECTOR item whose elements describe a sine wave. The sine wave
 /// for the element at index i is calulated as A*sin(2*pi*x*i + phi). The amplitude A (1st),
 /// the angle velocity x (2nd) and the phase angle phi (3rd) are taken from the FLOAT stack
-/// (in that order). The vector length is taken from the INTEGER stack.
+/// (in that order). The vector length is taken from the INTEGER stack and limited to the
+/// maximum vector size. Acts as NOOP if the length is smaller than 1.
 pub fn float_vector_sine(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(sine_params) = push_state.float_stack.pop_vec(3) {
-        if let Some(vector_size) = push_state.int_stack.pop() {
+        if let Some(vector_size) = pop_vector_size(push_state) {
+            if vector_size < 1 {
+                return;
+            }
             let mut sine_vector = vec![];
             for i in 0..vector_size as usize {
                 sine_vector.push(
@@ -1467,6 +3054,17 @@ pub fn float_vector_stack_depth(push_state: &mut PushState, _instruction_cache:
         .push(push_state.float_vector_stack.size() as i32);
 }
 
+/// FLOATVECTOR.STDDEV: Pushes the (population) standard deviation of the top FLOATVECTOR to
+/// the FLOAT stack.
+pub fn float_vector_stddev(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(fv) = push_state.float_vector_stack.get(0) {
+        if !fv.values.is_empty() {
+            let deviation = standard_deviation(&fv.values);
+            push_state.float_stack.push(deviation);
+        }
+    }
+}
+
 /// FLOATVECTOR.SUM Pushes the sum of the elements to the FLOAT stack.
 pub fn float_vector_sum(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(fvec) = push_state.float_vector_stack.get(0) {
@@ -1474,11 +3072,54 @@ pub fn float_vector_sum(push_state: &mut PushState, _instruction_cache: &Instruc
     }
 }
 
+/// FLOATVECTOR.SQRT: Replaces each element of the top FLOATVECTOR with its square root. If an
+/// element is negative this acts as a NOOP.
+pub fn float_vector_sqrt(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    float_vector_apply(push_state, |x| x >= 0.0, f32::sqrt);
+}
+
 /// FLOATVECTOR.SWAP: Swaps the top two FLOATVECTORs.
 pub fn float_vector_swap(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     push_state.float_vector_stack.shove(1);
 }
 
+/// FLOATVECTOR.TANH: Replaces each element x of the top FLOATVECTOR with tanh(x).
+pub fn float_vector_tanh(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    float_vector_apply(push_state, |_| true, f32::tanh);
+}
+
+/// FLOATVECTOR.XCORR: Pushes the cross-correlation of the second and the top item of the
+/// FLOATVECTOR stack as a new FLOATVECTOR. The correlation at lag k is the sum of
+/// second[i+k] * top[i] over all overlapping indices i. The lags are taken from the top
+/// INTVECTOR item. If it is empty, all lags with an overlap are used in ascending order.
+/// Lags without an overlap result in 0.
+pub fn float_vector_xcorr(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.int_vector_stack.size() > 0 && push_state.float_vector_stack.size() > 1 {
+        let lags = push_state.int_vector_stack.pop().unwrap().values;
+        let fv = push_state.float_vector_stack.pop_vec(2).unwrap();
+        let (second, top) = (&fv[0].values, &fv[1].values);
+        let lags = if lags.is_empty() {
+            (1 - top.len() as i32..second.len() as i32).collect()
+        } else {
+            lags
+        };
+        let mut correlations = vec![];
+        for lag in lags {
+            let mut sum = 0.0;
+            for (i, t) in top.iter().enumerate() {
+                let j = i as i64 + lag as i64;
+                if j >= 0 && (j as usize) < second.len() {
+                    sum += second[j as usize] * t;
+                }
+            }
+            correlations.push(sum);
+        }
+        push_state
+            .float_vector_stack
+            .push(FloatVector::new(correlations));
+    }
+}
+
 /// FLOATVECTOR.YANK: Removes an indexed item from "deep" in the stack and pushes it on top of the
 /// stack. The index is taken from the INTEGER stack, and the indexing is done after the index is
 /// removed.
@@ -1510,7 +3151,7 @@ pub fn float_vector_yank_dup(push_state: &mut PushState, _instruction_cache: &In
 /// FLOATVECTOR.ZEROS: Pushes a newly generated FLOATVECTOR with all elements set to 0. The size
 /// is taken from the INTEGER stack
 pub fn float_vector_zeros(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    if let Some(size) = push_state.int_stack.pop() {
+    if let Some(size) = pop_vector_size(push_state) {
         if size > 0 {
             push_state
                 .float_vector_stack
@@ -1522,6 +3163,7 @@ pub fn float_vector_zeros(push_state: &mut PushState, _instruction_cache: &Instr
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::push::configuration::DivZeroPolicy;
 
     pub fn icache() -> InstructionCache {
         InstructionCache::new(vec![])
@@ -1647,6 +3289,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn bool_vector_xor_hamming_and_overlap() {
+        let mut test_state = PushState::new();
+        test_state
+            .bool_vector_stack
+            .push(BoolVector::from_int_array(vec![1, 1, 0, 0, 1, 0]));
+        test_state
+            .bool_vector_stack
+            .push(BoolVector::from_int_array(vec![1, 0, 1]));
+        let mut distance_state = test_state.clone();
+        bool_vector_hamming(&mut distance_state, &icache());
+        distance_state.bool_vector_stack = test_state.bool_vector_stack.clone();
+        bool_vector_overlap(&mut distance_state, &icache());
+        assert_eq!(distance_state.int_stack.to_string(), "1 3");
+        // The top item is shorter than the second item
+        let mut and_state = test_state.clone();
+        and_state.int_stack.push(2);
+        bool_vector_and(&mut and_state, &icache());
+        assert_eq!(
+            and_state.bool_vector_stack.pop().unwrap(),
+            BoolVector::from_int_array(vec![1, 1, 0, 0, 1, 0])
+        );
+        let mut and_state = test_state.clone();
+        and_state.int_stack.push(-10);
+        bool_vector_and(&mut and_state, &icache());
+        assert_eq!(
+            and_state.bool_vector_stack.pop().unwrap(),
+            BoolVector::from_int_array(vec![1, 1, 0, 0, 1, 0])
+        );
+        test_state.int_stack.push(2);
+        bool_vector_xor(&mut test_state, &icache());
+        assert_eq!(
+            test_state.bool_vector_stack.pop().unwrap(),
+            BoolVector::from_int_array(vec![1, 1, 1, 0, 0, 0])
+        );
+    }
+
     #[test]
     fn bool_vector_not_with_different_overlaps() {
         let test_vec1 = BoolVector::from_int_array(vec![1, 1, 1, 1, 0, 0, 0, 0]);
@@ -1685,6 +3364,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn bool_vector_instructions_across_word_boundaries() {
+        let a: Vec<bool> = (0..150).map(|i| i % 3 == 0).collect();
+        let b: Vec<bool> = (0..100).map(|i| i % 5 == 0).collect();
+        let mut test_state = PushState::new();
+        test_state.bool_vector_stack.push(BoolVector::new(a.clone()));
+        test_state.bool_vector_stack.push(BoolVector::new(b.clone()));
+        test_state.int_stack.push(70);
+        bool_vector_and(&mut test_state, &icache());
+        let expected: Vec<bool> = (0..150)
+            .map(|i| if i >= 70 { a[i] && b[i - 70] } else { a[i] })
+            .collect();
+        assert_eq!(test_state.bool_vector_stack.get(0).unwrap().to_bools(), expected);
+
+        test_state.bool_vector_stack.push(BoolVector::new(b.clone()));
+        bool_vector_concat(&mut test_state, &icache());
+        test_state.int_stack.push(65);
+        bool_vector_drop(&mut test_state, &icache());
+        let concatenated: Vec<bool> = expected.iter().chain(b.iter()).copied().collect();
+        assert_eq!(test_state.bool_vector_stack.get(0).unwrap().to_bools(), &concatenated[65..]);
+
+        test_state.bool_stack.push(true);
+        bool_vector_rotate(&mut test_state, &icache());
+        bool_vector_sort_desc(&mut test_state, &icache());
+        bool_vector_count(&mut test_state, &icache());
+        let ones = concatenated[66..].iter().filter(|x| **x).count() + 1;
+        assert_eq!(test_state.int_stack.pop(), Some(ones as i32));
+        let sorted = test_state.bool_vector_stack.get(0).unwrap().to_bools();
+        assert!(sorted[..ones].iter().all(|x| *x) && !sorted[ones..].iter().any(|x| *x));
+    }
+
     #[test]
     fn bool_vector_define_creates_name_binding() {
         let mut test_state = PushState::new();
@@ -1712,6 +3422,49 @@ mod tests {
         assert_eq!(test_state.bool_stack.pop().unwrap(), true);
     }
 
+    #[test]
+    fn bool_vector_find_pushes_index_of_first_occurrence() {
+        let mut test_state = PushState::new();
+        test_state
+            .bool_vector_stack
+            .push(BoolVector::from_int_array(vec![0, 1, 1, 0, 1, 1]));
+        test_state
+            .bool_vector_stack
+            .push(BoolVector::from_int_array(vec![1, 1]));
+        bool_vector_find(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.pop().unwrap(), 1);
+        assert_eq!(test_state.bool_vector_stack.size(), 0);
+        test_state
+            .bool_vector_stack
+            .push(BoolVector::from_int_array(vec![0, 1, 0]));
+        test_state
+            .bool_vector_stack
+            .push(BoolVector::from_int_array(vec![1, 1]));
+        bool_vector_find(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.pop().unwrap(), -1);
+    }
+
+    #[test]
+    fn bool_vector_starts_with_compares_prefix() {
+        let mut test_state = PushState::new();
+        test_state
+            .bool_vector_stack
+            .push(BoolVector::from_int_array(vec![1, 0, 1, 1]));
+        test_state
+            .bool_vector_stack
+            .push(BoolVector::from_int_array(vec![1, 0]));
+        bool_vector_starts_with(&mut test_state, &icache());
+        assert_eq!(test_state.bool_stack.pop().unwrap(), true);
+        test_state
+            .bool_vector_stack
+            .push(BoolVector::from_int_array(vec![1, 0]));
+        test_state
+            .bool_vector_stack
+            .push(BoolVector::from_int_array(vec![1, 0, 1]));
+        bool_vector_starts_with(&mut test_state, &icache());
+        assert_eq!(test_state.bool_stack.pop().unwrap(), false);
+    }
+
     #[test]
     fn bool_vector_ones_creates_item() {
         let mut test_state = PushState::new();
@@ -1737,9 +3490,9 @@ mod tests {
         test_state.float_stack.push(test_sparsity);
         bool_vector_rand(&mut test_state, &icache());
         if let Some(rbv) = test_state.bool_vector_stack.pop() {
-            assert_eq!(rbv.values.len(), test_size as usize);
+            assert_eq!(rbv.len(), test_size as usize);
             assert_eq!(
-                rbv.values.iter().filter(|&n| *n == true).count(),
+                rbv.bits.count_ones(),
                 (test_sparsity * test_size as f32) as usize
             );
         } else {
@@ -1994,6 +3747,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn int_vector_align_replaces_offset() {
+        let test_vec1 = IntVector::new(vec![1, 1, 1, 1, 0, 0, 0, 0]);
+        let test_vec2 = IntVector::new(vec![1, 0, 1, 0, 1, 0, 1, 0]);
+        for offset in [-3, 0, 2, 9].iter() {
+            let mut offset_state = PushState::new();
+            offset_state.int_vector_stack.push(test_vec2.clone());
+            offset_state.int_vector_stack.push(test_vec1.clone());
+            offset_state.int_stack.push(*offset);
+            int_vector_add(&mut offset_state, &icache());
+            let mut aligned_state = PushState::new();
+            aligned_state.configuration.vector_offsets = false;
+            aligned_state.int_vector_stack.push(test_vec2.clone());
+            aligned_state.int_vector_stack.push(test_vec1.clone());
+            aligned_state.int_stack.push_vec(vec![7, *offset]);
+            int_vector_align(&mut aligned_state, &icache());
+            int_vector_add(&mut aligned_state, &icache());
+            assert_eq!(
+                aligned_state.int_vector_stack.to_string(),
+                offset_state.int_vector_stack.to_string()
+            );
+            assert_eq!(aligned_state.int_stack.to_string(), "7");
+        }
+        let mut test_state = PushState::new();
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![1.0, 2.0, 3.0]));
+        test_state.int_stack.push(-1);
+        float_vector_align(&mut test_state, &icache());
+        assert_eq!(
+            test_state.float_vector_stack.pop().unwrap().values,
+            vec![2.0, 3.0, 0.0]
+        );
+    }
+
     #[test]
     fn int_vector_add_with_different_overlaps() {
         let test_vec1 = IntVector::new(vec![1, 1, 1, 1, 0, 0, 0, 0]);
@@ -2131,6 +3919,35 @@ mod tests {
         assert_eq!(test_state.bool_stack.pop().unwrap(), true);
     }
 
+    #[test]
+    fn int_vector_find_pushes_index_of_first_occurrence() {
+        let mut test_state = PushState::new();
+        test_state
+            .int_vector_stack
+            .push(IntVector::new(vec![4, 2, 3, 2, 3]));
+        test_state.int_vector_stack.push(IntVector::new(vec![2, 3]));
+        int_vector_find(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.pop().unwrap(), 1);
+        test_state.int_vector_stack.push(IntVector::new(vec![4, 2]));
+        test_state.int_vector_stack.push(IntVector::new(vec![2, 4]));
+        int_vector_find(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.pop().unwrap(), -1);
+        assert_eq!(test_state.int_vector_stack.size(), 0);
+    }
+
+    #[test]
+    fn int_vector_starts_with_compares_prefix() {
+        let mut test_state = PushState::new();
+        test_state.int_vector_stack.push(IntVector::new(vec![7, 8, 9]));
+        test_state.int_vector_stack.push(IntVector::new(vec![7, 8]));
+        int_vector_starts_with(&mut test_state, &icache());
+        assert_eq!(test_state.bool_stack.pop().unwrap(), true);
+        test_state.int_vector_stack.push(IntVector::new(vec![7, 8, 9]));
+        test_state.int_vector_stack.push(IntVector::new(vec![8, 9]));
+        int_vector_starts_with(&mut test_state, &icache());
+        assert_eq!(test_state.bool_stack.pop().unwrap(), false);
+    }
+
     #[test]
     fn int_vector_from_int_pushes_item() {
         let mut test_state = PushState::new();
@@ -2282,6 +4099,41 @@ mod tests {
         assert_eq!(test_state.int_vector_stack.to_string(), "[0] [1]");
     }
 
+    #[test]
+    fn int_vector_histogram_counts_elements_per_bin() {
+        let mut test_state = PushState::new();
+        test_state
+            .int_vector_stack
+            .push(IntVector::new(vec![6, 1, 2, 3, 4, 5, 1]));
+        test_state.int_stack.push(3);
+        int_vector_histogram(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[3,2,2]");
+        test_state.int_stack.push(0);
+        int_vector_histogram(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[3,2,2]");
+        // The number of bins is limited to the maximum vector size
+        test_state.configuration.max_vector_size = 4;
+        test_state.int_stack.push(i32::MAX);
+        int_vector_histogram(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[2,0,1,0]");
+        test_state.float_vector_stack.push(FloatVector::new(vec![1.0]));
+        test_state.int_stack.push(i32::MAX);
+        float_vector_histogram(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[1,0,0,0] [2,0,1,0]");
+    }
+
+    #[test]
+    fn float_vector_histogram_counts_elements_per_bin() {
+        let mut test_state = PushState::new();
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![0.0, 0.5, 1.0, 0.2]));
+        test_state.int_stack.push(2);
+        float_vector_histogram(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[2,2]");
+        assert_eq!(test_state.float_vector_stack.size(), 0);
+    }
+
     #[test]
     fn int_vector_sum_pushes_aggregation_value() {
         let mut test_state = PushState::new();
@@ -2421,6 +4273,14 @@ mod tests {
         assert!(f32::abs(sine_vector[499]) < 0.01f32);
         assert!(f32::abs(sine_vector[749] + 1.0) < 0.01f32);
         assert!(f32::abs(sine_vector[999]) < 0.01f32);
+        // The length is limited to the maximum vector size
+        test_state.configuration.max_vector_size = 3;
+        test_state.int_stack.push(i32::MAX);
+        test_state.float_stack.push(0.0);
+        test_state.float_stack.push(0.001);
+        test_state.float_stack.push(1.0);
+        float_vector_sine(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.pop().unwrap().values.len(), 3);
     }
 
     #[test]
@@ -2472,6 +4332,90 @@ mod tests {
         );
     }
 
+    #[test]
+    fn strict_indexing_reports_out_of_range_indices() {
+        let mut test_state = PushState::new();
+        test_state.int_vector_stack.push(IntVector::new(vec![1, 2, 3]));
+        test_state.int_stack.push(5);
+        int_vector_get(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.to_string(), "3");
+        test_state.configuration.strict_indexing = true;
+        test_state.int_stack.push(-1);
+        int_vector_get(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.to_string(), "-1 3");
+        test_state.int_stack.push(3);
+        int_vector_set(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.to_string(), "3 -1 3");
+        assert_eq!(test_state.int_vector_stack.to_string(), "[1,2,3]");
+        test_state.int_stack.flush();
+        test_state.float_vector_stack.push(FloatVector::new(vec![]));
+        test_state.float_stack.push(1.0);
+        test_state.int_stack.push(0);
+        float_vector_set(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.size(), 1);
+        assert_eq!(
+            test_state.error_stack.to_string(),
+            "INDEX*OUT*OF*RANGE(0,0) INDEX*OUT*OF*RANGE(3,3) INDEX*OUT*OF*RANGE(-1,3)"
+        );
+        // Valid indices are not affected
+        test_state.bool_vector_stack.push(BoolVector::new(vec![false, true]));
+        test_state.int_stack.push(1);
+        bool_vector_get(&mut test_state, &icache());
+        assert_eq!(test_state.bool_stack.to_string(), "TRUE");
+        assert_eq!(test_state.error_stack.size(), 3);
+    }
+
+    #[test]
+    fn vector_size_is_capped() {
+        let mut test_state = PushState::new();
+        test_state.configuration.max_vector_size = 3;
+        test_state.int_stack.push(5);
+        int_vector_ones(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[1,1,1]");
+        test_state.int_stack.push(7);
+        int_vector_append(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[1,1,1]");
+        assert_eq!(test_state.int_stack.size(), 0);
+        assert_eq!(test_state.error_stack.size(), 0);
+        // Strict mode reports the truncation
+        test_state.configuration.strict = true;
+        test_state.int_stack.push(4);
+        float_vector_zeros(&mut test_state, &icache());
+        test_state.float_stack.push(1.0);
+        float_vector_append(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.to_string(), "[0.000,0.000,0.000]");
+        assert_eq!(
+            test_state.error_stack.to_string(),
+            "VECTOR*SIZE*CAP(4,3) VECTOR*SIZE*CAP(4,3)"
+        );
+    }
+
+    #[test]
+    fn vector_divide_by_zero_follows_policy() {
+        let mut test_state = PushState::new();
+        test_state.int_vector_stack.push(IntVector::new(vec![6, 6, 6]));
+        test_state.int_vector_stack.push(IntVector::new(vec![2, 0, 3]));
+        test_state.int_stack.push(0);
+        int_vector_divide(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[2,0,3] [6,6,6]");
+        assert_eq!(test_state.int_stack.to_string(), "0");
+        test_state.configuration.div_zero_policy = DivZeroPolicy::PushDividend;
+        int_vector_divide(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[3,6,2]");
+        test_state.int_vector_stack.push(IntVector::new(vec![i32::MIN]));
+        test_state.int_vector_stack.push(IntVector::new(vec![-1]));
+        test_state.int_stack.push(0);
+        int_vector_divide(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.pop().unwrap().values, vec![i32::MIN]);
+        test_state.configuration.div_zero_policy = DivZeroPolicy::PushZero;
+        test_state.float_vector_stack.push(FloatVector::new(vec![6.0, 6.0]));
+        test_state.float_vector_stack.push(FloatVector::new(vec![0.0, 2.0]));
+        test_state.int_stack.push(0);
+        float_vector_divide(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.to_string(), "[0.000,3.000]");
+        assert_eq!(test_state.div_zero_count, 3);
+    }
+
     #[test]
     fn float_vector_divide_with_partial_overlap() {
         let test_vec1 = FloatVector::new(vec![2.0, 2.0, 2.0, 2.0, 1.0, 1.0, 1.0, 1.0]);
@@ -2490,6 +4434,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn float_vector_divide_pad_extends_shorter_vector() {
+        let mut test_state = PushState::new();
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![6.0, 4.0, 3.0]));
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![2.0]));
+        test_state.float_stack.push(0.0);
+        // Division by the zero fill value is a NOOP with the default policy
+        float_vector_divide_pad(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.size(), 2);
+        assert_eq!(test_state.float_stack.size(), 1);
+        test_state.float_stack.push(2.0);
+        float_vector_divide_pad(&mut test_state, &icache());
+        assert_eq!(
+            test_state.float_vector_stack.pop().unwrap(),
+            FloatVector::new(vec![3.0, 2.0, 1.5])
+        );
+        assert_eq!(test_state.float_stack.to_string(), "0.0");
+    }
+
+    #[test]
+    fn int_vector_add_pad_produces_longer_length() {
+        let mut test_state = PushState::new();
+        test_state.int_vector_stack.push(IntVector::new(vec![1, 2]));
+        test_state.int_vector_stack.push(IntVector::new(vec![1, 1, 1, 1]));
+        test_state.int_stack.push(10);
+        int_vector_add_pad(&mut test_state, &icache());
+        assert_eq!(
+            test_state.int_vector_stack.pop().unwrap(),
+            IntVector::new(vec![2, 3, 11, 11])
+        );
+        test_state.int_vector_stack.push(IntVector::new(vec![5, 5, 5]));
+        test_state.int_vector_stack.push(IntVector::new(vec![1]));
+        test_state.int_stack.push(2);
+        int_vector_subtract_pad(&mut test_state, &icache());
+        assert_eq!(
+            test_state.int_vector_stack.pop().unwrap(),
+            IntVector::new(vec![4, 3, 3])
+        );
+    }
+
     #[test]
     fn float_vector_define_creates_name_binding() {
         let mut test_state = PushState::new();
@@ -2543,6 +4531,52 @@ mod tests {
             "[2.000] [3.000] [1.000] [4.000]"
         );
     }
+    #[test]
+    fn float_vector_map_applies_exec_body_to_elements() {
+        let mut instruction_set = crate::push::instructions::InstructionSet::new();
+        instruction_set.load();
+        let mut test_state = PushState::new();
+        crate::push::parser::PushParser::parse_program(
+            &mut test_state,
+            &instruction_set,
+            "( FLOATVECTOR.MAP ( FLOAT.DUP FLOAT.* 1.0 FLOAT.+ ) 2.5 )",
+        );
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![1.0, -2.0, 3.0]));
+        crate::push::interpreter::PushInterpreter::run(&mut test_state, &mut instruction_set);
+        assert_eq!(
+            test_state.float_vector_stack.pop().unwrap().values,
+            vec![2.0, 5.0, 10.0]
+        );
+        assert_eq!(test_state.float_vector_stack.size(), 0);
+        assert_eq!(test_state.float_stack.to_string(), "2.5");
+    }
+
+    #[test]
+    fn float_vector_resample_interpolates_linearly() {
+        let mut test_state = PushState::new();
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![0.0, 2.0, 1.0]));
+        test_state.int_stack.push(5);
+        float_vector_resample(&mut test_state, &icache());
+        assert_eq!(
+            test_state.float_vector_stack.get(0).unwrap().values,
+            vec![0.0, 1.0, 2.0, 1.5, 1.0]
+        );
+        test_state.int_stack.push(0);
+        float_vector_resample(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.get(0).unwrap().values.len(), 5);
+        test_state.float_stack.push(2.5);
+        float_vector_interp(&mut test_state, &icache());
+        test_state.float_stack.push(-3.0);
+        float_vector_interp(&mut test_state, &icache());
+        test_state.float_stack.push(9.0);
+        float_vector_interp(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.to_string(), "1.0 0.0 1.8");
+    }
+
     #[test]
     fn float_vector_ones_creates_item() {
         let mut test_state = PushState::new();
@@ -2559,6 +4593,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn float_vector_rand_uniform_pushes_bounded_item() {
+        let mut test_state = PushState::new();
+        test_state.int_stack.push(100);
+        test_state.float_stack.push(2.0);
+        test_state.float_stack.push(3.0);
+        float_vector_rand_uniform(&mut test_state, &icache());
+        let fv = test_state.float_vector_stack.pop().unwrap();
+        assert_eq!(fv.values.len(), 100);
+        assert!(fv.values.iter().all(|x| *x >= 2.0 && *x < 3.0));
+    }
+
+    #[test]
+    fn distribution_rand_instructions_are_reproducible() {
+        let mut test_state = PushState::new();
+        for _ in 0..2 {
+            test_state.seed(11);
+            test_state.int_stack.push(20);
+            test_state.float_stack.push(4.0);
+            int_vector_rand_poisson(&mut test_state, &icache());
+            test_state.int_stack.push(20);
+            test_state.float_stack.push(0.5);
+            float_vector_rand_exp(&mut test_state, &icache());
+            test_state.int_stack.push(20);
+            test_state.float_stack.push(0.5);
+            bool_vector_rand_bernoulli(&mut test_state, &icache());
+        }
+        assert_eq!(test_state.int_vector_stack.size(), 2);
+        assert_eq!(test_state.int_vector_stack.get(0), test_state.int_vector_stack.get(1));
+        assert_eq!(test_state.float_vector_stack.get(0), test_state.float_vector_stack.get(1));
+        assert_eq!(test_state.bool_vector_stack.get(0), test_state.bool_vector_stack.get(1));
+    }
+
     #[test]
     fn float_vector_rand_pushes_new_item() {
         let mut test_state = PushState::new();
@@ -2583,6 +4650,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn float_vector_argsort_pushes_indices() {
+        let mut test_state = PushState::new();
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![34.2, 0.0, -28.1, 111.1, 0.0]));
+        float_vector_argsort(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[2,1,4,0,3]");
+        assert_eq!(test_state.float_vector_stack.size(), 1);
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![2.0, f32::NAN, 1.0, f32::NAN, 0.5]));
+        float_vector_argsort(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[4,2,0,1,3] [2,1,4,0,3]");
+    }
+
     #[test]
     fn float_vector_sort_top_item() {
         let mut test_state = PushState::new();
@@ -2644,6 +4727,244 @@ mod tests {
         assert_eq!(test_state.float_stack.to_string(), "14.0");
     }
 
+    #[test]
+    fn float_vector_dot_norm_and_cosine_push_float() {
+        let mut test_state = PushState::new();
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![1.0, -2.0, 2.0]));
+        float_vector_norm_l1(&mut test_state, &icache());
+        float_vector_norm_l2(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.to_string(), "3.0 5.0");
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![2.0, 0.0, 1.0]));
+        let mut cosine_state = test_state.clone();
+        float_vector_dot(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.get(0), Some(&4.0));
+        assert_eq!(test_state.float_vector_stack.size(), 0);
+        float_vector_cosine(&mut cosine_state, &icache());
+        let cosine = cosine_state.float_stack.get(0).unwrap();
+        assert!((cosine - 4.0 / (3.0 * 5f32.sqrt())).abs() < 1e-6);
+        // Length mismatch and zero norm
+        test_state.float_vector_stack.push(FloatVector::new(vec![0.0, 0.0]));
+        test_state.float_vector_stack.push(FloatVector::new(vec![1.0]));
+        float_vector_dot(&mut test_state, &icache());
+        float_vector_cosine(&mut test_state, &icache());
+        test_state.float_vector_stack.push(FloatVector::new(vec![1.0, 0.0]));
+        float_vector_cosine(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.size(), 3);
+        assert_eq!(test_state.float_stack.size(), 3);
+    }
+
+    #[test]
+    fn float_vector_transfer_functions_apply_element_wise() {
+        let mut test_state = PushState::new();
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![-4.0, 0.0, 9.0]));
+        float_vector_abs(&mut test_state, &icache());
+        float_vector_sqrt(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.to_string(), "[2.000,0.000,3.000]");
+        // The logarithm of zero is undefined
+        float_vector_log(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.to_string(), "[2.000,0.000,3.000]");
+        float_vector_exp(&mut test_state, &icache());
+        float_vector_log(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.to_string(), "[2.000,0.000,3.000]");
+        test_state.float_vector_stack.push(FloatVector::new(vec![-1.0, 0.0]));
+        let mut tanh_state = test_state.clone();
+        float_vector_sqrt(&mut test_state, &icache());
+        float_vector_sigmoid(&mut test_state, &icache());
+        assert_eq!(
+            test_state.float_vector_stack.to_string(),
+            "[0.269,0.500] [2.000,0.000,3.000]"
+        );
+        float_vector_tanh(&mut tanh_state, &icache());
+        assert_eq!(tanh_state.float_vector_stack.get(0).unwrap().values[0], (-1f32).tanh());
+    }
+
+    #[test]
+    fn vector_statistics_push_aggregation_values() {
+        let mut test_state = PushState::new();
+        test_state
+            .int_vector_stack
+            .push(IntVector::new(vec![3, -1, 7, -1, 7, 2]));
+        int_vector_argmax(&mut test_state, &icache());
+        int_vector_argmin(&mut test_state, &icache());
+        int_vector_max(&mut test_state, &icache());
+        int_vector_min(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.to_string(), "-1 7 1 2");
+        int_vector_median(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.to_string(), "2.5");
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]));
+        float_vector_stddev(&mut test_state, &icache());
+        float_vector_median(&mut test_state, &icache());
+        float_vector_max(&mut test_state, &icache());
+        float_vector_min(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.to_string(), "2.0 9.0 4.5 2.0 2.5");
+        float_vector_argmax(&mut test_state, &icache());
+        float_vector_argmin(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.to_string(), "0 7 -1 7 1 2");
+        // Empty vectors have no statistics
+        let mut empty_state = PushState::new();
+        empty_state.int_vector_stack.push(IntVector::new(vec![]));
+        empty_state.float_vector_stack.push(FloatVector::new(vec![]));
+        int_vector_stddev(&mut empty_state, &icache());
+        int_vector_max(&mut empty_state, &icache());
+        float_vector_argmax(&mut empty_state, &icache());
+        float_vector_median(&mut empty_state, &icache());
+        assert_eq!(empty_state.int_stack.size() + empty_state.float_stack.size(), 0);
+    }
+
+    #[test]
+    fn vectors_are_sliced_and_concatenated() {
+        let mut test_state = PushState::new();
+        test_state
+            .int_vector_stack
+            .push(IntVector::new(vec![0, 1, 2, 3, 4, 5]));
+        let mut reversed_state = test_state.clone();
+        reversed_state.int_stack.push(4);
+        reversed_state.int_stack.push(1);
+        int_vector_slice(&mut reversed_state, &icache());
+        assert_eq!(reversed_state.int_vector_stack.to_string(), "[]");
+        test_state.int_stack.push(1);
+        test_state.int_stack.push(4);
+        int_vector_slice(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[1,2,3]");
+        test_state
+            .int_vector_stack
+            .push(IntVector::new(vec![4, 5, 6]));
+        int_vector_concat(&mut test_state, &icache());
+        int_vector_reverse(&mut test_state, &icache());
+        test_state.int_stack.push(5);
+        int_vector_take(&mut test_state, &icache());
+        test_state.int_stack.push(1);
+        int_vector_drop(&mut test_state, &icache());
+        int_vector_first(&mut test_state, &icache());
+        int_vector_last(&mut test_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[5,4,3,2]");
+        assert_eq!(test_state.int_stack.to_string(), "2 5");
+        // Out-of-range indices and counts are bound to the valid range
+        test_state
+            .bool_vector_stack
+            .push(BoolVector::new(vec![true, false, false]));
+        test_state.int_stack.push(-3);
+        test_state.int_stack.push(9);
+        bool_vector_slice(&mut test_state, &icache());
+        test_state.int_stack.push(-1);
+        bool_vector_drop(&mut test_state, &icache());
+        test_state.int_stack.push(7);
+        bool_vector_take(&mut test_state, &icache());
+        assert_eq!(test_state.bool_vector_stack.to_string(), "[TRUE,FALSE,FALSE]");
+        // The concatenation must not exceed the maximum vector size
+        test_state.configuration.max_vector_size = 4;
+        test_state.float_vector_stack.push(FloatVector::new(vec![1.0, 2.0]));
+        test_state.float_vector_stack.push(FloatVector::new(vec![3.0; 3]));
+        float_vector_concat(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.size(), 2);
+    }
+
+    #[test]
+    fn vectors_are_converted_between_types() {
+        let mut test_state = PushState::new();
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![-1.5, 0.4, 2.5, 2.7]));
+        let mut floor_state = test_state.clone();
+        // Without conversion mode the FLOATVECTOR is left on the stack
+        int_vector_from_float_vector(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.size(), 1);
+        test_state.bool_stack.push(true);
+        int_vector_from_float_vector(&mut test_state, &icache());
+        floor_state.bool_stack.push(false);
+        int_vector_from_float_vector(&mut floor_state, &icache());
+        assert_eq!(test_state.int_vector_stack.to_string(), "[-2,0,3,3]");
+        assert_eq!(floor_state.int_vector_stack.to_string(), "[-2,0,2,2]");
+        let mut bool_state = test_state.clone();
+        bool_vector_from_int_vector(&mut bool_state, &icache());
+        assert_eq!(
+            bool_state.bool_vector_stack.to_string(),
+            "[TRUE,FALSE,TRUE,TRUE]"
+        );
+        let mut float_state = test_state.clone();
+        float_vector_from_int_vector(&mut float_state, &icache());
+        assert_eq!(
+            float_state.float_vector_stack.get(0).unwrap().values,
+            vec![-2.0, 0.0, 3.0, 3.0]
+        );
+        int_vector_to_float(&mut test_state, &icache());
+        assert_eq!(test_state.float_stack.to_string(), "3.0 3.0 0.0 -2.0");
+        assert_eq!(test_state.int_vector_stack.size(), 0);
+    }
+
+    #[test]
+    fn float_vector_nearest_pushes_index_and_similarity() {
+        let mut test_state = PushState::new();
+        test_state.float_vector_stack.push(FloatVector::new(vec![0.0, 1.0]));
+        test_state.float_vector_stack.push(FloatVector::new(vec![3.0, 0.1]));
+        test_state.float_vector_stack.push(FloatVector::new(vec![1.0, 2.0, 3.0]));
+        test_state.float_vector_stack.push(FloatVector::new(vec![2.0, 0.0]));
+        test_state.float_vector_stack.push(FloatVector::new(vec![1.0, 1.0]));
+        test_state.float_vector_stack.push(FloatVector::new(vec![1.0, 0.0]));
+        // Cosine similarity
+        let mut cosine_state = test_state.clone();
+        cosine_state.int_stack.push(0);
+        float_vector_nearest(&mut cosine_state, &icache());
+        assert_eq!(cosine_state.int_stack.to_string(), "1");
+        assert_eq!(cosine_state.float_stack.to_string(), "1.0");
+        assert_eq!(cosine_state.float_vector_stack.size(), 5);
+        // Euclidean distance
+        test_state.int_stack.push(1);
+        float_vector_nearest(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.to_string(), "0");
+        assert_eq!(test_state.float_stack.to_string(), "1.0");
+        // No comparable vector
+        test_state.float_vector_stack.flush();
+        test_state.float_vector_stack.push(FloatVector::new(vec![1.0, 0.0]));
+        test_state.int_stack.push(1);
+        float_vector_nearest(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.to_string(), "1 0");
+        assert_eq!(test_state.float_vector_stack.size(), 1);
+    }
+
+    #[test]
+    fn float_vector_xcorr_pushes_correlations_at_lags() {
+        let mut test_state = PushState::new();
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![1.0, 2.0, 3.0]));
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![1.0, 1.0]));
+        test_state.int_vector_stack.push(IntVector::new(vec![0, 1, 5]));
+        float_vector_xcorr(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.to_string(), "[3.000,5.000,0.000]");
+        assert_eq!(test_state.int_vector_stack.size(), 0);
+        test_state.float_vector_stack.flush();
+        test_state.float_vector_stack.push(FloatVector::new(vec![1.0, 2.0]));
+        test_state.float_vector_stack.push(FloatVector::new(vec![1.0, 1.0]));
+        test_state.int_vector_stack.push(IntVector::new(vec![i32::MAX, i32::MIN]));
+        float_vector_xcorr(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.to_string(), "[0.000,0.000]");
+    }
+
+    #[test]
+    fn float_vector_xcorr_uses_all_lags_for_empty_lag_vector() {
+        let mut test_state = PushState::new();
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![1.0, 2.0, 3.0]));
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![1.0, 1.0]));
+        test_state.int_vector_stack.push(IntVector::new(vec![]));
+        float_vector_xcorr(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.to_string(), "[1.000,3.000,5.000,3.000]");
+    }
+
     #[test]
     fn float_vector_swaps_top_elements() {
         let mut test_state = PushState::new();
@@ -0,0 +1,377 @@
+use crate::push::instructions::Instruction;
+use crate::push::instructions::InstructionCache;
+use crate::push::item::Item;
+use crate::push::stack::PushPrint;
+use crate::push::state::PushState;
+use crate::push::state::*;
+use crate::push::vector::FloatVector;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// List of FLOATVECTOR rows, e.g. a dataset where each row is a sample. In contrast to a
+/// MATRIX the rows can have different lengths.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VectorList {
+    pub rows: Vec<FloatVector>,
+}
+
+impl VectorList {
+    pub fn new(rows: Vec<FloatVector>) -> Self {
+        Self { rows }
+    }
+
+    /// Returns the position of the index bound to the valid range or None if the list is
+    /// empty.
+    fn bound(&self, index: i32) -> Option<usize> {
+        if self.rows.is_empty() {
+            None
+        } else {
+            Some(i32::max(i32::min(self.rows.len() as i32 - 1, index), 0) as usize)
+        }
+    }
+}
+
+impl PushPrint for VectorList {
+    fn to_pstring(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for VectorList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<String> = self.rows.iter().map(|row| row.to_string()).collect();
+        write!(f, "[{}]", rows.join(","))
+    }
+}
+
+/// Lists of FLOATVECTOR rows.
+pub fn load_vector_list_instructions(map: &mut HashMap<String, Instruction>) {
+    map.insert(
+        String::from("EXEC.DO*VECTORLIST"),
+        Instruction::new(exec_do_vector_list).with_blocks(1),
+    );
+    map.insert(
+        String::from("VECTORLIST.="),
+        Instruction::new(vector_list_equal),
+    );
+    map.insert(
+        String::from("VECTORLIST.APPEND"),
+        Instruction::new(vector_list_append),
+    );
+    map.insert(
+        String::from("VECTORLIST.DUP"),
+        Instruction::new(vector_list_dup),
+    );
+    map.insert(
+        String::from("VECTORLIST.EMPTY"),
+        Instruction::new(vector_list_empty),
+    );
+    map.insert(
+        String::from("VECTORLIST.FLUSH"),
+        Instruction::new(vector_list_flush),
+    );
+    map.insert(
+        String::from("VECTORLIST.GET"),
+        Instruction::new(vector_list_get),
+    );
+    map.insert(
+        String::from("VECTORLIST.ID"),
+        Instruction::new(vector_list_id),
+    );
+    map.insert(
+        String::from("VECTORLIST.LENGTH"),
+        Instruction::new(vector_list_length),
+    );
+    map.insert(
+        String::from("VECTORLIST.POP"),
+        Instruction::new(vector_list_pop),
+    );
+    map.insert(
+        String::from("VECTORLIST.REMOVE"),
+        Instruction::new(vector_list_remove),
+    );
+    map.insert(
+        String::from("VECTORLIST.ROT"),
+        Instruction::new(vector_list_rot),
+    );
+    map.insert(
+        String::from("VECTORLIST.SET"),
+        Instruction::new(vector_list_set),
+    );
+    map.insert(
+        String::from("VECTORLIST.SHOVE"),
+        Instruction::new(vector_list_shove),
+    );
+    map.insert(
+        String::from("VECTORLIST.STACKDEPTH"),
+        Instruction::new(vector_list_stack_depth),
+    );
+    map.insert(
+        String::from("VECTORLIST.SWAP"),
+        Instruction::new(vector_list_swap),
+    );
+    map.insert(
+        String::from("VECTORLIST.YANK"),
+        Instruction::new(vector_list_yank),
+    );
+    map.insert(
+        String::from("VECTORLIST.YANKDUP"),
+        Instruction::new(vector_list_yank_dup),
+    );
+}
+
+/// Returns the index taken from the INTEGER stack bound to the valid positions of the
+/// VECTORLIST stack.
+fn pop_vector_list_index(push_state: &mut PushState) -> Option<usize> {
+    let index = push_state.int_stack.pop()?;
+    Some(i32::max(
+        i32::min(push_state.vector_list_stack.size() as i32 - 1, index),
+        0,
+    ) as usize)
+}
+
+/// EXEC.DO*VECTORLIST: Pops the top VECTORLIST item and executes the top item of the EXEC stack
+/// once for each of its rows. Before each execution the row is pushed to the FLOATVECTOR stack.
+/// The rows are pushed as FLOATVECTOR literals together with the copies of the body onto the
+/// EXEC stack so that the body is free to modify the VECTORLIST stack. Acts as NOOP if the EXEC
+/// or the VECTORLIST stack is empty.
+fn exec_do_vector_list(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.exec_stack.size() > 0 {
+        if let Some(list) = push_state.vector_list_stack.pop() {
+            let body = push_state.exec_stack.pop().unwrap();
+            let mut iterations = vec![];
+            // The first row is executed first, i.e. it ends up on top
+            for row in list.rows.into_iter().rev() {
+                iterations.push(body.clone());
+                iterations.push(Item::floatvec(row));
+            }
+            if !iterations.is_empty() {
+                push_state.exec_stack.push(Item::list(iterations));
+            }
+        }
+    }
+}
+
+/// VECTORLIST.=: Pushes TRUE onto the BOOLEAN stack if the top two VECTORLIST items are equal,
+/// or FALSE otherwise.
+fn vector_list_equal(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(lists) = push_state.vector_list_stack.pop_vec(2) {
+        push_state.bool_stack.push(lists[0] == lists[1]);
+    }
+}
+
+/// VECTORLIST.APPEND: Pops the top FLOATVECTOR and appends it as last row to the top
+/// VECTORLIST item. If the VECTORLIST stack is empty a new list is created. Acts as NOOP if
+/// the list already has the maximum vector size of rows.
+fn vector_list_append(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.float_vector_stack.size() > 0 {
+        if push_state.vector_list_stack.size() == 0 {
+            push_state.vector_list_stack.push(VectorList::default());
+        }
+        let cap = push_state.configuration.max_vector_size;
+        let list = push_state.vector_list_stack.get_mut(0).unwrap();
+        if list.rows.len() < cap {
+            list.rows.push(push_state.float_vector_stack.pop().unwrap());
+        }
+    }
+}
+
+/// VECTORLIST.DUP: Duplicates the top item on the VECTORLIST stack. Does not pop its argument.
+fn vector_list_dup(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(list) = push_state.vector_list_stack.copy(0) {
+        push_state.vector_list_stack.push(list);
+    }
+}
+
+/// VECTORLIST.EMPTY: Pushes a VECTORLIST without rows.
+fn vector_list_empty(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.vector_list_stack.push(VectorList::default());
+}
+
+/// VECTORLIST.FLUSH: Empties the VECTORLIST stack.
+fn vector_list_flush(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.vector_list_stack.flush();
+}
+
+/// VECTORLIST.GET: Copies the row at index i of the top VECTORLIST item to the FLOATVECTOR
+/// stack where i is taken from the INTEGER stack and bound to the valid range. Acts as NOOP if
+/// the list has no rows.
+fn vector_list_get(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(list) = push_state.vector_list_stack.get(0) {
+        if let Some(index) = push_state.int_stack.get(0) {
+            if let Some(i) = list.bound(*index) {
+                push_state.int_stack.pop();
+                push_state.float_vector_stack.push(list.rows[i].clone());
+            }
+        }
+    }
+}
+
+/// VECTORLIST.ID: Pushes the ID of the VECTORLIST stack to the INTEGER stack.
+fn vector_list_id(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.int_stack.push(VECTOR_LIST_STACK_ID);
+}
+
+/// VECTORLIST.LENGTH: Pushes the number of rows of the top VECTORLIST item to the INTEGER
+/// stack.
+fn vector_list_length(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(list) = push_state.vector_list_stack.get(0) {
+        push_state.int_stack.push(list.rows.len() as i32);
+    }
+}
+
+/// VECTORLIST.POP: Pops the VECTORLIST stack.
+fn vector_list_pop(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.vector_list_stack.pop();
+}
+
+/// VECTORLIST.REMOVE: Removes the row at index i from the top VECTORLIST item and pushes it to
+/// the FLOATVECTOR stack. The index i is taken from the INTEGER stack and bound to the valid
+/// range. Acts as NOOP if the list has no rows.
+fn vector_list_remove(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    let bound = match (
+        push_state.vector_list_stack.get(0),
+        push_state.int_stack.get(0),
+    ) {
+        (Some(list), Some(index)) => list.bound(*index),
+        _ => None,
+    };
+    if let Some(i) = bound {
+        push_state.int_stack.pop();
+        let list = push_state.vector_list_stack.get_mut(0).unwrap();
+        let row = list.rows.remove(i);
+        push_state.float_vector_stack.push(row);
+    }
+}
+
+/// VECTORLIST.ROT: Rotates the top three items on the VECTORLIST stack, pulling the third item
+/// out and pushing it on top.
+fn vector_list_rot(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.vector_list_stack.yank(2);
+}
+
+/// VECTORLIST.SET: Replaces the row at index i of the top VECTORLIST item by the top
+/// FLOATVECTOR where i is taken from the INTEGER stack and bound to the valid range. Acts as
+/// NOOP if the list has no rows.
+fn vector_list_set(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if push_state.float_vector_stack.size() == 0 {
+        return;
+    }
+    let bound = match (
+        push_state.vector_list_stack.get(0),
+        push_state.int_stack.get(0),
+    ) {
+        (Some(list), Some(index)) => list.bound(*index),
+        _ => None,
+    };
+    if let Some(i) = bound {
+        push_state.int_stack.pop();
+        let row = push_state.float_vector_stack.pop().unwrap();
+        push_state.vector_list_stack.get_mut(0).unwrap().rows[i] = row;
+    }
+}
+
+/// VECTORLIST.SHOVE: Inserts the top VECTORLIST "deep" in the stack, at the position indexed by
+/// the top INTEGER.
+fn vector_list_shove(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = pop_vector_list_index(push_state) {
+        push_state.vector_list_stack.shove(index);
+    }
+}
+
+/// VECTORLIST.STACKDEPTH: Pushes the stack depth onto the INTEGER stack.
+fn vector_list_stack_depth(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state
+        .int_stack
+        .push(push_state.vector_list_stack.size() as i32);
+}
+
+/// VECTORLIST.SWAP: Swaps the top two VECTORLIST items.
+fn vector_list_swap(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    push_state.vector_list_stack.shove(1);
+}
+
+/// VECTORLIST.YANK: Removes an indexed item from "deep" in the stack and pushes it on top of
+/// the stack. The index is taken from the INTEGER stack.
+fn vector_list_yank(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = pop_vector_list_index(push_state) {
+        push_state.vector_list_stack.yank(index);
+    }
+}
+
+/// VECTORLIST.YANKDUP: Pushes a copy of an indexed item "deep" in the stack onto the top of the
+/// stack, without removing the deep item. The index is taken from the INTEGER stack.
+fn vector_list_yank_dup(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
+    if let Some(index) = pop_vector_list_index(push_state) {
+        if let Some(deep_item) = push_state.vector_list_stack.copy(index) {
+            push_state.vector_list_stack.push(deep_item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::interpreter::PushInterpreter;
+
+    pub fn icache() -> InstructionCache {
+        InstructionCache::new(vec![])
+    }
+
+    #[test]
+    fn vector_list_rows_are_appended_and_indexed() {
+        let mut test_state = PushState::new();
+        test_state
+            .float_vector_stack
+            .push(FloatVector::new(vec![1.0, 2.0]));
+        test_state.float_vector_stack.push(FloatVector::new(vec![3.0]));
+        vector_list_append(&mut test_state, &icache());
+        vector_list_append(&mut test_state, &icache());
+        assert_eq!(
+            test_state.vector_list_stack.to_string(),
+            "[[3.000],[1.000,2.000]]"
+        );
+        test_state.int_stack.push(5);
+        vector_list_get(&mut test_state, &icache());
+        assert_eq!(test_state.float_vector_stack.to_string(), "[1.000,2.000]");
+        test_state.int_stack.push(0);
+        vector_list_set(&mut test_state, &icache());
+        vector_list_length(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.to_string(), "2");
+        test_state.int_stack.push(-1);
+        vector_list_remove(&mut test_state, &icache());
+        assert_eq!(
+            test_state.vector_list_stack.to_string(),
+            "[[1.000,2.000]]"
+        );
+        assert_eq!(test_state.float_vector_stack.to_string(), "[1.000,2.000]");
+        // Rows of an empty list cannot be accessed
+        vector_list_empty(&mut test_state, &icache());
+        vector_list_get(&mut test_state, &icache());
+        vector_list_remove(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.to_string(), "2");
+        assert_eq!(test_state.float_vector_stack.size(), 1);
+    }
+
+    #[test]
+    fn exec_do_vector_list_iterates_rows_in_order() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        push_state.vector_list_stack.push(VectorList::new(vec![
+            FloatVector::new(vec![1.0, 2.0]),
+            FloatVector::new(vec![]),
+            FloatVector::new(vec![3.0, 4.0, 5.0]),
+        ]));
+        PushInterpreter::run_program(
+            "( EXEC.DO*VECTORLIST ( FLOATVECTOR.LENGTH VECTORLIST.EMPTY ) )",
+            &mut push_state,
+            &mut instruction_set,
+        );
+        assert_eq!(push_state.int_stack.to_string(), "3 0 2");
+        assert_eq!(push_state.vector_list_stack.size(), 3);
+        assert_eq!(push_state.float_vector_stack.size(), 3);
+    }
+}